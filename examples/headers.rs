@@ -0,0 +1,55 @@
+// Counts headers in PGNs, skipping all movetext.
+// Usage: cargo run --release --example headers -- [--fast] [PGN]...
+//
+// With --fast, uses BufferedReader::read_headers_only() instead of
+// returning Skip(true) from end_headers().
+
+use std::{env, fs::File, io};
+
+use pgn_reader::{BufferedReader, RawHeader, Skip, Visitor};
+
+#[derive(Debug, Default)]
+struct Headers {
+    games: usize,
+    headers: usize,
+}
+
+impl Visitor for Headers {
+    type Result = ();
+
+    fn header(&mut self, _key: &[u8], _value: RawHeader<'_>) {
+        self.headers += 1;
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) {
+        self.games += 1;
+    }
+}
+
+fn main() -> Result<(), io::Error> {
+    let mut fast = false;
+
+    for arg in env::args().skip(1) {
+        if arg == "--fast" {
+            fast = true;
+            continue;
+        }
+
+        let file = File::open(&arg)?;
+        let mut reader = BufferedReader::new(file);
+
+        let mut headers = Headers::default();
+        if fast {
+            while reader.read_headers_only(&mut headers)?.is_some() {}
+        } else {
+            reader.read_all(&mut headers)?;
+        }
+        println!("{}: {:?}", arg, headers);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+// Counts games and moves in uncompressed PGNs, once parsing a memory map in
+// place and once reading the file through a BufferedReader.
+// Usage: cargo run --release --example mmap -- [PGN]...
+//
+// Memory maps are set up with libc, so this only runs on Unix.
+
+#[cfg(unix)]
+use std::{env, fs::File, io, os::unix::io::AsRawFd, ptr, slice, time::Instant};
+
+#[cfg(unix)]
+use pgn_reader::{BufferedReader, SanPlus, SliceReader, Visitor};
+
+#[cfg(unix)]
+#[derive(Debug, Default, Eq, PartialEq)]
+struct Stats {
+    games: usize,
+    sans: usize,
+}
+
+#[cfg(unix)]
+impl Visitor for Stats {
+    type Result = ();
+
+    fn san(&mut self, _san: SanPlus) {
+        self.sans += 1;
+    }
+
+    fn end_game(&mut self) {
+        self.games += 1;
+    }
+}
+
+/// A read-only memory map of a whole file.
+#[cfg(unix)]
+struct Mmap {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+#[cfg(unix)]
+impl Mmap {
+    /// Maps the file.
+    ///
+    /// # Safety
+    ///
+    /// The file must not be modified or truncated while it is mapped.
+    unsafe fn new(file: &File) -> io::Result<Mmap> {
+        let len = file.metadata()?.len() as usize;
+        if len == 0 {
+            return Ok(Mmap {
+                ptr: ptr::null_mut(),
+                len,
+            });
+        }
+        let ptr = libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ,
+            libc::MAP_PRIVATE,
+            file.as_raw_fd(),
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        libc::madvise(ptr, len, libc::MADV_SEQUENTIAL);
+        Ok(Mmap { ptr, len })
+    }
+
+    fn as_slice(&self) -> &[u8] {
+        if self.len == 0 {
+            return &[];
+        }
+        // Valid for as long as the mapping, which the borrow ties to self.
+        unsafe { slice::from_raw_parts(self.ptr as *const u8, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if self.len > 0 {
+            unsafe {
+                libc::munmap(self.ptr, self.len);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn main() -> Result<(), io::Error> {
+    for arg in env::args().skip(1) {
+        let file = File::open(&arg)?;
+
+        let start = Instant::now();
+        let mmap = unsafe { Mmap::new(&file)? };
+        let mut mapped = Stats::default();
+        SliceReader::new(mmap.as_slice()).read_all(&mut mapped)?;
+        let mapped_time = start.elapsed();
+        drop(mmap);
+
+        let start = Instant::now();
+        let mut buffered = Stats::default();
+        BufferedReader::new(File::open(&arg)?).read_all(&mut buffered)?;
+        let buffered_time = start.elapsed();
+
+        assert_eq!(mapped, buffered);
+        println!(
+            "{}: {:?}, mmap: {:.3?}, buffered: {:.3?}",
+            arg, mapped, mapped_time, buffered_time
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("the mmap example only runs on Unix");
+}
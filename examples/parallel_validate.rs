@@ -132,9 +132,9 @@ fn main() {
         } else if arg.ends_with(".bz2") {
             Box::new(bzip2::read::MultiBzDecoder::new(file))
         } else if arg.ends_with(".xz") {
-            Box::new(xz2::read::XzDecoder::new(file))
+            Box::new(xz2::read::XzDecoder::new_multi_decoder(file))
         } else if arg.ends_with(".gz") {
-            Box::new(flate2::read::GzDecoder::new(file))
+            Box::new(flate2::read::MultiGzDecoder::new(file))
         } else if arg.ends_with(".lz4") {
             Box::new(lz4::Decoder::new(file).expect("lz4 decoder"))
         } else {
@@ -0,0 +1,1318 @@
+use std::{collections::HashMap, time::Duration};
+
+use shakmaty::{
+    fen::Fen,
+    san::{San, SanPlus},
+    ByColor, Color, Outcome, Setup,
+};
+
+use crate::{
+    comment::{Clock, Eval},
+    headers::{Headers, Period, TimeControl},
+    types::{Nag, RawComment, RawHeader, Skip},
+    visitor::Visitor,
+};
+
+/// Which kinds of annotations occur in the movetext of a game.
+///
+/// Also a visitor that detects them. Variations are only noted, not
+/// descended into, so comments and NAGs inside variations do not count.
+///
+/// ```
+/// use pgn_reader::{BufferedReader, MovetextFeatures};
+///
+/// let pgn = b"1. e4 { best by test } e5 (1... c5) *";
+///
+/// let features = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut MovetextFeatures::default())?;
+///
+/// assert_eq!(features, Some(MovetextFeatures {
+///     comments: true,
+///     variations: true,
+///     nags: false,
+/// }));
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct MovetextFeatures {
+    pub comments: bool,
+    pub variations: bool,
+    pub nags: bool,
+}
+
+impl Visitor for MovetextFeatures {
+    type Result = MovetextFeatures;
+
+    fn begin_game(&mut self) {
+        *self = MovetextFeatures::default();
+    }
+
+    fn nag(&mut self, _nag: Nag) {
+        self.nags = true;
+    }
+
+    fn comment(&mut self, _comment: RawComment<'_>) {
+        self.comments = true;
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.variations = true;
+        Skip(true)
+    }
+
+    fn end_game(&mut self) -> MovetextFeatures {
+        *self
+    }
+}
+
+/// Collects the headers and the mainline moves of each game.
+///
+/// Header values are decoded (see [`RawHeader::decode()`]). If a header
+/// occurs more than once, the last value wins.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, GameSummary};
+///
+/// let pgn = b"[White \"Fischer\"]\n[Black \"Spassky\"]\n\n1. c4 e6 2. Nf3 d5 (2... Nf6) 3. d4 *";
+///
+/// let (headers, moves) = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut GameSummary::new())?
+///     .expect("game");
+///
+/// assert_eq!(headers[&b"White"[..]], b"Fischer");
+/// assert_eq!(moves.len(), 5);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GameSummary {
+    headers: Headers,
+    moves: Vec<San>,
+}
+
+impl GameSummary {
+    pub fn new() -> GameSummary {
+        GameSummary::default()
+    }
+}
+
+impl Visitor for GameSummary {
+    type Result = (Headers, Vec<San>);
+
+    fn begin_game(&mut self) {
+        self.headers.clear();
+        self.moves.clear();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.headers
+            .insert(key.to_owned(), value.decode().into_owned());
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.moves.push(san_plus.san);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        (
+            std::mem::take(&mut self.headers),
+            std::mem::take(&mut self.moves),
+        )
+    }
+}
+
+/// The Seven Tag Roster and any extra headers of a game, as collected by
+/// [`HeaderSelector`]. Values are decoded, and `None` if missing.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct SelectedHeaders {
+    pub event: Option<Vec<u8>>,
+    pub site: Option<Vec<u8>>,
+    pub date: Option<Vec<u8>>,
+    pub round: Option<Vec<u8>>,
+    pub white: Option<Vec<u8>>,
+    pub black: Option<Vec<u8>>,
+    pub result: Option<Vec<u8>>,
+    /// Values of the extra keys, in the order they were given to
+    /// [`HeaderSelector::with_extra()`].
+    pub extra: Vec<Option<Vec<u8>>>,
+}
+
+impl SelectedHeaders {
+    fn slot(&mut self, key: &[u8], extra_keys: &[Vec<u8>]) -> Option<&mut Option<Vec<u8>>> {
+        Some(match key {
+            b"Event" => &mut self.event,
+            b"Site" => &mut self.site,
+            b"Date" => &mut self.date,
+            b"Round" => &mut self.round,
+            b"White" => &mut self.white,
+            b"Black" => &mut self.black,
+            b"Result" => &mut self.result,
+            _ => {
+                let index = extra_keys.iter().position(|extra| extra == key)?;
+                &mut self.extra[index]
+            }
+        })
+    }
+}
+
+/// Collects the Seven Tag Roster and selected extra headers of each game,
+/// and skips the movetext.
+///
+/// Only the selected values are copied, so this is the fast path for
+/// building a database of game metadata, especially with
+/// [`BufferedReader::read_headers_only()`](crate::BufferedReader::read_headers_only).
+/// If a header occurs more than once, the last value wins.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, HeaderSelector};
+///
+/// let pgn = b"[White \"Fischer\"]\n[WhiteElo \"2785\"]\n[ECO \"D59\"]\n\n1. c4 e6 *";
+///
+/// let mut selector = HeaderSelector::with_extra([&b"WhiteElo"[..], b"BlackElo"]);
+/// let headers = BufferedReader::new_cursor(&pgn[..])
+///     .read_headers_only(&mut selector)?
+///     .expect("game");
+///
+/// assert_eq!(headers.white.as_deref(), Some(&b"Fischer"[..]));
+/// assert_eq!(headers.black, None);
+/// assert_eq!(headers.extra, [Some(b"2785".to_vec()), None]);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HeaderSelector {
+    extra_keys: Vec<Vec<u8>>,
+    headers: SelectedHeaders,
+}
+
+impl HeaderSelector {
+    /// A selector for just the Seven Tag Roster.
+    pub fn new() -> HeaderSelector {
+        HeaderSelector::default()
+    }
+
+    /// Also collects the headers with the given keys, into the
+    /// corresponding slots of [`SelectedHeaders::extra`]. Keys of the Seven
+    /// Tag Roster always go into their own fields.
+    pub fn with_extra<I>(keys: I) -> HeaderSelector
+    where
+        I: IntoIterator,
+        I::Item: Into<Vec<u8>>,
+    {
+        HeaderSelector {
+            extra_keys: keys.into_iter().map(Into::into).collect(),
+            headers: SelectedHeaders::default(),
+        }
+    }
+}
+
+impl Visitor for HeaderSelector {
+    type Result = SelectedHeaders;
+
+    fn begin_game(&mut self) {
+        self.headers = SelectedHeaders {
+            extra: vec![None; self.extra_keys.len()],
+            ..SelectedHeaders::default()
+        };
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if let Some(slot) = self.headers.slot(key, &self.extra_keys) {
+            *slot = Some(value.decode().into_owned());
+        }
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(&mut self.headers)
+    }
+}
+
+/// Collects all headers of each game as strings, in order, and skips the
+/// movetext.
+///
+/// Values are decoded, and invalid UTF-8 in keys and values is replaced
+/// with U+FFFD. Meant for exploring unfamiliar files, not for lossless
+/// processing.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, DumpHeaders};
+///
+/// let pgn = b"[Event \"Caf\xe9 \\\"Cup\\\"\"]\n[Round \"1\"]\n\n1. e4 *";
+///
+/// let headers = BufferedReader::new_cursor(&pgn[..]).read_game(&mut DumpHeaders::new())?;
+/// assert_eq!(headers, Some(vec![
+///     ("Event".to_owned(), "Caf\u{fffd} \"Cup\"".to_owned()),
+///     ("Round".to_owned(), "1".to_owned()),
+/// ]));
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DumpHeaders {
+    headers: Vec<(String, String)>,
+}
+
+impl DumpHeaders {
+    pub fn new() -> DumpHeaders {
+        DumpHeaders::default()
+    }
+}
+
+impl Visitor for DumpHeaders {
+    type Result = Vec<(String, String)>;
+
+    fn begin_game(&mut self) {
+        self.headers.clear();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.headers.push((
+            String::from_utf8_lossy(key).into_owned(),
+            String::from_utf8_lossy(&value.decode()).into_owned(),
+        ));
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(&mut self.headers)
+    }
+}
+
+/// Collects the NAGs of each game, paired with the ply of the move they
+/// annotate.
+///
+/// Plies count from 1 for the first move of the game. NAGs before the first
+/// move are paired with ply 0. Only the mainline is considered.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, Nag, Nags};
+///
+/// let pgn = b"1. e4 e5 2. Qh5?! Nc6 3. Bc4 Nf6?? 4. Qxf7# 1-0";
+///
+/// let nags = BufferedReader::new_cursor(&pgn[..]).read_game(&mut Nags::new())?;
+/// assert_eq!(nags, Some(vec![(Nag::DUBIOUS_MOVE, 3), (Nag::BLUNDER, 6)]));
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Nags {
+    ply: usize,
+    nags: Vec<(Nag, usize)>,
+}
+
+impl Nags {
+    pub fn new() -> Nags {
+        Nags::default()
+    }
+}
+
+impl Visitor for Nags {
+    type Result = Vec<(Nag, usize)>;
+
+    fn begin_game(&mut self) {
+        self.ply = 0;
+        self.nags.clear();
+    }
+
+    fn san(&mut self, _san_plus: SanPlus) {
+        self.ply += 1;
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.nags.push((nag, self.ply));
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(&mut self.nags)
+    }
+}
+
+/// Compares the `PlyCount` header of each game to the number of plies in the
+/// mainline.
+///
+/// Produces `Some((claimed, actual))` if they differ, which hints at a
+/// truncated or otherwise corrupted game. Produces `None` if they match, or
+/// if there is no `PlyCount` header with a number to check.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, PlyCountCheck};
+///
+/// let pgn = b"[PlyCount \"3\"]\n\n1. e4 e5 2. Nf3 *\n\n\
+///             [PlyCount \"41\"]\n\n1. d4 d5 *";
+///
+/// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+/// let mut check = PlyCountCheck::new();
+/// assert_eq!(reader.read_game(&mut check)?, Some(None));
+/// assert_eq!(reader.read_game(&mut check)?, Some(Some((41, 2))));
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PlyCountCheck {
+    claimed: Option<usize>,
+    actual: usize,
+}
+
+impl PlyCountCheck {
+    pub fn new() -> PlyCountCheck {
+        PlyCountCheck::default()
+    }
+}
+
+impl Visitor for PlyCountCheck {
+    type Result = Option<(usize, usize)>;
+
+    fn begin_game(&mut self) {
+        self.claimed = None;
+        self.actual = 0;
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if key == b"PlyCount" {
+            self.claimed = btoi::btou(value.decode().trim_ascii()).ok();
+        }
+    }
+
+    fn san(&mut self, _san_plus: SanPlus) {
+        self.actual += 1;
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.claimed
+            .filter(|&claimed| claimed != self.actual)
+            .map(|claimed| (claimed, self.actual))
+    }
+}
+
+/// Reports the outcome from the `Result` header of each game next to the
+/// outcome from the termination marker at the end of the movetext.
+///
+/// Produces `(header, movetext)`. Either side is `None` if it is `*`,
+/// missing, or not a valid outcome. Comparing the two flags games where
+/// they disagree, like abandoned games with a `Result` that was filled in
+/// later.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, Color, Outcome, OutcomeCheck};
+///
+/// let pgn = b"[Result \"1-0\"]\n\n1. e4 e5 *\n\n[Result \"1/2-1/2\"]\n\n1. d4 d5 1/2-1/2";
+///
+/// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+/// let mut check = OutcomeCheck::new();
+/// assert_eq!(
+///     reader.read_game(&mut check)?,
+///     Some((Some(Outcome::Decisive { winner: Color::White }), None))
+/// );
+/// assert_eq!(
+///     reader.read_game(&mut check)?,
+///     Some((Some(Outcome::Draw), Some(Outcome::Draw)))
+/// );
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OutcomeCheck {
+    header: Option<Outcome>,
+    movetext: Option<Outcome>,
+}
+
+impl OutcomeCheck {
+    pub fn new() -> OutcomeCheck {
+        OutcomeCheck::default()
+    }
+}
+
+impl Visitor for OutcomeCheck {
+    type Result = (Option<Outcome>, Option<Outcome>);
+
+    fn begin_game(&mut self) {
+        self.header = None;
+        self.movetext = None;
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if key == b"Result" {
+            self.header = Outcome::from_ascii(value.decode().trim_ascii()).ok();
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.movetext = outcome;
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        (self.header, self.movetext)
+    }
+}
+
+/// A visitor that tells whether each game starts from the standard starting
+/// position, for example to handle puzzles and odds games separately.
+///
+/// Produces `true` if there is no `FEN` header, or if it describes the
+/// standard starting position, regardless of the move counters. An invalid
+/// `FEN` counts as a non-standard start. The `SetUp` header is not
+/// consulted, because many files omit it. The movetext is skipped.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, StandardStart};
+///
+/// let pgn = b"1. e4 *\n\n[SetUp \"1\"]\n[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\"]\n\n1. Ra8# *";
+///
+/// let results = BufferedReader::new_cursor(&pgn[..])
+///     .into_iter(&mut StandardStart::new())
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(results, [true, false]);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StandardStart {
+    standard: bool,
+}
+
+impl StandardStart {
+    pub fn new() -> StandardStart {
+        StandardStart::default()
+    }
+}
+
+impl Visitor for StandardStart {
+    type Result = bool;
+
+    fn begin_game(&mut self) {
+        self.standard = true;
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if key == b"FEN" {
+            self.standard = Fen::from_ascii(&value.decode()).is_ok_and(|Fen(setup)| {
+                let standard = Setup::default();
+                Setup {
+                    halfmoves: standard.halfmoves,
+                    fullmoves: standard.fullmoves,
+                    ..setup
+                } == standard
+            });
+        }
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(true)
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.standard
+    }
+}
+
+/// Average centipawn loss of each player, as computed by [`CentipawnLoss`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Acpl {
+    /// Average loss of the moves of White, or `None` if none of them could
+    /// be assessed.
+    pub white_acpl: Option<f64>,
+    /// Average loss of the moves of Black, or `None` if none of them could
+    /// be assessed.
+    pub black_acpl: Option<f64>,
+}
+
+/// Computes the average centipawn loss of each player from the `[%eval]`
+/// annotations in the mainline.
+///
+/// The loss of a move is how much the evaluation drops from the point of
+/// view of the player who made it, or 0 if it improves. Only moves with an
+/// evaluation of the positions before and after them count. The first
+/// evaluation in the comments after a move is used.
+///
+/// Mate scores are replaced by a bound, 1000 centipawns by default, and
+/// centipawn values are clamped to it, so that a single blunder does not
+/// dominate the average.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Acpl, BufferedReader, CentipawnLoss};
+///
+/// let pgn = b"1. e4 { [%eval 0.2] } 1... e5 { [%eval 0.3] } 2. Qh5 { [%eval -0.4] } *";
+///
+/// let acpl = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut CentipawnLoss::new())?
+///     .unwrap();
+/// assert_eq!(acpl, Acpl {
+///     white_acpl: Some(70.0),
+///     black_acpl: Some(10.0),
+/// });
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct CentipawnLoss {
+    bound: i32,
+    /// Side to move in the current position.
+    turn: Color,
+    /// Evaluation of the current position.
+    eval: Option<i32>,
+    /// Evaluation of the position before the last move.
+    before: Option<i32>,
+    /// Total loss and number of assessed moves.
+    losses: ByColor<(u64, usize)>,
+}
+
+impl CentipawnLoss {
+    pub fn new() -> CentipawnLoss {
+        CentipawnLoss {
+            bound: 1000,
+            turn: Color::White,
+            eval: None,
+            before: None,
+            losses: ByColor::default(),
+        }
+    }
+
+    /// Sets the bound for mate scores and large evaluations, in centipawns.
+    pub fn set_bound(&mut self, centipawns: i32) {
+        self.bound = centipawns.max(0);
+    }
+
+    fn centipawns(&self, eval: Eval) -> i32 {
+        match eval {
+            Eval::Centipawns(cp) => cp.clamp(-self.bound, self.bound),
+            Eval::Mate(moves) if moves > 0 => self.bound,
+            Eval::Mate(moves) if moves < 0 => -self.bound,
+            // Mate on the board: the side to move has lost.
+            Eval::Mate(_) => match self.turn {
+                Color::White => -self.bound,
+                Color::Black => self.bound,
+            },
+        }
+    }
+}
+
+impl Default for CentipawnLoss {
+    fn default() -> CentipawnLoss {
+        CentipawnLoss::new()
+    }
+}
+
+impl Visitor for CentipawnLoss {
+    type Result = Acpl;
+
+    fn begin_game(&mut self) {
+        self.turn = Color::White;
+        self.eval = None;
+        self.before = None;
+        self.losses = ByColor::default();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if key == b"FEN" {
+            if let Ok(fen) = Fen::from_ascii(&value.decode()) {
+                self.turn = fen.0.turn;
+            }
+        }
+    }
+
+    fn san(&mut self, _san_plus: SanPlus) {
+        self.before = self.eval.take();
+        self.turn = !self.turn;
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        if self.eval.is_some() {
+            return;
+        }
+        let Some(eval) = Eval::parse(comment.as_bytes()) else {
+            return;
+        };
+        let after = self.centipawns(eval);
+        self.eval = Some(after);
+
+        if let Some(before) = self.before {
+            let mover = !self.turn;
+            let (before, after) = (i64::from(before), i64::from(after));
+            let loss = match mover {
+                Color::White => before - after,
+                Color::Black => after - before,
+            };
+            let (total, moves) = self.losses.get_mut(mover);
+            *total = total.saturating_add(loss.max(0) as u64);
+            *moves += 1;
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        let average =
+            |(total, moves): (u64, usize)| (moves > 0).then(|| total as f64 / moves as f64);
+        Acpl {
+            white_acpl: average(self.losses.white),
+            black_acpl: average(self.losses.black),
+        }
+    }
+}
+
+/// Computes the time spent on each mainline move from the `[%clk]`
+/// annotations, as a list of plies (starting at 1) and durations.
+///
+/// The time spent on a move is the clock before it, plus the increment,
+/// minus the clock after it. The increment of the period of the
+/// `TimeControl` header that the move is played in is used, if any. The
+/// move that completes a period, like the 40th in `40/7200:3600`, is also
+/// credited with the base time of the next period, because clocks show it
+/// right away.
+///
+/// The first move of each player is compared to the base time of the
+/// `TimeControl`, without adding the increment, because clocks usually do
+/// not run before the first move. Moves without a clock, and moves after
+/// them whose previous clock is unknown, are left out.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use pgn_reader::{analysis::TimeUsage, BufferedReader};
+///
+/// let pgn = b"[TimeControl \"180+2\"]
+///
+///             1. e4 { [%clk 0:03:00] } 1... e5 { [%clk 0:02:58] }
+///             2. Nf3 { [%clk 0:02:55] } 2... Nc6 { [%clk 0:02:50] } *";
+///
+/// let spent = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut TimeUsage::new())?
+///     .unwrap();
+/// assert_eq!(spent, [
+///     (1, Duration::ZERO),
+///     (2, Duration::from_secs(2)),
+///     (3, Duration::from_secs(7)),
+///     (4, Duration::from_secs(10)),
+/// ]);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct TimeUsage {
+    /// Periods of the time control, empty if unknown.
+    periods: Vec<Period>,
+    /// Side that made the last move.
+    turn: Color,
+    ply: usize,
+    /// The clock of the last move is still expected.
+    pending: bool,
+    /// Clock after the last move of each player, or `None` if unknown.
+    clocks: ByColor<Option<Duration>>,
+    /// Number of moves made by each player.
+    moves: ByColor<u32>,
+    spent: Vec<(usize, Duration)>,
+}
+
+impl TimeUsage {
+    pub fn new() -> TimeUsage {
+        TimeUsage {
+            periods: Vec::new(),
+            turn: Color::Black,
+            ply: 0,
+            pending: false,
+            clocks: ByColor::default(),
+            moves: ByColor::default(),
+            spent: Vec::new(),
+        }
+    }
+
+    /// The time added by the `n`th move of a player, counting from 1: the
+    /// increment of its period, and the base time of the next period if
+    /// the move completes one.
+    fn time_added(&self, n: u32) -> Duration {
+        let mut end: u32 = 0;
+        for (i, period) in self.periods.iter().enumerate() {
+            let Some(moves) = period.moves else {
+                return period.increment;
+            };
+            end = end.saturating_add(moves);
+            if n < end {
+                return period.increment;
+            } else if n == end {
+                return period.increment.saturating_add(
+                    self.periods
+                        .get(i + 1)
+                        .map_or(Duration::ZERO, |next| next.base),
+                );
+            }
+        }
+        // The last period has been completed, so the time control is not
+        // quite valid. Stick to the last increment.
+        self.periods
+            .last()
+            .map_or(Duration::ZERO, |period| period.increment)
+    }
+}
+
+impl Default for TimeUsage {
+    fn default() -> TimeUsage {
+        TimeUsage::new()
+    }
+}
+
+impl Visitor for TimeUsage {
+    type Result = Vec<(usize, Duration)>;
+
+    fn begin_game(&mut self) {
+        self.periods.clear();
+        self.turn = Color::Black;
+        self.ply = 0;
+        self.pending = false;
+        self.clocks = ByColor::default();
+        self.moves = ByColor::default();
+        self.spent.clear();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        match key {
+            b"TimeControl" => {
+                if let Ok(TimeControl::Periods(periods)) = TimeControl::from_ascii(&value.decode())
+                {
+                    self.periods = periods;
+                }
+            }
+            b"FEN" => {
+                if let Ok(fen) = Fen::from_ascii(&value.decode()) {
+                    self.turn = !fen.0.turn;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn san(&mut self, _san_plus: SanPlus) {
+        if self.pending {
+            *self.clocks.get_mut(self.turn) = None;
+        }
+        self.turn = !self.turn;
+        self.ply += 1;
+        self.pending = true;
+        *self.moves.get_mut(self.turn) += 1;
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        if !self.pending {
+            return;
+        }
+        let Some(Clock(clock)) = Clock::parse(comment.as_bytes()) else {
+            return;
+        };
+        self.pending = false;
+
+        let mover = self.turn;
+        let before = match *self.moves.get(mover) {
+            1 => self.periods.first().map(|period| period.base),
+            n => self
+                .clocks
+                .get(mover)
+                .map(|before| before.saturating_add(self.time_added(n))),
+        };
+        if let Some(before) = before {
+            self.spent.push((self.ply, before.saturating_sub(clock)));
+        }
+        *self.clocks.get_mut(mover) = Some(clock);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(&mut self.spent)
+    }
+}
+
+/// A node of an [`OpeningTree`]: the games that reached a move sequence and
+/// how they continued.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct OpeningNode {
+    games: usize,
+    children: HashMap<San, OpeningNode>,
+}
+
+impl OpeningNode {
+    /// Number of games that reached this node.
+    pub fn games(&self) -> usize {
+        self.games
+    }
+
+    /// Gets the node reached by playing `san`, if any game did.
+    pub fn get(&self, san: &San) -> Option<&OpeningNode> {
+        self.children.get(san)
+    }
+
+    /// Iterates over all continuations, in no particular order.
+    pub fn children(&self) -> impl Iterator<Item = (&San, &OpeningNode)> {
+        self.children.iter()
+    }
+
+    /// Returns the continuation that was played most often. Ties are broken
+    /// by the notation of the move, so that the result is deterministic.
+    pub fn most_common(&self) -> Option<(&San, &OpeningNode)> {
+        self.children.iter().max_by(|(a_san, a), (b_san, b)| {
+            a.games
+                .cmp(&b.games)
+                .then_with(|| b_san.to_string().cmp(&a_san.to_string()))
+        })
+    }
+}
+
+/// Aggregates move frequencies by ply over many games.
+///
+/// Reuse the same visitor for all games. Only the mainline up to the
+/// configured depth is considered.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, OpeningTree, San};
+///
+/// let pgn = b"1. e4 e5 *\n\n1. e4 c5 *\n\n1. d4 d5 *";
+///
+/// let mut tree = OpeningTree::new(2);
+/// BufferedReader::new_cursor(&pgn[..]).read_all(&mut tree)?;
+///
+/// let (first, node) = tree.summary().most_common().unwrap();
+/// assert_eq!(*first, "e4".parse::<San>().unwrap());
+/// assert_eq!(node.games(), 2);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct OpeningTree {
+    max_plies: usize,
+    root: OpeningNode,
+    line: Vec<San>,
+}
+
+impl OpeningTree {
+    /// Creates an empty tree that records up to `max_plies` moves of each
+    /// game.
+    pub fn new(max_plies: usize) -> OpeningTree {
+        OpeningTree {
+            max_plies,
+            root: OpeningNode::default(),
+            line: Vec::with_capacity(max_plies),
+        }
+    }
+
+    /// Returns the root of the tree, covering all games read so far.
+    pub fn summary(&self) -> &OpeningNode {
+        &self.root
+    }
+
+    /// Consumes the visitor and returns the tree.
+    pub fn into_summary(self) -> OpeningNode {
+        self.root
+    }
+}
+
+impl Visitor for OpeningTree {
+    type Result = ();
+
+    fn begin_game(&mut self) {
+        self.line.clear();
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(self.max_plies == 0)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        if self.line.len() < self.max_plies {
+            self.line.push(san_plus.san);
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) {
+        let mut node = &mut self.root;
+        node.games += 1;
+        for san in self.line.drain(..) {
+            node = node.children.entry(san).or_default();
+            node.games += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::reader::BufferedReader;
+
+    fn san(s: &str) -> San {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_header_selector() -> io::Result<()> {
+        let pgn = b"[Event \"Rematch\"]\n[White \"O\\\"Kelly\"]\n[Black \"?\"]\n\
+                    [Black \"Spassky\"]\n[Annotator \"A\"]\n[PlyCount \"3\"]\n\n\
+                    1. e4 e5 2. Nf3 1-0\n\n\
+                    [Site \"Belgrade\"]\n\n1. d4 *";
+
+        let mut selector = HeaderSelector::with_extra([&b"PlyCount"[..], b"Result"]);
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let first = reader.read_game(&mut selector)?.unwrap();
+        assert_eq!(first.event.as_deref(), Some(&b"Rematch"[..]));
+        assert_eq!(first.white.as_deref(), Some(&b"O\"Kelly"[..]));
+        assert_eq!(first.black.as_deref(), Some(&b"Spassky"[..]));
+        assert_eq!(first.result, None);
+        assert_eq!(first.extra, [Some(b"3".to_vec()), None]);
+
+        let second = reader.read_game(&mut selector)?.unwrap();
+        assert_eq!(
+            second,
+            SelectedHeaders {
+                site: Some(b"Belgrade".to_vec()),
+                extra: vec![None, None],
+                ..SelectedHeaders::default()
+            }
+        );
+        assert_eq!(reader.read_game(&mut selector)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dump_headers() -> io::Result<()> {
+        let pgn = b"[White \"Ni\xc5\xa1\"]\n[Black \"\xff\"]\n[White \"again\"]\n\n\
+                    1. e4 { [%clk 0:01:00] } 1-0\n\n\
+                    1. d4 *";
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let mut visitor = DumpHeaders::new();
+        assert_eq!(
+            reader.read_game(&mut visitor)?,
+            Some(vec![
+                ("White".to_owned(), "Ni\u{161}".to_owned()),
+                ("Black".to_owned(), "\u{fffd}".to_owned()),
+                ("White".to_owned(), "again".to_owned()),
+            ])
+        );
+        assert_eq!(reader.read_game(&mut visitor)?, Some(vec![]));
+        assert_eq!(reader.read_game(&mut visitor)?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_summary() -> io::Result<()> {
+        let pgn = b"[Event \"Rematch\"]\n\
+                    [White \"Deep Blue\"]\n\
+                    [Black \"Kasparov, Garry\"]\n\
+                    [Annotator \"\\\"Anonymous\\\"\"]\n\
+                    \n\
+                    1. e4 c6 { Caro-Kann } 2. d4 (2. Nc3) 2... d5 3. Nc3 dxe4 1-0\n\n\
+                    [Event \"Next\"]\n\n*";
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let mut visitor = GameSummary::new();
+
+        let (headers, moves) = reader.read_game(&mut visitor)?.unwrap();
+        assert_eq!(headers.len(), 4);
+        assert_eq!(headers[&b"Black"[..]], b"Kasparov, Garry");
+        assert_eq!(headers[&b"Annotator"[..]], b"\"Anonymous\"");
+        assert_eq!(
+            moves,
+            ["e4", "c6", "d4", "d5", "Nc3", "dxe4"]
+                .iter()
+                .map(|s| san(s))
+                .collect::<Vec<_>>()
+        );
+
+        let (headers, moves) = reader.read_game(&mut visitor)?.unwrap();
+        assert_eq!(headers.len(), 1);
+        assert!(moves.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_nags() -> io::Result<()> {
+        let pgn = b"$10 1. e4 $1 $36 e5 (1... c5 $2) 2. Nf3 {!} Nc6 ?! 3. Bb5!? *";
+
+        let nags = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut Nags::new())?
+            .unwrap();
+        assert_eq!(
+            nags,
+            [
+                (Nag(10), 0),
+                (Nag::GOOD_MOVE, 1),
+                (Nag(36), 1),
+                (Nag::DUBIOUS_MOVE, 4),
+                (Nag::SPECULATIVE_MOVE, 5),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ply_count_check() -> io::Result<()> {
+        let pgn = b"[PlyCount \"4\"]\n\n1. e4 e5 (1... c5 2. Nf3) 2. Nf3 Nc6 1-0\n\n\
+                    [PlyCount \"0\"]\n\n*\n\n\
+                    [PlyCount \"80\"]\n\n1. d4 d5 2. c4 *\n\n\
+                    [PlyCount \"2\"]\n\n1. e4 e5 2. Nf3 *\n\n\
+                    [Event \"?\"]\n\n1. e4 *\n\n\
+                    [PlyCount \"?\"]\n\n1. e4 *";
+
+        let results = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut PlyCountCheck::new())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(
+            results,
+            [None, None, Some((80, 3)), Some((2, 3)), None, None]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_outcome_check() -> io::Result<()> {
+        let white = Some(Outcome::Decisive {
+            winner: Color::White,
+        });
+        let black = Some(Outcome::Decisive {
+            winner: Color::Black,
+        });
+        let pgn = b"[Result \"0-1\"]\n\n1. e4 e5 (1... c5 1-0) 0-1\n\n\
+                    [Result \"1-0\"]\n\n1. f3 e5 2. g4 Qh4# 0-1\n\n\
+                    [Result \"*\"]\n\n1. e4 1-0\n\n\
+                    [Result \"?\"]\n\n*\n\n\
+                    [Event \"?\"]\n\n1. d4";
+
+        let results = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut OutcomeCheck::new())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(
+            results,
+            [
+                (black, black),
+                (white, black),
+                (None, white),
+                (None, None),
+                (None, None)
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_usage() -> io::Result<()> {
+        // Black to move in a 5+3 game. Black's second move has no clock, so
+        // the time spent on the third is unknown. The clock in the
+        // variation is ignored.
+        let pgn = b"[TimeControl \"300+3\"]\n\
+                    [FEN \"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1\"]\n\n\
+                    1... e5 { [%clk 0:04:58] } 2. Nf3 { [%clk 0:05:00] } 2... Nc6 \
+                    3. Bb5 { [%clk 0:04:51.5] } (3. Bc4 { [%clk 0:01:00] }) \
+                    3... a6 { [%clk 0:04:40] } 4. Ba4 { [%clk 0:04:54] } *";
+        let spent = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut TimeUsage::new())?
+            .unwrap();
+        assert_eq!(
+            spent,
+            [
+                (1, Duration::from_secs(2)),
+                (2, Duration::ZERO),
+                (4, Duration::from_millis(11_500)),
+                (6, Duration::from_millis(500)),
+            ]
+        );
+
+        // Without a TimeControl, first moves are left out.
+        let pgn =
+            b"1. e4 { [%clk 0:03:00] } 1... e5 { [%clk 0:03:00] } 2. Nf3 { [%clk 0:02:55] } *";
+        let spent = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut TimeUsage::new())?
+            .unwrap();
+        assert_eq!(spent, [(3, Duration::from_secs(5))]);
+
+        // Two moves in 10 minutes, then 5 more minutes with an increment
+        // from move 3. The clock jumps with the 2nd move of each player.
+        let pgn = b"[TimeControl \"2/600:300+10\"]\n\n\
+                    1. e4 { [%clk 0:09:50] } e5 { [%clk 0:09:00] } \
+                    2. Nf3 { [%clk 0:14:30] } Nc6 { [%clk 0:13:00] } \
+                    3. Bb5 { [%clk 0:14:00] } a6 { [%clk 0:12:40] } *";
+        let spent = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut TimeUsage::new())?
+            .unwrap();
+        assert_eq!(
+            spent,
+            [
+                (1, Duration::from_secs(10)),
+                (2, Duration::from_secs(60)),
+                (3, Duration::from_secs(20)),
+                (4, Duration::from_secs(60)),
+                (5, Duration::from_secs(40)),
+                (6, Duration::from_secs(30)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_time_usage_extreme_time_control() -> io::Result<()> {
+        let pgn = b"[TimeControl \"10+18446744073709551615\"]\n\n\
+                    1. e4 { [%clk 0:00:10] } e5 { [%clk 0:00:10] } \
+                    2. Nf3 { [%clk 0:00:05] } *";
+        let spent = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut TimeUsage::new())?
+            .unwrap();
+        assert_eq!(
+            spent,
+            [
+                (1, Duration::ZERO),
+                (2, Duration::ZERO),
+                (3, Duration::MAX - Duration::from_secs(5)),
+            ]
+        );
+
+        // The move count of the second period would overflow the end of it.
+        let pgn = b"[TimeControl \"1/60:4294967295/60:0+5\"]\n\n\
+                    1. e4 { [%clk 0:01:00] } e5 { [%clk 0:01:00] } \
+                    2. Nf3 { [%clk 0:00:50] } *";
+        let spent = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut TimeUsage::new())?
+            .unwrap();
+        assert_eq!(
+            spent,
+            [
+                (1, Duration::ZERO),
+                (2, Duration::ZERO),
+                (3, Duration::from_secs(10)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_centipawn_loss() -> io::Result<()> {
+        let pgn =
+            b"{ [%eval 0.20] } 1. e4 { [%eval 0.15] } 1... e5 { [%clk 0:03:00] [%eval 0.35,20] } \
+                    2. Qh5 { [%eval -0.5] } { [%eval 9.99] } (2. Nf3 { [%eval 0.3] }) \
+                    2... Ke7 { [%eval #1] } 3. Qxe5# { [%eval #0] } 1-0\n\n\
+                    1. d4 d5 { no evals } *";
+
+        let mut visitor = CentipawnLoss::new();
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        assert_eq!(
+            reader.read_game(&mut visitor)?,
+            Some(Acpl {
+                white_acpl: Some((5.0 + 85.0 + 0.0) / 3.0),
+                black_acpl: Some((20.0 + 1050.0) / 2.0),
+            })
+        );
+        assert_eq!(reader.read_game(&mut visitor)?, Some(Acpl::default()));
+
+        // Mate clamped to a lower bound.
+        visitor.set_bound(500);
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        assert_eq!(
+            reader.read_game(&mut visitor)?,
+            Some(Acpl {
+                white_acpl: Some((5.0 + 85.0 + 0.0) / 3.0),
+                black_acpl: Some((20.0 + 550.0) / 2.0),
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_centipawn_loss_large_bound() -> io::Result<()> {
+        let pgn = b"{ [%eval #3] } 1. e4 { [%eval #-3] } 1... e5 { [%eval #3] } *";
+        let mut visitor = CentipawnLoss::new();
+        visitor.set_bound(i32::MAX);
+        let acpl = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut visitor)?
+            .unwrap();
+        assert_eq!(
+            acpl,
+            Acpl {
+                white_acpl: Some(2.0 * f64::from(i32::MAX)),
+                black_acpl: Some(2.0 * f64::from(i32::MAX)),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_standard_start() -> io::Result<()> {
+        let pgn = b"[Event \"No FEN\"]\n\n1. e4 *\n\n\
+                    [SetUp \"1\"]\n[FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1\"]\n\n1. e4 *\n\n\
+                    [FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 12\"]\n\n1. e4 *\n\n\
+                    [SetUp \"1\"]\n[FEN \"r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4\"]\n\n4. Qxf7# *\n\n\
+                    [FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1\"]\n\n1... e5 *\n\n\
+                    [FEN \"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1\"]\n\n*\n\n\
+                    [FEN \"invalid\"]\n\n*";
+
+        let results = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut StandardStart::new())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(results, [true, true, true, false, false, false, false]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_opening_tree() -> io::Result<()> {
+        let pgn = b"1. e4 e5 2. Nf3 *\n\n\
+                    1. e4 (1. d4) c5 *\n\n\
+                    1. d4 d5 *\n\n\
+                    1. e4 e5 0-1\n\n\
+                    *";
+
+        let mut tree = OpeningTree::new(2);
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut tree)?;
+
+        let root = tree.summary();
+        assert_eq!(root.games(), 5);
+        assert_eq!(root.children().count(), 2);
+
+        let e4 = root.get(&san("e4")).unwrap();
+        assert_eq!(e4.games(), 3);
+        assert_eq!(e4.get(&san("e5")).unwrap().games(), 2);
+        assert_eq!(e4.get(&san("c5")).unwrap().games(), 1);
+        assert_eq!(e4.get(&san("e5")).unwrap().children().count(), 0);
+
+        let d4 = root.get(&san("d4")).unwrap();
+        assert_eq!(d4.games(), 1);
+
+        let (best, _) = e4.most_common().unwrap();
+        assert_eq!(*best, san("e5"));
+        Ok(())
+    }
+}
@@ -0,0 +1,1444 @@
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt,
+};
+
+use shakmaty::{
+    fen::Fen,
+    san::{San, SanPlus, Suffix},
+    uci::UciMove,
+    zobrist::{Zobrist64, ZobristHash},
+    CastlingMode, Chess, Color, EnPassantMode, FromSetup, Move, Outcome, Position, PositionError,
+    Rank, Role, Setup,
+};
+
+use crate::{
+    error::PgnError,
+    headers::{castling_files_from_fen, Variant},
+    types::{Nag, RawComment, RawHeader, Skip},
+    visitor::Visitor,
+};
+
+/// Sets up the position of a `FEN` header, or returns `None` if it is
+/// invalid. Chess960 castling rights are detected automatically.
+pub(crate) fn position_from_fen(fen: &[u8]) -> Option<Chess> {
+    position_from_fen_in(fen, CastlingMode::Standard)
+}
+
+/// Like [`position_from_fen()`], but always in Chess960 mode if `mode` is
+/// [`CastlingMode::Chess960`].
+fn position_from_fen_in(fen: &[u8], mode: CastlingMode) -> Option<Chess> {
+    // Many files repeat the starting position in a FEN header.
+    if mode == CastlingMode::Standard && is_standard_start(fen) {
+        return Some(Chess::default());
+    }
+    let mode = match castling_files_from_fen(fen) {
+        Some(files) if !files.white.is_standard() || !files.black.is_standard() => {
+            CastlingMode::Chess960
+        }
+        _ => mode,
+    };
+    Fen::from_ascii(fen)
+        .ok()?
+        .into_position(mode)
+        .or_else(PositionError::ignore_too_much_material)
+        .ok()
+}
+
+/// The standard starting position, without the move counters.
+const STANDARD_START: &[u8] = b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+
+/// Checks whether a FEN is exactly the standard starting position, so that
+/// it can be set up without parsing. The move counters may be omitted, but
+/// otherwise have to be `0 1`. Surrounding whitespace is ignored.
+///
+/// This is a quick textual check, so equivalent spellings of the position
+/// do not count.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::board::is_standard_start;
+///
+/// assert!(is_standard_start(b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"));
+/// assert!(is_standard_start(b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -"));
+/// assert!(!is_standard_start(b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 5"));
+/// assert!(!is_standard_start(b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1"));
+/// ```
+pub fn is_standard_start(fen: &[u8]) -> bool {
+    let Some(counters) = fen.trim_ascii().strip_prefix(STANDARD_START) else {
+        return false;
+    };
+    if !counters.first().is_none_or(u8::is_ascii_whitespace) {
+        return false;
+    }
+    let mut counters = counters
+        .split(u8::is_ascii_whitespace)
+        .filter(|part| !part.is_empty());
+    matches!(
+        (counters.next(), counters.next(), counters.next()),
+        (None, _, _) | (Some(b"0"), Some(b"1"), None)
+    )
+}
+
+/// Checks whether `san` denotes the move `m` in the position `pos`.
+///
+/// Unlike [`San::matches()`], this also takes the position into account, so
+/// SAN that would be ambiguous in the position does not match any move.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{board::san_matches, San, Square};
+/// use shakmaty::{fen::Fen, CastlingMode, Chess, Move, Role};
+///
+/// // Knights on b1 and f3 can both go to d2.
+/// let pos: Chess = "4k3/8/8/8/8/5N2/8/1N2K3 w - - 0 1"
+///     .parse::<Fen>()?
+///     .into_position(CastlingMode::Standard)?;
+/// let m = Move::Normal {
+///     role: Role::Knight,
+///     from: Square::B1,
+///     capture: None,
+///     to: Square::D2,
+///     promotion: None,
+/// };
+///
+/// assert!(san_matches(&"Nbd2".parse::<San>()?, &m, &pos));
+/// assert!(!san_matches(&"Nd2".parse::<San>()?, &m, &pos));
+/// assert!("Nd2".parse::<San>()?.matches(&m)); // ignores the position
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub fn san_matches<P: Position>(san: &San, m: &Move, pos: &P) -> bool {
+    san.to_move(pos).is_ok_and(|legal| legal == *m)
+}
+
+/// Converts `san` to UCI notation, like `e2e4` or `e7e8q`, by finding the
+/// legal move it denotes in the position `pos`. Returns `None` if the move
+/// is illegal or ambiguous.
+///
+/// Castling moves go to the square of the king, like `e1g1`, or to the
+/// square of the rook, like `e1h1`, if the position was set up with
+/// [`CastlingMode::Chess960`].
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{board::san_to_uci, San};
+/// use shakmaty::{fen::Fen, CastlingMode, Chess};
+///
+/// let pos: Chess = "r3k3/1P6/8/8/8/8/8/4K2R w Kq - 0 1"
+///     .parse::<Fen>()?
+///     .into_position(CastlingMode::Standard)?;
+/// let uci = |san: &str| san_to_uci(&san.parse::<San>().unwrap(), &pos).map(|uci| uci.to_string());
+/// assert_eq!(uci("bxa8=N+").as_deref(), Some("b7a8n"));
+/// assert_eq!(uci("O-O").as_deref(), Some("e1g1"));
+/// assert_eq!(uci("O-O-O"), None);
+///
+/// let pos: Chess = "4k3/8/8/8/8/8/8/4K2R w K - 0 1"
+///     .parse::<Fen>()?
+///     .into_position(CastlingMode::Chess960)?;
+/// assert_eq!(san_to_uci(&"O-O".parse()?, &pos).map(|uci| uci.to_string()).as_deref(), Some("e1h1"));
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+pub fn san_to_uci<P: Position>(san: &San, pos: &P) -> Option<UciMove> {
+    let m = san.to_move(pos).ok()?;
+    Some(m.to_uci(pos.castles().mode()))
+}
+
+/// Checks whether `san` could denote a legal move in some position of
+/// standard chess, without knowing the position.
+///
+/// Every [`San`] follows the syntax of SAN, but that still allows tokens
+/// that are never legal, like a pawn move to the last rank without a
+/// promotion, a promotion to a king, or a pawn capture from the same file.
+/// This is a cheap first pass to find corrupted movetext. Drops and null
+/// moves are considered well-formed, unless they drop a pawn on the first
+/// or last rank, or a king.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{board::san_is_well_formed, San};
+///
+/// let well_formed = |token: &[u8]| San::from_ascii(token).is_ok_and(|san| san_is_well_formed(&san));
+/// assert!(well_formed(b"Nbd7"));
+/// assert!(well_formed(b"exd8=Q#"));
+/// assert!(!well_formed(b"e8")); // no promotion
+/// assert!(!well_formed(b"Nf3=Q")); // only pawns promote
+/// assert!(!well_formed(b"exe5")); // pawns capture diagonally
+/// assert!(well_formed(b"e2e4") && well_formed(b"e7e5"));
+/// assert!(!well_formed(b"e3e5") && !well_formed(b"e5e3")); // not from the start
+/// assert!(!well_formed(b"e1e2") && !well_formed(b"e8e7")); // not from the back rank
+/// assert!(well_formed(b"e2e1=Q") && well_formed(b"d2xe1=N"));
+/// assert!(!well_formed(b"e9")); // not SAN at all
+/// ```
+pub fn san_is_well_formed(san: &San) -> bool {
+    match *san {
+        San::Normal {
+            role,
+            file,
+            rank,
+            capture,
+            to,
+            promotion,
+        } => {
+            let back_rank = to.rank() == Rank::First || to.rank() == Rank::Eighth;
+            if file == Some(to.file()) && rank == Some(to.rank()) {
+                return false;
+            }
+            match promotion {
+                Some(Role::Pawn | Role::King) => return false,
+                Some(_) if role != Role::Pawn || !back_rank => return false,
+                None if role == Role::Pawn && back_rank => return false,
+                _ => (),
+            }
+            if role != Role::Pawn {
+                return true;
+            }
+            // Pawns never stand on the back ranks, move one rank forward, or
+            // two from their starting rank, and capture diagonally.
+            let rank_ok = rank.is_none_or(|rank| {
+                (rank != Rank::First && rank != Rank::Eighth && rank.distance(to.rank()) == 1)
+                    || (!capture
+                        && matches!(
+                            (rank, to.rank()),
+                            (Rank::Second, Rank::Fourth) | (Rank::Seventh, Rank::Fifth)
+                        ))
+            });
+            let file_ok = if capture {
+                file.is_some_and(|file| file.distance(to.file()) == 1)
+            } else {
+                file.is_none_or(|file| file == to.file())
+            };
+            file_ok && rank_ok
+        }
+        San::Put { role, to } => match role {
+            Role::King => false,
+            Role::Pawn => to.rank() != Rank::First && to.rank() != Rank::Eighth,
+            _ => true,
+        },
+        San::Castle(_) | San::Null => true,
+    }
+}
+
+/// Tracks the position while replaying a game, including its variations.
+///
+/// After an illegal or ambiguous move, the rest of the line is not
+/// replayed, but the lines around it are.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Replay {
+    /// Position in the current line, or `None` if it could not be followed.
+    pos: Option<Chess>,
+    /// Position before the last move of the current line, where a
+    /// variation would start.
+    before: Option<Chess>,
+    /// The `pos` and `before` of the lines containing the current
+    /// variation.
+    stack: Vec<(Option<Chess>, Option<Chess>)>,
+}
+
+impl Replay {
+    /// Starts over from the given position.
+    pub fn reset(&mut self, pos: Option<Chess>) {
+        self.pos = pos;
+        self.before = None;
+        self.stack.clear();
+    }
+
+    /// The current position, if the line could be followed so far.
+    pub fn position(&self) -> Option<&Chess> {
+        self.pos.as_ref()
+    }
+
+    /// Plays a move, returning it if it is legal.
+    pub fn play(&mut self, san: &San) -> Option<Move> {
+        let pos = self.pos.as_mut()?;
+        match san.to_move(pos) {
+            Ok(m) => {
+                self.before = Some(pos.clone());
+                pos.play_unchecked(&m);
+                Some(m)
+            }
+            Err(_) => {
+                self.pos = None;
+                self.before = None;
+                None
+            }
+        }
+    }
+
+    /// Enters a variation, which is an alternative to the last move.
+    pub fn begin_variation(&mut self) {
+        let pos = self.before.clone();
+        self.stack
+            .push((std::mem::replace(&mut self.pos, pos), self.before.take()));
+    }
+
+    /// Returns to the line containing the variation.
+    pub fn end_variation(&mut self) {
+        if let Some((pos, before)) = self.stack.pop() {
+            self.pos = pos;
+            self.before = before;
+        }
+    }
+}
+
+/// Rewrites moves to canonical SAN before passing them on to another
+/// visitor.
+///
+/// Replays the game, to find the shortest disambiguation and the correct
+/// check and checkmate suffixes of each move. The starting position is
+/// taken from the `FEN` header, if any. Moves that are illegal in the
+/// replayed position, and all following moves of the same line, are passed
+/// on unchanged.
+///
+/// Use [`SanNormalizer::set_suffixes_only()`] to correct only the suffixes,
+/// keeping the moves as written.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, PgnWriter, SanNormalizer};
+///
+/// let pgn = b"1. e4 e5 2. Ng1f3 Nc6 3. Bf1c4 Nf6 4. Ng5 d5 5. exd5 Nxd5 6. Ng5xf7 Kxf7 7. Qf3 *";
+///
+/// let mut normalizer = SanNormalizer::new(PgnWriter::new(Vec::new()));
+/// BufferedReader::new_cursor(&pgn[..]).read_all(&mut normalizer)?;
+///
+/// assert_eq!(
+///     normalizer.into_inner().into_inner(),
+///     b"1. e4 e5 2. Nf3 Nc6 3. Bc4 Nf6 4. Ng5 d5 5. exd5 Nxd5 6. Nxf7 Kxf7 7. Qf3+ *\n\n"
+/// );
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct SanNormalizer<V> {
+    inner: V,
+    replay: Replay,
+    suffixes_only: bool,
+}
+
+impl<V> SanNormalizer<V> {
+    pub fn new(inner: V) -> SanNormalizer<V> {
+        SanNormalizer {
+            inner,
+            replay: Replay::default(),
+            suffixes_only: false,
+        }
+    }
+
+    /// Only corrects the check and checkmate suffixes, adding missing ones
+    /// and removing spurious ones. Disambiguations are kept as written.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, PgnWriter, SanNormalizer};
+    ///
+    /// let pgn = b"1. e4+ e5 2. Bf1c4 Nc6 3. Qh5 Nf6 4. Qxf7 *";
+    ///
+    /// let mut normalizer = SanNormalizer::new(PgnWriter::new(Vec::new()));
+    /// normalizer.set_suffixes_only(true);
+    /// BufferedReader::new_cursor(&pgn[..]).read_all(&mut normalizer)?;
+    ///
+    /// assert_eq!(
+    ///     normalizer.into_inner().into_inner(),
+    ///     b"1. e4 e5 2. Bf1c4 Nc6 3. Qh5 Nf6 4. Qxf7# *\n\n"
+    /// );
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_suffixes_only(&mut self, suffixes_only: bool) {
+        self.suffixes_only = suffixes_only;
+    }
+
+    /// Returns the wrapped visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+impl<V: Visitor> Visitor for SanNormalizer<V> {
+    type Result = V::Result;
+
+    fn begin_game(&mut self) {
+        self.replay.reset(Some(Chess::default()));
+        self.inner.begin_game();
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.inner.game_index(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.inner.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.inner.header(key, value);
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.replay.reset(position_from_fen(fen));
+        self.inner.setup(fen);
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        self.inner.raw_headers(headers);
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.inner.end_headers()
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        self.inner.move_number(number, black_to_move);
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        let before = self.replay.position().cloned();
+        let san_plus = match (self.replay.play(&san_plus.san), before) {
+            (Some(_), Some(_)) if self.suffixes_only => SanPlus {
+                san: san_plus.san,
+                suffix: self.replay.position().and_then(Suffix::from_position),
+            },
+            (Some(m), Some(before)) => SanPlus::from_move(before, &m),
+            _ => san_plus,
+        };
+        self.inner.san(san_plus);
+    }
+
+    fn null_move(&mut self) {
+        self.replay.play(&San::Null);
+        self.inner.null_move();
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        self.inner.unknown_token(token);
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        self.inner.garbage(token);
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.inner.nag(nag);
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        self.inner.comment(comment);
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        self.inner.pre_game_comment(comment);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        let skip = self.inner.begin_variation();
+        // Skipped variations still end with end_variation().
+        self.replay.begin_variation();
+        skip
+    }
+
+    fn end_variation(&mut self) {
+        self.replay.end_variation();
+        self.inner.end_variation();
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.inner.outcome(outcome);
+    }
+
+    fn escape(&mut self, line: &[u8]) {
+        self.inner.escape(line);
+    }
+
+    fn truncated(&mut self) {
+        self.inner.truncated();
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        self.inner.raw_movetext(movetext);
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        self.inner.warning(error);
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.inner.recovered(error);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.inner.end_game()
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.inner.abort()
+    }
+}
+
+/// A position in a variation that also occurs in the mainline.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Transposition {
+    /// Index of the variation, counting the `(` of the game in order.
+    pub variation: usize,
+    /// Number of plies from the start of the game to the position, following
+    /// the variation.
+    pub variation_ply: usize,
+    /// Number of mainline plies to the same position.
+    pub mainline_ply: usize,
+}
+
+/// A visitor that finds variations transposing into the mainline.
+///
+/// Replays the game, including all variations, and compares the positions
+/// by their Zobrist hash. Positions are the same if the pieces, the side to
+/// move, castling rights and legal en passant squares are the same. A
+/// variation may transpose into an earlier or a later position of the
+/// mainline. Only the first transposition of each variation is reported,
+/// in the order they occur in the movetext.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, Transposition, Transpositions};
+///
+/// let pgn = b"1. Nf3 d5 (1... Nf6 2. d4 d5) 2. d4 (2. g3) 2... Nf6 *";
+///
+/// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+/// let transpositions = reader.read_game(&mut Transpositions::new())?;
+/// assert_eq!(
+///     transpositions,
+///     Some(vec![Transposition {
+///         variation: 0,
+///         variation_ply: 4,
+///         mainline_ply: 4,
+///     }])
+/// );
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Transpositions {
+    replay: Replay,
+    /// Current variation, or `None` in the mainline.
+    variation: Option<usize>,
+    /// Number of plies to the current position.
+    ply: usize,
+    /// The `variation` and `ply` of the lines containing the current
+    /// variation.
+    stack: Vec<(Option<usize>, usize)>,
+    /// Number of variations so far.
+    variations: usize,
+    /// First mainline ply of each position.
+    mainline: HashMap<Zobrist64, usize>,
+    /// Positions reached in variations, with `variation` and `ply`.
+    candidates: Vec<(Zobrist64, usize, usize)>,
+}
+
+impl Transpositions {
+    pub fn new() -> Transpositions {
+        Transpositions::default()
+    }
+
+    fn record(&mut self) {
+        let Some(pos) = self.replay.position() else {
+            return;
+        };
+        let hash = pos.zobrist_hash(EnPassantMode::Legal);
+        match self.variation {
+            None => {
+                self.mainline.entry(hash).or_insert(self.ply);
+            }
+            Some(variation) => self.candidates.push((hash, variation, self.ply)),
+        }
+    }
+}
+
+impl Visitor for Transpositions {
+    type Result = Vec<Transposition>;
+
+    fn begin_game(&mut self) {
+        self.replay.reset(Some(Chess::default()));
+        self.variation = None;
+        self.ply = 0;
+        self.stack.clear();
+        self.variations = 0;
+        self.mainline.clear();
+        self.candidates.clear();
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.replay.reset(position_from_fen(fen));
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.record();
+        Skip(false)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.ply += 1;
+        if self.replay.play(&san_plus.san).is_some() {
+            self.record();
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.stack.push((self.variation, self.ply));
+        self.variation = Some(self.variations);
+        self.variations += 1;
+        self.ply = self.ply.saturating_sub(1);
+        self.replay.begin_variation();
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        if let Some((variation, ply)) = self.stack.pop() {
+            self.variation = variation;
+            self.ply = ply;
+        }
+        self.replay.end_variation();
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        let mut found = HashSet::new();
+        let mut transpositions = Vec::new();
+        for &(hash, variation, variation_ply) in &self.candidates {
+            if let Some(&mainline_ply) = self.mainline.get(&hash) {
+                if found.insert(variation) {
+                    transpositions.push(Transposition {
+                        variation,
+                        variation_ply,
+                        mainline_ply,
+                    });
+                }
+            }
+        }
+        transpositions
+    }
+}
+
+/// The pieces each side captured in a game, as found by [`Captures`].
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct CapturedPieces {
+    /// Black pieces captured by White, in the order of the captures.
+    pub white_captured: Vec<Role>,
+    /// White pieces captured by Black, in the order of the captures.
+    pub black_captured: Vec<Role>,
+}
+
+/// A visitor that collects the pieces each side captured in the mainline.
+///
+/// Replays the game from the starting position, which is taken from the
+/// `FEN` header, if any. Captures after an illegal move are not found.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, Captures, Role};
+///
+/// let pgn = b"1. e4 d5 2. exd5 Qxd5 3. Nc3 Qxa2 4. Rxa2 *";
+///
+/// let captured = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut Captures::new())?
+///     .unwrap();
+/// assert_eq!(captured.white_captured, [Role::Pawn, Role::Queen]);
+/// assert_eq!(captured.black_captured, [Role::Pawn, Role::Pawn]);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Captures {
+    replay: Replay,
+    captured: CapturedPieces,
+}
+
+impl Captures {
+    pub fn new() -> Captures {
+        Captures::default()
+    }
+}
+
+impl Visitor for Captures {
+    type Result = CapturedPieces;
+
+    fn begin_game(&mut self) {
+        self.replay.reset(Some(Chess::default()));
+        self.captured = CapturedPieces::default();
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.replay.reset(position_from_fen(fen));
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        let Some(turn) = self.replay.position().map(Position::turn) else {
+            return;
+        };
+        // En passant captures report a pawn, too.
+        if let Some(role) = self.replay.play(&san_plus.san).and_then(|m| m.capture()) {
+            match turn {
+                Color::White => self.captured.white_captured.push(role),
+                Color::Black => self.captured.black_captured.push(role),
+            }
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(&mut self.captured)
+    }
+}
+
+/// A visitor that replays the mainline of each game up to a given ply and
+/// produces the FEN of the position reached, for example to extract puzzle
+/// positions.
+///
+/// Ply 0 is the starting position, which is taken from the `FEN` header, if
+/// any. Produces `None` if the game is shorter, or if a move before the
+/// target ply is illegal. Later moves and all variations are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{board::FenAtPly, BufferedReader};
+///
+/// let pgn = b"1. e4 e5 (1... c5) 2. Nf3 Nc6 *\n\n1. d4 *";
+///
+/// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+/// let mut visitor = FenAtPly::new(3);
+/// assert_eq!(
+///     reader.read_game(&mut visitor)?.flatten().as_deref(),
+///     Some("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+/// );
+/// assert_eq!(reader.read_game(&mut visitor)?, Some(None));
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct FenAtPly {
+    replay: Replay,
+    target: usize,
+    ply: usize,
+    fen: Option<String>,
+}
+
+impl FenAtPly {
+    /// Creates a visitor that stops after `ply` half-moves.
+    pub fn new(ply: usize) -> FenAtPly {
+        FenAtPly {
+            replay: Replay::default(),
+            target: ply,
+            ply: 0,
+            fen: None,
+        }
+    }
+
+    fn record(&mut self) {
+        if self.ply == self.target {
+            self.fen = self
+                .replay
+                .position()
+                .map(|pos| Fen::from_position(pos.clone(), EnPassantMode::Legal).to_string());
+        }
+    }
+}
+
+impl Visitor for FenAtPly {
+    type Result = Option<String>;
+
+    fn begin_game(&mut self) {
+        self.replay.reset(Some(Chess::default()));
+        self.ply = 0;
+        self.fen = None;
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.replay.reset(position_from_fen(fen));
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.record();
+        Skip(false)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        if self.ply < self.target {
+            self.ply += 1;
+            self.replay.play(&san_plus.san);
+            self.record();
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.fen.take()
+    }
+}
+
+/// Receives the mainline moves of each game, after a [`BoardVisitor`]
+/// checked them against the position.
+pub trait MoveVisitor {
+    /// Value produced after each game.
+    type Result;
+
+    /// Called at the start of the game.
+    fn begin_game(&mut self) {}
+
+    /// Called after the headers, with the variant from the `Variant`
+    /// header, or [`Variant::Chess960`] if the `FEN` header has Chess960
+    /// castling rights.
+    fn variant(&mut self, _variant: Variant) {}
+
+    /// Called for each legal move, with the position before the move.
+    fn legal_move(&mut self, _pos: &Chess, _m: &Move) {}
+
+    /// Called for the first move that is not legal in the position, or
+    /// ambiguous, or a null move. The following moves of the game cannot
+    /// be replayed and are ignored.
+    fn illegal_move(&mut self, _san: San) {}
+
+    /// Called after the game.
+    fn end_game(&mut self) -> Self::Result;
+}
+
+/// A visitor that replays the mainline of each game, and passes the legal
+/// moves on to a [`MoveVisitor`], for example to convert them to UCI or to
+/// find illegal moves.
+///
+/// The starting position is taken from the `FEN` header, if any, and is
+/// the standard starting position otherwise. If the `FEN` header is
+/// invalid, no moves are passed on.
+///
+/// Games of Chess960 are replayed in [`CastlingMode::Chess960`], so that
+/// castling moves are to the square of the rook. No moves are passed on
+/// for other variants, which follow different rules, but the
+/// [`variant()`](MoveVisitor::variant) is, so that the moves can be
+/// replayed with the rules of `shakmaty` (see
+/// [`Variant::to_shakmaty()`](crate::headers::Variant)).
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{
+///     board::{BoardVisitor, MoveVisitor},
+///     BufferedReader, San,
+/// };
+/// use shakmaty::{CastlingMode, Chess, Move};
+///
+/// #[derive(Default)]
+/// struct Uci {
+///     moves: Vec<String>,
+///     illegal: Option<San>,
+/// }
+///
+/// impl MoveVisitor for Uci {
+///     type Result = (Vec<String>, Option<San>);
+///
+///     fn legal_move(&mut self, _pos: &Chess, m: &Move) {
+///         self.moves.push(m.to_uci(CastlingMode::Standard).to_string());
+///     }
+///
+///     fn illegal_move(&mut self, san: San) {
+///         self.illegal = Some(san);
+///     }
+///
+///     fn end_game(&mut self) -> Self::Result {
+///         (std::mem::take(&mut self.moves), self.illegal.take())
+///     }
+/// }
+///
+/// let pgn = b"1. e4 e5 2. Nf3 Nf6 3. Nxe5 Qh4 4. Qe2 *";
+///
+/// let (moves, illegal) = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut BoardVisitor::new(Uci::default()))?
+///     .unwrap();
+/// assert_eq!(moves, ["e2e4", "e7e5", "g1f3", "g8f6", "f3e5"]);
+/// assert_eq!(illegal.map(|san| san.to_string()), Some("Qh4".to_owned()));
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct BoardVisitor<V> {
+    inner: V,
+    replay: Replay,
+    variant: Variant,
+}
+
+impl<V> BoardVisitor<V> {
+    pub fn new(inner: V) -> BoardVisitor<V> {
+        BoardVisitor {
+            inner,
+            replay: Replay::default(),
+            variant: Variant::Standard,
+        }
+    }
+
+    /// Gets a reference to the wrapped visitor.
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    /// Returns the wrapped visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+impl<V: MoveVisitor> Visitor for BoardVisitor<V> {
+    type Result = V::Result;
+
+    fn begin_game(&mut self) {
+        self.replay.reset(Some(Chess::default()));
+        self.variant = Variant::Standard;
+        self.inner.begin_game();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if key == b"Variant" {
+            self.variant = Variant::from_ascii(value.as_bytes());
+            let mode = self.variant.castling_mode();
+            self.replay
+                .reset(Chess::from_setup(Setup::default(), mode).ok());
+        }
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.replay
+            .reset(position_from_fen_in(fen, self.variant.castling_mode()));
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        match self.variant {
+            Variant::Standard => {
+                if self
+                    .replay
+                    .position()
+                    .is_some_and(|pos| pos.castles().mode() == CastlingMode::Chess960)
+                {
+                    self.variant = Variant::Chess960;
+                }
+            }
+            Variant::Chess960 => {}
+            _ => self.replay.reset(None),
+        }
+        self.inner.variant(self.variant);
+        Skip(false)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        let Some(pos) = self.replay.position().cloned() else {
+            return;
+        };
+        match self.replay.play(&san_plus.san) {
+            Some(m) => self.inner.legal_move(&pos, &m),
+            None => self.inner.illegal_move(san_plus.san),
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.inner.end_game()
+    }
+}
+
+/// Encodes a move in two bytes, as documented for [`MoveEncoder`].
+fn encode_move(m: &Move) -> [u8; 2] {
+    let from = m.from().map_or(0, u32::from);
+    let to = u32::from(m.to());
+    let promotion = m.promotion().map_or(0, u32::from);
+    ((from | to << 6 | promotion << 12) as u16).to_le_bytes()
+}
+
+/// A visitor that encodes the mainline of each game as a compact byte
+/// stream, for example to feed games into a training pipeline.
+///
+/// Each move takes two bytes, forming a little-endian `u16`:
+///
+/// | Bits  | Content                                                       |
+/// | ----- | ------------------------------------------------------------- |
+/// | 0-5   | Origin square, from 0 for a1, 1 for b1, up to 63 for h8       |
+/// | 6-11  | Target square. For castling, the square of the rook.          |
+/// | 12-14 | Promotion: 0 for none, 2 to 5 for knight, bishop, rook, queen |
+/// | 15    | Always 0                                                      |
+///
+/// Moves are encoded after replaying them from the starting position,
+/// which is taken from the `FEN` header, if any. The encoding stops before
+/// the first null move or illegal move, since the following moves cannot
+/// be replayed. Use [`decode_moves()`] to get the moves back.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{board::decode_moves, BufferedReader, MoveEncoder};
+/// use shakmaty::Chess;
+///
+/// let pgn = b"1. e4 e5 2. Nf3 *";
+/// let encoded = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut MoveEncoder::new())?
+///     .unwrap();
+/// assert_eq!(encoded, [0x0c, 0x07, 0x34, 0x09, 0x46, 0x05]);
+///
+/// let moves = decode_moves(Chess::default(), &encoded)?;
+/// assert_eq!(moves.len(), 3);
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MoveEncoder {
+    replay: Replay,
+    encoded: Vec<u8>,
+}
+
+impl MoveEncoder {
+    pub fn new() -> MoveEncoder {
+        MoveEncoder::default()
+    }
+}
+
+impl Visitor for MoveEncoder {
+    type Result = Vec<u8>;
+
+    fn begin_game(&mut self) {
+        self.replay.reset(Some(Chess::default()));
+        self.encoded.clear();
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.replay.reset(position_from_fen(fen));
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        if san_plus.san == San::Null {
+            self.replay.reset(None);
+        } else if let Some(m) = self.replay.play(&san_plus.san) {
+            self.encoded.extend_from_slice(&encode_move(&m));
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        Skip(true) // stay in the mainline
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        std::mem::take(&mut self.encoded)
+    }
+}
+
+/// Decodes moves encoded by [`MoveEncoder`], replaying them from `pos`.
+///
+/// # Errors
+///
+/// Returns [`InvalidMoveEncoding`] if the input has an odd length, or
+/// encodes a move that is not legal in the replayed position.
+pub fn decode_moves(mut pos: Chess, encoded: &[u8]) -> Result<Vec<Move>, InvalidMoveEncoding> {
+    if !encoded.len().is_multiple_of(2) {
+        return Err(InvalidMoveEncoding { _priv: () });
+    }
+
+    let mut moves = Vec::with_capacity(encoded.len() / 2);
+    for code in encoded.chunks_exact(2) {
+        let m = pos
+            .legal_moves()
+            .into_iter()
+            .find(|m| encode_move(m) == code)
+            .ok_or(InvalidMoveEncoding { _priv: () })?;
+        pos.play_unchecked(&m);
+        moves.push(m);
+    }
+    Ok(moves)
+}
+
+/// Error when decoding moves with [`decode_moves()`].
+#[derive(Clone, Eq, PartialEq)]
+pub struct InvalidMoveEncoding {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidMoveEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidMoveEncoding").finish()
+    }
+}
+
+impl fmt::Display for InvalidMoveEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("invalid move encoding")
+    }
+}
+
+impl Error for InvalidMoveEncoding {}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::{reader::BufferedReader, writer::PgnWriter};
+
+    fn normalize(pgn: &[u8]) -> io::Result<String> {
+        let mut normalizer = SanNormalizer::new(PgnWriter::new(Vec::new()));
+        BufferedReader::new_cursor(pgn).read_all(&mut normalizer)?;
+        Ok(String::from_utf8(normalizer.into_inner().into_inner()).unwrap())
+    }
+
+    #[test]
+    fn test_is_standard_start() {
+        assert!(is_standard_start(
+            b"  rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -  0\t1 "
+        ));
+        assert!(!is_standard_start(
+            b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0"
+        ));
+        assert!(!is_standard_start(
+            b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 x"
+        ));
+        assert!(!is_standard_start(
+            b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -0 1"
+        ));
+        assert!(!is_standard_start(
+            b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1"
+        ));
+        assert!(!is_standard_start(b""));
+
+        // The shortcut sets up the same position as parsing.
+        let fen = b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let parsed: Chess = Fen::from_ascii(fen)
+            .unwrap()
+            .into_position(CastlingMode::Standard)
+            .unwrap();
+        assert_eq!(position_from_fen(fen), Some(parsed));
+        assert_eq!(position_from_fen(fen), Some(Chess::default()));
+    }
+
+    #[test]
+    fn test_san_matches() {
+        fn pos(fen: &str) -> Chess {
+            position_from_fen(fen.as_bytes()).unwrap()
+        }
+
+        fn san(s: &str) -> San {
+            s.parse().unwrap()
+        }
+
+        // Rooks on a1 and f1 both reach d1, but only one reaches a8.
+        let rooks = pos("4k3/8/8/8/8/8/8/R4RK1 w - - 0 1");
+        let rad1 = san("Rad1").to_move(&rooks).unwrap();
+        assert!(san_matches(&san("Rad1"), &rad1, &rooks));
+        assert!(san_matches(&san("Ra1d1"), &rad1, &rooks));
+        assert!(!san_matches(&san("Rd1"), &rad1, &rooks));
+        assert!(!san_matches(&san("Rfd1"), &rad1, &rooks));
+        let ra8 = san("Ra8").to_move(&rooks).unwrap();
+        assert!(san_matches(&san("Ra8+"), &ra8, &rooks));
+        assert!(!san_matches(&san("Ra7"), &ra8, &rooks));
+
+        // Promotion pieces and castling.
+        let promotion = pos("8/P3k3/8/8/8/8/8/4K2R w K - 0 1");
+        let queen = san("a8=Q").to_move(&promotion).unwrap();
+        assert!(san_matches(&san("a8=Q"), &queen, &promotion));
+        assert!(!san_matches(&san("a8=N"), &queen, &promotion));
+        assert!(!san_matches(&san("a8"), &queen, &promotion));
+        let castle = san("O-O").to_move(&promotion).unwrap();
+        assert!(san_matches(&san("O-O"), &castle, &promotion));
+        assert!(!san_matches(&san("Kg1"), &castle, &promotion));
+    }
+
+    #[test]
+    fn test_san_normalizer() -> io::Result<()> {
+        // The knights on c6 and d5 can both go to b4, so Ncb4 must keep its
+        // disambiguation. Ng8f6 and Nbxc2 can drop theirs.
+        assert_eq!(
+            normalize(b"1. e4 e5 2. Nf3 Nc6 3. d4 Ng8f6 4. Bg5 d5 5. exd5 Nxd5 6. Nc3 Ncb4 7. a3+ Nbxc2 *")?,
+            "1. e4 e5 2. Nf3 Nc6 3. d4 Nf6 4. Bg5 d5 5. exd5 Nxd5 6. Nc3 Ncb4 7. a3 Nxc2+ *\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_san_normalizer_suffixes_only() -> io::Result<()> {
+        fn fix_suffixes(pgn: &[u8]) -> io::Result<String> {
+            let mut normalizer = SanNormalizer::new(PgnWriter::new(Vec::new()));
+            normalizer.set_suffixes_only(true);
+            BufferedReader::new_cursor(pgn).read_all(&mut normalizer)?;
+            Ok(String::from_utf8(normalizer.into_inner().into_inner()).unwrap())
+        }
+
+        // Spurious check, missing check, check instead of mate, and a
+        // disambiguation that is kept. The illegal move and the rest of its
+        // line stay as they are.
+        assert_eq!(
+            fix_suffixes(b"1. f3+ e5 2. g4 Qh4+ *\n\n1. e4 e5 2. Ng1f3 d6 3. Bb5 Nd7 4. Bxd7 Kxd7# 5. Ke3+ *")?,
+            "1. f3 e5 2. g4 Qh4# *\n\n1. e4 e5 2. Ng1f3 d6 3. Bb5+ Nd7 4. Bxd7+ Kxd7 5. Ke3+ *\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_san_normalizer_variations() -> io::Result<()> {
+        assert_eq!(
+            normalize(b"[FEN \"4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1\"]\n\n1. Rh1h8 (1. Ra1a8 Kd7 (1... Ke7 2. Rh1h7) 2. Rh1h7) 1... Kd7 2. Ra1a7 2... Kc6 3. Rb1 Kb6 4. Rh1h6+ *")?,
+            "[FEN \"4k3/8/8/8/8/8/8/R3K2R w KQ - 0 1\"]\n\
+             \n\
+             1. Rh8+ (1. Ra8+ Kd7 (1... Ke7 2. Rh7+) 2. Rh7+) 1... Kd7 2. Ra7+ Kc6 3. Rb1 Kb6\n\
+             4. Rh1h6+ *\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_transpositions() -> io::Result<()> {
+        // The first variation returns to the position after 1... e5. The
+        // second variation reaches the position after 4. Nc3. The third
+        // never meets the mainline, and neither does a king walk that loses
+        // the castling rights.
+        let pgn = b"1. e4 e5 2. Nf3 (2. Nc3 Nc6 3. Nb1 Nb8) (2. Ke2 Ke7 3. Ke1 Ke8) 2... Nc6 \
+                    3. Bc4 (3. Nc3 Nf6 (3... Bc5) 4. Bc4) (3. d4 exd4) 3... Nf6 4. Nc3 *";
+        let transpositions = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut Transpositions::new())?
+            .unwrap();
+        assert_eq!(
+            transpositions,
+            [
+                Transposition {
+                    variation: 0,
+                    variation_ply: 6,
+                    mainline_ply: 2,
+                },
+                Transposition {
+                    variation: 2,
+                    variation_ply: 7,
+                    mainline_ply: 7,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_board_visitor() -> io::Result<()> {
+        #[derive(Default)]
+        struct Moves {
+            legal: Vec<(Color, Move)>,
+            illegal: Vec<San>,
+        }
+
+        impl MoveVisitor for Moves {
+            type Result = (Vec<(Color, Move)>, Vec<San>);
+
+            fn legal_move(&mut self, pos: &Chess, m: &Move) {
+                self.legal.push((pos.turn(), m.clone()));
+            }
+
+            fn illegal_move(&mut self, san: San) {
+                self.illegal.push(san);
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                (
+                    std::mem::take(&mut self.legal),
+                    std::mem::take(&mut self.illegal),
+                )
+            }
+        }
+
+        let pgn =
+            b"[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n\n1... Kd7 (1... Kf7) 2. e4 Ke6 *\n\n\
+                    [SetUp \"1\"]\n[FEN \"garbage\"]\n\n1. e4 *\n\n\
+                    1. e4 Nf6 2. Nc3 Nc6 3. Nc3 e5 4. Nd5 *\n\n\
+                    1. e4 -- 2. d4 *";
+        let games = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut BoardVisitor::new(Moves::default()))
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(games.len(), 4);
+
+        let (legal, illegal) = &games[0];
+        assert_eq!(
+            legal.iter().map(|(turn, _)| *turn).collect::<Vec<_>>(),
+            [Color::Black, Color::White, Color::Black]
+        );
+        assert_eq!(legal[1].1.to(), shakmaty::Square::E4);
+        assert!(illegal.is_empty());
+
+        assert!(games[1].0.is_empty() && games[1].1.is_empty());
+
+        // The knight on b1 already went to c3, so the second Nc3 is illegal.
+        let (legal, illegal) = &games[2];
+        assert_eq!(legal.len(), 4);
+        assert_eq!(illegal, &["Nc3".parse::<San>().unwrap()]);
+
+        assert_eq!(games[3].0.len(), 1);
+        assert_eq!(games[3].1, [San::Null]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_board_visitor_variant() -> io::Result<()> {
+        #[derive(Default)]
+        struct Uci {
+            variant: Option<Variant>,
+            moves: Vec<String>,
+        }
+
+        impl MoveVisitor for Uci {
+            type Result = (Option<Variant>, Vec<String>);
+
+            fn variant(&mut self, variant: Variant) {
+                self.variant = Some(variant);
+            }
+
+            fn legal_move(&mut self, pos: &Chess, m: &Move) {
+                self.moves.push(m.to_uci(pos.castles().mode()).to_string());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                (self.variant.take(), std::mem::take(&mut self.moves))
+            }
+        }
+
+        let pgn = b"[Variant \"Chess960\"]\n\n1. e4 e5 2. Nf3 Nf6 3. Bc4 Bc5 4. O-O *\n\n\
+                    [FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O *\n\n\
+                    [Variant \"Chess960\"]\n[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n\n1. O-O *\n\n\
+                    [FEN \"4k3/8/8/8/8/8/8/1R2K3 w B - 0 1\"]\n\n1. O-O-O *\n\n\
+                    [Variant \"Atomic\"]\n\n1. e4 *";
+        let games = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut BoardVisitor::new(Uci::default()))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        assert_eq!(games[0].0, Some(Variant::Chess960));
+        assert_eq!(games[0].1.last().map(String::as_str), Some("e1h1"));
+        assert_eq!(games[1], (Some(Variant::Standard), vec!["e1g1".to_owned()]));
+        assert_eq!(games[2], (Some(Variant::Chess960), vec!["e1h1".to_owned()]));
+        assert_eq!(games[3], (Some(Variant::Chess960), vec!["e1b1".to_owned()]));
+        assert_eq!(games[4], (Some(Variant::Atomic), Vec::new()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_encoder() -> io::Result<()> {
+        let pgn = b"[FEN \"r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1\"]\n\n\
+                    1. exd6 (1. O-O) 1... O-O 2. bxa8=N Rxa8 3. O-O-O -- 4. Kb1 *";
+        let encoded = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut MoveEncoder::new())?
+            .unwrap();
+        // Stops at the null move.
+        assert_eq!(encoded.len(), 10);
+
+        let mut pos = position_from_fen(b"r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1").unwrap();
+        let moves = decode_moves(pos.clone(), &encoded).unwrap();
+        let sans: Vec<String> = moves
+            .iter()
+            .map(|m| SanPlus::from_move_and_play_unchecked(&mut pos, m).to_string())
+            .collect();
+        assert_eq!(sans, ["exd6", "O-O", "bxa8=N", "Rxa8", "O-O-O"]);
+
+        assert!(decode_moves(Chess::default(), &encoded[..1]).is_err());
+        assert!(decode_moves(Chess::default(), &encoded).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_captures() -> io::Result<()> {
+        let pgn =
+            b"1. e4 Nf6 2. e5 d5 3. exd6 (3. exf6 exf6) 3... cxd6 4. Bb5+ Bd7 5. Bxd7+ Nbxd7 \
+                    6. Qf3 Ne5 7. Qxb7 Nd3+ 8. cxd3 Rb8 9. Qxb8+ Qxb8 *\n\n\
+                    [FEN \"4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1\"]\n\n1. exd6 Kd7 2. Kd2 Kxd6 *";
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let captured = reader.read_game(&mut Captures::new())?.unwrap();
+        assert_eq!(
+            captured,
+            CapturedPieces {
+                white_captured: vec![
+                    Role::Pawn,
+                    Role::Bishop,
+                    Role::Pawn,
+                    Role::Knight,
+                    Role::Rook
+                ],
+                black_captured: vec![Role::Pawn, Role::Bishop, Role::Queen],
+            }
+        );
+
+        let captured = reader.read_game(&mut Captures::new())?.unwrap();
+        assert_eq!(captured.white_captured, [Role::Pawn]);
+        assert_eq!(captured.black_captured, [Role::Pawn]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_san_is_well_formed() {
+        let well_formed = |token: &str| san_is_well_formed(&token.parse::<San>().unwrap());
+        for token in [
+            "e4", "exd5", "e7e5", "e8=Q", "axb1=N", "Nf3", "Nbd2", "R1e1", "Qh4e1", "Ke2", "O-O",
+            "O-O-O", "--", "N@f3", "@e4", "e2e1=Q", "d2xe1=R",
+        ] {
+            assert!(well_formed(token), "{token}");
+        }
+        for token in [
+            "e8", "a1", "e7=Q", "e8=K", "e8=P", "Bf1=Q", "exe5", "xd5", "axc5", "e2e5", "e4e4",
+            "Ne4e4", "K@e2", "@e8", "e1e2", "e8e7", "d1xe2", "f8xe7", "a1a2",
+        ] {
+            assert!(!well_formed(token), "{token}");
+        }
+
+        // Random tokens from the alphabet of SAN never panic.
+        let alphabet = b"abcdefghi0123456789KQRBNPxX=+#@-O";
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for _ in 0..100_000 {
+            let mut token = Vec::new();
+            for _ in 0..(state % 8 + 1) {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                token.push(alphabet[(state % alphabet.len() as u64) as usize]);
+            }
+            if let Ok(san) = San::from_ascii(&token) {
+                let _ = san_is_well_formed(&san);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fen_at_ply() -> io::Result<()> {
+        const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let fen = "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1";
+        let pgn = format!(
+            "[FEN \"{fen}\"]\n\n1. exd6 Kd7 *\n\n\
+             1. e4 Ke7 2. d4 *\n\n\
+             1. e4 e5 2. Nf3 *"
+        );
+
+        let fens = |ply| {
+            BufferedReader::new_cursor(pgn.as_bytes())
+                .into_iter(&mut FenAtPly::new(ply))
+                .collect::<io::Result<Vec<_>>>()
+        };
+        assert_eq!(
+            fens(0)?,
+            [
+                Some(fen.to_owned()),
+                Some(START.to_owned()),
+                Some(START.to_owned()),
+            ]
+        );
+        assert_eq!(
+            fens(2)?,
+            [
+                Some("8/3k4/3P4/8/8/8/8/4K3 w - - 1 2".to_owned()),
+                None,
+                Some("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2".to_owned()),
+            ]
+        );
+        assert_eq!(fens(4)?, [None, None, None]);
+        Ok(())
+    }
+}
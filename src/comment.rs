@@ -0,0 +1,325 @@
+use std::time::Duration;
+
+use shakmaty::Square;
+
+use crate::headers::parse_hms;
+
+/// Finds the payload of an embedded command like `[%eval 0.17]` in a
+/// comment, given the name of the command without the `%`.
+pub(crate) fn find_command<'a>(comment: &'a [u8], name: &[u8]) -> Option<&'a [u8]> {
+    commands(comment, name).next()
+}
+
+/// Finds the payloads of all embedded commands with the given name, in
+/// order.
+fn commands<'a: 'n, 'n>(comment: &'a [u8], name: &'n [u8]) -> impl Iterator<Item = &'a [u8]> + 'n {
+    let mut rest = comment;
+    std::iter::from_fn(move || {
+        while let Some(start) = memchr::memchr(b'[', rest) {
+            rest = &rest[start + 1..];
+            let Some(after_name) = rest
+                .strip_prefix(b"%")
+                .and_then(|command| command.strip_prefix(name))
+            else {
+                continue;
+            };
+            if !after_name.first().is_some_and(u8::is_ascii_whitespace) {
+                continue;
+            }
+            let end = memchr::memchr(b']', after_name)?;
+            rest = &after_name[end + 1..];
+            return Some(after_name[..end].trim_ascii());
+        }
+        None
+    })
+}
+
+/// The time left on the clock of the player who just moved, from a
+/// `[%clk ...]` command.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Clock(pub Duration);
+
+impl Clock {
+    /// Finds and parses a `[%clk ...]` command in a comment, like
+    /// `[%clk 0:01:23]`, `[%clk 1:23]` or `[%clk 0:00:09.7]`. The comment
+    /// itself is not modified.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use pgn_reader::comment::Clock;
+    ///
+    /// let clock = Clock::parse(b" [%clk 0:00:09.7] time trouble ");
+    /// assert_eq!(clock, Some(Clock(Duration::from_millis(9700))));
+    /// assert_eq!(Clock::parse(b"[%clk 1:23]"), Some(Clock(Duration::from_secs(83))));
+    /// assert_eq!(Clock::parse(b"[%clk soon]"), None);
+    /// ```
+    pub fn parse(comment: &[u8]) -> Option<Clock> {
+        find_command(comment, b"clk").and_then(parse_hms).map(Clock)
+    }
+}
+
+/// The time spent on a move, from an `[%emt ...]` command.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Emt(pub Duration);
+
+impl Emt {
+    /// Finds and parses an `[%emt ...]` command in a comment, in the same
+    /// formats as [`Clock::parse()`]. A `[%clk ...]` command in the same
+    /// comment is left alone.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use pgn_reader::comment::{Clock, Emt};
+    ///
+    /// let comment = b"[%clk 0:09:51] [%emt 0:00:04]";
+    /// assert_eq!(Emt::parse(comment), Some(Emt(Duration::from_secs(4))));
+    /// assert_eq!(Clock::parse(comment), Some(Clock(Duration::from_secs(591))));
+    /// ```
+    pub fn parse(comment: &[u8]) -> Option<Emt> {
+        find_command(comment, b"emt").and_then(parse_hms).map(Emt)
+    }
+}
+
+/// An engine evaluation from White's point of view, as in the
+/// `[%eval ...]` commands of Lichess.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Eval {
+    /// Advantage in hundredths of a pawn, positive if White is better.
+    Centipawns(i32),
+    /// Mate in the given number of moves, negative if Black mates.
+    Mate(i32),
+}
+
+impl Eval {
+    /// Finds and parses an `[%eval ...]` command in a comment.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::comment::Eval;
+    ///
+    /// assert_eq!(Eval::parse(b"[%eval -4.37] [%clk 0:01:00]"), Some(Eval::Centipawns(-437)));
+    /// assert_eq!(Eval::parse(b"[%eval #-2]"), Some(Eval::Mate(-2)));
+    /// assert_eq!(Eval::parse(b"[%eval big]"), None);
+    /// ```
+    pub fn parse(comment: &[u8]) -> Option<Eval> {
+        find_command(comment, b"eval").and_then(Eval::from_ascii)
+    }
+
+    /// Parses the payload of an `[%eval ...]` command, like `-0.35` or
+    /// `#3`. A search depth after a comma, as in `0.17,23`, is ignored.
+    pub fn from_ascii(s: &[u8]) -> Option<Eval> {
+        let s = s.split(|&ch| ch == b',').next()?;
+        if let Some(mate) = s.strip_prefix(b"#") {
+            return btoi::btoi(mate).ok().map(Eval::Mate);
+        }
+        if s.is_empty()
+            || !s
+                .iter()
+                .all(|&ch| ch.is_ascii_digit() || matches!(ch, b'+' | b'-' | b'.'))
+        {
+            return None;
+        }
+        let pawns: f64 = std::str::from_utf8(s).ok()?.parse().ok()?;
+        let centipawns = (pawns * 100.0).round();
+        (centipawns.abs() <= f64::from(i32::MAX)).then_some(Eval::Centipawns(centipawns as i32))
+    }
+}
+
+/// The color of a [`Shape`], as in the drawing tools of Lichess.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ShapeColor {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+}
+
+impl ShapeColor {
+    fn from_ascii(ch: u8) -> Option<ShapeColor> {
+        Some(match ch {
+            b'G' => ShapeColor::Green,
+            b'R' => ShapeColor::Red,
+            b'Y' => ShapeColor::Yellow,
+            b'B' => ShapeColor::Blue,
+            _ => return None,
+        })
+    }
+}
+
+/// An arrow or a highlighted square drawn on the board.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Shape {
+    /// From a `[%cal ...]` command, like `Gd2d4`.
+    Arrow {
+        color: ShapeColor,
+        from: Square,
+        to: Square,
+    },
+    /// From a `[%csl ...]` command, like `Ye5`.
+    Circle { color: ShapeColor, square: Square },
+}
+
+/// The shapes drawn on the board in a comment.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct Shapes(pub Vec<Shape>);
+
+impl Shapes {
+    /// Finds and parses all `[%cal ...]` and `[%csl ...]` commands in a
+    /// comment. Entries are separated by commas. Entries that are not
+    /// valid are ignored. Circles come before arrows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::comment::{Shape, ShapeColor, Shapes};
+    /// use pgn_reader::Square;
+    ///
+    /// let Shapes(shapes) = Shapes::parse(b"[%csl Ye5] [%cal Gd2d4,Xa1a2,Re1h1]");
+    /// assert_eq!(shapes, [
+    ///     Shape::Circle { color: ShapeColor::Yellow, square: Square::E5 },
+    ///     Shape::Arrow { color: ShapeColor::Green, from: Square::D2, to: Square::D4 },
+    ///     Shape::Arrow { color: ShapeColor::Red, from: Square::E1, to: Square::H1 },
+    /// ]);
+    /// ```
+    pub fn parse(comment: &[u8]) -> Shapes {
+        let mut shapes = Vec::new();
+        for (name, num_squares) in [(&b"csl"[..], 1), (b"cal", 2)] {
+            for payload in commands(comment, name) {
+                shapes.extend(
+                    payload
+                        .split(|&ch| ch == b',')
+                        .filter_map(|entry| parse_shape(entry.trim_ascii(), num_squares)),
+                );
+            }
+        }
+        Shapes(shapes)
+    }
+}
+
+/// Parses an entry with a color and one or two squares, like `Ye5` or
+/// `Gd2d4`.
+fn parse_shape(entry: &[u8], num_squares: usize) -> Option<Shape> {
+    let (&color, squares) = entry.split_first()?;
+    let color = ShapeColor::from_ascii(color)?;
+    if squares.len() != num_squares * 2 {
+        return None;
+    }
+    let square = |i: usize| Square::from_ascii(&squares[i * 2..i * 2 + 2]).ok();
+    Some(if num_squares == 1 {
+        Shape::Circle {
+            color,
+            square: square(0)?,
+        }
+    } else {
+        Shape::Arrow {
+            color,
+            from: square(0)?,
+            to: square(1)?,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_command() {
+        let comment = b" [%evaluation 3] [%clk 0:01:00] [%eval  -0.35 ] text [%eval 1";
+        assert_eq!(find_command(comment, b"eval"), Some(&b"-0.35"[..]));
+        assert_eq!(find_command(comment, b"clk"), Some(&b"0:01:00"[..]));
+        assert_eq!(find_command(comment, b"emt"), None);
+        assert_eq!(find_command(b"[%eval 1", b"eval"), None);
+    }
+
+    #[test]
+    fn test_shapes() {
+        let arrow = |color, from, to| Shape::Arrow { color, from, to };
+        let circle = |color, square| Shape::Circle { color, square };
+
+        assert_eq!(
+            Shapes::parse(b"[%cal Gd2d4, Re1h1] text [%cal Bb1c3] [%csl Ye5,Ge5e6,Rh9]"),
+            Shapes(vec![
+                circle(ShapeColor::Yellow, Square::E5),
+                arrow(ShapeColor::Green, Square::D2, Square::D4),
+                arrow(ShapeColor::Red, Square::E1, Square::H1),
+                arrow(ShapeColor::Blue, Square::B1, Square::C3),
+            ])
+        );
+        assert_eq!(Shapes::parse(b"[%cal Gd2d4"), Shapes::default());
+        assert_eq!(Shapes::parse(b"[%call Gd2d4]"), Shapes::default());
+        assert_eq!(Shapes::parse(b"[%cal] [%cal ,]"), Shapes::default());
+    }
+
+    #[test]
+    fn test_clock() {
+        let secs = |secs| Some(Clock(Duration::from_secs(secs)));
+        assert_eq!(Clock::parse(b"[%clk 0:01:23]"), secs(83));
+        assert_eq!(Clock::parse(b"[%eval 0.2] [%clk 1:00:00] book"), secs(3600));
+        assert_eq!(Clock::parse(b"[%clk 12:03]"), secs(723));
+        assert_eq!(
+            Clock::parse(b"[%clk 0:00:00.25]"),
+            Some(Clock(Duration::from_millis(250)))
+        );
+        assert_eq!(Clock::parse(b"[%clk]"), None);
+        assert_eq!(Clock::parse(b"[%clk 0:61]"), None);
+        assert_eq!(Clock::parse(b"[%clk 1:00:00"), None);
+        assert_eq!(Clock::parse(b"clk 0:01:00"), None);
+        assert_eq!(Clock::parse(b"[%clk 9999999999999999:00:00]"), None);
+    }
+
+    #[test]
+    fn test_emt() {
+        let secs = |secs| Some(Emt(Duration::from_secs(secs)));
+        assert_eq!(Emt::parse(b"[%emt 0:00:04]"), secs(4));
+        assert_eq!(Emt::parse(b"[%emt 1:05]"), secs(65));
+        assert_eq!(
+            Emt::parse(b"[%clk 1:00:00] [%emt 0:00:12] [%clk 0:59:00]"),
+            secs(12)
+        );
+        assert_eq!(
+            Clock::parse(b"[%emt 0:00:12] [%clk 0:59:00]"),
+            Some(Clock(Duration::from_secs(3540)))
+        );
+        assert_eq!(
+            Emt::parse(b"[%emt 0:00:01.5]"),
+            Some(Emt(Duration::from_millis(1500)))
+        );
+        assert_eq!(Emt::parse(b"[%emtx 0:00:04]"), None);
+        assert_eq!(Emt::parse(b"[%clk 0:00:04]"), None);
+        assert_eq!(Emt::parse(b"[%emt]"), None);
+        assert_eq!(Emt::parse(b"[%emt 999999999999999999:00]"), None);
+        assert_eq!(Emt::parse(b"[%emt 9999999999999999:00:00]"), None);
+    }
+
+    #[test]
+    fn test_eval() {
+        assert_eq!(Eval::from_ascii(b"0.00"), Some(Eval::Centipawns(0)));
+        assert_eq!(Eval::from_ascii(b"+1.23"), Some(Eval::Centipawns(123)));
+        assert_eq!(Eval::from_ascii(b"-4.37"), Some(Eval::Centipawns(-437)));
+        assert_eq!(Eval::from_ascii(b"2,30"), Some(Eval::Centipawns(200)));
+        assert_eq!(Eval::from_ascii(b"#5"), Some(Eval::Mate(5)));
+        assert_eq!(Eval::from_ascii(b"#-2"), Some(Eval::Mate(-2)));
+        assert_eq!(Eval::from_ascii(b""), None);
+        assert_eq!(Eval::from_ascii(b"#"), None);
+        assert_eq!(Eval::from_ascii(b"1.2.3"), None);
+        assert_eq!(Eval::from_ascii(b"inf"), None);
+        assert_eq!(Eval::from_ascii(b"1e400"), None);
+        assert_eq!(Eval::from_ascii(b"."), None);
+        assert_eq!(Eval::from_ascii(b"+-1"), None);
+        assert_eq!(Eval::from_ascii(b"#1.5"), None);
+
+        assert_eq!(Eval::parse(b" [%eval 0.00] "), Some(Eval::Centipawns(0)));
+        assert_eq!(
+            Eval::parse(b"[%clk 0:00:05] [%eval #5]"),
+            Some(Eval::Mate(5))
+        );
+        assert_eq!(Eval::parse(b"[%eval]"), None);
+        assert_eq!(Eval::parse(b"eval 1.0"), None);
+    }
+}
@@ -0,0 +1,309 @@
+use std::{error::Error, fmt, io};
+
+/// Maximum number of bytes captured in a [`Span`].
+const SNIPPET_LEN: usize = 16;
+
+/// Maximum number of bytes before the offset captured in a [`Span`].
+pub(crate) const SNIPPET_BEFORE: usize = SNIPPET_LEN / 2;
+
+/// Where a [`PgnError`] occurred.
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+pub struct Span {
+    /// Byte offset from the start of the input.
+    pub offset: u64,
+    /// Line number of `offset`, starting at 1.
+    pub line: u64,
+    /// Up to 16 bytes of input around `offset`: up to 8 bytes before it,
+    /// and the rest from `offset` on.
+    pub snippet: Vec<u8>,
+    /// Number of bytes in `snippet` before `offset`.
+    pub before: usize,
+}
+
+impl Span {
+    pub(crate) fn new(offset: u64, line: u64, before: &[u8], input: &[u8]) -> Span {
+        let before = &before[before.len().saturating_sub(SNIPPET_BEFORE)..];
+        let after = &input[..input.len().min(SNIPPET_LEN - before.len())];
+        Span {
+            offset,
+            line,
+            snippet: [before, after].concat(),
+            before: before.len(),
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "at line {}, byte {} near \"{}\"",
+            self.line,
+            self.offset,
+            self.snippet.escape_ascii()
+        )
+    }
+}
+
+/// An error that the reader could not recover from.
+///
+/// The reader reports these as [`io::Error`] with kind
+/// [`io::ErrorKind::InvalidData`]. Use [`PgnError::from_io()`] to get the
+/// details back.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, PgnError, Visitor};
+///
+/// struct Nothing;
+///
+/// impl Visitor for Nothing {
+///     type Result = ();
+///     fn end_game(&mut self) {}
+/// }
+///
+/// let pgn = b"1. e4 { unterminated";
+/// let err = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut Nothing)
+///     .unwrap_err();
+///
+/// match PgnError::from_io(&err) {
+///     Some(PgnError::UnterminatedComment(span)) => {
+///         assert_eq!(span.offset, 7);
+///         assert_eq!(span.line, 1);
+///         assert_eq!(span.snippet, b"1. e4 { untermin");
+///         assert_eq!(&span.snippet[span.before..], b" untermin");
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum PgnError {
+    /// A header without a value, closing bracket or line break until the
+    /// end of the input, or too long to buffer (see
+    /// [`BufferedReader::with_capacity()`](crate::BufferedReader::with_capacity)).
+    /// The span starts after the opening `[`.
+    UnterminatedHeader(Span),
+    /// A comment without a closing brace, or too long to buffer (see
+    /// [`BufferedReader::with_capacity()`](crate::BufferedReader::with_capacity)).
+    /// The span starts after the opening `{`.
+    UnterminatedComment(Span),
+    /// A token in the movetext that is not valid SAN, with
+    /// [`InvalidSanPolicy::Strict`](crate::reader::InvalidSanPolicy::Strict).
+    /// The span starts at the token.
+    InvalidSan(Span),
+    /// A variation nested deeper than
+    /// [`BufferedReader::set_max_variation_depth()`](crate::BufferedReader::set_max_variation_depth)
+    /// allows. Reported as a [warning](crate::Visitor::warning). The span
+    /// starts at the opening `(`.
+    VariationTooDeep(Span),
+    /// The input exceeds the limit set with
+    /// [`BufferedReader::set_max_bytes()`](crate::BufferedReader::set_max_bytes).
+    InputTooLarge {
+        /// The configured limit in bytes.
+        limit: u64,
+    },
+    /// The input starts with a UTF-16 byte order mark. PGN is read as
+    /// UTF-8 or Latin-1, so the input needs to be transcoded first.
+    Utf16 {
+        /// Whether the byte order mark is big endian (`FE FF`) rather than
+        /// little endian (`FF FE`).
+        big_endian: bool,
+    },
+}
+
+impl PgnError {
+    /// Returns the location of the error, if it is a syntax error.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            PgnError::UnterminatedHeader(span)
+            | PgnError::UnterminatedComment(span)
+            | PgnError::InvalidSan(span)
+            | PgnError::VariationTooDeep(span) => Some(span),
+            PgnError::InputTooLarge { .. } | PgnError::Utf16 { .. } => None,
+        }
+    }
+
+    /// Gets the `PgnError` wrapped in an I/O error returned by the reader,
+    /// if any.
+    pub fn from_io(err: &io::Error) -> Option<&PgnError> {
+        err.get_ref().and_then(|inner| inner.downcast_ref())
+    }
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PgnError::UnterminatedHeader(span) => write!(f, "unterminated header {}", span),
+            PgnError::UnterminatedComment(span) => write!(f, "unterminated comment {}", span),
+            PgnError::InvalidSan(span) => write!(f, "invalid san {}", span),
+            PgnError::VariationTooDeep(span) => write!(f, "variation nested too deeply {}", span),
+            PgnError::InputTooLarge { limit } => write!(f, "input exceeds {} bytes", limit),
+            PgnError::Utf16 { big_endian } => write!(
+                f,
+                "input is UTF-16{}, transcode it to UTF-8",
+                if *big_endian { "BE" } else { "LE" }
+            ),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+impl From<PgnError> for io::Error {
+    fn from(err: PgnError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// An error returned by the reader, split into I/O errors and errors in the
+/// PGN itself.
+///
+/// The reader returns [`io::Error`], so that `?` works in functions that
+/// also do other I/O. Convert it with `ReadError::from` to tell the two
+/// cases apart, for example to retry after a network error but skip a
+/// corrupt file.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{error::ReadError, BufferedReader, GameSummary, PgnError};
+///
+/// let pgn = b"1. e4 { unterminated";
+/// let err = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut GameSummary::new())
+///     .map_err(ReadError::from)
+///     .unwrap_err();
+///
+/// match err {
+///     ReadError::Pgn(err) => assert_eq!(err.span().map(|span| span.line), Some(1)),
+///     ReadError::Io(_) => unreachable!(),
+/// }
+/// ```
+#[derive(Debug)]
+pub enum ReadError {
+    /// The underlying reader failed.
+    Io(io::Error),
+    /// The input is not valid PGN, or exceeds a limit.
+    Pgn(PgnError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReadError::Io(err) => err.fmt(f),
+            ReadError::Pgn(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadError::Io(err) => Some(err),
+            ReadError::Pgn(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> ReadError {
+        if PgnError::from_io(&err).is_none() {
+            return ReadError::Io(err);
+        }
+        match err.into_inner().map(|inner| inner.downcast::<PgnError>()) {
+            Some(Ok(err)) => ReadError::Pgn(*err),
+            _ => unreachable!("checked by from_io"),
+        }
+    }
+}
+
+impl From<PgnError> for ReadError {
+    fn from(err: PgnError) -> ReadError {
+        ReadError::Pgn(err)
+    }
+}
+
+impl From<ReadError> for io::Error {
+    fn from(err: ReadError) -> io::Error {
+        match err {
+            ReadError::Io(err) => err,
+            ReadError::Pgn(err) => err.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{reader::BufferedReader, visitor::Visitor};
+
+    struct Nothing;
+
+    impl Visitor for Nothing {
+        type Result = ();
+
+        fn end_game(&mut self) {}
+    }
+
+    fn error(pgn: &[u8]) -> PgnError {
+        let err = BufferedReader::new_cursor(pgn)
+            .read_all(&mut Nothing)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        PgnError::from_io(&err).expect("PgnError").clone()
+    }
+
+    #[test]
+    fn test_unterminated_comment() {
+        let err = error(b"[Event \"?\"]\n\n1. e4 e5 {\ttrailing comment without end");
+        assert_eq!(
+            err,
+            PgnError::UnterminatedComment(Span {
+                offset: 23,
+                line: 3,
+                snippet: b" e4 e5 {\ttrailin".to_vec(),
+                before: 8,
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "unterminated comment at line 3, byte 23 near \" e4 e5 {\\ttrailin\""
+        );
+    }
+
+    #[test]
+    fn test_unterminated_header() {
+        // Neither a value, a closing bracket nor a line break until the end
+        // of the input.
+        let mut pgn = b"1. e4 *\n\n[Event ".to_vec();
+        pgn.resize(pgn.len() + 20_000, b'x');
+        let err = error(&pgn);
+        let span = err.span().unwrap();
+        assert_eq!(span.offset, 10);
+        assert_eq!(span.line, 3);
+        assert_eq!(span.snippet, b" e4 *\n\n[Event xx");
+        assert_eq!(span.before, 8);
+    }
+
+    #[test]
+    fn test_read_error() {
+        let err = BufferedReader::new_cursor(&b"[Event "[..])
+            .read_all(&mut Nothing)
+            .unwrap_err();
+        let err = ReadError::from(err);
+        assert!(matches!(
+            err,
+            ReadError::Pgn(PgnError::UnterminatedHeader(_))
+        ));
+        let err = io::Error::from(err);
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(PgnError::from_io(&err).is_some());
+
+        let err = ReadError::from(io::Error::new(io::ErrorKind::InvalidData, "not pgn"));
+        assert!(matches!(&err, ReadError::Io(err) if err.kind() == io::ErrorKind::InvalidData));
+        assert_eq!(err.to_string(), "not pgn");
+    }
+}
@@ -0,0 +1,1922 @@
+use std::{borrow::Cow, error::Error, fmt, str::FromStr};
+
+use shakmaty::{san::SanPlus, Color, Outcome};
+
+use crate::{
+    analysis::MovetextFeatures,
+    error::PgnError,
+    headers::{canonical_tag, Date, KnownKey, Round, TimeControl},
+    types::{Nag, RawComment, RawHeader, Skip},
+    visitor::{Buffered, SkipVisitor, Visitor},
+};
+
+/// Filter configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub filters: Filters,
+    /// An additional condition on the headers, that has to hold as well as
+    /// the `filters`.
+    pub expr: Option<FilterExpr>,
+}
+
+/// Criteria a game has to meet in order to be written.
+#[derive(Debug, Clone, Default)]
+pub struct Filters {
+    /// Minimum estimated game duration in seconds, based on the first
+    /// period of the time control (see [`Period::estimated_duration()`]).
+    ///
+    /// [`Period::estimated_duration()`]: crate::headers::Period::estimated_duration
+    pub time_control: MinFilter,
+    pub white_elo: MinFilter,
+    pub black_elo: MinFilter,
+    /// Drop games with a player whose title is `BOT`, or whose name ends
+    /// with `bot` in any case, as with the pattern [`NamePattern::BOT`].
+    pub exclude_bots: bool,
+    /// Names of the `White` and `Black` players.
+    pub players: PlayerFilter,
+    /// Range of the `Date` and `UTCDate` headers.
+    pub date: DateFilter,
+    /// Keep only games with one of these results, according to the
+    /// `Result` header. A missing header counts as
+    /// [`GameResult::Unknown`].
+    pub result: Option<Vec<GameResult>>,
+    /// Opening codes of the `ECO` header.
+    pub eco: EcoFilter,
+    /// Keep only games with (`Some(true)`) or without (`Some(false)`)
+    /// comments in the mainline.
+    ///
+    /// Unlike the header based filters, this requires reading the
+    /// movetext, which is considerably slower than skipping it.
+    pub require_comments: Option<bool>,
+    /// Keep only games with (`Some(true)`) or without (`Some(false)`)
+    /// variations. Requires reading the movetext, like `require_comments`.
+    pub require_variations: Option<bool>,
+    /// Minimum number of half-moves in the mainline. Requires reading the
+    /// movetext, like `require_comments`.
+    pub min_plies: Option<u32>,
+    /// Maximum number of half-moves in the mainline. Requires reading the
+    /// movetext, like `require_comments`.
+    pub max_plies: Option<u32>,
+}
+
+impl Filters {
+    fn needs_movetext(&self) -> bool {
+        self.require_comments.is_some()
+            || self.require_variations.is_some()
+            || self.min_plies.is_some()
+            || self.max_plies.is_some()
+    }
+
+    /// The header based criteria, as an expression.
+    pub fn to_expr(&self) -> FilterExpr {
+        let mut all = vec![
+            FilterExpr::TimeControl(self.time_control.clone()),
+            FilterExpr::WhiteElo(self.white_elo.clone()),
+            FilterExpr::BlackElo(self.black_elo.clone()),
+        ];
+        if self.exclude_bots {
+            all.push(FilterExpr::Not(Box::new(FilterExpr::Bot)));
+        }
+        if !self.players.include.is_empty() || !self.players.exclude.is_empty() {
+            all.push(FilterExpr::Players(self.players.clone()));
+        }
+        if self.date.is_set() {
+            all.push(FilterExpr::Date(self.date.clone()));
+        }
+        if let Some(result) = &self.result {
+            all.push(FilterExpr::Result(result.clone()));
+        }
+        if !self.eco.prefixes.is_empty() {
+            all.push(FilterExpr::Eco(self.eco.clone()));
+        }
+        FilterExpr::All(all)
+    }
+}
+
+/// A condition on the headers of a game, combining criteria with `All`,
+/// `Any` and `Not`.
+///
+/// The criteria hold for games without the headers in question, like the
+/// corresponding fields of [`Filters`], unless noted otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{
+///     filter::{Config, FilterExpr, FilteringVisitor, MinFilter},
+///     BufferedReader,
+/// };
+///
+/// let pgn = b"[WhiteElo \"2100\"]\n[BlackElo \"1500\"]\n\n*\n\n\
+///             [WhiteElo \"1800\"]\n[BlackElo \"1900\"]\n[BlackTitle \"FM\"]\n\n*\n\n\
+///             [WhiteElo \"1800\"]\n[BlackElo \"1900\"]\n\n*\n";
+///
+/// // Either player is rated 2000 or more, or titled.
+/// let config = Config {
+///     expr: Some(FilterExpr::Any(vec![
+///         FilterExpr::WhiteElo(MinFilter { min: 2000 }),
+///         FilterExpr::BlackElo(MinFilter { min: 2000 }),
+///         FilterExpr::Titled,
+///     ])),
+///     ..Config::default()
+/// };
+/// let mut visitor = FilteringVisitor::new(&config);
+/// let results = BufferedReader::new_cursor(&pgn[..])
+///     .into_iter(&mut visitor)
+///     .collect::<Result<Vec<_>, _>>()?;
+/// assert_eq!(results, [true, true, false]);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub enum FilterExpr {
+    /// All of the conditions hold. True if there are none.
+    All(Vec<FilterExpr>),
+    /// Any of the conditions holds. False if there are none.
+    Any(Vec<FilterExpr>),
+    Not(Box<FilterExpr>),
+    /// Minimum estimated game duration in seconds, like
+    /// [`Filters::time_control`]. False for invalid and unknown time
+    /// controls.
+    TimeControl(MinFilter),
+    /// False for invalid ratings.
+    WhiteElo(MinFilter),
+    /// False for invalid ratings.
+    BlackElo(MinFilter),
+    /// A player is a bot, as detected for [`Filters::exclude_bots`].
+    Bot,
+    /// A player has a title other than `BOT`.
+    Titled,
+    Players(PlayerFilter),
+    Date(DateFilter),
+    /// The game has one of the results, like [`Filters::result`].
+    Result(Vec<GameResult>),
+    Eco(EcoFilter),
+    /// The `Round` header is one of the rounds, or one of their subrounds,
+    /// as in [`Round::is_in()`]. False for games without a known round.
+    Round(Vec<Round>),
+}
+
+impl FilterExpr {
+    /// Sets `invalid` if a header value that is looked at cannot be parsed.
+    fn eval(&self, headers: &FilterHeaders, invalid: &mut bool) -> bool {
+        match self {
+            FilterExpr::All(exprs) => exprs.iter().all(|expr| expr.eval(headers, invalid)),
+            FilterExpr::Any(exprs) => exprs.iter().any(|expr| expr.eval(headers, invalid)),
+            FilterExpr::Not(expr) => !expr.eval(headers, invalid),
+            FilterExpr::TimeControl(filter) => {
+                let Some(value) = &headers.time_control else {
+                    return true;
+                };
+                match TimeControl::from_ascii(value) {
+                    Ok(TimeControl::Unlimited) => true,
+                    Ok(TimeControl::Periods(periods)) => {
+                        // The speed of a game is determined by its first period.
+                        let estimated = periods[0].estimated_duration().as_secs();
+                        estimated >= u64::from(filter.min)
+                    }
+                    Ok(TimeControl::Unknown) => false,
+                    Err(_) => {
+                        *invalid = true;
+                        false
+                    }
+                }
+            }
+            FilterExpr::WhiteElo(filter) => {
+                elo_at_least(headers.white_elo.as_deref(), filter, invalid)
+            }
+            FilterExpr::BlackElo(filter) => {
+                elo_at_least(headers.black_elo.as_deref(), filter, invalid)
+            }
+            FilterExpr::Bot => {
+                headers.players().any(|name| NamePattern::BOT.matches(name))
+                    || headers.titles().any(|title| title == b"BOT")
+            }
+            FilterExpr::Titled => headers
+                .titles()
+                .any(|title| !title.is_empty() && title != b"BOT"),
+            FilterExpr::Players(filter) => {
+                (filter.include.is_empty()
+                    || headers
+                        .players()
+                        .any(|name| filter.include.iter().any(|p| p.matches(name))))
+                    && !headers
+                        .players()
+                        .any(|name| filter.exclude.iter().any(|p| p.matches(name)))
+            }
+            FilterExpr::Date(filter) => {
+                !filter.is_set()
+                    || headers.dates.iter().all(|value| {
+                        Date::from_ascii(value).is_ok_and(|date| filter.matches(&date))
+                    })
+            }
+            FilterExpr::Result(allowed) => {
+                allowed.contains(&headers.result.unwrap_or(GameResult::Unknown))
+            }
+            FilterExpr::Eco(filter) => {
+                filter.prefixes.is_empty()
+                    || headers
+                        .eco
+                        .as_ref()
+                        .map_or(filter.keep_missing, |eco| filter.matches(eco.trim_ascii()))
+            }
+            FilterExpr::Round(rounds) => headers.round.as_ref().is_some_and(|value| {
+                let round = Round::parse(value);
+                rounds.iter().any(|prefix| round.is_in(prefix))
+            }),
+        }
+    }
+}
+
+fn elo_at_least(value: Option<&[u8]>, filter: &MinFilter, invalid: &mut bool) -> bool {
+    let Some(value) = value else {
+        return true;
+    };
+    if let Ok(elo) = std::str::from_utf8(value).unwrap_or("").parse::<u16>() {
+        elo >= filter.min
+    } else {
+        *invalid = true;
+        false
+    }
+}
+
+/// The header values that filters look at, collected by
+/// [`FilteringVisitor`].
+#[derive(Debug, Clone, Default)]
+struct FilterHeaders {
+    time_control: Option<Vec<u8>>,
+    white_elo: Option<Vec<u8>>,
+    black_elo: Option<Vec<u8>>,
+    /// Decoded player names.
+    white: Option<Vec<u8>>,
+    black: Option<Vec<u8>>,
+    white_title: Option<Vec<u8>>,
+    black_title: Option<Vec<u8>>,
+    /// Values of the `Date` and `UTCDate` headers.
+    dates: Vec<Vec<u8>>,
+    result: Option<GameResult>,
+    eco: Option<Vec<u8>>,
+    round: Option<Vec<u8>>,
+}
+
+impl FilterHeaders {
+    fn players(&self) -> impl Iterator<Item = &[u8]> {
+        self.white.iter().chain(&self.black).map(Vec::as_slice)
+    }
+
+    fn titles(&self) -> impl Iterator<Item = &[u8]> {
+        self.white_title
+            .iter()
+            .chain(&self.black_title)
+            .map(Vec::as_slice)
+    }
+}
+
+/// An inclusive range of dates. Bounds may be partial, so that a `max` of
+/// `2024.06.??` includes all of June.
+///
+/// A partial game date like `2024.??.??` passes if any day it may stand
+/// for is in the range. A date with an unknown year, like `????.??.??`,
+/// and an invalid date fail if any bound is set, because the game cannot
+/// be placed in the range. Games without a date header pass.
+#[derive(Debug, Clone, Default)]
+pub struct DateFilter {
+    pub min: Option<Date>,
+    pub max: Option<Date>,
+}
+
+impl DateFilter {
+    fn is_set(&self) -> bool {
+        self.min.is_some() || self.max.is_some()
+    }
+
+    fn matches(&self, date: &Date) -> bool {
+        let (Some(earliest), Some(latest)) = (date.earliest(), date.latest()) else {
+            return false;
+        };
+        self.min
+            .and_then(|min| min.earliest())
+            .is_none_or(|min| latest >= min)
+            && self
+                .max
+                .and_then(|max| max.latest())
+                .is_none_or(|max| earliest <= max)
+    }
+}
+
+/// A pattern for player names, where `*` stands for any number of
+/// characters and `?` for a single byte. The pattern has to match the
+/// whole name.
+///
+/// These are glob patterns, not regular expressions, so that the crate
+/// does not depend on `regex`. They cover the usual cases of prefixes,
+/// suffixes and substrings. Matching never allocates and takes at most
+/// quadratic time. Since `?` matches a single byte, a non-ASCII character in UTF-8 needs
+/// one `?` per byte.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::filter::NamePattern;
+///
+/// let pattern = NamePattern::new("Carlsen, *");
+/// assert!(pattern.matches(b"Carlsen, Magnus"));
+/// assert!(!pattern.matches(b"Magnus Carlsen"));
+///
+/// assert!(NamePattern::BOT.matches(b"Stockfish Bot"));
+/// assert!(!NamePattern::BOT.matches(b"Botvinnik, Mikhail"));
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct NamePattern {
+    pattern: Cow<'static, [u8]>,
+    ignore_case: bool,
+}
+
+impl NamePattern {
+    /// Names ending with `bot`, in any case.
+    pub const BOT: NamePattern = NamePattern {
+        pattern: Cow::Borrowed(b"*bot"),
+        ignore_case: true,
+    };
+
+    /// A case sensitive pattern.
+    pub fn new(pattern: &str) -> NamePattern {
+        NamePattern {
+            pattern: Cow::Owned(pattern.as_bytes().to_vec()),
+            ignore_case: false,
+        }
+    }
+
+    /// A pattern that ignores ASCII case.
+    pub fn ignore_case(pattern: &str) -> NamePattern {
+        NamePattern {
+            ignore_case: true,
+            ..NamePattern::new(pattern)
+        }
+    }
+
+    /// Checks whether the pattern matches the whole name.
+    pub fn matches(&self, name: &[u8]) -> bool {
+        let eq = |p: u8, ch: u8| {
+            p == b'?' || p == ch || (self.ignore_case && p.eq_ignore_ascii_case(&ch))
+        };
+
+        // Backtrack to the last `*`, letting it consume one more byte.
+        let pattern = &self.pattern[..];
+        let (mut p, mut n) = (0, 0);
+        let mut star = None;
+        while n < name.len() {
+            if pattern.get(p) == Some(&b'*') {
+                star = Some((p, n));
+                p += 1;
+            } else if pattern.get(p).is_some_and(|&pc| eq(pc, name[n])) {
+                p += 1;
+                n += 1;
+            } else if let Some((star_p, star_n)) = star {
+                p = star_p + 1;
+                n = star_n + 1;
+                star = Some((star_p, star_n + 1));
+            } else {
+                return false;
+            }
+        }
+        pattern[p..].iter().all(|&pc| pc == b'*')
+    }
+}
+
+/// Include and exclude lists of player names.
+///
+/// If `include` is not empty, at least one of the players has to match one
+/// of its patterns. Games where any player matches a pattern of `exclude`
+/// are dropped.
+///
+/// The patterns are [`NamePattern`] globs like `Carlsen*`, rather than
+/// regular expressions, to avoid a dependency on `regex`.
+#[derive(Debug, Clone, Default)]
+pub struct PlayerFilter {
+    pub include: Vec<NamePattern>,
+    pub exclude: Vec<NamePattern>,
+}
+
+/// Prefixes of allowed opening codes, like `B` for all semi-open games or
+/// `C5` for `C50` to `C59`. An empty list allows all games.
+#[derive(Debug, Clone, Default)]
+pub struct EcoFilter {
+    pub prefixes: Vec<Vec<u8>>,
+    /// Whether to keep games without an `ECO` header, if there are
+    /// prefixes.
+    pub keep_missing: bool,
+}
+
+impl EcoFilter {
+    fn matches(&self, eco: &[u8]) -> bool {
+        self.prefixes.iter().any(|prefix| eco.starts_with(prefix))
+    }
+}
+
+/// The result of a game, as in the `Result` header.
+///
+/// Unlike [`Outcome`], which only covers finished games, this includes
+/// `*` for unknown results.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{filter::GameResult, Color};
+///
+/// let result: GameResult = "0-1".parse()?;
+/// assert_eq!(result, GameResult::BlackWin);
+/// assert_eq!(result.winner(), Some(Color::Black));
+/// assert_eq!(result.to_string(), "0-1");
+/// assert!("0:1".parse::<GameResult>().is_err());
+/// # Ok::<_, pgn_reader::filter::InvalidGameResult>(())
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum GameResult {
+    /// `1-0`
+    WhiteWin,
+    /// `0-1`
+    BlackWin,
+    /// `1/2-1/2`
+    Draw,
+    /// `*`, or any other value.
+    Unknown,
+}
+
+impl GameResult {
+    /// Classifies the value of a `Result` header. Surrounding whitespace is
+    /// ignored.
+    pub fn from_ascii(s: &[u8]) -> GameResult {
+        match s.trim_ascii() {
+            b"1-0" => GameResult::WhiteWin,
+            b"0-1" => GameResult::BlackWin,
+            b"1/2-1/2" => GameResult::Draw,
+            _ => GameResult::Unknown,
+        }
+    }
+
+    /// The result for an outcome as passed to [`Visitor::outcome()`].
+    pub fn from_outcome(outcome: Option<Outcome>) -> GameResult {
+        match outcome {
+            Some(Outcome::Decisive {
+                winner: Color::White,
+            }) => GameResult::WhiteWin,
+            Some(Outcome::Decisive {
+                winner: Color::Black,
+            }) => GameResult::BlackWin,
+            Some(Outcome::Draw) => GameResult::Draw,
+            None => GameResult::Unknown,
+        }
+    }
+
+    /// Returns the winning side, or `None` for draws and unknown results.
+    pub fn winner(self) -> Option<Color> {
+        match self {
+            GameResult::WhiteWin => Some(Color::White),
+            GameResult::BlackWin => Some(Color::Black),
+            GameResult::Draw | GameResult::Unknown => None,
+        }
+    }
+
+    /// The result as written in PGN: `1-0`, `0-1`, `1/2-1/2` or `*`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GameResult::WhiteWin => "1-0",
+            GameResult::BlackWin => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::Unknown => "*",
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = InvalidGameResult;
+
+    /// Parses exactly one of `1-0`, `0-1`, `1/2-1/2` or `*`. Unlike
+    /// [`GameResult::from_ascii()`], anything else is an error.
+    fn from_str(s: &str) -> Result<GameResult, InvalidGameResult> {
+        match s {
+            "1-0" => Ok(GameResult::WhiteWin),
+            "0-1" => Ok(GameResult::BlackWin),
+            "1/2-1/2" => Ok(GameResult::Draw),
+            "*" => Ok(GameResult::Unknown),
+            _ => Err(InvalidGameResult { _priv: () }),
+        }
+    }
+}
+
+/// Error when parsing an invalid [`GameResult`].
+#[derive(Clone, Eq, PartialEq)]
+pub struct InvalidGameResult {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidGameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidGameResult").finish()
+    }
+}
+
+impl fmt::Display for InvalidGameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid game result".fmt(f)
+    }
+}
+
+impl Error for InvalidGameResult {}
+
+/// A lower bound for a numeric header value.
+#[derive(Debug, Clone, Default)]
+pub struct MinFilter {
+    pub min: u16,
+}
+
+/// A visitor that decides whether games match the configured [`Filters`].
+///
+/// Produces `true` for games that should be written.
+///
+/// The result filter goes by the `Result` header, so that it can be
+/// decided before the movetext. If the movetext is read anyway and ends
+/// with a different result, the discrepancy is counted in
+/// [`result_mismatches()`](FilteringVisitor::result_mismatches).
+///
+/// Games with a `TimeControl`, `WhiteElo` or `BlackElo` value that a filter
+/// cannot parse do not match that filter, and are counted in
+/// [`invalid_values()`](FilteringVisitor::invalid_values).
+///
+/// To write the matching games, wrap the writer in a [`Filtered`] visitor,
+/// or use a [`Split`] visitor to write the other games elsewhere.
+#[derive(Debug)]
+pub struct FilteringVisitor<'a> {
+    config: &'a Config,
+    /// The `filters` and the `expr` of the config.
+    expr: FilterExpr,
+    should_write: bool, // Flag to indicate whether the current game should be written to the output
+    headers: FilterHeaders,
+    features: MovetextFeatures,
+    /// Number of moves in the mainline.
+    plies: u32,
+    /// Result from the end of the movetext.
+    movetext_result: Option<GameResult>,
+    result_mismatches: usize,
+    invalid_values: usize,
+}
+
+impl<'a> FilteringVisitor<'a> {
+    pub fn new(config: &'a Config) -> Self {
+        let mut all = vec![config.filters.to_expr()];
+        all.extend(config.expr.clone());
+        Self {
+            config,
+            expr: FilterExpr::All(all),
+            should_write: true,
+            headers: FilterHeaders::default(),
+            features: MovetextFeatures::default(),
+            plies: 0,
+            movetext_result: None,
+            result_mismatches: 0,
+            invalid_values: 0,
+        }
+    }
+
+    /// Number of games so far whose `Result` header disagrees with the
+    /// result at the end of the movetext. Only games whose movetext was
+    /// read are checked.
+    pub fn result_mismatches(&self) -> usize {
+        self.result_mismatches
+    }
+
+    /// Number of games so far with a header value that a filter looked at,
+    /// but could not parse, like a `WhiteElo` of `"strong"`.
+    pub fn invalid_values(&self) -> usize {
+        self.invalid_values
+    }
+}
+
+impl<'a> Visitor for FilteringVisitor<'a> {
+    type Result = bool;
+
+    fn begin_game(&mut self) {
+        self.should_write = true;
+        self.headers = FilterHeaders::default();
+        self.features.begin_game();
+        self.plies = 0;
+        self.movetext_result = None;
+    }
+
+    fn header(&mut self, tag_name: &[u8], tag: RawHeader<'_>) {
+        let value = || Some(tag.as_bytes().to_vec());
+        let headers = &mut self.headers;
+        match canonical_tag(tag_name) {
+            Some(KnownKey::TimeControl) => headers.time_control = value(),
+            Some(KnownKey::WhiteElo) => headers.white_elo = value(),
+            Some(KnownKey::BlackElo) => headers.black_elo = value(),
+            Some(KnownKey::White) => headers.white = Some(tag.decode().into_owned()),
+            Some(KnownKey::Black) => headers.black = Some(tag.decode().into_owned()),
+            Some(KnownKey::WhiteTitle) => headers.white_title = value(),
+            Some(KnownKey::BlackTitle) => headers.black_title = value(),
+            Some(KnownKey::Date | KnownKey::UTCDate) => headers.dates.push(tag.as_bytes().to_vec()),
+            Some(KnownKey::Result) => {
+                headers.result = Some(GameResult::from_ascii(tag.as_bytes()));
+            }
+            Some(KnownKey::Eco) => headers.eco = value(),
+            Some(KnownKey::Round) => headers.round = value(),
+            _ => {} // Ignore other headers
+        }
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        let mut invalid = false;
+        self.should_write = self.expr.eval(&self.headers, &mut invalid);
+        self.invalid_values += usize::from(invalid);
+
+        // If the `should_write` flag is false at this point, it means
+        // that one of the header fields failed the filter criteria.
+        // In that case, we skip the rest of the game.
+        if !self.should_write {
+            return Skip(true);
+        }
+        // Annotation filters can only be decided from the movetext.
+        Skip(!self.config.filters.needs_movetext())
+    }
+
+    fn san(&mut self, _san_plus: SanPlus) {
+        // Variations are skipped, so this is the mainline.
+        self.plies += 1;
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.features.nag(nag);
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        self.features.comment(comment);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.features.begin_variation()
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.movetext_result = Some(GameResult::from_outcome(outcome));
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        if let (Some(header), Some(movetext)) = (self.headers.result, self.movetext_result) {
+            if header != movetext {
+                self.result_mismatches += 1;
+            }
+        }
+
+        let features = self.features.end_game();
+        let filters = &self.config.filters;
+        if filters
+            .require_comments
+            .is_some_and(|r| r != features.comments)
+            || filters
+                .require_variations
+                .is_some_and(|r| r != features.variations)
+            || filters.min_plies.is_some_and(|min| self.plies < min)
+            || filters.max_plies.is_some_and(|max| self.plies > max)
+        {
+            self.should_write = false;
+        }
+        self.should_write
+    }
+}
+
+/// Passes the games that match the configured [`Filters`] on to another
+/// visitor, like a [`PgnWriter`](crate::PgnWriter), and skips the movetext
+/// of the others.
+///
+/// Produces the result of the inner visitor for matching games, and `None`
+/// for the others.
+///
+/// The headers are held back until the header based filters are decided.
+/// If the filters also need the movetext, the matching games are recorded
+/// like with [`Buffered`] and replayed to the inner visitor at the end, so
+/// they arrive exactly as they would without the movetext filters.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{
+///     filter::{Config, Filtered, Filters},
+///     BufferedReader, PgnWriter,
+/// };
+///
+/// let pgn = b"[White \"Stockfish Bot\"]\n\n1. e4 *\n\n[White \"Tal\"]\n\n1. d4 *\n\n";
+///
+/// let config = Config {
+///     filters: Filters {
+///         exclude_bots: true,
+///         ..Filters::default()
+///     },
+///     ..Config::default()
+/// };
+/// let mut visitor = Filtered::new(&config, PgnWriter::new(Vec::new()));
+/// BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+/// assert_eq!(visitor.into_inner().into_inner(), b"[White \"Tal\"]\n\n1. d4 *\n\n");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Filtered<'a, V> {
+    filter: FilteringVisitor<'a>,
+    inner: V,
+    mode: Mode,
+    headers: Vec<(Vec<u8>, Vec<u8>)>,
+    fen: Option<Vec<u8>>,
+    raw_headers: Option<Vec<u8>>,
+    index: Option<usize>,
+    /// Records matching games until the movetext filters are decided.
+    buffered: Recorder,
+    /// Number of open variations, which the filter does not see.
+    depth: usize,
+}
+
+/// Records a game to replay it exactly, without looking at it.
+type Recorder = Buffered<SkipVisitor, fn(()) -> bool, SkipVisitor>;
+
+/// Where a [`Filtered`] visitor sends the movetext.
+#[derive(Debug, Clone, Copy)]
+enum Mode {
+    /// The game does not match.
+    Rejected,
+    /// The game matches and goes straight to the inner visitor.
+    Inner,
+    /// The game matches so far and goes to the filter and the recorder.
+    Buffer,
+}
+
+impl<'a, V> Filtered<'a, V> {
+    pub fn new(config: &'a Config, inner: V) -> Filtered<'a, V> {
+        Filtered {
+            filter: FilteringVisitor::new(config),
+            inner,
+            mode: Mode::Rejected,
+            headers: Vec::new(),
+            fen: None,
+            raw_headers: None,
+            index: None,
+            buffered: Buffered::new(SkipVisitor, |()| true, SkipVisitor),
+            depth: 0,
+        }
+    }
+
+    /// Gets a reference to the wrapped visitor.
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    /// Returns the wrapped visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+
+    /// See [`FilteringVisitor::result_mismatches()`].
+    pub fn result_mismatches(&self) -> usize {
+        self.filter.result_mismatches()
+    }
+
+    /// See [`FilteringVisitor::invalid_values()`].
+    pub fn invalid_values(&self) -> usize {
+        self.filter.invalid_values()
+    }
+}
+
+/// Passes held back headers to a visitor.
+fn replay_headers<V: Visitor>(
+    visitor: &mut V,
+    index: Option<usize>,
+    headers: &[(Vec<u8>, Vec<u8>)],
+    fen: Option<&[u8]>,
+    raw_headers: Option<&[u8]>,
+) -> Skip {
+    visitor.begin_game();
+    if let Some(index) = index {
+        visitor.game_index(index);
+    }
+    visitor.begin_headers();
+    for (key, value) in headers {
+        visitor.header(key, RawHeader(value));
+    }
+    if let Some(fen) = fen {
+        visitor.setup(fen);
+    }
+    if let Some(raw_headers) = raw_headers {
+        visitor.raw_headers(raw_headers);
+    }
+    visitor.end_headers()
+}
+
+impl<V: Visitor> Visitor for Filtered<'_, V> {
+    type Result = Option<V::Result>;
+
+    fn begin_game(&mut self) {
+        self.filter.begin_game();
+        self.mode = Mode::Rejected;
+        self.headers.clear();
+        self.fen = None;
+        self.raw_headers = None;
+        self.index = None;
+        self.depth = 0;
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.index = Some(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.filter.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.filter.header(key, value.clone());
+        self.headers.push((key.to_vec(), value.as_bytes().to_vec()));
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.filter.setup(fen);
+        self.fen = Some(fen.to_vec());
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        self.raw_headers = Some(headers.to_vec());
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        let skip = self.filter.end_headers();
+        if !self.filter.should_write {
+            self.mode = Mode::Rejected;
+            return Skip(true);
+        }
+        let fen = self.fen.as_deref();
+        let raw_headers = self.raw_headers.as_deref();
+        if let Skip(true) = skip {
+            self.mode = Mode::Inner;
+            replay_headers(&mut self.inner, self.index, &self.headers, fen, raw_headers)
+        } else {
+            self.mode = Mode::Buffer;
+            let _ = replay_headers(
+                &mut self.buffered,
+                self.index,
+                &self.headers,
+                fen,
+                raw_headers,
+            );
+            Skip(false)
+        }
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.move_number(number, black_to_move),
+            Mode::Buffer => {
+                if self.depth == 0 {
+                    self.filter.move_number(number, black_to_move);
+                }
+                self.buffered.move_number(number, black_to_move);
+            }
+        }
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.san(san_plus),
+            Mode::Buffer => {
+                if self.depth == 0 {
+                    self.filter.san(san_plus.clone());
+                }
+                self.buffered.san(san_plus);
+            }
+        }
+    }
+
+    fn null_move(&mut self) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.null_move(),
+            Mode::Buffer => {
+                if self.depth == 0 {
+                    self.filter.null_move();
+                }
+                self.buffered.null_move();
+            }
+        }
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.unknown_token(token),
+            Mode::Buffer => {
+                if self.depth == 0 {
+                    self.filter.unknown_token(token);
+                }
+                self.buffered.unknown_token(token);
+            }
+        }
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.garbage(token),
+            Mode::Buffer => {
+                if self.depth == 0 {
+                    self.filter.garbage(token);
+                }
+                self.buffered.garbage(token);
+            }
+        }
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.nag(nag),
+            Mode::Buffer => {
+                if self.depth == 0 {
+                    self.filter.nag(nag.clone());
+                }
+                self.buffered.nag(nag);
+            }
+        }
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.comment(comment),
+            Mode::Buffer => {
+                if self.depth == 0 {
+                    self.filter.comment(comment.clone());
+                }
+                self.buffered.comment(comment);
+            }
+        }
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.pre_game_comment(comment),
+            Mode::Buffer => {
+                self.filter.pre_game_comment(comment.clone());
+                self.buffered.pre_game_comment(comment);
+            }
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        match self.mode {
+            Mode::Rejected => Skip(true),
+            Mode::Inner => self.inner.begin_variation(),
+            Mode::Buffer => {
+                if self.depth == 0 {
+                    // The filter only looks at the mainline, so its answer
+                    // does not matter.
+                    let _ = self.filter.begin_variation();
+                }
+                self.depth += 1;
+                self.buffered.begin_variation()
+            }
+        }
+    }
+
+    fn end_variation(&mut self) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.end_variation(),
+            Mode::Buffer => {
+                self.depth -= 1;
+                if self.depth == 0 {
+                    self.filter.end_variation();
+                }
+                self.buffered.end_variation();
+            }
+        }
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.outcome(outcome),
+            Mode::Buffer => {
+                self.filter.outcome(outcome);
+                self.buffered.outcome(outcome);
+            }
+        }
+    }
+
+    fn escape(&mut self, line: &[u8]) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.escape(line),
+            Mode::Buffer => {
+                self.filter.escape(line);
+                self.buffered.escape(line);
+            }
+        }
+    }
+
+    fn truncated(&mut self) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.truncated(),
+            Mode::Buffer => {
+                self.filter.truncated();
+                self.buffered.truncated();
+            }
+        }
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.raw_movetext(movetext),
+            Mode::Buffer => self.buffered.raw_movetext(movetext),
+        }
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.warning(error),
+            Mode::Buffer => {
+                self.filter.warning(error);
+                self.buffered.warning(error);
+            }
+        }
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.filter.recovered(error);
+        match self.mode {
+            Mode::Rejected => {}
+            Mode::Inner => self.inner.recovered(error),
+            Mode::Buffer => self.buffered.recovered(error),
+        }
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        let matches = self.filter.end_game();
+        match self.mode {
+            Mode::Rejected => None,
+            Mode::Inner => Some(self.inner.end_game()),
+            Mode::Buffer => matches.then(|| self.buffered.replay_to(&mut self.inner)),
+        }
+    }
+
+    fn finish(&mut self) {
+        self.filter.finish();
+        self.inner.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.inner.abort()
+    }
+}
+
+/// Where a [`Split`] visitor sent a game, with the result of the visitor
+/// that got it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Routed<A, B> {
+    Accepted(A),
+    Rejected(B),
+}
+
+/// Passes the games that match the configured [`Filters`] on to one
+/// visitor, and all other games to another, like writing the rejects to a
+/// separate file for auditing.
+///
+/// Since both sides get whole games, the movetext of every game is read
+/// and held in memory until the filters are decided at the end of the
+/// game. Only one game is held at a time. The number of games on each side
+/// is counted.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{
+///     filter::{Config, Filters, Split},
+///     BufferedReader, PgnWriter,
+/// };
+///
+/// let pgn = b"1. e4 e5 2. Nf3 *\n\n1. d4 *\n\n";
+///
+/// let config = Config {
+///     filters: Filters {
+///         min_plies: Some(2),
+///         ..Filters::default()
+///     },
+///     ..Config::default()
+/// };
+/// let mut visitor = Split::new(
+///     &config,
+///     PgnWriter::new(Vec::new()),
+///     PgnWriter::new(Vec::new()),
+/// );
+/// BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+/// assert_eq!((visitor.accepted(), visitor.rejected()), (1, 1));
+/// let (accepted, rejected) = visitor.into_inner();
+/// assert_eq!(accepted.into_inner(), b"1. e4 e5 2. Nf3 *\n\n");
+/// assert_eq!(rejected.into_inner(), b"1. d4 *\n\n");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Split<'a, A, B> {
+    buffered: Buffered<FilteringVisitor<'a>, fn(bool) -> bool, SkipVisitor>,
+    accepted: A,
+    rejected: B,
+    accepted_games: usize,
+    rejected_games: usize,
+}
+
+impl<'a, A, B> Split<'a, A, B> {
+    pub fn new(config: &'a Config, accepted: A, rejected: B) -> Split<'a, A, B> {
+        Split {
+            buffered: Buffered::new(
+                FilteringVisitor::new(config),
+                |matches| matches,
+                SkipVisitor,
+            ),
+            accepted,
+            rejected,
+            accepted_games: 0,
+            rejected_games: 0,
+        }
+    }
+
+    /// Number of games passed to the visitor for matching games so far.
+    pub fn accepted(&self) -> usize {
+        self.accepted_games
+    }
+
+    /// Number of games passed to the visitor for rejected games so far.
+    pub fn rejected(&self) -> usize {
+        self.rejected_games
+    }
+
+    /// See [`FilteringVisitor::result_mismatches()`].
+    pub fn result_mismatches(&self) -> usize {
+        self.buffered.predicate().result_mismatches()
+    }
+
+    /// See [`FilteringVisitor::invalid_values()`].
+    pub fn invalid_values(&self) -> usize {
+        self.buffered.predicate().invalid_values()
+    }
+
+    /// Returns the visitors for matching and rejected games.
+    pub fn into_inner(self) -> (A, B) {
+        (self.accepted, self.rejected)
+    }
+}
+
+impl<A: Visitor, B: Visitor> Visitor for Split<'_, A, B> {
+    type Result = Routed<A::Result, B::Result>;
+
+    fn begin_game(&mut self) {
+        self.buffered.begin_game();
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.buffered.game_index(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.buffered.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.buffered.header(key, value);
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.buffered.setup(fen);
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        self.buffered.raw_headers(headers);
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.buffered.end_headers()
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        self.buffered.move_number(number, black_to_move);
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.buffered.san(san_plus);
+    }
+
+    fn null_move(&mut self) {
+        self.buffered.null_move();
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        self.buffered.unknown_token(token);
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        self.buffered.garbage(token);
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.buffered.nag(nag);
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        self.buffered.comment(comment);
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        self.buffered.pre_game_comment(comment);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.buffered.begin_variation()
+    }
+
+    fn end_variation(&mut self) {
+        self.buffered.end_variation();
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.buffered.outcome(outcome);
+    }
+
+    fn escape(&mut self, line: &[u8]) {
+        self.buffered.escape(line);
+    }
+
+    fn truncated(&mut self) {
+        self.buffered.truncated();
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        self.buffered.raw_movetext(movetext);
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        self.buffered.warning(error);
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.buffered.recovered(error);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        if self.buffered.decide() {
+            self.accepted_games += 1;
+            Routed::Accepted(self.buffered.replay_to(&mut self.accepted))
+        } else {
+            self.rejected_games += 1;
+            Routed::Rejected(self.buffered.replay_to(&mut self.rejected))
+        }
+    }
+
+    fn finish(&mut self) {
+        self.buffered.finish();
+        self.accepted.finish();
+        self.rejected.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.accepted.abort() && self.rejected.abort()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::{game::GameBuilder, reader::BufferedReader, visitor::Tee, writer::PgnWriter};
+
+    const PGN: &[u8] = b"[Event \"Bare\"]\n\n1. e4 e5 *\n\n\
+                         [Event \"Commented\"]\n\n1. e4 { best by test } e5 *\n\n\
+                         [Event \"Variation\"]\n\n1. e4 (1. d4) e5 *\n\n\
+                         [Event \"Both\"]\n\n1. e4 { main } (1. d4) e5 *\n";
+
+    fn filter(filters: Filters) -> io::Result<Vec<bool>> {
+        let config = Config {
+            filters,
+            ..Config::default()
+        };
+        let mut visitor = FilteringVisitor::new(&config);
+        BufferedReader::new_cursor(PGN)
+            .into_iter(&mut visitor)
+            .collect()
+    }
+
+    #[test]
+    fn test_time_control() -> io::Result<()> {
+        let pgn = b"[TimeControl \"60+0\"]\n\n*\n\n\
+                    [TimeControl \"180+2\"]\n\n*\n\n\
+                    [TimeControl \"40/7200:20/3600:900+30\"]\n\n*\n\n\
+                    [TimeControl \"-\"]\n\n*\n\n\
+                    [TimeControl \"?\"]\n\n*\n\n\
+                    [TimeControl \"0+999999999999999999\"]\n\n*\n";
+        let config = Config {
+            filters: Filters {
+                time_control: MinFilter { min: 180 },
+                ..Filters::default()
+            },
+            ..Config::default()
+        };
+        let mut visitor = FilteringVisitor::new(&config);
+        let results = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(results, [false, true, true, true, false, true]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_values() -> io::Result<()> {
+        let pgn = b"[TimeControl \"fast\"]\n\n*\n\n\
+                    [TimeControl \"?\"]\n\n*\n\n\
+                    [TimeControl \"180+2\"]\n[WhiteElo \"strong\"]\n\n*\n\n\
+                    [WhiteElo \"?\"]\n[BlackElo \"-\"]\n\n*\n\n\
+                    [TimeControl \"180+2\"]\n[WhiteElo \"2000\"]\n\n*\n";
+        let config = Config {
+            filters: Filters {
+                time_control: MinFilter { min: 180 },
+                ..Filters::default()
+            },
+            ..Config::default()
+        };
+
+        let mut visitor = FilteringVisitor::new(&config);
+        let results = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(results, [false, false, false, false, true]);
+        // The second rating is not looked at once the first is invalid.
+        assert_eq!(visitor.invalid_values(), 3);
+
+        let mut filtered = Filtered::new(&config, SkipVisitor);
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut filtered)?;
+        assert_eq!(filtered.invalid_values(), 3);
+
+        let mut split = Split::new(&config, SkipVisitor, SkipVisitor);
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut split)?;
+        assert_eq!(split.invalid_values(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_date() -> io::Result<()> {
+        let pgn = b"[Date \"2024.05.31\"]\n\n*\n\n\
+                    [Date \"2024.06.01\"]\n\n*\n\n\
+                    [UTCDate \"2024.06.30\"]\n\n*\n\n\
+                    [Date \"2024.07.01\"]\n\n*\n\n\
+                    [Date \"2024.??.??\"]\n\n*\n\n\
+                    [Date \"????.??.??\"]\n\n*\n\n\
+                    [Date \"yesterday\"]\n\n*\n\n\
+                    [Date \"2024.06.15\"]\n[UTCDate \"2024.07.01\"]\n\n*\n\n\
+                    [Event \"No date\"]\n\n*\n";
+        let config = Config {
+            filters: Filters {
+                date: DateFilter {
+                    min: Some("2024.06.01".parse().unwrap()),
+                    max: Some("2024.06.??".parse().unwrap()),
+                },
+                ..Filters::default()
+            },
+            ..Config::default()
+        };
+        let mut visitor = FilteringVisitor::new(&config);
+        let results = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(
+            results,
+            [false, true, true, false, true, false, false, false, true]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_result() -> io::Result<()> {
+        let pgn = b"[Result \"1-0\"]\n\n1-0\n\n\
+                    [Result \"0-1\"]\n\n0-1\n\n\
+                    [Result \"1/2-1/2\"]\n\n1/2-1/2\n\n\
+                    [Result \"*\"]\n\n*\n\n\
+                    [Event \"No result\"]\n\n*\n\n\
+                    [Result \"1-0\"]\n\n0-1\n";
+        let results = |allowed: &[GameResult]| {
+            let config = Config {
+                filters: Filters {
+                    result: Some(allowed.to_vec()),
+                    ..Filters::default()
+                },
+                ..Config::default()
+            };
+            let mut visitor = FilteringVisitor::new(&config);
+            BufferedReader::new_cursor(&pgn[..])
+                .into_iter(&mut visitor)
+                .collect::<io::Result<Vec<_>>>()
+        };
+        assert_eq!(
+            results(&[GameResult::WhiteWin, GameResult::BlackWin])?,
+            [true, true, false, false, false, true]
+        );
+        assert_eq!(
+            results(&[GameResult::Draw])?,
+            [false, false, true, false, false, false]
+        );
+        assert_eq!(
+            results(&[GameResult::Unknown])?,
+            [false, false, false, true, true, false]
+        );
+
+        // Mismatches are only checked if the filters need the movetext.
+        let mismatches = |filters| {
+            let config = Config {
+                filters,
+                ..Config::default()
+            };
+            let mut visitor = FilteringVisitor::new(&config);
+            BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+            let mut split = Split::new(&config, SkipVisitor, SkipVisitor);
+            BufferedReader::new_cursor(&pgn[..]).read_all(&mut split)?;
+            Ok::<_, io::Error>((visitor.result_mismatches(), split.result_mismatches()))
+        };
+        assert_eq!(mismatches(Filters::default())?, (0, 0));
+        let filters = Filters {
+            min_plies: Some(0),
+            ..Filters::default()
+        };
+        assert_eq!(mismatches(filters)?, (1, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_eco() -> io::Result<()> {
+        let pgn = b"[ECO \"B90\"]\n\n*\n\n\
+                    [ECO \"C50\"]\n\n*\n\n\
+                    [ECO \"C59\"]\n\n*\n\n\
+                    [ECO \"C60\"]\n\n*\n\n\
+                    [ECO \"A00\"]\n\n*\n\n\
+                    [Event \"No ECO\"]\n\n*\n";
+        let results = |keep_missing| {
+            let config = Config {
+                filters: Filters {
+                    eco: EcoFilter {
+                        prefixes: vec![b"B".to_vec(), b"C5".to_vec()],
+                        keep_missing,
+                    },
+                    ..Filters::default()
+                },
+                ..Config::default()
+            };
+            let mut visitor = FilteringVisitor::new(&config);
+            BufferedReader::new_cursor(&pgn[..])
+                .into_iter(&mut visitor)
+                .collect::<io::Result<Vec<_>>>()
+        };
+        assert_eq!(results(false)?, [true, true, true, false, false, false]);
+        assert_eq!(results(true)?, [true, true, true, false, false, true]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_name_pattern() {
+        let pattern = NamePattern::new("*a*b?");
+        for name in [&b"ab_"[..], b"xaxbx", b"aab_", b"abab_", b"a*b?"] {
+            assert!(pattern.matches(name), "{:?}", name);
+        }
+        for name in [&b""[..], b"ab", b"ba_", b"Ab_", b"ab__"] {
+            assert!(!pattern.matches(name), "{:?}", name);
+        }
+        // Backtracking past an earlier match of the last segment.
+        let pattern = NamePattern::new("*a*b");
+        for name in [&b"ab"[..], b"aab", b"abab", b"bab", b"axbyb", b"abxb"] {
+            assert!(pattern.matches(name), "{:?}", name);
+        }
+        for name in [&b"a"[..], b"ba", b"abx", b"abba", b"bbb"] {
+            assert!(!pattern.matches(name), "{:?}", name);
+        }
+        assert!(NamePattern::ignore_case("carlsen*").matches(b"Carlsen, Magnus"));
+        let pattern = NamePattern::ignore_case("?AGNUS*?");
+        for name in [&b"magnus!"[..], b"Magnus C", b"1agnus 2"] {
+            assert!(pattern.matches(name), "{:?}", name);
+        }
+        for name in [&b"magnus"[..], b"MAGNU", b"agnus!"] {
+            assert!(!pattern.matches(name), "{:?}", name);
+        }
+        assert!(!NamePattern::new("?AGNUS*?").matches(b"magnus!"));
+        assert!(NamePattern::new("Dub?v").matches(b"Dubov"));
+        assert!(!NamePattern::new("M?ller").matches("Müller".as_bytes()));
+        assert!(NamePattern::new("M??ller").matches("Müller".as_bytes()));
+        assert!(NamePattern::new("").matches(b""));
+        assert!(NamePattern::new("*").matches(b""));
+        for bot in [&b"maia1bot"[..], b"Stockfish BOT", b"bot"] {
+            assert!(NamePattern::BOT.matches(bot));
+        }
+        for human in [&b"Botvinnik, Mikhail"[..], b"Abbott", b"Robotnik"] {
+            assert!(!NamePattern::BOT.matches(human));
+        }
+    }
+
+    #[test]
+    fn test_players() -> io::Result<()> {
+        let pgn = b"[White \"DrNykterstein\"]\n[Black \"Someone\"]\n\n*\n\n\
+                    [White \"Someone\"]\n[Black \"Firouzja2003\"]\n\n*\n\n\
+                    [White \"Someone\"]\n[Black \"Someone else\"]\n\n*\n\n\
+                    [White \"Firouzja2003\"]\n[Black \"LeelaBot\"]\n\n*\n\n\
+                    [White \"Botvinnik\"]\n[Black \"DrNykterstein\"]\n\n*\n\n\
+                    [White \"DrNykterstein\"]\n[Black \"Firouzja2003_alt\"]\n\n*\n";
+        let config = Config {
+            filters: Filters {
+                exclude_bots: true,
+                players: PlayerFilter {
+                    include: vec![
+                        NamePattern::new("DrNykterstein"),
+                        NamePattern::ignore_case("firouzja*"),
+                    ],
+                    exclude: vec![NamePattern::new("*_alt")],
+                },
+                ..Filters::default()
+            },
+            ..Config::default()
+        };
+        let mut visitor = FilteringVisitor::new(&config);
+        let results = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(results, [true, true, false, false, true, false]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_expr() -> io::Result<()> {
+        let pgn = b"[WhiteElo \"2100\"]\n[Black \"maia1bot\"]\n\n*\n\n\
+                    [WhiteElo \"1800\"]\n[WhiteTitle \"FM\"]\n\n*\n\n\
+                    [WhiteElo \"1800\"]\n[BlackTitle \"BOT\"]\n\n*\n\n\
+                    [WhiteElo \"strong\"]\n[WhiteTitle \"GM\"]\n\n*\n\n\
+                    [WhiteElo \"2100\"]\n[TimeControl \"60+0\"]\n\n*\n";
+        // The filters reject invalid ratings and fast games, whatever the
+        // expression.
+        let results = |expr| {
+            let config = Config {
+                filters: Filters {
+                    time_control: MinFilter { min: 180 },
+                    ..Filters::default()
+                },
+                expr: Some(expr),
+            };
+            let mut visitor = FilteringVisitor::new(&config);
+            BufferedReader::new_cursor(&pgn[..])
+                .into_iter(&mut visitor)
+                .collect::<io::Result<Vec<_>>>()
+        };
+        let strong_or_titled = FilterExpr::Any(vec![
+            FilterExpr::WhiteElo(MinFilter { min: 2000 }),
+            FilterExpr::Titled,
+        ]);
+        assert_eq!(
+            results(strong_or_titled.clone())?,
+            [true, true, false, false, false]
+        );
+        assert_eq!(
+            results(FilterExpr::All(vec![
+                strong_or_titled,
+                FilterExpr::Not(Box::new(FilterExpr::Bot)),
+            ]))?,
+            [false, true, false, false, false]
+        );
+        assert_eq!(
+            results(FilterExpr::Any(Vec::new()))?,
+            [false, false, false, false, false]
+        );
+        assert_eq!(
+            results(FilterExpr::All(Vec::new()))?,
+            [true, true, true, false, false]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_tag_spellings() -> io::Result<()> {
+        let pgn = b"[whiteelo \"2100\"]\n\n*\n\n\
+                    [WHITEELO \"1800\"]\n\n*\n\n\
+                    [WhiteElo \"2100\"]\n[timecontrol \"60+0\"]\n\n*\n\n\
+                    [White_Elo \"2100\"]\n\n*\n";
+        let config = Config {
+            filters: Filters {
+                time_control: MinFilter { min: 180 },
+                ..Filters::default()
+            },
+            expr: Some(FilterExpr::WhiteElo(MinFilter { min: 2000 })),
+        };
+        let mut visitor = FilteringVisitor::new(&config);
+        let results = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        // Unknown tags are ignored, so the last game has no rating, which
+        // passes as for a missing tag.
+        assert_eq!(results, [true, false, false, true]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_round() -> io::Result<()> {
+        let pgn = b"[Round \"3\"]\n\n*\n\n\
+                    [Round \"3.1\"]\n\n*\n\n\
+                    [Round \"31\"]\n\n*\n\n\
+                    [Round \"4.2\"]\n\n*\n\n\
+                    [Round \"?\"]\n\n*\n\n\
+                    [Event \"No round\"]\n\n*\n";
+        let results = |rounds: &[&[u8]]| {
+            let config = Config {
+                expr: Some(FilterExpr::Round(
+                    rounds.iter().map(|round| Round::parse(round)).collect(),
+                )),
+                ..Config::default()
+            };
+            let mut visitor = FilteringVisitor::new(&config);
+            BufferedReader::new_cursor(&pgn[..])
+                .into_iter(&mut visitor)
+                .collect::<io::Result<Vec<_>>>()
+        };
+        assert_eq!(results(&[b"3"])?, [true, true, false, false, false, false]);
+        assert_eq!(
+            results(&[b"3.1", b"4"])?,
+            [false, true, false, true, false, false]
+        );
+        assert_eq!(results(&[b"?"])?, [false; 6]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_require_annotations() -> io::Result<()> {
+        assert_eq!(
+            filter(Filters {
+                require_comments: Some(true),
+                ..Filters::default()
+            })?,
+            [false, true, false, true]
+        );
+        assert_eq!(
+            filter(Filters {
+                require_variations: Some(true),
+                ..Filters::default()
+            })?,
+            [false, false, true, true]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_matching() -> io::Result<()> {
+        let config = Config {
+            filters: Filters {
+                require_variations: Some(true),
+                ..Filters::default()
+            },
+            ..Config::default()
+        };
+        let mut visitor = Tee::new(FilteringVisitor::new(&config), GameBuilder::new());
+        let mut writer = PgnWriter::new(Vec::new());
+        for game in BufferedReader::new_cursor(PGN).into_iter(&mut visitor) {
+            if let (true, game) = game? {
+                game.visit(&mut writer)?;
+            }
+        }
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "[Event \"Variation\"]\n\n1. e4 (1. d4) 1... e5 *\n\n\
+             [Event \"Both\"]\n\n1. e4 { main } (1. d4) 1... e5 *\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_filtered() -> io::Result<()> {
+        let write = |pgn: &[u8], filters| {
+            let config = Config {
+                filters,
+                ..Config::default()
+            };
+            let mut visitor = Filtered::new(&config, PgnWriter::new(Vec::new()));
+            let results = BufferedReader::new_cursor(pgn)
+                .into_iter(&mut visitor)
+                .map(|result| result.map(|written| written.is_some()))
+                .collect::<io::Result<Vec<_>>>()?;
+            let out = String::from_utf8(visitor.into_inner().into_inner()).unwrap();
+            Ok::<_, io::Error>((results, out))
+        };
+
+        // Decided by the headers.
+        let pgn = b"[White \"LeelaBot\"]\n\n1. e4 { engine } e5 *\n\n\
+                    [White \"Tal\"]\n[FEN \"8/8/8/4k3/8/8/8/4K2R w K - 0 1\"]\n\n\
+                    1. O-O (1. Rh5+) 1... Kd4 *\n";
+        let (results, out) = write(
+            pgn,
+            Filters {
+                exclude_bots: true,
+                ..Filters::default()
+            },
+        )?;
+        assert_eq!(results, [false, true]);
+        assert_eq!(
+            out,
+            "[White \"Tal\"]\n[FEN \"8/8/8/4k3/8/8/8/4K2R w K - 0 1\"]\n\n\
+             1. O-O (1. Rh5+) 1... Kd4 *\n\n"
+        );
+
+        // Decided by the movetext.
+        let (results, out) = write(
+            PGN,
+            Filters {
+                require_comments: Some(true),
+                ..Filters::default()
+            },
+        )?;
+        assert_eq!(results, [false, true, false, true]);
+        assert_eq!(
+            out,
+            "[Event \"Commented\"]\n\n1. e4 { best by test } 1... e5 *\n\n\
+             [Event \"Both\"]\n\n1. e4 { main } (1. d4) 1... e5 *\n\n"
+        );
+
+        // A comment in a variation does not count.
+        let (results, _) = write(
+            b"1. e4 (1. d4 { queen's pawn }) 1... e5 *",
+            Filters {
+                require_comments: Some(true),
+                ..Filters::default()
+            },
+        )?;
+        assert_eq!(results, [false]);
+
+        // Movetext filters do not change how the game is written.
+        let pgn = b"1. e4 (1. d4 d5) { after var } e5\n%escaped\n2. Xz9 Nf3 *\n";
+        let (_, unfiltered) = write(pgn, Filters::default())?;
+        let (results, filtered) = write(
+            pgn,
+            Filters {
+                min_plies: Some(1),
+                ..Filters::default()
+            },
+        )?;
+        assert_eq!(results, [true]);
+        assert_eq!(filtered, unfiltered);
+        assert!(filtered.contains("(1. d4 d5) { after var }"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split() -> io::Result<()> {
+        let split = |filters| {
+            let config = Config {
+                filters,
+                ..Config::default()
+            };
+            let mut visitor = Split::new(
+                &config,
+                PgnWriter::new(Vec::new()),
+                PgnWriter::new(Vec::new()),
+            );
+            let routed = BufferedReader::new_cursor(PGN)
+                .into_iter(&mut visitor)
+                .map(|routed| {
+                    Ok(match routed? {
+                        Routed::Accepted(written) => written.map(|()| true)?,
+                        Routed::Rejected(written) => written.map(|()| false)?,
+                    })
+                })
+                .collect::<io::Result<Vec<_>>>()?;
+            let counts = (visitor.accepted(), visitor.rejected());
+            let (accepted, rejected) = visitor.into_inner();
+            let out = |writer: PgnWriter<Vec<u8>>| String::from_utf8(writer.into_inner()).unwrap();
+            Ok::<_, io::Error>((routed, counts, out(accepted), out(rejected)))
+        };
+
+        // Decided by the movetext.
+        let (routed, counts, accepted, rejected) = split(Filters {
+            require_variations: Some(true),
+            ..Filters::default()
+        })?;
+        assert_eq!(routed, [false, false, true, true]);
+        assert_eq!(counts, (2, 2));
+        assert_eq!(
+            accepted,
+            "[Event \"Variation\"]\n\n1. e4 (1. d4) 1... e5 *\n\n\
+             [Event \"Both\"]\n\n1. e4 { main } (1. d4) 1... e5 *\n\n"
+        );
+        assert_eq!(
+            rejected,
+            "[Event \"Bare\"]\n\n1. e4 e5 *\n\n\
+             [Event \"Commented\"]\n\n1. e4 { best by test } 1... e5 *\n\n"
+        );
+
+        // Decided by the headers, but the rejects still get the movetext.
+        let (routed, _, _, rejected) = split(Filters {
+            result: Some(vec![GameResult::WhiteWin]),
+            ..Filters::default()
+        })?;
+        assert_eq!(routed, [false; 4]);
+        assert!(rejected.contains("1. e4 { main } (1. d4) 1... e5 *"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_filtered_game_index() -> io::Result<()> {
+        struct Index(usize);
+
+        impl Visitor for Index {
+            type Result = usize;
+
+            fn game_index(&mut self, index: usize) {
+                self.0 = index;
+            }
+
+            fn end_game(&mut self) -> usize {
+                self.0
+            }
+        }
+
+        // Games held back for the movetext filters keep their index.
+        let config = Config {
+            filters: Filters {
+                require_comments: Some(true),
+                ..Filters::default()
+            },
+            ..Config::default()
+        };
+        let mut visitor = Filtered::new(&config, Index(usize::MAX));
+        let results = BufferedReader::new_cursor(PGN)
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(results, [None, Some(1), None, Some(3)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_require_bare() -> io::Result<()> {
+        assert_eq!(
+            filter(Filters {
+                require_comments: Some(false),
+                require_variations: Some(false),
+                ..Filters::default()
+            })?,
+            [true, false, false, false]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_plies() -> io::Result<()> {
+        let pgn = b"1. e4 *\n\n\
+                    1. e4 e5 (1... c5 2. Nf3 d6 3. d4) 2. Nf3 *\n\n\
+                    1. e4 e5 2. Nf3 Nc6 3. Bb5 *\n\n\
+                    [Event \"No moves\"]\n\n*\n";
+        let results = |min_plies, max_plies| {
+            let config = Config {
+                filters: Filters {
+                    min_plies,
+                    max_plies,
+                    ..Filters::default()
+                },
+                ..Config::default()
+            };
+            let mut visitor = FilteringVisitor::new(&config);
+            BufferedReader::new_cursor(&pgn[..])
+                .into_iter(&mut visitor)
+                .collect::<io::Result<Vec<_>>>()
+        };
+        assert_eq!(results(Some(2), None)?, [false, true, true, false]);
+        assert_eq!(results(None, Some(3))?, [true, true, false, true]);
+        assert_eq!(results(Some(3), Some(3))?, [false, true, false, false]);
+
+        // The movetext of matching games is passed on.
+        let config = Config {
+            filters: Filters {
+                min_plies: Some(5),
+                ..Filters::default()
+            },
+            ..Config::default()
+        };
+        let mut visitor = Filtered::new(&config, PgnWriter::new(Vec::new()));
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+        assert_eq!(
+            visitor.into_inner().into_inner(),
+            b"1. e4 e5 2. Nf3 Nc6 3. Bb5 *\n\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,493 @@
+use shakmaty::{
+    san::{San, SanPlus},
+    Outcome,
+};
+
+use crate::{
+    types::{Nag, RawComment, RawHeader, Skip},
+    visitor::Visitor,
+};
+
+/// Header keys and values in order, with values as in the PGN, so escapes
+/// are preserved (see [`RawHeader`]).
+pub type HeaderList = Vec<(Vec<u8>, Vec<u8>)>;
+
+/// A game held in memory.
+///
+/// Use a [`GameBuilder`] to read games into memory, and
+/// [`Game::visit()`] to pass them to any other visitor, like a
+/// [`PgnWriter`](crate::PgnWriter).
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Game, PgnWriter, SanMove};
+///
+/// let mut game = Game::default();
+/// game.headers.push((b"White".to_vec(), b"Fischer".to_vec()));
+/// game.mainline.moves.push(SanMove::new("e4".parse()?));
+///
+/// let mut writer = PgnWriter::new(Vec::new());
+/// game.visit(&mut writer)?;
+/// assert_eq!(writer.into_inner(), b"[White \"Fischer\"]\n\n1. e4 *\n\n");
+/// # Ok::<_, Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Serialization
+///
+/// With the `serde` feature, games implement `Serialize` and
+/// `Deserialize`. Byte strings become strings, with invalid UTF-8 replaced
+/// by `U+FFFD`. In JSON, the game above looks like this:
+///
+/// ```json
+/// {
+///   "headers": [["White", "Fischer"]],
+///   "mainline": {
+///     "comments": [],
+///     "moves": [{ "san": "e4", "nags": [], "comments": [], "variations": [] }]
+///   },
+///   "outcome": "*",
+///   "truncated": false,
+///   "raw_headers": null,
+///   "raw_movetext": null
+/// }
+/// ```
+///
+/// Variations are nested in the move they are an alternative to, in the
+/// same shape as the mainline. NAGs are numbers, and the outcome is the
+/// game termination marker.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Game {
+    #[cfg_attr(feature = "serde", serde(with = "serde_impls::headers"))]
+    pub headers: HeaderList,
+    pub mainline: Variation,
+    /// The result of the game, or `None` for `*`.
+    #[cfg_attr(feature = "serde", serde(with = "serde_impls::outcome"))]
+    pub outcome: Option<Outcome>,
+    /// Whether the input ended in the middle of the movetext. See
+    /// [`Visitor::truncated()`].
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub truncated: bool,
+    /// The header block as it appeared in the input, if the reader was set
+    /// to [pass it on](crate::BufferedReader::set_raw_headers).
+    #[cfg_attr(feature = "serde", serde(default, with = "serde_impls::raw"))]
+    pub raw_headers: Option<Vec<u8>>,
+    /// The movetext as it appeared in the input, if the reader was set to
+    /// [pass it on](crate::BufferedReader::set_raw_movetext).
+    #[cfg_attr(feature = "serde", serde(with = "serde_impls::raw"))]
+    pub raw_movetext: Option<Vec<u8>>,
+}
+
+/// A line of moves, either the mainline or a variation.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Variation {
+    /// Comments before the first move. For the mainline, these are the
+    /// comments passed to [`Visitor::pre_game_comment()`].
+    #[cfg_attr(feature = "serde", serde(with = "serde_impls::comments"))]
+    pub comments: Vec<Vec<u8>>,
+    pub moves: Vec<SanMove>,
+}
+
+/// A move with its annotations.
+#[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SanMove {
+    #[cfg_attr(feature = "serde", serde(rename = "san"))]
+    pub san_plus: SanPlus,
+    pub nags: Vec<Nag>,
+    /// Comments after the move.
+    #[cfg_attr(feature = "serde", serde(with = "serde_impls::comments"))]
+    pub comments: Vec<Vec<u8>>,
+    /// Alternatives to this move.
+    pub variations: Vec<Variation>,
+}
+
+/// Representations of the fields of [`Game`] that do not map well to
+/// serde by themselves.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use std::borrow::Cow;
+
+    fn lossy(bytes: &[u8]) -> Cow<'_, str> {
+        String::from_utf8_lossy(bytes)
+    }
+
+    pub mod headers {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        use crate::game::HeaderList;
+
+        pub fn serialize<S: Serializer>(
+            headers: &[(Vec<u8>, Vec<u8>)],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(
+                headers
+                    .iter()
+                    .map(|(key, value)| (super::lossy(key), super::lossy(value))),
+            )
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<HeaderList, D::Error> {
+            let headers = Vec::<(String, String)>::deserialize(deserializer)?;
+            Ok(headers
+                .into_iter()
+                .map(|(key, value)| (key.into_bytes(), value.into_bytes()))
+                .collect())
+        }
+    }
+
+    pub mod comments {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            comments: &[Vec<u8>],
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.collect_seq(comments.iter().map(|comment| super::lossy(comment)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Vec<Vec<u8>>, D::Error> {
+            let comments = Vec::<String>::deserialize(deserializer)?;
+            Ok(comments.into_iter().map(String::into_bytes).collect())
+        }
+    }
+
+    pub mod raw {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer>(
+            raw: &Option<Vec<u8>>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            raw.as_deref().map(super::lossy).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Vec<u8>>, D::Error> {
+            Ok(Option::<String>::deserialize(deserializer)?.map(String::into_bytes))
+        }
+    }
+
+    pub mod outcome {
+        use serde::{de::Error, Deserialize, Deserializer, Serializer};
+        use shakmaty::Outcome;
+
+        pub fn serialize<S: Serializer>(
+            outcome: &Option<Outcome>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(outcome.map_or("*", Outcome::as_str))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<Outcome>, D::Error> {
+            let outcome = String::deserialize(deserializer)?;
+            match outcome.as_str() {
+                "*" => Ok(None),
+                _ => Outcome::from_ascii(outcome.as_bytes())
+                    .map(Some)
+                    .map_err(D::Error::custom),
+            }
+        }
+    }
+}
+
+impl SanMove {
+    /// A move without annotations.
+    pub fn new(san_plus: SanPlus) -> SanMove {
+        SanMove {
+            san_plus,
+            nags: Vec::new(),
+            comments: Vec::new(),
+            variations: Vec::new(),
+        }
+    }
+}
+
+impl Game {
+    /// Passes the game to a visitor, with the same callbacks as if it was
+    /// read from PGN. Skipping the movetext or variations is respected.
+    pub fn visit<V: Visitor>(&self, visitor: &mut V) -> V::Result {
+        visitor.begin_game();
+        visitor.begin_headers();
+        for (key, value) in &self.headers {
+            visitor.header(key, RawHeader(value));
+        }
+        if let Some((_, fen)) = self.headers.iter().rev().find(|(key, _)| key == b"FEN") {
+            visitor.setup(&RawHeader(fen).decode());
+        }
+        if let Some(raw_headers) = &self.raw_headers {
+            visitor.raw_headers(raw_headers);
+        }
+        if let Skip(false) = visitor.end_headers() {
+            for comment in &self.mainline.comments {
+                visitor.pre_game_comment(RawComment(comment));
+            }
+            self.mainline.visit_moves(visitor);
+            visitor.outcome(self.outcome);
+            if self.truncated {
+                visitor.truncated();
+            }
+        }
+        if let Some(raw_movetext) = &self.raw_movetext {
+            visitor.raw_movetext(raw_movetext);
+        }
+        visitor.end_game()
+    }
+}
+
+/// A visitor that reads a whole game into a [`Game`], including all
+/// headers, comments, NAGs and variations.
+///
+/// Comments after a variation are attached to the move before it, so
+/// [`Game::visit()`] delivers them before the variation. NAGs, and
+/// variations before the first move of a line, have nothing to be attached
+/// to and are dropped. Escaped lines are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, GameBuilder};
+///
+/// let pgn = b"[White \"Fischer\"]\n\n1. e4 { best by test } e5 (1... c5 $1) 1-0";
+///
+/// let game = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut GameBuilder::new())?
+///     .unwrap();
+/// assert_eq!(game.headers, [(b"White".to_vec(), b"Fischer".to_vec())]);
+/// assert_eq!(game.mainline.moves[0].comments, [b" best by test ".to_vec()]);
+/// let variation = &game.mainline.moves[1].variations[0];
+/// assert_eq!(variation.moves[0].san_plus.to_string(), "c5");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct GameBuilder {
+    game: Game,
+    /// Variations that are being read, innermost last.
+    variations: Vec<Variation>,
+}
+
+impl GameBuilder {
+    pub fn new() -> GameBuilder {
+        GameBuilder::default()
+    }
+
+    /// The line that is currently being read.
+    fn line(&mut self) -> &mut Variation {
+        self.variations
+            .last_mut()
+            .unwrap_or(&mut self.game.mainline)
+    }
+}
+
+impl Visitor for GameBuilder {
+    type Result = Game;
+
+    fn begin_game(&mut self) {
+        self.game = Game::default();
+        self.variations.clear();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.game
+            .headers
+            .push((key.to_vec(), value.as_bytes().to_vec()));
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        self.game.raw_headers = Some(headers.to_vec());
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.line().moves.push(SanMove::new(san_plus));
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        if let Some(san_move) = self.line().moves.last_mut() {
+            san_move.nags.push(nag);
+        }
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        let line = self.line();
+        let comments = match line.moves.last_mut() {
+            Some(san_move) => &mut san_move.comments,
+            None => &mut line.comments,
+        };
+        comments.push(comment.as_bytes().to_vec());
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.variations.push(Variation::default());
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        if let Some(variation) = self.variations.pop() {
+            if let Some(san_move) = self.line().moves.last_mut() {
+                san_move.variations.push(variation);
+            }
+        }
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.game.outcome = outcome;
+    }
+
+    fn truncated(&mut self) {
+        self.game.truncated = true;
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        self.game.raw_movetext = Some(movetext.to_vec());
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.variations.clear();
+        std::mem::take(&mut self.game)
+    }
+}
+
+impl Variation {
+    fn visit<V: Visitor>(&self, visitor: &mut V) {
+        for comment in &self.comments {
+            visitor.comment(RawComment(comment));
+        }
+        self.visit_moves(visitor);
+    }
+
+    fn visit_moves<V: Visitor>(&self, visitor: &mut V) {
+        for san_move in &self.moves {
+            if san_move.san_plus.san == San::Null {
+                visitor.null_move();
+            } else {
+                visitor.san(san_move.san_plus.clone());
+            }
+            for nag in &san_move.nags {
+                visitor.nag(nag.clone());
+            }
+            for comment in &san_move.comments {
+                visitor.comment(RawComment(comment));
+            }
+            for variation in &san_move.variations {
+                if let Skip(false) = visitor.begin_variation() {
+                    variation.visit(visitor);
+                }
+                visitor.end_variation();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::{reader::BufferedReader, writer::PgnWriter};
+
+    #[test]
+    fn test_game_builder() -> io::Result<()> {
+        let pgn = b"[Event \"Test\"]\n[White \"O\\\"Brien\"]\n\n\
+                    { start } 1. e4 $1 { first } { second } e5 \
+                    (1... c5 { sicilian } 2. Nf3 (2. c3) d6) (1... e6) \
+                    2. Nf3 Nc6 0-1\n\n\
+                    1. d4 *";
+
+        let mut direct = PgnWriter::new(Vec::new());
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.read_game(&mut direct)?.unwrap()?;
+        reader.read_game(&mut direct)?.unwrap()?;
+
+        let games = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut GameBuilder::new())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(games.len(), 2);
+        assert_eq!(games[0].headers[1].1, b"O\\\"Brien");
+        assert_eq!(games[0].mainline.comments, [b" start ".to_vec()]);
+        assert_eq!(games[0].mainline.moves[1].variations.len(), 2);
+        assert_eq!(games[1].mainline.moves.len(), 1);
+        assert_eq!(games[1].outcome, None);
+
+        let mut rebuilt = PgnWriter::new(Vec::new());
+        for game in &games {
+            game.visit(&mut rebuilt)?;
+        }
+        assert_eq!(
+            String::from_utf8(rebuilt.into_inner()).unwrap(),
+            String::from_utf8(direct.into_inner()).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_comment_attachment() -> io::Result<()> {
+        #[derive(Default)]
+        struct Log(Vec<String>);
+
+        impl Visitor for Log {
+            type Result = Vec<String>;
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0.push(san_plus.to_string());
+            }
+
+            fn comment(&mut self, comment: RawComment<'_>) {
+                self.0.push(format!(
+                    "{{{}}}",
+                    String::from_utf8_lossy(comment.as_bytes())
+                ));
+            }
+
+            fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+                self.0.push(format!(
+                    "pre{{{}}}",
+                    String::from_utf8_lossy(comment.as_bytes())
+                ));
+            }
+
+            fn begin_variation(&mut self) -> Skip {
+                self.0.push("(".to_owned());
+                Skip(false)
+            }
+
+            fn end_variation(&mut self) {
+                self.0.push(")".to_owned());
+            }
+
+            fn end_game(&mut self) -> Vec<String> {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        let pgn = b"{a} 1. e4 {b} e5 ({c} 1... c5 {d}) 2. Nf3 *";
+        let game = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut GameBuilder::new())?
+            .unwrap();
+        let mainline = &game.mainline;
+        assert_eq!(mainline.comments, [b"a".to_vec()]);
+        assert_eq!(mainline.moves[0].comments, [b"b".to_vec()]);
+        let variation = &mainline.moves[1].variations[0];
+        assert_eq!(variation.comments, [b"c".to_vec()]);
+        assert_eq!(variation.moves[0].comments, [b"d".to_vec()]);
+
+        let expected = [
+            "pre{a}", "e4", "{b}", "e5", "(", "{c}", "c5", "{d}", ")", "Nf3",
+        ];
+        assert_eq!(game.visit(&mut Log::default()), expected);
+        let direct = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut Log::default())?
+            .unwrap();
+        assert_eq!(direct, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,903 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    io::{self, Read, Write},
+};
+
+use shakmaty::{san::SanPlus, Outcome};
+
+use crate::{
+    error::PgnError,
+    game::GameBuilder,
+    headers::SEVEN_TAG_ROSTER,
+    reader::{BufferedReader, SliceReader},
+    types::{Nag, RawComment, RawHeader, Skip},
+    visitor::{Tee, Visitor},
+};
+
+/// A fingerprint of a game.
+///
+/// Computed from the Seven Tag Roster and the mainline moves. Comments, NAGs,
+/// variations and check or checkmate suffixes do not contribute, so the same
+/// game with different annotations has the same hash.
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct GameHash(pub u64);
+
+/// 64 bit FNV-1a, which is stable across platforms and compiler versions.
+#[derive(Debug, Clone)]
+struct Fnv(u64);
+
+impl Fnv {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    fn new() -> Fnv {
+        Fnv(Fnv::OFFSET_BASIS)
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(Fnv::PRIME);
+        }
+    }
+}
+
+impl Write for Fnv {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A visitor that computes the [`GameHash`] of each game.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, GameHasher};
+///
+/// let pgn = b"1. e4 { best by test } e5 (1... c5) 2. Nf3 *
+///
+///             1. e4 e5 2. Nf3 $1 *";
+///
+/// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+/// let mut hasher = GameHasher::new();
+/// let first = reader.read_game(&mut hasher)?;
+/// let second = reader.read_game(&mut hasher)?;
+/// assert_eq!(first, second);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct GameHasher {
+    hash: Fnv,
+    depth: usize,
+}
+
+impl GameHasher {
+    pub fn new() -> GameHasher {
+        GameHasher {
+            hash: Fnv::new(),
+            depth: 0,
+        }
+    }
+}
+
+impl Default for GameHasher {
+    fn default() -> GameHasher {
+        GameHasher::new()
+    }
+}
+
+impl Visitor for GameHasher {
+    type Result = GameHash;
+
+    fn begin_game(&mut self) {
+        self.hash = Fnv::new();
+        self.depth = 0;
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if SEVEN_TAG_ROSTER.contains(&key) {
+            self.hash.update(key);
+            self.hash.update(b"\0");
+            self.hash.update(&value.decode());
+            self.hash.update(b"\0");
+        }
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        if self.depth == 0 {
+            let _ = san_plus.san.write_ascii_to(&mut self.hash);
+            self.hash.update(b" ");
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.depth += 1;
+        Skip(true)
+    }
+
+    fn end_variation(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
+    fn end_game(&mut self) -> GameHash {
+        GameHash(self.hash.0)
+    }
+}
+
+/// Pairs the result of a visitor with the [`GameHash`] of the game.
+///
+/// The movetext is always read, because the hash depends on it. If the inner
+/// visitor skips the movetext, it simply does not receive the callbacks.
+///
+/// # Examples
+///
+/// Collecting games into a map keyed by their hash:
+///
+/// ```
+/// use std::collections::HashMap;
+/// use pgn_reader::{BufferedReader, GameHash, Visitor, WithHash};
+///
+/// struct Plies(usize);
+///
+/// impl Visitor for Plies {
+///     type Result = usize;
+///
+///     fn begin_game(&mut self) {
+///         self.0 = 0;
+///     }
+///
+///     fn san(&mut self, _san_plus: pgn_reader::SanPlus) {
+///         self.0 += 1;
+///     }
+///
+///     fn end_game(&mut self) -> usize {
+///         self.0
+///     }
+/// }
+///
+/// let pgn = b"1. d4 d5 *\n\n1. e4 *\n\n1. d4 d5 *";
+///
+/// let reader = BufferedReader::new_cursor(&pgn[..]);
+/// let mut visitor = WithHash::new(Plies(0));
+/// let games = reader.into_iter(&mut visitor).collect::<Result<HashMap<GameHash, usize>, _>>()?;
+/// assert_eq!(games.len(), 2);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WithHash<V> {
+    inner: V,
+    hasher: GameHasher,
+    skip_movetext: bool,
+    skip_depth: usize,
+}
+
+impl<V> WithHash<V> {
+    pub fn new(inner: V) -> WithHash<V> {
+        WithHash {
+            inner,
+            hasher: GameHasher::new(),
+            skip_movetext: false,
+            skip_depth: 0,
+        }
+    }
+
+    /// Returns the wrapped visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+
+    /// Whether the inner visitor should currently receive movetext.
+    fn forward(&self) -> bool {
+        !self.skip_movetext && self.skip_depth == 0
+    }
+}
+
+impl<V: Visitor> Visitor for WithHash<V> {
+    type Result = (GameHash, V::Result);
+
+    fn begin_game(&mut self) {
+        self.hasher.begin_game();
+        self.skip_movetext = false;
+        self.skip_depth = 0;
+        self.inner.begin_game();
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.inner.game_index(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.inner.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.hasher.header(key, value.clone());
+        self.inner.header(key, value);
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.inner.setup(fen);
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        self.inner.raw_headers(headers);
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        let Skip(skip) = self.inner.end_headers();
+        self.skip_movetext = skip;
+        Skip(false)
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        if self.forward() {
+            self.inner.move_number(number, black_to_move);
+        }
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        if self.forward() {
+            self.inner.san(san_plus.clone());
+        }
+        self.hasher.san(san_plus);
+    }
+
+    fn null_move(&mut self) {
+        if self.forward() {
+            self.inner.null_move();
+        }
+        self.hasher.null_move();
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        if self.forward() {
+            self.inner.unknown_token(token);
+        }
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        if self.forward() {
+            self.inner.garbage(token);
+        }
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        if self.forward() {
+            self.inner.nag(nag);
+        }
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        if self.forward() {
+            self.inner.comment(comment);
+        }
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        if self.forward() {
+            self.inner.pre_game_comment(comment);
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        // Variations do not contribute to the hash, so the reader may skip
+        // them whenever the inner visitor is not interested.
+        let _ = self.hasher.begin_variation();
+        if self.forward() {
+            self.inner.begin_variation()
+        } else {
+            self.skip_depth += 1;
+            Skip(true)
+        }
+    }
+
+    fn end_variation(&mut self) {
+        self.hasher.end_variation();
+        if self.skip_depth > 0 {
+            self.skip_depth -= 1;
+        } else if !self.skip_movetext {
+            self.inner.end_variation();
+        }
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        if self.forward() {
+            self.inner.outcome(outcome);
+        }
+    }
+
+    fn escape(&mut self, line: &[u8]) {
+        if self.forward() {
+            self.inner.escape(line);
+        }
+    }
+
+    fn truncated(&mut self) {
+        if !self.skip_movetext {
+            self.inner.truncated();
+        }
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        self.inner.raw_movetext(movetext);
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        if self.forward() {
+            self.inner.warning(error);
+        }
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.inner.recovered(error);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        (self.hasher.end_game(), self.inner.end_game())
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.inner.abort()
+    }
+}
+
+/// Drops games that were already seen, identified by a key visitor like
+/// [`GameHasher`].
+///
+/// Each game is read into a [`Game`](crate::Game) while the key is
+/// computed. If the key is new, the game is passed to the inner visitor
+/// with [`Game::visit()`](crate::Game::visit) and the result is `Some`.
+/// Otherwise the inner visitor does not see the game at all, and the
+/// result is `None`. Like with [`GameBuilder`],
+/// escaped lines, unknown tokens and warnings are not passed on.
+///
+/// The default key ignores comments, NAGs, variations and all headers
+/// except the Seven Tag Roster. Use [`DedupVisitor::with_key()`] with a
+/// different visitor to change what counts as a duplicate.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, DedupVisitor, PgnWriter};
+///
+/// let pgn = b"1. e4 e5 *\n\n1. d4 *\n\n1. e4 { [%clk 0:03:00] } e5 (1... c5) *";
+///
+/// let mut visitor = DedupVisitor::new(PgnWriter::new(Vec::new()));
+/// BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+/// assert_eq!(visitor.dropped(), 1);
+/// assert_eq!(visitor.into_inner().into_inner(), b"1. e4 e5 *\n\n1. d4 *\n\n");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct DedupVisitor<V, K = GameHasher, S = HashSet<GameHash>> {
+    inner: V,
+    tee: Tee<K, GameBuilder>,
+    seen: S,
+    dropped: usize,
+}
+
+impl<V> DedupVisitor<V> {
+    pub fn new(inner: V) -> DedupVisitor<V> {
+        DedupVisitor::with_key(inner, GameHasher::new(), HashSet::new())
+    }
+}
+
+impl<V, K, S> DedupVisitor<V, K, S> {
+    /// Identifies games by the result of `key`, recording the keys that
+    /// were seen in `seen`.
+    pub fn with_key(inner: V, key: K, seen: S) -> DedupVisitor<V, K, S> {
+        DedupVisitor {
+            inner,
+            tee: Tee::new(key, GameBuilder::new()),
+            seen,
+            dropped: 0,
+        }
+    }
+
+    /// Returns the number of games that were dropped so far.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Returns a reference to the wrapped visitor.
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    /// Returns the wrapped visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+impl<V, K, S> Visitor for DedupVisitor<V, K, S>
+where
+    V: Visitor,
+    K: Visitor<Result = GameHash>,
+    S: SeenGames,
+{
+    type Result = Option<V::Result>;
+
+    fn begin_game(&mut self) {
+        self.tee.begin_game();
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.tee.game_index(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.tee.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.tee.header(key, value);
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.tee.setup(fen);
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        self.tee.raw_headers(headers);
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.tee.end_headers()
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        self.tee.move_number(number, black_to_move);
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.tee.san(san_plus);
+    }
+
+    fn null_move(&mut self) {
+        self.tee.null_move();
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        self.tee.unknown_token(token);
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        self.tee.garbage(token);
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.tee.nag(nag);
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        self.tee.comment(comment);
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        self.tee.pre_game_comment(comment);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.tee.begin_variation()
+    }
+
+    fn end_variation(&mut self) {
+        self.tee.end_variation();
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.tee.outcome(outcome);
+    }
+
+    fn escape(&mut self, line: &[u8]) {
+        self.tee.escape(line);
+    }
+
+    fn truncated(&mut self) {
+        self.tee.truncated();
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        self.tee.raw_movetext(movetext);
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        self.tee.warning(error);
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.tee.recovered(error);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        let (hash, game) = self.tee.end_game();
+        if self.seen.insert(hash) {
+            Some(game.visit(&mut self.inner))
+        } else {
+            self.dropped += 1;
+            None
+        }
+    }
+
+    fn finish(&mut self) {
+        self.tee.finish();
+        self.inner.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.inner.abort()
+    }
+}
+
+/// A set of [`GameHash`] values seen so far, used by
+/// [`merge_dedup_with()`] and [`DedupVisitor`].
+pub trait SeenGames {
+    /// Adds a hash. Returns `false` if it may have been added before.
+    fn insert(&mut self, hash: GameHash) -> bool;
+}
+
+impl<S: std::hash::BuildHasher> SeenGames for HashSet<GameHash, S> {
+    fn insert(&mut self, hash: GameHash) -> bool {
+        HashSet::insert(self, hash)
+    }
+}
+
+impl SeenGames for BTreeSet<GameHash> {
+    fn insert(&mut self, hash: GameHash) -> bool {
+        BTreeSet::insert(self, hash)
+    }
+}
+
+/// A Bloom filter of [`GameHash`] values, using about 10 bits per game
+/// instead of a full hash set entry.
+///
+/// Lookups have no false negatives. About 1% of new games are falsely
+/// reported as seen, if no more than the expected number of games are
+/// inserted.
+#[derive(Debug, Clone)]
+pub struct BloomFilter {
+    bits: Vec<u64>,
+}
+
+impl BloomFilter {
+    const BITS_PER_GAME: usize = 10;
+    const NUM_PROBES: u64 = 7;
+
+    /// Creates a filter sized for the given number of games.
+    pub fn new(expected_games: usize) -> BloomFilter {
+        let words = expected_games.saturating_mul(BloomFilter::BITS_PER_GAME) / 64 + 1;
+        BloomFilter {
+            bits: vec![0; words],
+        }
+    }
+}
+
+impl SeenGames for BloomFilter {
+    fn insert(&mut self, GameHash(hash): GameHash) -> bool {
+        let num_bits = self.bits.len() as u64 * 64;
+        let step = (hash >> 32) | 1;
+        let mut seen = true;
+        for i in 0..BloomFilter::NUM_PROBES {
+            let bit = hash.wrapping_add(i.wrapping_mul(step)) % num_bits;
+            let (word, mask) = ((bit / 64) as usize, 1 << (bit % 64));
+            seen &= self.bits[word] & mask != 0;
+            self.bits[word] |= mask;
+        }
+        !seen
+    }
+}
+
+/// Writes all games from `a`, followed by the games from `b` that are not
+/// already in `a`, identified by their [`GameHash`]. Returns the number of
+/// games from `b` that were dropped.
+///
+/// Games are copied as they appear in the input, including comments,
+/// variations and unknown tags. Only the whitespace after each game is
+/// replaced by a single blank line. The hash is used to decide which games
+/// to keep, never to produce the output. If the bytes of a game parse to more
+/// than one game, all of them are hashed, and the bytes are dropped only if
+/// every one of them is a duplicate.
+///
+/// Duplicates within `a` are kept. Duplicates within `b` are dropped.
+///
+/// The hashes of all games are kept in memory. Use [`merge_dedup_with()`]
+/// to provide a different [`SeenGames`] implementation, like a
+/// [`BloomFilter`].
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::hash::merge_dedup;
+///
+/// let a = b"1. e4 e5 *\n\n1. d4 d5 *";
+/// let b = b"1. c4 { English } *\n\n1. e4 { again } e5 *";
+///
+/// let mut out = Vec::new();
+/// let dropped = merge_dedup(&a[..], &b[..], &mut out)?;
+/// assert_eq!(dropped, 1);
+/// assert_eq!(out, b"1. e4 e5 *\n\n1. d4 d5 *\n\n1. c4 { English } *\n\n");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Stops at the first error from reading or writing.
+pub fn merge_dedup<R1: Read, R2: Read, W: Write>(a: R1, b: R2, out: W) -> io::Result<usize> {
+    merge_dedup_with(a, b, out, &mut HashSet::new())
+}
+
+/// Like [`merge_dedup()`], but records hashes in `seen`.
+///
+/// With a [`BloomFilter`], a few games from `b` may be dropped even though
+/// they are not duplicates.
+///
+/// # Errors
+///
+/// Stops at the first error from reading or writing.
+pub fn merge_dedup_with<R1, R2, W, S>(a: R1, b: R2, mut out: W, seen: &mut S) -> io::Result<usize>
+where
+    R1: Read,
+    R2: Read,
+    W: Write,
+    S: SeenGames + ?Sized,
+{
+    let mut hasher = GameHasher::new();
+
+    for raw in BufferedReader::new(a).raw_games() {
+        let raw = raw?;
+        insert_raw_game(&raw, &mut hasher, seen)?;
+        write_raw_game(&mut out, &raw)?;
+    }
+
+    let mut dropped = 0;
+    for raw in BufferedReader::new(b).raw_games() {
+        let raw = raw?;
+        if insert_raw_game(&raw, &mut hasher, seen)? {
+            write_raw_game(&mut out, &raw)?;
+        } else {
+            dropped += 1;
+        }
+    }
+
+    out.flush()?;
+    Ok(dropped)
+}
+
+/// Records the hash of every game in `raw`. Returns `false` if all of them
+/// may have been seen before, and `true` if there are none.
+fn insert_raw_game<S: SeenGames + ?Sized>(
+    raw: &[u8],
+    hasher: &mut GameHasher,
+    seen: &mut S,
+) -> io::Result<bool> {
+    let mut reader = SliceReader::new(raw);
+    let mut games = 0;
+    let mut new = false;
+    while let Some(hash) = reader.read_game(hasher)? {
+        games += 1;
+        new |= seen.insert(hash);
+    }
+    Ok(new || games == 0)
+}
+
+fn write_raw_game<W: Write>(out: &mut W, raw: &[u8]) -> io::Result<()> {
+    out.write_all(raw.trim_ascii_end())?;
+    out.write_all(b"\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::reader::BufferedReader;
+
+    struct Sans(Vec<String>);
+
+    impl Visitor for Sans {
+        type Result = Vec<String>;
+
+        fn san(&mut self, san_plus: SanPlus) {
+            self.0.push(san_plus.to_string());
+        }
+
+        fn begin_variation(&mut self) -> Skip {
+            Skip(true)
+        }
+
+        fn end_game(&mut self) -> Self::Result {
+            std::mem::take(&mut self.0)
+        }
+    }
+
+    #[test]
+    fn test_with_hash_map() -> io::Result<()> {
+        let pgn = b"[White \"Deep Blue\"]\n[Black \"Kasparov\"]\n\n1. e4 c5 2. Nf3 *\n\n\
+                    [White \"Kasparov\"]\n[Black \"Deep Blue\"]\n\n1. e4 c5 2. Nf3 *\n\n\
+                    [White \"Deep Blue\"]\n[Black \"Kasparov\"]\n\n1. e4 { again } c5 (1... e5) 2. Nf3+ *\n";
+
+        let reader = BufferedReader::new_cursor(&pgn[..]);
+        let mut visitor = WithHash::new(Sans(Vec::new()));
+
+        let mut games = HashMap::new();
+        let mut duplicates = 0;
+        for game in reader.into_iter(&mut visitor) {
+            let (hash, sans) = game?;
+            assert_eq!(sans.len(), 3);
+            if games.insert(hash, sans).is_some() {
+                duplicates += 1;
+            }
+        }
+
+        assert_eq!(games.len(), 2);
+        assert_eq!(duplicates, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_dedup_visitor() -> io::Result<()> {
+        let pgn = b"[White \"A\"]\n\n1. e4 e5 *\n\n\
+                    [White \"B\"]\n\n1. e4 e5 *\n\n\
+                    [White \"A\"]\n[Annotator \"X\"]\n\n1. e4 $1 { [%clk 0:01:00] } e5 (1... c5) *\n\n\
+                    [White \"A\"]\n\n1. d4 *\n\n";
+
+        let mut visitor = DedupVisitor::new(Sans(Vec::new()));
+        let games = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(
+            games,
+            [
+                Some(vec!["e4".to_owned(), "e5".to_owned()]),
+                Some(vec!["e4".to_owned(), "e5".to_owned()]),
+                None,
+                Some(vec!["d4".to_owned()]),
+            ]
+        );
+        assert_eq!(visitor.dropped(), 1);
+
+        // A key that only looks at White.
+        struct White(Fnv);
+
+        impl Visitor for White {
+            type Result = GameHash;
+
+            fn begin_game(&mut self) {
+                self.0 = Fnv::new();
+            }
+
+            fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+                if key == b"White" {
+                    self.0.update(value.as_bytes());
+                }
+            }
+
+            fn end_headers(&mut self) -> Skip {
+                Skip(true)
+            }
+
+            fn end_game(&mut self) -> GameHash {
+                GameHash(self.0 .0)
+            }
+        }
+
+        let mut visitor =
+            DedupVisitor::with_key(Sans(Vec::new()), White(Fnv::new()), BTreeSet::new());
+        let games = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(games.iter().filter(|game| game.is_some()).count(), 2);
+        assert_eq!(visitor.dropped(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_dedup() -> io::Result<()> {
+        let a = b"[White \"A\"]\n\n1. e4 e5 *\n\n[White \"A\"]\n\n1. d4 d5 1-0\n\n";
+        let b = b"[White \"A\"]\n\n1. d4 d5 1-0\n\n\
+                  [White \"B\"]\n\n1. d4 { kept } d5 (1... Nf6) 1-0\n\n\
+                  [White \"A\"]\n\n1. e4 {x} e5 (1... c5) *\n\n\
+                  [White \"B\"]\n\n1. d4 d5 1-0\n\n";
+
+        let expected: &[u8] = b"[White \"A\"]\n\n1. e4 e5 *\n\n\
+                                [White \"A\"]\n\n1. d4 d5 1-0\n\n\
+                                [White \"B\"]\n\n1. d4 { kept } d5 (1... Nf6) 1-0\n\n";
+
+        let mut out = Vec::new();
+        assert_eq!(merge_dedup(&a[..], &b[..], &mut out)?, 3);
+        assert_eq!(out, expected);
+
+        let mut seen = BloomFilter::new(100);
+        let mut out = Vec::new();
+        assert_eq!(merge_dedup_with(&a[..], &b[..], &mut out, &mut seen)?, 3);
+        assert_eq!(out, expected);
+
+        // An external set keeps the hashes for the next merge.
+        let mut seen = BTreeSet::new();
+        merge_dedup_with(&a[..], &b""[..], io::sink(), &mut seen)?;
+        let mut out = Vec::new();
+        assert_eq!(merge_dedup_with(&b""[..], &b[..], &mut out, &mut seen)?, 3);
+        assert_eq!(
+            out,
+            b"[White \"B\"]\n\n1. d4 { kept } d5 (1... Nf6) 1-0\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_insert_raw_game() -> io::Result<()> {
+        let mut hasher = GameHasher::new();
+        let mut seen = HashSet::new();
+        assert!(insert_raw_game(b"1. e4 *", &mut hasher, &mut seen)?);
+        assert!(!insert_raw_game(b"1. e4 *", &mut hasher, &mut seen)?);
+        assert!(insert_raw_game(b"  \n", &mut hasher, &mut seen)?);
+
+        // Every game in the bytes counts, not just the first.
+        assert!(insert_raw_game(
+            b"1. e4 *\n\n1. d4 *",
+            &mut hasher,
+            &mut seen
+        )?);
+        assert_eq!(seen.len(), 2);
+        assert!(!insert_raw_game(
+            b"1. d4 *\n\n1. e4 *",
+            &mut hasher,
+            &mut seen
+        )?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_bloom_filter() {
+        let mut filter = BloomFilter::new(1000);
+        let mut false_positives = 0;
+        for i in 0..1000u64 {
+            let hash = GameHash(i.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+            if !filter.insert(hash) {
+                false_positives += 1;
+            }
+            assert!(!filter.insert(hash));
+        }
+        assert!(false_positives < 30, "{} false positives", false_positives);
+    }
+
+    #[test]
+    fn test_with_hash_inner_skips() -> io::Result<()> {
+        struct Skipper;
+
+        impl Visitor for Skipper {
+            type Result = ();
+
+            fn end_headers(&mut self) -> Skip {
+                Skip(true)
+            }
+
+            fn san(&mut self, _san_plus: SanPlus) {
+                panic!("movetext should not be forwarded");
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let pgn = b"1. e4 e5 *";
+        let (hash, ()) = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut WithHash::new(Skipper))?
+            .unwrap();
+        let expected = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut GameHasher::new())?
+            .unwrap();
+        assert_eq!(hash, expected);
+        Ok(())
+    }
+}
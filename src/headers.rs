@@ -0,0 +1,1008 @@
+use std::{collections::BTreeMap, error::Error, fmt, str::FromStr, time::Duration};
+
+use shakmaty::{fen::Fen, Bitboard, ByColor, CastlingMode, Color, File};
+
+/// The tags of the Seven Tag Roster, in the order required by the PGN
+/// standard.
+pub const SEVEN_TAG_ROSTER: [&[u8]; 7] = [
+    b"Event", b"Site", b"Date", b"Round", b"White", b"Black", b"Result",
+];
+
+/// Decoded header values by tag name, as collected by
+/// [`GameSummary`](crate::GameSummary).
+pub type Headers = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// Header keys that visitors commonly look for, like filters on players,
+/// ratings and time controls.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum KnownKey {
+    White,
+    Black,
+    WhiteElo,
+    BlackElo,
+    WhiteTitle,
+    BlackTitle,
+    Result,
+    TimeControl,
+    Date,
+    UTCDate,
+    Eco,
+    Round,
+}
+
+impl KnownKey {
+    /// Classifies a header key, or returns `None` if it is not one of the
+    /// known keys. Keys are case sensitive.
+    ///
+    /// Dispatches on the length and the first byte, so that at most one
+    /// full comparison is needed.
+    ///
+    /// ```
+    /// use pgn_reader::headers::KnownKey;
+    ///
+    /// assert_eq!(KnownKey::from_ascii(b"WhiteElo"), Some(KnownKey::WhiteElo));
+    /// assert_eq!(KnownKey::from_ascii(b"Event"), None);
+    /// ```
+    #[inline]
+    pub fn from_ascii(key: &[u8]) -> Option<KnownKey> {
+        let (candidate, name) = KnownKey::candidate(key.len(), *key.first()?)?;
+        (key == name).then_some(candidate)
+    }
+
+    /// Like [`KnownKey::from_ascii()`], but ignores ASCII case, so that
+    /// `whiteelo` and `WHITEELO` are [`KnownKey::WhiteElo`] as well.
+    ///
+    /// ```
+    /// use pgn_reader::headers::KnownKey;
+    ///
+    /// assert_eq!(KnownKey::from_ascii_ignore_case(b"whiteelo"), Some(KnownKey::WhiteElo));
+    /// assert_eq!(KnownKey::from_ascii_ignore_case(b"Eco"), Some(KnownKey::Eco));
+    /// ```
+    #[inline]
+    pub fn from_ascii_ignore_case(key: &[u8]) -> Option<KnownKey> {
+        let (candidate, name) = KnownKey::candidate(key.len(), key.first()?.to_ascii_uppercase())?;
+        key.eq_ignore_ascii_case(name).then_some(candidate)
+    }
+
+    fn candidate(len: usize, first: u8) -> Option<(KnownKey, &'static [u8])> {
+        Some(match (len, first) {
+            (5, b'W') => (KnownKey::White, b"White"),
+            (5, b'B') => (KnownKey::Black, b"Black"),
+            (6, b'R') => (KnownKey::Result, b"Result"),
+            (5, b'R') => (KnownKey::Round, b"Round"),
+            (8, b'W') => (KnownKey::WhiteElo, b"WhiteElo"),
+            (8, b'B') => (KnownKey::BlackElo, b"BlackElo"),
+            (10, b'W') => (KnownKey::WhiteTitle, b"WhiteTitle"),
+            (10, b'B') => (KnownKey::BlackTitle, b"BlackTitle"),
+            (11, b'T') => (KnownKey::TimeControl, b"TimeControl"),
+            (4, b'D') => (KnownKey::Date, b"Date"),
+            (7, b'U') => (KnownKey::UTCDate, b"UTCDate"),
+            (3, b'E') => (KnownKey::Eco, b"ECO"),
+            _ => return None,
+        })
+    }
+}
+
+/// Classifies a header key like [`KnownKey::from_ascii()`], but also
+/// accepts the spellings found in real files, like `whiteelo` or `Eco`.
+/// Returns `None` for other keys, which visitors should pass through as
+/// they are.
+///
+/// Synonyms keep their own variant where the distinction matters, like
+/// [`KnownKey::UTCDate`] next to [`KnownKey::Date`], so that callers can
+/// decide whether to treat them alike.
+///
+/// ```
+/// use pgn_reader::headers::{canonical_tag, KnownKey};
+///
+/// assert_eq!(canonical_tag(b"WhiteElo"), Some(KnownKey::WhiteElo));
+/// assert_eq!(canonical_tag(b"whiteelo"), Some(KnownKey::WhiteElo));
+/// assert_eq!(canonical_tag(b"utcdate"), Some(KnownKey::UTCDate));
+/// assert_eq!(canonical_tag(b"Event"), None);
+/// ```
+#[inline]
+pub fn canonical_tag(key: &[u8]) -> Option<KnownKey> {
+    KnownKey::from_ascii(key).or_else(|| KnownKey::from_ascii_ignore_case(key))
+}
+
+/// The rules a game is played with.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Variant {
+    /// Standard chess, possibly from a custom position.
+    Standard,
+    /// Chess960, also known as Fischer Random Chess.
+    Chess960,
+    Crazyhouse,
+    Atomic,
+    /// Also known as Giveaway or Losing Chess.
+    Antichess,
+    Horde,
+    RacingKings,
+    KingOfTheHill,
+    ThreeCheck,
+    /// A variant that is not recognized.
+    Other,
+}
+
+impl Variant {
+    /// Parses the value of a `Variant` header, like `Chess960`,
+    /// `Crazyhouse` or `Racing Kings`, ignoring case. Empty values and names
+    /// of standard chess, like `Standard` or `From Position`, are
+    /// [`Variant::Standard`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::headers::Variant;
+    ///
+    /// assert_eq!(Variant::from_ascii(b"Fischer Random"), Variant::Chess960);
+    /// assert_eq!(Variant::from_ascii(b"racing kings"), Variant::RacingKings);
+    /// assert_eq!(Variant::from_ascii(b"From Position"), Variant::Standard);
+    /// assert_eq!(Variant::from_ascii(b"Bughouse"), Variant::Other);
+    /// ```
+    pub fn from_ascii(s: &[u8]) -> Variant {
+        match s.trim_ascii().to_ascii_lowercase().as_slice() {
+            b"" | b"standard" | b"chess" | b"normal" | b"from position" | b"fromposition" => {
+                Variant::Standard
+            }
+            b"chess960"
+            | b"chess 960"
+            | b"960"
+            | b"fischerandom"
+            | b"fischer random"
+            | b"fischer random chess" => Variant::Chess960,
+            b"crazyhouse" | b"zh" => Variant::Crazyhouse,
+            b"atomic" | b"atomic chess" => Variant::Atomic,
+            b"antichess" | b"giveaway" | b"losing chess" | b"suicide" => Variant::Antichess,
+            b"horde" | b"horde chess" => Variant::Horde,
+            b"racing kings" | b"racingkings" | b"racing-kings" => Variant::RacingKings,
+            b"king of the hill" | b"kingofthehill" | b"koth" => Variant::KingOfTheHill,
+            b"three-check" | b"threecheck" | b"three check" | b"3check" | b"3-check" => {
+                Variant::ThreeCheck
+            }
+            _ => Variant::Other,
+        }
+    }
+
+    /// How castling moves are represented in this variant, for example in
+    /// UCI notation.
+    pub fn castling_mode(self) -> CastlingMode {
+        match self {
+            Variant::Chess960 => CastlingMode::Chess960,
+            _ => CastlingMode::Standard,
+        }
+    }
+
+    /// The rules of the variant in `shakmaty`, or `None` for
+    /// [`Variant::Other`]. Chess960 uses the rules of standard chess, with
+    /// [`Variant::castling_mode()`].
+    ///
+    /// Requires the `variant` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::headers::Variant;
+    /// use shakmaty::{variant::VariantPosition, Position};
+    ///
+    /// let rules = Variant::from_ascii(b"Horde").to_shakmaty().unwrap();
+    /// let pos = VariantPosition::new(rules);
+    /// assert_eq!(pos.board().white().count(), 36);
+    /// ```
+    #[cfg(feature = "variant")]
+    pub fn to_shakmaty(self) -> Option<shakmaty::variant::Variant> {
+        use shakmaty::variant::Variant as Rules;
+
+        Some(match self {
+            Variant::Standard | Variant::Chess960 => Rules::Chess,
+            Variant::Crazyhouse => Rules::Crazyhouse,
+            Variant::Atomic => Rules::Atomic,
+            Variant::Antichess => Rules::Antichess,
+            Variant::Horde => Rules::Horde,
+            Variant::RacingKings => Rules::RacingKings,
+            Variant::KingOfTheHill => Rules::KingOfTheHill,
+            Variant::ThreeCheck => Rules::ThreeCheck,
+            Variant::Other => return None,
+        })
+    }
+}
+
+/// Detects the variant of a game from its `Variant` and `FEN` headers.
+///
+/// Games without a `Variant` header, or with one that just names standard
+/// chess, are detected as Chess960 if the `FEN` has castling rights that
+/// are impossible in standard chess.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::headers::{detect_variant, Headers, Variant};
+///
+/// let mut headers = Headers::new();
+/// assert_eq!(detect_variant(&headers), Variant::Standard);
+///
+/// headers.insert(b"FEN".to_vec(), b"bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9".to_vec());
+/// assert_eq!(detect_variant(&headers), Variant::Chess960);
+/// ```
+pub fn detect_variant(headers: &Headers) -> Variant {
+    if let Some(variant) = headers.get(&b"Variant"[..]) {
+        match Variant::from_ascii(variant) {
+            Variant::Standard => {}
+            variant => return variant,
+        }
+    }
+
+    match headers
+        .get(&b"FEN"[..])
+        .and_then(|fen| castling_files_from_fen(fen))
+    {
+        Some(files) if !files.white.is_standard() || !files.black.is_standard() => {
+            Variant::Chess960
+        }
+        _ => Variant::Standard,
+    }
+}
+
+/// The files of the king and of the rooks that still have castling rights,
+/// for one side.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct CastlingFiles {
+    pub king: Option<File>,
+    pub king_side: Option<File>,
+    pub queen_side: Option<File>,
+}
+
+impl CastlingFiles {
+    /// Whether these castling rights are possible in standard chess: no
+    /// castling rights at all, or the king on the e-file with rooks on the
+    /// a- and h-file.
+    pub fn is_standard(&self) -> bool {
+        (self.king_side.is_none() && self.queen_side.is_none())
+            || (self.king == Some(File::E)
+                && self.king_side.is_none_or(|file| file == File::H)
+                && self.queen_side.is_none_or(|file| file == File::A))
+    }
+}
+
+/// Gets the files of king and castling rooks from a FEN, or `None` if the
+/// FEN is invalid.
+///
+/// Supports the traditional `KQkq` notation as well as Shredder-FEN and
+/// X-FEN.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{headers::castling_files_from_fen, File};
+///
+/// let files = castling_files_from_fen(b"rkrnbbqn/pppppppp/8/8/8/8/PPPPPPPP/RKRNBBQN w KQkq - 0 1").unwrap();
+/// assert_eq!(files.white.king, Some(File::B));
+/// assert_eq!(files.white.king_side, Some(File::C));
+/// assert_eq!(files.white.queen_side, Some(File::A));
+/// ```
+pub fn castling_files_from_fen(fen: &[u8]) -> Option<ByColor<CastlingFiles>> {
+    let Fen(setup) = Fen::from_ascii(fen).ok()?;
+    Some(ByColor::new_with(|color: Color| {
+        let king = setup
+            .board
+            .king_of(color)
+            .filter(|sq| sq.rank() == color.backrank());
+        let rooks = setup.castling_rights & Bitboard::from_rank(color.backrank());
+        let (king_side, queen_side) = match king {
+            Some(king) => (
+                rooks.into_iter().rev().find(|sq| sq.file() > king.file()),
+                rooks.into_iter().find(|sq| sq.file() < king.file()),
+            ),
+            None => (None, None),
+        };
+        CastlingFiles {
+            king: king.map(|sq| sq.file()),
+            king_side: king_side.map(|sq| sq.file()),
+            queen_side: queen_side.map(|sq| sq.file()),
+        }
+    }))
+}
+
+/// A period of a [`TimeControl`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Period {
+    /// Number of moves to be made in this period, or `None` for the rest of
+    /// the game.
+    pub moves: Option<u32>,
+    /// Time available for the period.
+    pub base: Duration,
+    /// Time added after each move.
+    pub increment: Duration,
+    /// Whether this is a sandclock period (`*180`), where the time spent by
+    /// one player is added to the clock of the other.
+    pub sandclock: bool,
+}
+
+impl Period {
+    /// Estimates the total time per player, assuming a game of 40 moves.
+    /// This is the estimate commonly used to classify games by speed.
+    ///
+    /// Saturates at [`Duration::MAX`] for absurdly long time controls.
+    pub fn estimated_duration(&self) -> Duration {
+        self.base.saturating_add(self.increment.saturating_mul(40))
+    }
+}
+
+/// The value of a `TimeControl` header.
+///
+/// See the [PGN
+/// standard](http://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm#c9.6.1)
+/// for the format.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use pgn_reader::headers::TimeControl;
+///
+/// let tc = TimeControl::from_ascii(b"40/7200:20/3600:900+30")?;
+/// let periods = tc.periods();
+/// assert_eq!(periods.len(), 3);
+/// assert_eq!(periods[0].moves, Some(40));
+/// assert_eq!(periods[0].base, Duration::from_secs(7200));
+/// assert_eq!(periods[2].moves, None);
+/// assert_eq!(periods[2].increment, Duration::from_secs(30));
+/// # Ok::<_, pgn_reader::headers::InvalidTimeControl>(())
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub enum TimeControl {
+    /// `?`
+    Unknown,
+    /// `-`, no time control.
+    Unlimited,
+    /// One or more periods, separated by `:`.
+    Periods(Vec<Period>),
+}
+
+impl TimeControl {
+    /// Tries to parse a time control from ASCII. Surrounding whitespace is
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidTimeControl`] if the input does not follow the
+    /// grammar of the PGN standard.
+    pub fn from_ascii(s: &[u8]) -> Result<TimeControl, InvalidTimeControl> {
+        let s = s.trim_ascii();
+        match s {
+            b"?" | b"" => Ok(TimeControl::Unknown),
+            b"-" => Ok(TimeControl::Unlimited),
+            _ => s
+                .split(|&ch| ch == b':')
+                .map(parse_period)
+                .collect::<Option<Vec<_>>>()
+                .map(TimeControl::Periods)
+                .ok_or(InvalidTimeControl { _priv: () }),
+        }
+    }
+
+    /// Returns the periods, which is empty for unknown and unlimited time
+    /// controls.
+    pub fn periods(&self) -> &[Period] {
+        match self {
+            TimeControl::Periods(periods) => periods,
+            _ => &[],
+        }
+    }
+
+    /// Returns the first period, which is the one that determines the speed
+    /// of the game.
+    pub fn first_period(&self) -> Option<&Period> {
+        self.periods().first()
+    }
+}
+
+fn parse_seconds(s: &[u8]) -> Option<Duration> {
+    btoi::btou(s).ok().map(Duration::from_secs)
+}
+
+fn parse_period(s: &[u8]) -> Option<Period> {
+    if let Some(sandclock) = s.strip_prefix(b"*") {
+        return Some(Period {
+            moves: None,
+            base: parse_seconds(sandclock)?,
+            increment: Duration::ZERO,
+            sandclock: true,
+        });
+    }
+
+    let (moves, s) = match memchr::memchr(b'/', s) {
+        Some(slash) => (Some(btoi::btou(&s[..slash]).ok()?), &s[slash + 1..]),
+        None => (None, s),
+    };
+
+    let (base, increment) = match memchr::memchr(b'+', s) {
+        Some(plus) => (parse_seconds(&s[..plus])?, parse_seconds(&s[plus + 1..])?),
+        None => (parse_seconds(s)?, Duration::ZERO),
+    };
+
+    Some(Period {
+        moves,
+        base,
+        increment,
+        sandclock: false,
+    })
+}
+
+impl FromStr for TimeControl {
+    type Err = InvalidTimeControl;
+
+    fn from_str(s: &str) -> Result<TimeControl, InvalidTimeControl> {
+        TimeControl::from_ascii(s.as_bytes())
+    }
+}
+
+/// Error when parsing an invalid [`TimeControl`].
+#[derive(Clone, Eq, PartialEq)]
+pub struct InvalidTimeControl {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidTimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidTimeControl").finish()
+    }
+}
+
+impl fmt::Display for InvalidTimeControl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid time control".fmt(f)
+    }
+}
+
+impl Error for InvalidTimeControl {}
+
+/// Parses a clock value like `1:00:00` or `05:00`, as found in
+/// `WhiteClock` and `BlackClock` headers. Fractions of a second, like
+/// `0:00:59.5`, are supported.
+///
+/// Returns `Ok(None)` if the clock is unknown (`?`, `-` or empty).
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use pgn_reader::headers::parse_clock;
+///
+/// assert_eq!(parse_clock(b"1:30:00"), Ok(Some(Duration::from_secs(5400))));
+/// assert_eq!(parse_clock(b"?"), Ok(None));
+/// ```
+///
+/// # Errors
+///
+/// Returns [`InvalidClock`] if the value is neither unknown nor of the
+/// form `h:mm:ss` or `mm:ss`.
+pub fn parse_clock(s: &[u8]) -> Result<Option<Duration>, InvalidClock> {
+    let s = s.trim_ascii();
+    match s {
+        b"?" | b"-" | b"" => Ok(None),
+        _ => parse_hms(s).map(Some).ok_or(InvalidClock { _priv: () }),
+    }
+}
+
+/// Parses `h:mm:ss` or `mm:ss`, with an optional fraction of a second.
+pub(crate) fn parse_hms(s: &[u8]) -> Option<Duration> {
+    let (s, nanos) = match memchr::memchr(b'.', s) {
+        Some(dot) => {
+            let fraction = &s[dot + 1..];
+            if fraction.is_empty() || fraction.len() > 9 {
+                return None;
+            }
+            let digits: u32 = btoi::btou(fraction).ok()?;
+            (&s[..dot], digits * 10u32.pow(9 - fraction.len() as u32))
+        }
+        None => (s, 0),
+    };
+
+    let mut parts = s.rsplit(|&ch| ch == b':');
+    let seconds: u64 = btoi::btou(parts.next()?).ok()?;
+    let minutes: u64 = btoi::btou(parts.next()?).ok()?;
+    let hours: u64 = match parts.next() {
+        Some(hours) => btoi::btou(hours).ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() || seconds >= 60 || (minutes >= 60 && hours > 0) {
+        return None;
+    }
+
+    let secs = hours
+        .checked_mul(3600)?
+        .checked_add(minutes.checked_mul(60)?)?
+        .checked_add(seconds)?;
+    Some(Duration::new(secs, nanos))
+}
+
+/// Error when parsing an invalid clock with [`parse_clock()`].
+#[derive(Clone, Eq, PartialEq)]
+pub struct InvalidClock {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidClock").finish()
+    }
+}
+
+impl fmt::Display for InvalidClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid clock".fmt(f)
+    }
+}
+
+impl Error for InvalidClock {}
+
+/// The value of a `Date` or `UTCDate` header, like `2024.06.15`. Unknown
+/// parts are given as `??`, or `????` for the year, and are `None`.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::headers::Date;
+///
+/// let date = Date::from_ascii(b"1972.??.??")?;
+/// assert_eq!(date, Date { year: Some(1972), month: None, day: None });
+/// assert_eq!(date.to_string(), "1972.??.??");
+/// # Ok::<_, pgn_reader::headers::InvalidDate>(())
+/// ```
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct Date {
+    pub year: Option<u16>,
+    /// From 1 to 12.
+    pub month: Option<u8>,
+    /// From 1 to 31.
+    pub day: Option<u8>,
+}
+
+impl Date {
+    /// Tries to parse a date in the format `YYYY.MM.DD`. Surrounding
+    /// whitespace is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`InvalidDate`] if the input is not of that format, or the
+    /// month or day is out of range.
+    pub fn from_ascii(s: &[u8]) -> Result<Date, InvalidDate> {
+        let mut parts = s.trim_ascii().split(|&ch| ch == b'.');
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(year), Some(month), Some(day), None) => (|| {
+                Some(Date {
+                    year: parse_date_part(year, 4, 0, u16::MAX)?,
+                    month: parse_date_part(month, 2, 1, 12)?.map(|month| month as u8),
+                    day: parse_date_part(day, 2, 1, 31)?.map(|day| day as u8),
+                })
+            })(),
+            _ => None,
+        }
+        .ok_or(InvalidDate { _priv: () })
+    }
+
+    /// The first day the date may stand for, or `None` if the year is
+    /// unknown.
+    pub(crate) fn earliest(&self) -> Option<(u16, u8, u8)> {
+        Some((self.year?, self.month.unwrap_or(1), self.day.unwrap_or(1)))
+    }
+
+    /// The last day the date may stand for, or `None` if the year is
+    /// unknown. An unknown day is assumed to be the 31st.
+    pub(crate) fn latest(&self) -> Option<(u16, u8, u8)> {
+        Some((self.year?, self.month.unwrap_or(12), self.day.unwrap_or(31)))
+    }
+}
+
+/// Parses a part of a date with exactly `len` digits, or `?` for unknown.
+fn parse_date_part(s: &[u8], len: usize, min: u16, max: u16) -> Option<Option<u16>> {
+    if s.len() != len {
+        return None;
+    }
+    if s.iter().all(|&ch| ch == b'?') {
+        return Some(None);
+    }
+    if !s.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let value = btoi::btou(s).ok()?;
+    (min..=max).contains(&value).then_some(Some(value))
+}
+
+impl FromStr for Date {
+    type Err = InvalidDate;
+
+    fn from_str(s: &str) -> Result<Date, InvalidDate> {
+        Date::from_ascii(s.as_bytes())
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.year {
+            Some(year) => write!(f, "{:04}", year)?,
+            None => f.write_str("????")?,
+        }
+        for part in [self.month, self.day] {
+            match part {
+                Some(part) => write!(f, ".{:02}", part)?,
+                None => f.write_str(".??")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error when parsing an invalid [`Date`].
+#[derive(Clone, Eq, PartialEq)]
+pub struct InvalidDate {
+    _priv: (),
+}
+
+impl fmt::Debug for InvalidDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InvalidDate").finish()
+    }
+}
+
+impl fmt::Display for InvalidDate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        "invalid date".fmt(f)
+    }
+}
+
+impl Error for InvalidDate {}
+
+/// The value of a `Round` header, like `3` or `3.1` for the first game of
+/// the third round.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::headers::Round;
+///
+/// assert_eq!(Round::parse(b"3.1"), Round::Known(vec![3, 1]));
+/// assert_eq!(Round::parse(b"?"), Round::Unknown);
+/// assert_eq!(Round::parse(b"3.1").to_string(), "3.1");
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum Round {
+    /// `?` for an unknown round, `-` if rounds do not apply, or a value
+    /// that is not a round.
+    #[default]
+    Unknown,
+    /// The round, followed by the subrounds, if any. Never empty.
+    Known(Vec<u32>),
+}
+
+impl Round {
+    /// Parses a round like `3`, `3.1` or `3.1.2`. Surrounding whitespace
+    /// is ignored. Anything else, including `?` and `-`, is
+    /// [`Round::Unknown`].
+    pub fn parse(s: &[u8]) -> Round {
+        s.trim_ascii()
+            .split(|&ch| ch == b'.')
+            .map(|part| {
+                if part.iter().all(u8::is_ascii_digit) {
+                    btoi::btou(part).ok()
+                } else {
+                    None
+                }
+            })
+            .collect::<Option<Vec<u32>>>()
+            .map_or(Round::Unknown, Round::Known)
+    }
+
+    /// Whether this is the round `prefix`, or one of its subrounds. So
+    /// `3.1` is in round `3`, but not in round `31`. An unknown round is in
+    /// no round.
+    pub fn is_in(&self, prefix: &Round) -> bool {
+        match (self, prefix) {
+            (Round::Known(parts), Round::Known(prefix)) => parts.starts_with(prefix),
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Round {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Round::Unknown => f.write_str("?"),
+            Round::Known(parts) => {
+                for (i, part) in parts.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(".")?;
+                    }
+                    write!(f, "{}", part)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn period(moves: Option<u32>, base: u64, increment: u64) -> Period {
+        Period {
+            moves,
+            base: Duration::from_secs(base),
+            increment: Duration::from_secs(increment),
+            sandclock: false,
+        }
+    }
+
+    #[test]
+    fn test_single_period() {
+        assert_eq!(
+            TimeControl::from_ascii(b"300"),
+            Ok(TimeControl::Periods(vec![period(None, 300, 0)]))
+        );
+        assert_eq!(
+            TimeControl::from_ascii(b"40/9000"),
+            Ok(TimeControl::Periods(vec![period(Some(40), 9000, 0)]))
+        );
+        assert!(TimeControl::from_ascii(b"*180").unwrap().periods()[0].sandclock);
+        assert_eq!(TimeControl::from_ascii(b"?"), Ok(TimeControl::Unknown));
+        assert_eq!(TimeControl::from_ascii(b"-"), Ok(TimeControl::Unlimited));
+    }
+
+    #[test]
+    fn test_multi_period() {
+        assert_eq!(
+            TimeControl::from_ascii(b"40/7200:20/3600:900+30"),
+            Ok(TimeControl::Periods(vec![
+                period(Some(40), 7200, 0),
+                period(Some(20), 3600, 0),
+                period(None, 900, 30),
+            ]))
+        );
+        assert_eq!(
+            TimeControl::from_ascii(b"40/7200:1800"),
+            Ok(TimeControl::Periods(vec![
+                period(Some(40), 7200, 0),
+                period(None, 1800, 0),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_increment() {
+        let tc = TimeControl::from_ascii(b"0+1").unwrap();
+        assert_eq!(tc.periods(), [period(None, 0, 1)]);
+        assert_eq!(
+            tc.first_period().unwrap().estimated_duration(),
+            Duration::from_secs(40)
+        );
+        assert_eq!(
+            TimeControl::from_ascii(b"180+2").unwrap().periods(),
+            [period(None, 180, 2)]
+        );
+    }
+
+    #[test]
+    fn test_estimated_duration_overflow() {
+        let tc = TimeControl::from_ascii(b"0+999999999999999999").unwrap();
+        assert_eq!(
+            tc.first_period().unwrap().estimated_duration(),
+            Duration::MAX
+        );
+        let tc = TimeControl::from_ascii(b"18446744073709551615+1").unwrap();
+        assert_eq!(
+            tc.first_period().unwrap().estimated_duration(),
+            Duration::MAX
+        );
+    }
+
+    #[test]
+    fn test_round() {
+        assert_eq!(Round::parse(b"3"), Round::Known(vec![3]));
+        assert_eq!(Round::parse(b" 3.1 "), Round::Known(vec![3, 1]));
+        assert_eq!(Round::parse(b"3.1.2"), Round::Known(vec![3, 1, 2]));
+        assert_eq!(Round::parse(b"?"), Round::Unknown);
+        assert_eq!(Round::parse(b"-"), Round::Unknown);
+        assert_eq!(Round::parse(b""), Round::Unknown);
+        assert_eq!(Round::parse(b"3."), Round::Unknown);
+        assert_eq!(Round::parse(b"+3"), Round::Unknown);
+        assert_eq!(Round::parse(b"3.x"), Round::Unknown);
+        assert_eq!(Round::parse(b"99999999999"), Round::Unknown);
+
+        let round = |parts: &[u32]| Round::Known(parts.to_vec());
+        assert!(round(&[3, 1]).is_in(&round(&[3])));
+        assert!(round(&[3]).is_in(&round(&[3])));
+        assert!(!round(&[3]).is_in(&round(&[3, 1])));
+        assert!(!round(&[31]).is_in(&round(&[3])));
+        assert!(!Round::Unknown.is_in(&Round::Unknown));
+    }
+
+    #[test]
+    fn test_known_key() {
+        for (key, expected) in [
+            (&b"White"[..], Some(KnownKey::White)),
+            (b"Black", Some(KnownKey::Black)),
+            (b"WhiteElo", Some(KnownKey::WhiteElo)),
+            (b"BlackElo", Some(KnownKey::BlackElo)),
+            (b"WhiteTitle", Some(KnownKey::WhiteTitle)),
+            (b"BlackTitle", Some(KnownKey::BlackTitle)),
+            (b"Result", Some(KnownKey::Result)),
+            (b"TimeControl", Some(KnownKey::TimeControl)),
+            (b"Date", Some(KnownKey::Date)),
+            (b"UTCDate", Some(KnownKey::UTCDate)),
+            (b"EventDate", None),
+            (b"ECO", Some(KnownKey::Eco)),
+            (b"Eco", None),
+            (b"", None),
+            (b"white", None),
+            (b"Whitx", None),
+            (b"Blitz", None),
+            (b"WhiteEl", None),
+            (b"WhiteElo ", None),
+            (b"WhiteTeam", None),
+            (b"Round", Some(KnownKey::Round)),
+            (b"Rounds", None),
+            (b"Termination", None),
+        ] {
+            assert_eq!(KnownKey::from_ascii(key), expected, "{:?}", key);
+            if expected.is_some() {
+                assert_eq!(canonical_tag(key), expected, "{:?}", key);
+            }
+        }
+
+        for (key, expected) in [
+            (&b"white"[..], Some(KnownKey::White)),
+            (b"WHITEELO", Some(KnownKey::WhiteElo)),
+            (b"blackElo", Some(KnownKey::BlackElo)),
+            (b"Eco", Some(KnownKey::Eco)),
+            (b"timecontrol", Some(KnownKey::TimeControl)),
+            (b"utcDate", Some(KnownKey::UTCDate)),
+            (b"round", Some(KnownKey::Round)),
+            (b"EventDate", None),
+            (b"Whitx", None),
+            (b"WhiteElo ", None),
+            (b"\xc3\xa9", None),
+            (b"", None),
+        ] {
+            assert_eq!(canonical_tag(key), expected, "{:?}", key);
+        }
+    }
+
+    #[test]
+    fn test_castling_files() {
+        let standard =
+            castling_files_from_fen(b"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+                .unwrap();
+        assert_eq!(
+            standard.black,
+            CastlingFiles {
+                king: Some(File::E),
+                king_side: Some(File::H),
+                queen_side: Some(File::A),
+            }
+        );
+        assert!(standard.white.is_standard());
+
+        // Chess960 start position #518 is the standard one, so take another.
+        let chess960 =
+            castling_files_from_fen(b"nrbqkbrn/pppppppp/8/8/8/8/PPPPPPPP/NRBQKBRN w GBgb - 0 1")
+                .unwrap();
+        assert_eq!(chess960.white.king, Some(File::E));
+        assert_eq!(chess960.white.king_side, Some(File::G));
+        assert_eq!(chess960.white.queen_side, Some(File::B));
+        assert!(!chess960.white.is_standard());
+
+        assert_eq!(castling_files_from_fen(b"not a fen"), None);
+    }
+
+    #[test]
+    fn test_detect_variant() {
+        fn detect(pairs: &[(&[u8], &[u8])]) -> Variant {
+            let headers = pairs
+                .iter()
+                .map(|(k, v)| (k.to_vec(), v.to_vec()))
+                .collect();
+            detect_variant(&headers)
+        }
+
+        let chess960 = &b"nrbqkbrn/pppppppp/8/8/8/8/PPPPPPPP/NRBQKBRN w KQkq - 0 1"[..];
+        let puzzle = &b"6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1"[..];
+
+        assert_eq!(detect(&[]), Variant::Standard);
+        assert_eq!(detect(&[(b"Variant", b"Chess960")]), Variant::Chess960);
+        assert_eq!(detect(&[(b"Variant", b"fischerandom")]), Variant::Chess960);
+        assert_eq!(detect(&[(b"Variant", b"Atomic")]), Variant::Atomic);
+        assert_eq!(
+            detect(&[(b"Variant", b" Racing Kings "), (b"FEN", chess960)]),
+            Variant::RacingKings
+        );
+        assert_eq!(detect(&[(b"Variant", b"Bughouse")]), Variant::Other);
+        assert_eq!(detect(&[(b"FEN", chess960)]), Variant::Chess960);
+        assert_eq!(
+            detect(&[(b"Variant", b"Standard"), (b"FEN", chess960)]),
+            Variant::Chess960
+        );
+        assert_eq!(detect(&[(b"FEN", puzzle)]), Variant::Standard);
+    }
+
+    #[test]
+    fn test_clock() {
+        assert_eq!(parse_clock(b"1:00:00"), Ok(Some(Duration::from_secs(3600))));
+        assert_eq!(parse_clock(b"0:03:07"), Ok(Some(Duration::from_secs(187))));
+        assert_eq!(parse_clock(b"05:30"), Ok(Some(Duration::from_secs(330))));
+        assert_eq!(parse_clock(b"90:00"), Ok(Some(Duration::from_secs(5400))));
+        assert_eq!(
+            parse_clock(b"0:00:59.25"),
+            Ok(Some(Duration::from_millis(59_250)))
+        );
+        assert_eq!(parse_clock(b"?"), Ok(None));
+        assert_eq!(parse_clock(b"-"), Ok(None));
+        assert_eq!(parse_clock(b""), Ok(None));
+
+        for invalid in [
+            &b"60"[..],
+            b"1:2:3:4",
+            b"0:60",
+            b"1:60:00",
+            b"a:00",
+            b"1:00.",
+            b":",
+            b"9999999999999999:00:00",
+            b"999999999999999999:00",
+        ] {
+            assert!(parse_clock(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_date() {
+        let date = |year, month, day| Ok(Date { year, month, day });
+        assert_eq!(
+            Date::from_ascii(b"2024.06.15"),
+            date(Some(2024), Some(6), Some(15))
+        );
+        assert_eq!(
+            Date::from_ascii(b" 1851.??.?? "),
+            date(Some(1851), None, None)
+        );
+        assert_eq!(Date::from_ascii(b"????.??.??"), date(None, None, None));
+        assert_eq!(
+            Date::from_ascii(b"????.12.31"),
+            date(None, Some(12), Some(31))
+        );
+        for invalid in [
+            &b""[..],
+            b"2024",
+            b"2024.06",
+            b"2024.6.15",
+            b"2024.13.01",
+            b"2024.00.01",
+            b"2024.06.32",
+            b"2024.06.1?",
+            b"2024.06.15.",
+            b"+024.06.15",
+        ] {
+            assert!(Date::from_ascii(invalid).is_err(), "{:?}", invalid);
+        }
+        for s in ["2024.06.15", "????.??.??", "0999.??.07"] {
+            assert_eq!(s.parse::<Date>().unwrap().to_string(), s);
+        }
+    }
+
+    #[test]
+    fn test_invalid() {
+        for invalid in [
+            &b"40/"[..],
+            b"+3",
+            b"300+",
+            b"abc",
+            b"40/7200::1800",
+            b"1/2/3",
+        ] {
+            assert!(TimeControl::from_ascii(invalid).is_err(), "{:?}", invalid);
+        }
+    }
+}
@@ -3,7 +3,8 @@
 //! [`BufferedReader`] parses games and calls methods of a user provided
 //! [`Visitor`]. Implementing custom visitors allows for maximum flexibility:
 //!
-//! * The reader itself does not allocate (besides a single fixed-size buffer).
+//! * The reader itself does not allocate (besides a single buffer, which
+//!   only grows for headers or comments that do not fit).
 //!   The visitor can decide if and how to represent games in memory.
 //! * The reader does not validate move legality. This allows implementing
 //!   support for custom chess variants, or delaying move validation.
@@ -78,7 +79,7 @@
 //! use shakmaty::{CastlingMode, Chess, Position};
 //! use shakmaty::fen::Fen;
 //!
-//! use pgn_reader::{Visitor, Skip, RawHeader, BufferedReader, SanPlus};
+//! use pgn_reader::{Visitor, Skip, BufferedReader, SanPlus};
 //!
 //! struct LastPosition {
 //!     pos: Chess,
@@ -93,15 +94,13 @@
 //! impl Visitor for LastPosition {
 //!     type Result = Chess;
 //!
-//!     fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+//!     fn setup(&mut self, fen: &[u8]) {
 //!         // Support games from a non-standard starting position.
-//!         if key == b"FEN" {
-//!             let pos = Fen::from_ascii(value.as_bytes()).ok()
-//!                 .and_then(|f| f.into_position(CastlingMode::Standard).ok());
+//!         let pos = Fen::from_ascii(fen).ok()
+//!             .and_then(|f| f.into_position(CastlingMode::Standard).ok());
 //!
-//!             if let Some(pos) = pos {
-//!                 self.pos = pos;
-//!             }
+//!         if let Some(pos) = pos {
+//!             self.pos = pos;
 //!         }
 //!     }
 //!
@@ -133,22 +132,54 @@
 //! }
 //! ```
 //!
+//! # Async sources
+//!
+//! The readers are synchronous. To parse PGN from an async source, either
+//! run a [`BufferedReader`] on a blocking thread, using an adapter from the
+//! async runtime that implements [`std::io::Read`], or collect the input
+//! asynchronously and parse it in place with a [`SliceReader`].
+//!
 //! [Shakmaty]: ../shakmaty/index.html
 
 #![doc(html_root_url = "https://docs.rs/pgn-reader/0.26.0")]
 #![forbid(unsafe_op_in_unsafe_fn)]
 #![warn(missing_debug_implementations)]
 
+pub mod analysis;
+pub mod board;
+pub mod comment;
+pub mod error;
+pub mod filter;
+pub mod game;
+pub mod hash;
+pub mod headers;
+pub mod parallel;
 pub mod reader;
 pub mod types;
 pub mod visitor;
-pub mod filter;
+pub mod writer;
 
-pub use reader::{BufferedReader, IntoIter};
+pub use analysis::{
+    Acpl, CentipawnLoss, DumpHeaders, GameSummary, HeaderSelector, MovetextFeatures, Nags,
+    OpeningNode, OpeningTree, OutcomeCheck, PlyCountCheck, SelectedHeaders, StandardStart,
+    TimeUsage,
+};
+pub use board::{
+    BoardVisitor, CapturedPieces, Captures, FenAtPly, MoveEncoder, MoveVisitor, SanNormalizer,
+    Transposition, Transpositions,
+};
+pub use error::{PgnError, ReadError, Span};
+pub use game::{Game, GameBuilder, SanMove, Variation};
+pub use hash::{merge_dedup, BloomFilter, DedupVisitor, GameHash, GameHasher, SeenGames, WithHash};
+pub use parallel::par_games;
+pub use reader::{
+    parse_movetext, BufferedReader, GameOffset, Games, IntoIter, InvalidSanPolicy, Position,
+    RawGames, ReaderBuilder, ReaderState, ReaderStats, SliceReader,
+};
 pub use shakmaty::{
     san::{San, SanPlus},
     CastlingSide, Color, File, Outcome, Rank, Role, Square,
 };
 pub use types::{Nag, RawComment, RawHeader, Skip};
 pub use visitor::Visitor;
-
+pub use writer::{Anonymizer, CastlingNotation, DisplaySan, PgnWriter};
@@ -0,0 +1,316 @@
+use std::{
+    io::{self, Read},
+    num::NonZeroUsize,
+    panic,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use crate::{
+    reader::{find_game_boundary, BufferedReader, SliceReader},
+    visitor::{SkipVisitor, Visitor},
+};
+
+/// Smallest chunk of input that is worth a thread.
+const MIN_CHUNK_SIZE: usize = 1 << 16;
+
+/// Reads all games of an in-memory input, like a memory-mapped file, on
+/// multiple threads. Each thread gets its own visitor from `factory`. The
+/// results are in the order of the games.
+///
+/// The input is split into one chunk per available core, at lines that
+/// start with `[` after a blank line. Such a line usually starts a game, but
+/// may also be part of a comment. So the chunks are first skipped through,
+/// to find where each one really starts and how many games come before it,
+/// and then parsed. The results are the same as from reading the games one
+/// after another with a [`SliceReader`], including the index passed to
+/// [`Visitor::game_index()`], except that [`Visitor::finish()`] is not
+/// called.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{par_games, GameSummary};
+///
+/// let pgn = b"1. e4 e5 *\n\n[Event \"?\"]\n\n1. d4 d5 2. c4 *\n";
+///
+/// let games = par_games(&pgn[..], GameSummary::new)?;
+/// let plies: Vec<usize> = games.iter().map(|(_, moves)| moves.len()).collect();
+/// assert_eq!(plies, [2, 3]);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// The first irrecoverable parser error, in the order of the input.
+pub fn par_games<V, F>(input: &[u8], factory: F) -> io::Result<Vec<V::Result>>
+where
+    V: Visitor,
+    V::Result: Send,
+    F: Fn() -> V + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    let chunk_size = (input.len() / threads).max(MIN_CHUNK_SIZE);
+    read_chunks(input, &chunk_starts(input, chunk_size), factory)
+}
+
+/// Reads games on a new thread and sends the results through a channel
+/// that holds at most `bound` results, so that parsing and processing can
+/// overlap, while memory stays bounded: the parser waits when the consumer
+/// falls behind.
+///
+/// An error ends the stream: it is sent as the last item. The parser also
+/// stops when the receiver is dropped.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{parallel::games_channel, BufferedReader, GameBuilder};
+///
+/// let pgn = b"1. e4 e5 *\n\n1. d4 { unterminated";
+/// let games = games_channel(BufferedReader::new_cursor(pgn), GameBuilder::new(), 16);
+///
+/// let game = games.recv().unwrap()?;
+/// assert_eq!(game.mainline.moves.len(), 2);
+/// assert!(games.recv().unwrap().is_err());
+/// assert!(games.recv().is_err()); // disconnected
+/// # Ok::<_, std::io::Error>(())
+/// ```
+pub fn games_channel<R, V>(
+    mut reader: BufferedReader<R>,
+    mut visitor: V,
+    bound: usize,
+) -> Receiver<io::Result<V::Result>>
+where
+    R: Read + Send + 'static,
+    V: Visitor + Send + 'static,
+    V::Result: Send + 'static,
+{
+    let (send, recv) = mpsc::sync_channel(bound);
+    thread::spawn(move || loop {
+        let game = match reader.read_game(&mut visitor) {
+            Ok(Some(game)) => Ok(game),
+            Ok(None) => break,
+            Err(err) => Err(err),
+        };
+        let stop = game.is_err();
+        if send.send(game).is_err() || stop {
+            break;
+        }
+    });
+    recv
+}
+
+/// Finds likely starts of games, about `chunk_size` bytes apart.
+fn chunk_starts(input: &[u8], chunk_size: usize) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut target = chunk_size;
+    while target < input.len() {
+        let Some(boundary) = find_game_boundary(&input[target..]) else {
+            break;
+        };
+        let line = target + boundary;
+        let start = line + memchr::memchr(b'[', &input[line..]).unwrap_or(0);
+        starts.push(start);
+        target = start + chunk_size;
+    }
+    starts
+}
+
+fn read_chunks<V, F>(input: &[u8], starts: &[usize], factory: F) -> io::Result<Vec<V::Result>>
+where
+    V: Visitor,
+    V::Result: Send,
+    F: Fn() -> V + Sync,
+{
+    let chunks = resolve_chunks(input, starts);
+
+    let read = spawn_each(chunks.iter().copied(), |(start, end, games)| {
+        read_chunk(input, start, end, games, &factory)
+    });
+
+    let mut results = Vec::new();
+    let mut stop = 0;
+    for (read, &(start, end, games)) in read.into_iter().zip(&chunks) {
+        let (games, chunk_stop) = if start == stop && games == results.len() {
+            read?
+        } else {
+            // Skipping and parsing disagreed about the end of a game.
+            read_chunk(input, stop, end, results.len(), &factory)?
+        };
+        results.extend(games);
+        stop = chunk_stop;
+    }
+    Ok(results)
+}
+
+/// Skips through the chunks that start at about `starts`, and returns where
+/// each one really starts and ends, and the number of games before it.
+fn resolve_chunks(input: &[u8], starts: &[usize]) -> Vec<(usize, usize, usize)> {
+    let end = |i: usize| starts.get(i + 1).copied().unwrap_or(input.len());
+
+    let skipped = spawn_each(starts.iter().copied().enumerate(), |(i, start)| {
+        read_chunk(input, start, end(i), 0, &SkipVisitor::new)
+    });
+
+    let mut chunks = Vec::new();
+    let mut stop = 0;
+    let mut games = 0;
+    for (i, (skipped, &start)) in skipped.into_iter().zip(starts).enumerate() {
+        let skipped = if start == stop {
+            skipped
+        } else {
+            // The chunk started in the middle of a game.
+            read_chunk(input, stop, end(i), 0, &SkipVisitor::new)
+        };
+        let Ok((skipped, chunk_stop)) = skipped else {
+            // Leave the error to parsing the rest of the input.
+            chunks.push((stop, input.len(), games));
+            break;
+        };
+        chunks.push((stop, chunk_stop, games));
+        games += skipped.len();
+        stop = chunk_stop;
+    }
+    chunks
+}
+
+/// Runs `f` for each item on its own thread, and collects the results in
+/// order.
+fn spawn_each<I, U, F>(items: I, f: F) -> Vec<U>
+where
+    I: IntoIterator,
+    I::Item: Send,
+    U: Send,
+    F: Fn(I::Item) -> U + Sync,
+{
+    thread::scope(|scope| {
+        let f = &f;
+        let handles: Vec<_> = items
+            .into_iter()
+            .map(|item| scope.spawn(move || f(item)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|err| panic::resume_unwind(err))
+            })
+            .collect()
+    })
+}
+
+/// Reads the games that start in `start..end`, with `games` games before
+/// them. Returns where reading stopped, which is the start of the next
+/// game.
+fn read_chunk<V: Visitor, F: Fn() -> V>(
+    input: &[u8],
+    start: usize,
+    end: usize,
+    games: usize,
+    factory: &F,
+) -> io::Result<(Vec<V::Result>, usize)> {
+    let mut visitor = factory();
+    let mut reader = SliceReader::new(input);
+    reader.skip_to(start, games);
+    let mut results = Vec::new();
+    let offset = |reader: &SliceReader<'_>| input.len() - reader.remaining_input().len();
+    while reader.has_more()? && offset(&reader) < end {
+        match reader.read_game(&mut visitor)? {
+            Some(result) => results.push(result),
+            None => break,
+        }
+    }
+    Ok((results, offset(&reader)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{analysis::GameSummary, board::Transpositions};
+
+    #[test]
+    fn test_read_chunks() -> io::Result<()> {
+        let games: [&[u8]; 4] = [
+            b"[Event \"1\"]\n\n1. e4 { a comment\n\n[with a bracket] } e5 *\n\n",
+            b"[Event \"2\"]\n\n1. d4 (1. c4\n\n) d5 *\n\n\n",
+            b"\r\n[Event \"3\"]\r\n\r\n1. Nf3 ; a line comment\r\n\r\n[%clk 0:00:01]\r\nNf6 *\r\n",
+            b"\n[Event \"4\"]\n\n1. b3 e5 2. Bb2 Nc6 1-0\n\n",
+        ];
+        let pgn = games.repeat(50).concat();
+
+        let mut reader = SliceReader::new(&pgn);
+        let mut sequential = Vec::new();
+        while let Some(game) = reader.read_game(&mut GameSummary::new())? {
+            sequential.push(game);
+        }
+
+        for chunk_size in [1, 7, 64, 100, 1000, pgn.len()] {
+            let starts = chunk_starts(&pgn, chunk_size);
+            assert!(starts.windows(2).all(|pair| pair[0] < pair[1]));
+            assert_eq!(read_chunks(&pgn, &starts, GameSummary::new)?, sequential);
+        }
+
+        // Starts in the middle of games, comments and variations.
+        let starts: Vec<usize> = (0..pgn.len()).step_by(97).collect();
+        assert_eq!(read_chunks(&pgn, &starts, GameSummary::new)?, sequential);
+
+        assert_eq!(par_games(&pgn, GameSummary::new)?, sequential);
+        assert_eq!(par_games(b"", Transpositions::new)?.len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_index() -> io::Result<()> {
+        struct Index(usize);
+
+        impl Visitor for Index {
+            type Result = usize;
+
+            fn game_index(&mut self, index: usize) {
+                self.0 = index;
+            }
+
+            fn end_game(&mut self) -> usize {
+                self.0
+            }
+        }
+
+        let pgn = b"[Event \"?\"]\n\n1. e4 { a\n\n[comment] } e5 *\n\n".repeat(100);
+        let expected: Vec<usize> = (0..100).collect();
+        for chunk_size in [1, 100, 1000] {
+            let starts = chunk_starts(&pgn, chunk_size);
+            assert_eq!(read_chunks(&pgn, &starts, || Index(usize::MAX))?, expected);
+        }
+        let starts: Vec<usize> = (0..pgn.len()).step_by(97).collect();
+        assert_eq!(read_chunks(&pgn, &starts, || Index(usize::MAX))?, expected);
+
+        let pgn = pgn.repeat(2 * MIN_CHUNK_SIZE / pgn.len() + 1);
+        let games = par_games(&pgn, || Index(usize::MAX))?;
+        assert!(games.iter().copied().eq(0..games.len()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_games_channel() -> io::Result<()> {
+        let pgn = b"1. e4 e5 *\n\n".repeat(100);
+        let games = games_channel(
+            BufferedReader::new_cursor(pgn.clone()),
+            GameSummary::new(),
+            1,
+        );
+        let games = games.into_iter().collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(games.len(), 100);
+
+        // The parser stops when the receiver is dropped.
+        let games = games_channel(
+            BufferedReader::new_cursor(pgn.repeat(1000)),
+            GameSummary::new(),
+            2,
+        );
+        assert!(games.recv().unwrap().is_ok());
+        drop(games);
+        Ok(())
+    }
+}
@@ -1,36 +1,308 @@
 use std::{
+    borrow::Cow,
+    cell::Cell,
     cmp::min,
-    io::{self, Chain, Cursor, Read},
+    fmt,
+    io::{self, Chain, Cursor, Read, Seek, SeekFrom},
+    mem,
 };
+#[cfg(feature = "compression")]
+use std::{fs::File, path::Path};
 
 use shakmaty::{
-    san::{San, SanPlus, Suffix},
+    san::{ParseSanError, San, SanPlus, Suffix},
     CastlingSide, Color, Outcome,
 };
 
 // use slice_deque::SliceDeque;
 use crate::{
+    error::{PgnError, Span, SNIPPET_BEFORE},
+    game::{GameBuilder, HeaderList},
     types::{Nag, RawComment, RawHeader, Skip},
     visitor::{SkipVisitor, Visitor},
 };
 
 const MIN_BUFFER_SIZE: usize = 8192;
 
+/// Largest buffer for a single header or comment, so that an unterminated
+/// one does not pull the rest of the input into memory.
+const MAX_BUFFER_LEN: usize = 64 << 20;
+
+/// Smallest capacity for [`BufferedReader::with_capacity()`], so that
+/// regular tokens are never split.
+const MIN_CAPACITY: usize = 64;
+
+/// Default maximum number of nested variations.
+const MAX_VARIATION_DEPTH: usize = 256;
+
+/// UTF-8 byte order mark, which is skipped before each game.
+const BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// Tokens that the reader passes to [`Visitor::null_move()`]: `--` as in
+/// SAN, `Z0` as in ChessBase exports, and `@@@@` as in some engines and
+/// variant tools.
+pub const NULL_MOVES: [&[u8]; 3] = [b"--", b"Z0", b"@@@@"];
+
+/// Parses a SAN token, also accepting notation that is common in the wild
+/// but not understood by [`SanPlus::from_ascii()`], like `o-o`.
+fn parse_san(token: &[u8]) -> Result<SanPlus, ParseSanError> {
+    if NULL_MOVES.contains(&token) {
+        Ok(SanPlus {
+            san: San::Null,
+            suffix: None,
+        })
+    } else if let Some(capture) = token.strip_suffix(b"ep").filter(|san| san.contains(&b'x')) {
+        // En passant marker without dots, which SAN never ends with.
+        SanPlus::from_ascii(capture)
+    } else if token.starts_with(b"o-o") && token.len() <= 6 {
+        let mut castle = [0; 6];
+        for (dst, &src) in castle.iter_mut().zip(token) {
+            *dst = if src == b'o' { b'O' } else { src };
+        }
+        SanPlus::from_ascii(&castle[..token.len()])
+    } else {
+        SanPlus::from_ascii(token)
+    }
+}
+
+/// Finds a blank line followed by `[`, the likely start of the next game.
+/// Returns the position after the first line break.
+pub(crate) fn find_game_boundary(bytes: &[u8]) -> Option<usize> {
+    memchr::memchr_iter(b'\n', bytes)
+        .map(|pos| pos + 1)
+        .find(|&pos| {
+            let rest = &bytes[pos..];
+            rest.strip_prefix(b"\r").unwrap_or(rest).starts_with(b"\n[")
+        })
+}
+
+/// Finds the `}` that ends a comment.
+///
+/// If `lenient`, a `}` in an embedded command like `[%cal ...]` does not
+/// count, as long as the command is closed on the same line. Returns `None`
+/// if the end may be after `comment`, unless `comment` is `complete`, and
+/// everything that follows is known.
+fn find_comment_end(comment: &[u8], lenient: bool, complete: bool) -> Option<usize> {
+    if !lenient {
+        return memchr::memchr(b'}', comment);
+    }
+    let mut pos = 0;
+    loop {
+        pos += memchr::memchr2(b'}', b'[', &comment[pos..])?;
+        if comment[pos] == b'}' {
+            return Some(pos);
+        }
+        pos += 1;
+        match comment.get(pos) {
+            Some(b'%') => (),
+            Some(_) => continue,
+            None => return None,
+        }
+        match memchr::memchr2(b']', b'\n', &comment[pos..]) {
+            Some(delta) if comment[pos + delta] == b']' => pos += delta + 1,
+            // Not closed on this line, so the command is just text.
+            Some(_) => (),
+            None if complete => (),
+            None => return None,
+        }
+    }
+}
+
+/// Replaces `\r\n` and lone `\r` with `\n`. Allocates only if there is a
+/// `\r`.
+fn normalize_line_endings(bytes: &[u8]) -> Cow<'_, [u8]> {
+    if memchr::memchr(b'\r', bytes).is_none() {
+        return Cow::Borrowed(bytes);
+    }
+    let mut normalized = Vec::with_capacity(bytes.len());
+    let mut rest = bytes;
+    while let Some(cr) = memchr::memchr(b'\r', rest) {
+        normalized.extend_from_slice(&rest[..cr]);
+        normalized.push(b'\n');
+        rest = &rest[cr + 1..];
+        rest = rest.strip_prefix(b"\n").unwrap_or(rest);
+    }
+    normalized.extend_from_slice(rest);
+    Cow::Owned(normalized)
+}
+
+/// Passes a comment to the visitor, to
+/// [`Visitor::pre_game_comment()`] if no move has been read yet.
+fn visit_comment<V: Visitor>(visitor: &mut V, comment: &[u8], pre_game: bool) {
+    if pre_game {
+        visitor.pre_game_comment(RawComment(comment));
+    } else {
+        visitor.comment(RawComment(comment));
+    }
+}
+
+/// Splits a result that directly follows a move, like `Qh4#0-1`.
+fn split_outcome(token: &[u8]) -> Option<(&[u8], Outcome)> {
+    for (suffix, outcome) in [
+        (
+            &b"1-0"[..],
+            Outcome::Decisive {
+                winner: Color::White,
+            },
+        ),
+        (
+            &b"0-1"[..],
+            Outcome::Decisive {
+                winner: Color::Black,
+            },
+        ),
+        (&b"1/2-1/2"[..], Outcome::Draw),
+    ] {
+        if let Some(san) = token.strip_suffix(suffix) {
+            return Some((san, outcome));
+        }
+    }
+    None
+}
+
+/// What the reader does with tokens in the movetext that look like moves,
+/// but are not valid SAN, like `Xe4` or `Nf9`.
+///
+/// Visitors that replay the game, like
+/// [`SanNormalizer`](crate::SanNormalizer), cannot follow the line after
+/// a missing move, no matter how the token is handled.
+///
+/// There is no policy that passes the token to
+/// [`Visitor::san()`](crate::Visitor::san) as an unknown move:
+/// [`San`] comes from `shakmaty` and has no variant for
+/// it. Use [`Report`](InvalidSanPolicy::Report) to see the token in move
+/// order instead.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+#[non_exhaustive]
+pub enum InvalidSanPolicy {
+    /// Passes the token to
+    /// [`Visitor::garbage()`](crate::Visitor::garbage) like any other
+    /// unrecognized token, and continues. Visitors that only look at moves
+    /// do not notice that a move is missing.
+    #[default]
+    Skip,
+    /// Passes the token to
+    /// [`Visitor::unknown_token()`](crate::Visitor::unknown_token) and
+    /// continues.
+    Report,
+    /// Fails with [`PgnError::InvalidSan`]. The rest of the game is not
+    /// read, so the visitor gets no
+    /// [`end_game()`](crate::Visitor::end_game) for it.
+    Strict,
+}
+
 trait ReadPgn {
-    type Err;
+    type Err: From<PgnError>;
 
-    /// Fill the buffer. The buffer must then contain at least MIN_BUFFER_SIZE
+    /// Fill the buffer. The buffer must then contain at least
+    /// min_buffer_size()
     /// bytes or all remaining bytes until the end of the source.
     fn fill_buffer_and_peek(&mut self) -> Result<Option<u8>, Self::Err>;
 
     /// Returns the current buffer.
     fn buffer(&self) -> &[u8];
 
+    /// Returns up to a few bytes directly before the buffer, for error
+    /// context.
+    fn before(&self) -> &[u8];
+
     /// Consume n bytes from the buffer.
     fn consume(&mut self, n: usize);
 
-    /// Constructs a parser error.
-    fn invalid_data() -> Self::Err;
+    /// Offset of the start of the buffer from the start of the input.
+    fn offset(&self) -> u64;
+
+    /// Returns the index of the next game, and counts it.
+    fn count_game(&mut self) -> usize;
+
+    /// Line number of the next byte, starting at 1.
+    fn line(&self) -> u64;
+
+    /// Whether the next byte is the first of a line, where `%` starts an
+    /// escaped line.
+    fn at_line_start(&self) -> bool;
+
+    /// Records whether the last consumed byte ended a line. Called where
+    /// the parser knows, rather than for every consumed byte.
+    fn set_line_start(&mut self, _line_start: bool) {}
+
+    fn invalid_san_policy(&self) -> InvalidSanPolicy {
+        InvalidSanPolicy::Skip
+    }
+
+    /// Number of bytes that fill_buffer_and_peek() buffers, unless the end
+    /// of the input is reached.
+    fn min_buffer_size(&self) -> usize {
+        MIN_BUFFER_SIZE
+    }
+
+    /// Whether to stop at the start of the next game when a comment runs
+    /// into it, see [`BufferedReader::set_recover()`].
+    fn recover(&self) -> bool {
+        false
+    }
+
+    /// See [`BufferedReader::set_max_variation_depth()`].
+    fn max_variation_depth(&self) -> usize {
+        MAX_VARIATION_DEPTH
+    }
+
+    /// See [`BufferedReader::set_lenient_comments()`].
+    fn lenient_comments(&self) -> bool {
+        false
+    }
+
+    /// See [`BufferedReader::set_normalize_line_endings()`].
+    fn normalize_line_endings(&self) -> bool {
+        false
+    }
+
+    /// See [`BufferedReader::set_skip_comments()`].
+    fn skip_comments(&self) -> bool {
+        false
+    }
+
+    /// See [`BufferedReader::set_skip_variations()`].
+    fn skip_variations(&self) -> bool {
+        false
+    }
+
+    /// See [`BufferedReader::set_raw_headers()`].
+    fn raw_headers(&self) -> bool {
+        false
+    }
+
+    /// See [`BufferedReader::set_raw_movetext()`].
+    fn raw_movetext(&self) -> bool {
+        false
+    }
+
+    /// Starts recording the consumed bytes, if `record` is set.
+    fn start_recording(&mut self, _record: bool) {}
+
+    /// Stops recording and returns the bytes consumed since
+    /// start_recording(), if any were recorded.
+    fn finish_recording(&mut self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Buffers more input, for a token that does not fit into the buffer.
+    /// Returns `false` if the buffer already holds all remaining input, or
+    /// as much as it may (see buffer_limit_reached()).
+    fn fill_more(&mut self) -> Result<bool, Self::Err> {
+        Ok(false)
+    }
+
+    /// Whether fill_more() stopped growing the buffer before the end of the
+    /// input.
+    fn buffer_limit_reached(&self) -> bool {
+        false
+    }
+
+    /// Location of the start of the buffer, for error reporting.
+    fn span(&self) -> Span {
+        Span::new(self.offset(), self.line(), self.before(), self.buffer())
+    }
 
     fn peek(&self) -> Option<u8> {
         self.buffer().first().cloned()
@@ -55,8 +327,17 @@ trait ReadPgn {
 
     fn skip_bom(&mut self) -> Result<(), Self::Err> {
         self.fill_buffer_and_peek()?;
-        if self.buffer().starts_with(b"\xef\xbb\xbf") {
-            self.consume(3);
+        if self.buffer().starts_with(BOM) {
+            self.consume(BOM.len());
+        } else if self.offset() == 0 {
+            // Would otherwise be read as Latin-1, with a null byte after
+            // each character.
+            let big_endian = match self.buffer() {
+                [0xff, 0xfe, ..] => false,
+                [0xfe, 0xff, ..] => true,
+                _ => return Ok(()),
+            };
+            return Err(PgnError::Utf16 { big_endian }.into());
         }
         Ok(())
     }
@@ -77,42 +358,155 @@ trait ReadPgn {
     fn skip_line(&mut self) -> Result<(), Self::Err> {
         self.skip_until(b'\n')?;
         self.bump();
+        self.set_line_start(true);
         Ok(())
     }
 
-    fn skip_whitespace(&mut self) -> Result<(), Self::Err> {
-        while let Some(ch) = self.fill_buffer_and_peek()? {
-            match ch {
-                b' ' | b'\t' | b'\r' | b'\n' => {
-                    self.bump();
+    /// Consumes stretches of input up to the next game boundary that
+    /// contain no headers, moves or results, like stray comments between
+    /// games or a checksum line at the end, so that they are not read as
+    /// games. Buffers as much as needed to see the end of such a stretch,
+    /// up to the buffer limit.
+    fn skip_non_games(&mut self) -> Result<(), Self::Err> {
+        loop {
+            self.skip_bom()?;
+            self.skip_whitespace()?;
+            // Headers, move numbers and results are content. Do not parse
+            // the game twice for them.
+            if matches!(self.peek(), None | Some(b'[' | b'0'..=b'9' | b'*')) {
+                return Ok(());
+            }
+
+            let len = loop {
+                let mut rest = SliceReader::new(self.buffer());
+                rest.set_lenient_comments(self.lenient_comments());
+                let mut probe = Probe { found: false };
+                let result = ReadPgn::read_game(&mut rest, &mut probe);
+                if probe.found {
+                    return Ok(());
+                }
+                // The stretch is complete if something follows it.
+                if result.is_ok() && rest.remaining() > 0 {
+                    break self.remaining() - rest.remaining();
                 }
-                b'%' => {
+                if self.fill_more()? {
+                    continue;
+                }
+                if result.is_err() || self.buffer_limit_reached() {
+                    // Leave reporting the error to the actual parser.
+                    return Ok(());
+                }
+                break self.remaining();
+            };
+            self.consume(len);
+        }
+    }
+
+    /// Skips lines until the next line that starts with a header, or the
+    /// end of the input.
+    fn resync(&mut self) -> Result<(), Self::Err> {
+        loop {
+            self.skip_whitespace()?;
+            match self.peek() {
+                None | Some(b'[') => return Ok(()),
+                Some(_) => self.skip_line()?,
+            }
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), Self::Err> {
+        while self.fill_buffer_and_peek()?.is_some() {
+            // Skip runs of whitespace a buffer at a time.
+            let buffer = self.buffer();
+            let run = buffer
+                .iter()
+                .position(|ch| !matches!(ch, b' ' | b'\t' | b'\r' | b'\n'))
+                .unwrap_or(buffer.len());
+            let line_start = run.checked_sub(1).map(|last| buffer[last] == b'\n');
+            self.consume(run);
+            if let Some(line_start) = line_start {
+                self.set_line_start(line_start);
+            }
+
+            match self.peek() {
+                Some(b'%') if self.at_line_start() => {
                     self.bump();
                     self.skip_line()?;
                 }
-                _ => return Ok(()),
+                Some(_) => return Ok(()),
+                None => (),
             }
         }
 
         Ok(())
     }
 
+    /// Reads the rest of an escaped line, after the `%`.
+    fn read_escape<V: Visitor>(&mut self, visitor: &mut V) -> Result<(), Self::Err> {
+        self.fill_buffer_and_peek()?;
+        let eol = match memchr::memchr(b'\n', self.buffer()) {
+            Some(eol) => eol,
+            None if self.remaining() < self.min_buffer_size() => self.remaining(),
+            // Too long to deliver in one piece.
+            None => return self.skip_line(),
+        };
+        let line = &self.buffer()[..eol];
+        visitor.escape(line.strip_suffix(b"\r").unwrap_or(line));
+        self.consume(eol);
+        self.bump();
+        self.set_line_start(true);
+        Ok(())
+    }
+
+    /// Looks at the start of a line in the movetext, after reading any
+    /// escape lines. Returns whether the line ends the game, because it
+    /// starts with `[` or is blank.
+    fn movetext_line_ends_game<V: Visitor>(&mut self, visitor: &mut V) -> Result<bool, Self::Err> {
+        loop {
+            match self.fill_buffer_and_peek()? {
+                Some(b'%') => {
+                    // Consumes the line break, so the next line starts here.
+                    self.bump();
+                    self.read_escape(visitor)?;
+                }
+                // Comments are consumed whole, so a line starting with `[`
+                // inside one never gets here. Inside variations it still
+                // ends the game, so that a truncated game cannot swallow the
+                // headers of the next.
+                Some(b'[') | Some(b'\n') => return Ok(true),
+                Some(b'\r') => {
+                    self.bump();
+                    return Ok(self.fill_buffer_and_peek()? == Some(b'\n'));
+                }
+                _ => return Ok(false),
+            }
+        }
+    }
+
     fn skip_ket(&mut self) -> Result<(), Self::Err> {
+        // Fast path for the common case of a well-formed header line.
+        if self.buffer().starts_with(b"]\n") {
+            self.consume(2);
+            self.set_line_start(true);
+            return Ok(());
+        } else if self.buffer().starts_with(b"]\r\n") {
+            self.consume(3);
+            self.set_line_start(true);
+            return Ok(());
+        }
+
         while let Some(ch) = self.fill_buffer_and_peek()? {
             match ch {
                 b' ' | b'\t' | b'\r' | b']' => {
                     self.bump();
                 }
-                b'%' => {
-                    self.bump();
-                    self.skip_line()?;
-                    return Ok(());
-                }
                 b'\n' => {
                     self.bump();
+                    self.set_line_start(true);
                     return Ok(());
                 }
                 _ => {
+                    self.set_line_start(false);
                     return Ok(());
                 }
             }
@@ -121,13 +515,34 @@ trait ReadPgn {
         Ok(())
     }
 
+    /// Reads the headers, passing them on to [`Visitor::raw_headers()`] as
+    /// well if the reader is set to do so.
+    fn read_raw_headers<V: Visitor>(&mut self, visitor: &mut V) -> Result<(), Self::Err> {
+        self.start_recording(self.raw_headers());
+        let headers = self.read_headers(visitor);
+        let raw_headers = self.finish_recording();
+        headers?;
+        if let Some(raw_headers) = raw_headers {
+            visitor.raw_headers(raw_headers.trim_ascii());
+        }
+        Ok(())
+    }
+
     fn read_headers<V: Visitor>(&mut self, visitor: &mut V) -> Result<(), Self::Err> {
+        let mut fen = None;
+
         while let Some(ch) = self.fill_buffer_and_peek()? {
             match ch {
                 b'[' => {
                     self.bump();
 
-                    let left_quote = match memchr::memchr3(b'"', b'\n', b']', self.buffer()) {
+                    let delimiter = loop {
+                        match memchr::memchr3(b'"', b'\n', b']', self.buffer()) {
+                            None if self.fill_more()? => continue,
+                            delimiter => break delimiter,
+                        }
+                    };
+                    let left_quote = match delimiter {
                         Some(left_quote) if self.buffer()[left_quote] == b'"' => left_quote,
                         Some(eol) => {
                             self.consume(eol + 1);
@@ -135,18 +550,13 @@ trait ReadPgn {
                             continue;
                         }
                         None => {
+                            let span = self.span();
                             self.consume_all();
                             self.skip_line()?;
-                            return Err(Self::invalid_data());
+                            return Err(PgnError::UnterminatedHeader(span).into());
                         }
                     };
 
-                    let space = if left_quote > 0 && self.buffer()[left_quote - 1] == b' ' {
-                        left_quote - 1
-                    } else {
-                        left_quote
-                    };
-
                     let value_start = left_quote + 1;
                     let mut right_quote = value_start;
                     let consumed = loop {
@@ -160,62 +570,122 @@ trait ReadPgn {
                                 break right_quote;
                             }
                             Some(delta) => {
-                                // Skip escaped character.
-                                right_quote = min(right_quote + delta + 2, self.remaining());
+                                // Skip escaped character, once it is buffered.
+                                right_quote += delta;
+                                if right_quote + 2 > self.remaining() && self.fill_more()? {
+                                    continue;
+                                }
+                                right_quote = min(right_quote + 2, self.remaining());
                             }
-                            None => {
+                            None if self.fill_more()? => (),
+                            None if self.buffer_limit_reached() => {
+                                let span = self.span();
                                 self.consume_all();
                                 self.skip_line()?;
-                                return Err(Self::invalid_data());
+                                return Err(PgnError::UnterminatedHeader(span).into());
+                            }
+                            None => {
+                                // The value runs until the end of the input.
+                                // Deliver it like a value that runs until the
+                                // end of the line.
+                                right_quote = self.remaining();
+                                break right_quote;
                             }
                         }
                     };
 
-                    visitor.header(
-                        &self.buffer()[..space],
-                        RawHeader(&self.buffer()[value_start..right_quote]),
-                    );
+                    // The key may be empty, or malformed with inner
+                    // whitespace. Deliver it as is, just trimmed.
+                    // A value without a closing quote may run until the end
+                    // of a CRLF line.
+                    let value = &self.buffer()[value_start..right_quote];
+                    let value = value.strip_suffix(b"\r").unwrap_or(value);
+                    let value = if self.normalize_line_endings() {
+                        normalize_line_endings(value)
+                    } else {
+                        Cow::Borrowed(value)
+                    };
+
+                    let key = self.buffer()[..left_quote].trim_ascii();
+                    if key == b"FEN" {
+                        fen = Some(RawHeader(&value).decode().into_owned());
+                    }
+                    visitor.header(key, RawHeader(&value));
                     self.consume(consumed);
                     self.skip_ket()?;
                 }
-                b'%' => self.skip_line()?,
-                _ => return Ok(()),
+                b'%' if self.at_line_start() => {
+                    self.bump();
+                    self.read_escape(visitor)?;
+                }
+                _ => break,
             }
         }
 
+        if let Some(fen) = fen {
+            visitor.setup(&fen);
+        }
         Ok(())
     }
 
-    fn skip_movetext(&mut self) -> Result<(), Self::Err> {
-        while let Some(ch) = self.fill_buffer_and_peek()? {
-            self.bump();
+    /// Skips to the end of the current game, scanning the buffer with memchr
+    /// for the blank line or `[` that ends the movetext, instead of going
+    /// through the reader one token at a time. Braced comments may contain
+    /// blank lines, and `;` comments and escaped lines are skipped whole.
+    fn skip_to_next_game(&mut self) -> Result<(), Self::Err> {
+        enum Next {
+            Comment(usize),
+            Refill(usize),
+            Boundary(usize),
+            Exhausted,
+        }
 
-            match ch {
-                b'{' => {
-                    self.skip_until(b'}')?;
-                    self.bump();
+        while self.fill_buffer_and_peek()?.is_some() {
+            let buffer = self.buffer();
+            let mut pos = 0;
+            let next = loop {
+                let Some(delta) = memchr::memchr3(b'\n', b'{', b';', &buffer[pos..]) else {
+                    break Next::Exhausted;
+                };
+                pos += delta;
+                match buffer[pos] {
+                    b'{' => break Next::Comment(pos),
+                    b';' => match memchr::memchr(b'\n', &buffer[pos..]) {
+                        Some(eol) => pos += eol,
+                        None => break Next::Exhausted,
+                    },
+                    _ => match buffer.get(pos + 1) {
+                        Some(b'\n') | Some(b'[') => break Next::Boundary(pos + 1),
+                        Some(b'\r') => match buffer.get(pos + 2) {
+                            Some(b'\n') => break Next::Boundary(pos + 2),
+                            Some(_) => pos += 2,
+                            None => break Next::Refill(pos),
+                        },
+                        Some(b'%') => match memchr::memchr(b'\n', &buffer[pos + 1..]) {
+                            Some(eol) => pos += 1 + eol,
+                            None => break Next::Refill(pos),
+                        },
+                        Some(_) => pos += 1,
+                        None => break Next::Refill(pos),
+                    },
                 }
-                b';' => {
-                    self.skip_until(b'\n')?;
+            };
+
+            match next {
+                Next::Comment(pos) => {
+                    self.consume(pos + 1);
+                    self.skip_comment()?;
                 }
-                b'\n' => match self.peek() {
-                    Some(b'%') => self.skip_until(b'\n')?,
-                    Some(b'\n') | Some(b'[') => break,
-                    Some(b'\r') => {
-                        self.bump();
-                        if let Some(b'\n') = self.peek() {
-                            break;
-                        }
-                    }
-                    _ => continue,
-                },
-                _ => {
-                    if let Some(consumed) = memchr::memchr3(b'\n', b'{', b';', self.buffer()) {
-                        self.consume(consumed);
-                    } else {
-                        self.consume_all();
-                    }
+                Next::Refill(pos) => {
+                    // Keep the line break for the next round, unless nothing
+                    // else could be consumed.
+                    self.consume(if pos > 0 { pos } else { 1 });
                 }
+                Next::Boundary(pos) => {
+                    self.consume(pos);
+                    return Ok(());
+                }
+                Next::Exhausted => self.consume_all(),
             }
         }
 
@@ -235,73 +705,116 @@ trait ReadPgn {
     }
 
     fn read_movetext<V: Visitor>(&mut self, visitor: &mut V) -> Result<(), Self::Err> {
-        while let Some(ch) = self.fill_buffer_and_peek()? {
+        // Number of variations that were passed to the visitor and not yet
+        // closed.
+        let mut depth = 0usize;
+        // Whether the mainline was terminated with an outcome, to detect
+        // games cut off by the end of the input.
+        let mut terminated = false;
+        // Whether no move or variation has been read yet.
+        let mut pre_game = true;
+
+        loop {
+            let Some(ch) = self.fill_buffer_and_peek()? else {
+                if !terminated {
+                    visitor.truncated();
+                }
+                break;
+            };
             match ch {
                 b'{' => {
                     self.bump();
 
-                    let right_brace = if let Some(right_brace) = memchr::memchr(b'}', self.buffer())
-                    {
+                    let lenient = self.lenient_comments();
+                    let right_brace = loop {
+                        match find_comment_end(self.buffer(), lenient, false) {
+                            None if self.fill_more()? => continue,
+                            None => break find_comment_end(self.buffer(), lenient, true),
+                            right_brace => break right_brace,
+                        }
+                    };
+                    if self.recover() {
+                        let comment = &self.buffer()[..right_brace.unwrap_or(self.remaining())];
+                        if let Some(boundary) = find_game_boundary(comment) {
+                            let span = self.span();
+                            self.consume(boundary);
+                            return Err(PgnError::UnterminatedComment(span).into());
+                        }
+                    }
+                    let right_brace = if let Some(right_brace) = right_brace {
                         right_brace
                     } else {
+                        let span = self.span();
                         self.consume_all();
                         self.skip_until(b'}')?;
                         self.bump();
-                        return Err(Self::invalid_data());
+                        return Err(PgnError::UnterminatedComment(span).into());
                     };
 
-                    visitor.comment(RawComment(&self.buffer()[..right_brace]));
+                    if !self.skip_comments() {
+                        let comment = &self.buffer()[..right_brace];
+                        if self.normalize_line_endings() {
+                            visit_comment(visitor, &normalize_line_endings(comment), pre_game);
+                        } else {
+                            visit_comment(visitor, comment, pre_game);
+                        }
+                    }
                     self.consume(right_brace + 1);
                 }
                 b'\n' => {
                     self.bump();
-
-                    match self.peek() {
-                        Some(b'%') => {
-                            self.bump();
-                            self.skip_line()?;
-                        }
-                        Some(b'[') | Some(b'\n') => {
-                            break;
-                        }
-                        Some(b'\r') => {
-                            self.bump();
-                            if self.peek() == Some(b'\n') {
-                                break;
-                            }
-                        }
-                        _ => continue,
+                    if self.movetext_line_ends_game(visitor)? {
+                        break;
                     }
                 }
                 b';' => {
                     self.bump();
+
+                    let eol = loop {
+                        match memchr::memchr(b'\n', self.buffer()) {
+                            None if self.fill_more()? => continue,
+                            eol => break eol.unwrap_or(self.remaining()),
+                        }
+                    };
+                    // Leave the line break, which may be part of the
+                    // boundary to the next game.
+                    if !self.skip_comments() {
+                        let comment = &self.buffer()[..eol];
+                        let comment = comment.strip_suffix(b"\r").unwrap_or(comment);
+                        visit_comment(visitor, comment, pre_game);
+                    }
+                    self.consume(eol);
+                    // Drop the rest of an overlong comment.
                     self.skip_until(b'\n')?;
                 }
                 b'1' => {
-                    self.bump();
-                    if self.buffer().starts_with(b"-0") {
-                        self.consume(2);
+                    if self.buffer().starts_with(b"1-0") {
+                        self.consume(3);
                         visitor.outcome(Some(Outcome::Decisive {
                             winner: Color::White,
                         }));
-                    } else if self.buffer().starts_with(b"/2-1/2") {
-                        self.consume(6);
+                        terminated |= depth == 0;
+                    } else if self.buffer().starts_with(b"1/2-1/2") {
+                        self.consume(7);
                         visitor.outcome(Some(Outcome::Draw));
+                        terminated |= depth == 0;
                     } else {
-                        let token_end = self.find_token_end(0);
-                        self.consume(token_end);
+                        self.read_move_number(visitor);
                     }
                 }
+                b'2'..=b'9' => {
+                    self.read_move_number(visitor);
+                }
                 b'0' => {
-                    self.bump();
-                    if self.buffer().starts_with(b"-1") {
-                        self.consume(2);
+                    if self.buffer().starts_with(b"0-1") {
+                        self.consume(3);
                         visitor.outcome(Some(Outcome::Decisive {
                             winner: Color::Black,
                         }));
-                    } else if self.buffer().starts_with(b"-0") {
+                        terminated |= depth == 0;
+                    } else if self.buffer().starts_with(b"0-0") {
                         // Castling notation with zeros.
-                        self.consume(2);
+                        self.consume(3);
                         let side = if self.buffer().starts_with(b"-0") {
                             self.consume(2);
                             CastlingSide::QueenSide
@@ -313,24 +826,55 @@ trait ReadPgn {
                             Some(b'#') => Some(Suffix::Checkmate),
                             _ => None,
                         };
+                        if suffix.is_some() {
+                            self.bump();
+                        }
                         visitor.san(SanPlus {
                             san: San::Castle(side),
                             suffix,
                         });
+                        pre_game = false;
                     } else {
-                        let token_end = self.find_token_end(0);
+                        let token_end = self.find_token_end(1);
+                        visitor.garbage(&self.buffer()[..token_end]);
                         self.consume(token_end);
                     }
                 }
                 b'(' => {
-                    self.bump();
-                    if let Skip(true) = visitor.begin_variation() {
+                    pre_game = false;
+                    if self.skip_variations() {
+                        self.bump();
+                        self.skip_variation()?;
+                        if self.peek() == Some(b')') {
+                            self.bump();
+                        }
+                    } else if depth >= self.max_variation_depth() {
+                        visitor.warning(&PgnError::VariationTooDeep(self.span()));
+                        self.bump();
                         self.skip_variation()?;
+                        // Also skip the closing parenthesis, to which the
+                        // visitor would otherwise get end_variation().
+                        if self.peek() == Some(b')') {
+                            self.bump();
+                        }
+                    } else {
+                        self.bump();
+                        depth += 1;
+                        if let Skip(true) = visitor.begin_variation() {
+                            self.skip_variation()?;
+                        }
                     }
                 }
                 b')' => {
                     self.bump();
-                    visitor.end_variation();
+                    if depth > 0 {
+                        depth -= 1;
+                        visitor.end_variation();
+                    } else if !self.skip_variations() {
+                        // Passed on as before, even without a matching
+                        // begin_variation().
+                        visitor.end_variation();
+                    }
                 }
                 b'$' => {
                     self.bump();
@@ -374,19 +918,66 @@ trait ReadPgn {
                 }
                 b'*' => {
                     visitor.outcome(None);
+                    terminated |= depth == 0;
                     self.bump();
                 }
                 b' ' | b'\t' | b'\r' | b'P' | b'.' => {
                     self.bump();
                 }
                 _ => {
-                    let token_end = self.find_token_end(1);
-                    if ch > b'9' || ch == b'-' {
-                        if let Ok(san) = SanPlus::from_ascii(&self.buffer()[..token_end]) {
-                            visitor.san(san);
+                    let mut token_end = self.find_token_end(1);
+                    let mut skip_end = token_end;
+                    // The e.p. marker of en passant captures, attached or
+                    // not, is not part of SAN. Attached ep is stripped by
+                    // parse_san().
+                    if self.buffer()[token_end..].starts_with(b".p.")
+                        && self.buffer()[..token_end].ends_with(b"e")
+                    {
+                        token_end -= 1;
+                        skip_end += 3;
+                    } else if &self.buffer()[..token_end] == b"ep" {
+                        token_end = 0;
+                    }
+                    if (ch > b'9' || ch == b'-') && token_end > 0 {
+                        let token = &self.buffer()[..token_end];
+                        let parsed = parse_san(token).map(|san| (san, None)).or_else(|err| {
+                            split_outcome(token)
+                                .and_then(|(san, outcome)| {
+                                    Some((parse_san(san).ok()?, Some(outcome)))
+                                })
+                                .ok_or(err)
+                        });
+                        match parsed {
+                            Ok((san, outcome)) => {
+                                if san.san == San::Null {
+                                    visitor.null_move();
+                                } else {
+                                    visitor.san(san);
+                                }
+                                pre_game = false;
+                                if let Some(outcome) = outcome {
+                                    visitor.outcome(Some(outcome));
+                                    terminated |= depth == 0;
+                                }
+                            }
+                            Err(_) => match self.invalid_san_policy() {
+                                InvalidSanPolicy::Skip => {
+                                    visitor.garbage(&self.buffer()[..token_end]);
+                                }
+                                InvalidSanPolicy::Report => {
+                                    visitor.unknown_token(&self.buffer()[..token_end]);
+                                }
+                                InvalidSanPolicy::Strict => {
+                                    let span = self.span();
+                                    self.consume(skip_end);
+                                    return Err(PgnError::InvalidSan(span).into());
+                                }
+                            },
                         }
+                    } else if token_end > 0 {
+                        visitor.garbage(&self.buffer()[..token_end]);
                     }
-                    self.consume(token_end);
+                    self.consume(skip_end);
                 }
             }
         }
@@ -394,6 +985,58 @@ trait ReadPgn {
         Ok(())
     }
 
+    /// Reads a token starting with a digit, which is usually a move number
+    /// like `12.` or `12...`. The dots are left for later.
+    fn read_move_number<V: Visitor>(&mut self, visitor: &mut V) {
+        let token_end = self.find_token_end(0);
+        let buffer = self.buffer();
+        let dots = buffer[token_end..]
+            .iter()
+            .take_while(|&&ch| ch == b'.')
+            .count();
+        if dots > 0 {
+            if let Ok(number) = btoi::btou(&buffer[..token_end]) {
+                let black_to_move = dots >= 3 || {
+                    // Spaced out, like `12. ...`.
+                    let rest = &buffer[token_end + dots..];
+                    let spaces = rest
+                        .iter()
+                        .take_while(|&&ch| ch == b' ' || ch == b'\t')
+                        .count();
+                    spaces > 0 && rest[spaces..].starts_with(b"...")
+                };
+                visitor.move_number(number, black_to_move);
+            } else {
+                visitor.garbage(&buffer[..token_end]);
+            }
+        } else {
+            visitor.garbage(&buffer[..token_end]);
+        }
+        self.consume(token_end);
+    }
+
+    /// Skips the rest of a braced comment and the closing `}`, ending it
+    /// where read_movetext() would.
+    fn skip_comment(&mut self) -> Result<(), Self::Err> {
+        if self.lenient_comments() {
+            let right_brace = loop {
+                match find_comment_end(self.buffer(), true, false) {
+                    None if self.fill_more()? => continue,
+                    None => break find_comment_end(self.buffer(), true, true),
+                    right_brace => break right_brace,
+                }
+            };
+            if let Some(right_brace) = right_brace {
+                self.consume(right_brace + 1);
+                return Ok(());
+            }
+            self.consume_all();
+        }
+        self.skip_until(b'}')?;
+        self.bump();
+        Ok(())
+    }
+
     fn skip_variation(&mut self) -> Result<(), Self::Err> {
         let mut depth = 0usize;
 
@@ -413,8 +1056,7 @@ trait ReadPgn {
                 }
                 b'{' => {
                     self.bump();
-                    self.skip_until(b'}')?;
-                    self.bump();
+                    self.skip_comment()?;
                 }
                 b';' => {
                     self.bump();
@@ -458,21 +1100,68 @@ trait ReadPgn {
             return Ok(None);
         }
 
+        let index = self.count_game();
         visitor.begin_game();
+        visitor.game_index(index);
         visitor.begin_headers();
-        self.read_headers(visitor)?;
-        if let Skip(false) = visitor.end_headers() {
-            self.read_movetext(visitor)?;
+        self.read_raw_headers(visitor)?;
+        self.start_recording(self.raw_movetext());
+        let movetext = if let Skip(false) = visitor.end_headers() {
+            self.read_movetext(visitor)
         } else {
-            self.skip_movetext()?;
+            self.skip_to_next_game()
+        };
+        let raw_movetext = self.finish_recording();
+        movetext?;
+        if let Some(raw_movetext) = raw_movetext {
+            visitor.raw_movetext(raw_movetext.trim_ascii());
         }
+        // Movetext ends at a line break or the end of the input.
+        self.set_line_start(true);
 
         self.skip_whitespace()?;
         Ok(Some(visitor.end_game()))
     }
 
     fn skip_game(&mut self) -> Result<bool, Self::Err> {
-        self.read_game(&mut SkipVisitor).map(|r| r.is_some())
+        self.skip_bom()?;
+        self.skip_whitespace()?;
+
+        if self.fill_buffer_and_peek()?.is_none() {
+            return Ok(false);
+        }
+
+        self.count_game();
+        self.read_headers(&mut SkipVisitor)?;
+        self.skip_to_next_game()?;
+        self.set_line_start(true);
+
+        self.skip_whitespace()?;
+        Ok(true)
+    }
+
+    fn read_headers_only<V: Visitor>(
+        &mut self,
+        visitor: &mut V,
+    ) -> Result<Option<V::Result>, Self::Err> {
+        self.skip_bom()?;
+        self.skip_whitespace()?;
+
+        if self.fill_buffer_and_peek()?.is_none() {
+            return Ok(None);
+        }
+
+        let index = self.count_game();
+        visitor.begin_game();
+        visitor.game_index(index);
+        visitor.begin_headers();
+        self.read_raw_headers(visitor)?;
+        let _ = visitor.end_headers();
+        self.skip_to_next_game()?;
+        self.set_line_start(true);
+
+        self.skip_whitespace()?;
+        Ok(Some(visitor.end_game()))
     }
 }
 
@@ -480,14 +1169,38 @@ trait ReadPgn {
 #[derive(Debug, Clone)]
 pub struct Buffer {
     inner: circular::Buffer,
+    /// The last consumed bytes, for error context.
+    before: [u8; SNIPPET_BEFORE],
+    before_len: usize,
 }
 
 impl Buffer {
-    fn new() -> Buffer {
+    fn with_capacity(capacity: usize) -> Buffer {
         Buffer {
-            inner: circular::Buffer::with_capacity(MIN_BUFFER_SIZE * 2),
+            inner: circular::Buffer::with_capacity(capacity * 2),
+            before: [0; SNIPPET_BEFORE],
+            before_len: 0,
         }
     }
+
+    fn consume(&mut self, bytes: usize) -> usize {
+        let data = self.inner.data();
+        let bytes = min(bytes, data.len());
+        let keep = min(bytes, SNIPPET_BEFORE);
+        self.before.copy_within(keep.., 0);
+        self.before[SNIPPET_BEFORE - keep..].copy_from_slice(&data[bytes - keep..bytes]);
+        self.before_len = min(self.before_len + bytes, SNIPPET_BEFORE);
+        self.inner.consume(bytes)
+    }
+
+    fn before(&self) -> &[u8] {
+        &self.before[SNIPPET_BEFORE - self.before_len..]
+    }
+
+    fn reset(&mut self) {
+        self.inner.reset();
+        self.before_len = 0;
+    }
 }
 
 impl AsRef<[u8]> for Buffer {
@@ -496,11 +1209,266 @@ impl AsRef<[u8]> for Buffer {
     }
 }
 
-/// A buffered PGN reader.
-#[derive(Debug)]
-pub struct BufferedReader<R> {
-    inner: R,
+/// Location of a game in the underlying reader, as byte offsets.
+///
+/// `start` is the offset of the first byte of the game (after skipping
+/// leading whitespace) and `end` the offset directly after the line break
+/// that terminates its movetext.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct GameOffset {
+    pub start: u64,
+    pub end: u64,
+    /// Line number of `start`, starting at 1.
+    pub line: u64,
+}
+
+/// The position of a [`BufferedReader`], to resume reading later with
+/// [`BufferedReader::resume()`].
+///
+/// This is just a byte offset and a line number, so it is easy to store
+/// or send anywhere, like the cursor of a paginated request.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ReaderState {
+    /// Offset of the first byte that was not yet consumed, relative to the
+    /// position of the underlying reader when the `BufferedReader` was
+    /// created.
+    pub offset: u64,
+    /// Line number of `offset`, starting at 1.
+    pub line: u64,
+}
+
+/// Counters of a [`BufferedReader`], as returned by
+/// [`BufferedReader::stats()`], to choose a good capacity for a workload.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct ReaderStats {
+    /// Number of reads from the underlying reader that returned data.
+    pub refills: u64,
+    /// Number of times the buffer was extended, for tokens that did not
+    /// fit into it.
+    pub grows: u64,
+    /// Total number of bytes read from the underlying reader.
+    pub bytes_read: u64,
+    /// Maximum number of bytes that were buffered at once.
+    pub max_buffer_len: usize,
+}
+
+/// Where a reader is in its input, as returned by
+/// [`BufferedReader::position()`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Position {
+    /// Line number, starting at 1. Lines end with `\n`.
+    pub line: u64,
+    /// Byte offset from the start of the input.
+    pub byte: u64,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, byte {}", self.line, self.byte)
+    }
+}
+
+fn count_lines(bytes: &[u8]) -> u64 {
+    memchr::memchr_iter(b'\n', bytes).count() as u64
+}
+
+/// Settings for [`BufferedReader`] and [`SliceReader`], to create many
+/// readers with the same configuration.
+///
+/// Each method corresponds to a setter of [`BufferedReader`], and the
+/// defaults are the same as for [`BufferedReader::new()`].
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{GameBuilder, ReaderBuilder};
+///
+/// let builder = ReaderBuilder::new()
+///     .capacity(64 * 1024)
+///     .skip_comments(true)
+///     .skip_variations(true);
+///
+/// for pgn in [&b"1. e4 { best by test } e5 *"[..], b"1. d4 (1. c4) d5 *"] {
+///     let mut reader = builder.build(pgn);
+///     let game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+///     assert_eq!(game.mainline.moves.len(), 2);
+///     assert!(game.mainline.moves.iter().all(|m| m.comments.is_empty()));
+///     assert!(game.mainline.moves.iter().all(|m| m.variations.is_empty()));
+/// }
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct ReaderBuilder {
+    capacity: usize,
+    max_bytes: Option<u64>,
+    invalid_san: InvalidSanPolicy,
+    recover: bool,
+    max_variation_depth: usize,
+    lenient_comments: bool,
+    normalize_line_endings: bool,
+    skip_comments: bool,
+    skip_variations: bool,
+    raw_headers: bool,
+    raw_movetext: bool,
+}
+
+impl Default for ReaderBuilder {
+    fn default() -> ReaderBuilder {
+        ReaderBuilder {
+            capacity: MIN_BUFFER_SIZE,
+            max_bytes: None,
+            invalid_san: InvalidSanPolicy::default(),
+            recover: false,
+            max_variation_depth: MAX_VARIATION_DEPTH,
+            lenient_comments: false,
+            normalize_line_endings: false,
+            skip_comments: false,
+            skip_variations: false,
+            raw_headers: false,
+            raw_movetext: false,
+        }
+    }
+}
+
+impl ReaderBuilder {
+    pub fn new() -> ReaderBuilder {
+        ReaderBuilder::default()
+    }
+
+    /// See [`BufferedReader::with_capacity()`].
+    pub fn capacity(mut self, capacity: usize) -> ReaderBuilder {
+        self.capacity = capacity;
+        self
+    }
+
+    /// See [`BufferedReader::set_max_bytes()`].
+    pub fn max_bytes(mut self, max_bytes: Option<u64>) -> ReaderBuilder {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// See [`BufferedReader::set_invalid_san_policy()`].
+    pub fn invalid_san_policy(mut self, policy: InvalidSanPolicy) -> ReaderBuilder {
+        self.invalid_san = policy;
+        self
+    }
+
+    /// See [`BufferedReader::set_recover()`].
+    pub fn recover(mut self, recover: bool) -> ReaderBuilder {
+        self.recover = recover;
+        self
+    }
+
+    /// See [`BufferedReader::set_max_variation_depth()`].
+    pub fn max_variation_depth(mut self, depth: usize) -> ReaderBuilder {
+        self.max_variation_depth = depth;
+        self
+    }
+
+    /// See [`BufferedReader::set_lenient_comments()`].
+    pub fn lenient_comments(mut self, lenient: bool) -> ReaderBuilder {
+        self.lenient_comments = lenient;
+        self
+    }
+
+    /// See [`BufferedReader::set_normalize_line_endings()`].
+    pub fn normalize_line_endings(mut self, normalize: bool) -> ReaderBuilder {
+        self.normalize_line_endings = normalize;
+        self
+    }
+
+    /// See [`BufferedReader::set_skip_comments()`].
+    pub fn skip_comments(mut self, skip: bool) -> ReaderBuilder {
+        self.skip_comments = skip;
+        self
+    }
+
+    /// See [`BufferedReader::set_skip_variations()`].
+    pub fn skip_variations(mut self, skip: bool) -> ReaderBuilder {
+        self.skip_variations = skip;
+        self
+    }
+
+    /// See [`BufferedReader::set_raw_headers()`].
+    pub fn raw_headers(mut self, raw_headers: bool) -> ReaderBuilder {
+        self.raw_headers = raw_headers;
+        self
+    }
+
+    /// See [`BufferedReader::set_raw_movetext()`].
+    pub fn raw_movetext(mut self, raw_movetext: bool) -> ReaderBuilder {
+        self.raw_movetext = raw_movetext;
+        self
+    }
+
+    /// Creates a [`BufferedReader`] with these settings.
+    pub fn build<R: Read>(&self, inner: R) -> BufferedReader<R> {
+        let mut reader = BufferedReader::with_capacity(inner, self.capacity);
+        reader.set_max_bytes(self.max_bytes);
+        reader.set_invalid_san_policy(self.invalid_san);
+        reader.set_recover(self.recover);
+        reader.set_max_variation_depth(self.max_variation_depth);
+        reader.set_lenient_comments(self.lenient_comments);
+        reader.set_normalize_line_endings(self.normalize_line_endings);
+        reader.set_skip_comments(self.skip_comments);
+        reader.set_skip_variations(self.skip_variations);
+        reader.set_raw_headers(self.raw_headers);
+        reader.set_raw_movetext(self.raw_movetext);
+        reader
+    }
+
+    /// Creates a [`SliceReader`] with these settings. The capacity, the
+    /// limit on the input size and recovery do not apply to it.
+    pub fn build_slice<'a>(&self, input: &'a [u8]) -> SliceReader<'a> {
+        let mut reader = SliceReader::new(input);
+        reader.set_invalid_san_policy(self.invalid_san);
+        reader.set_max_variation_depth(self.max_variation_depth);
+        reader.set_lenient_comments(self.lenient_comments);
+        reader.set_normalize_line_endings(self.normalize_line_endings);
+        reader.set_skip_comments(self.skip_comments);
+        reader.set_skip_variations(self.skip_variations);
+        reader.set_raw_headers(self.raw_headers);
+        reader.set_raw_movetext(self.raw_movetext);
+        reader
+    }
+}
+
+/// A buffered PGN reader.
+#[derive(Debug)]
+pub struct BufferedReader<R> {
+    inner: R,
     buffer: Buffer,
+    capacity: usize,
+    offset: u64,
+    /// Line number after the last byte in the buffer. Lines are counted
+    /// when bytes are read into the buffer, rather than when they are
+    /// consumed in many small steps.
+    end_line: u64,
+    /// Whether the last consumed byte was a line break, as far as the
+    /// parser has recorded.
+    line_start: bool,
+    bytes_read: u64,
+    refills: u64,
+    grows: u64,
+    max_buffer_len: usize,
+    /// See MAX_BUFFER_LEN.
+    buffer_limit: usize,
+    max_bytes: Option<u64>,
+    invalid_san: InvalidSanPolicy,
+    recover: bool,
+    max_variation_depth: usize,
+    lenient_comments: bool,
+    normalize_line_endings: bool,
+    skip_comments: bool,
+    skip_variations: bool,
+    raw_headers: bool,
+    raw_movetext: bool,
+    recording: bool,
+    last_game_len: u64,
+    games: usize,
+    /// Bytes consumed since start_recording(), while recording.
+    raw: Vec<u8>,
 }
 
 impl<T: AsRef<[u8]>> BufferedReader<Cursor<T>> {
@@ -534,80 +1502,434 @@ impl<R: Read> BufferedReader<R> {
     /// # }
     /// ```
     pub fn new(inner: R) -> BufferedReader<R> {
+        BufferedReader::with_capacity(inner, MIN_BUFFER_SIZE)
+    }
+
+    /// Create a new buffered PGN reader that reads ahead at least
+    /// `capacity` bytes, instead of 8 KiB. Larger reads help with slow
+    /// sources, and a smaller buffer saves memory when there are many
+    /// readers for short inputs.
+    ///
+    /// The capacity is a lower bound. It is at least 64 bytes, and the
+    /// buffer grows for headers and comments that do not fit, up to 64 MiB
+    /// or the capacity, whichever is larger. Longer headers and braced
+    /// comments are reported like unterminated ones, and longer `;`
+    /// comments are cut off.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"1. e4 { a comment that is longer than the 64 bytes of the buffer } e5 *";
+    /// let mut reader = BufferedReader::with_capacity(&pgn[..], 16);
+    /// let (_, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+    /// assert_eq!(moves.len(), 2);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn with_capacity(inner: R, capacity: usize) -> BufferedReader<R> {
+        let capacity = capacity.max(MIN_CAPACITY);
         BufferedReader {
             inner,
-            buffer: Buffer::new(),
+            buffer: Buffer::with_capacity(capacity),
+            capacity,
+            offset: 0,
+            end_line: 1,
+            line_start: true,
+            bytes_read: 0,
+            refills: 0,
+            grows: 0,
+            max_buffer_len: 0,
+            buffer_limit: MAX_BUFFER_LEN.max(capacity),
+            max_bytes: None,
+            invalid_san: InvalidSanPolicy::default(),
+            recover: false,
+            max_variation_depth: MAX_VARIATION_DEPTH,
+            lenient_comments: false,
+            normalize_line_endings: false,
+            skip_comments: false,
+            skip_variations: false,
+            raw_headers: false,
+            raw_movetext: false,
+            recording: false,
+            last_game_len: 0,
+            games: 0,
+            raw: Vec::new(),
         }
     }
 
-    /// Read a single game, if any, and returns the result produced by the
-    /// visitor. Returns Ok(None) if the underlying reader is empty.
+    /// Limits the number of bytes read from the underlying reader. Once more
+    /// bytes are read, all reads fail with [`PgnError::InputTooLarge`].
     ///
-    /// # Errors
+    /// This guards against unexpectedly large inputs, like uploads. The
+    /// limit is for the input as a whole: bytes of skipped games and bytes
+    /// read ahead into the buffer count as well. `None`, the default,
+    /// disables the limit.
     ///
-    /// * I/O error from the underlying reader.
-    /// * Irrecoverable parser errors.
-    pub fn read_game<V: Visitor>(&mut self, visitor: &mut V) -> io::Result<Option<V::Result>> {
-        ReadPgn::read_game(self, visitor)
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary, PgnError};
+    ///
+    /// let mut reader = BufferedReader::new_cursor(&b"1. e4 e5 2. Nf3 Nc6 3. Bb5 *"[..]);
+    /// reader.set_max_bytes(Some(16));
+    ///
+    /// let err = reader.read_game(&mut GameSummary::new()).unwrap_err();
+    /// assert_eq!(
+    ///     PgnError::from_io(&err),
+    ///     Some(&PgnError::InputTooLarge { limit: 16 })
+    /// );
+    /// ```
+    pub fn set_max_bytes(&mut self, max_bytes: Option<u64>) {
+        self.max_bytes = max_bytes;
     }
 
-    /// Skip a single game, if any.
+    /// Sets how to handle tokens in the movetext that are not valid SAN.
+    /// The default is [`InvalidSanPolicy::Skip`].
     ///
-    /// # Errors
+    /// ```
+    /// use pgn_reader::{reader::InvalidSanPolicy, BufferedReader, PgnError, Visitor};
     ///
-    /// * I/O error from the underlying reader.
-    /// * Irrecoverable parser errors.
-    pub fn skip_game<V: Visitor>(&mut self) -> io::Result<bool> {
-        ReadPgn::skip_game(self)
+    /// struct Nothing;
+    ///
+    /// impl Visitor for Nothing {
+    ///     type Result = ();
+    ///     fn end_game(&mut self) {}
+    /// }
+    ///
+    /// let mut reader = BufferedReader::new_cursor(&b"1. e4 Xe5 *"[..]);
+    /// reader.set_invalid_san_policy(InvalidSanPolicy::Strict);
+    ///
+    /// let err = reader.read_game(&mut Nothing).unwrap_err();
+    /// assert!(matches!(PgnError::from_io(&err), Some(PgnError::InvalidSan(_))));
+    /// ```
+    pub fn set_invalid_san_policy(&mut self, policy: InvalidSanPolicy) {
+        self.invalid_san = policy;
     }
 
-    /// Read all games.
+    /// Sets whether to recover from syntax errors in a game, instead of
+    /// failing. Disabled by default.
     ///
-    /// # Errors
+    /// When enabled, [`read_game()`](BufferedReader::read_game) reports a
+    /// syntax error to [`Visitor::recovered()`], skips to the next line
+    /// that starts with a header, and ends the game early with
+    /// [`Visitor::end_game()`]. A comment that is not closed before a blank
+    /// line followed by `[` is assumed to be unterminated, so that it does
+    /// not swallow the next game. Comments in skipped movetext are not
+    /// checked.
     ///
-    /// * I/O error from the underlying reader.
-    /// * Irrecoverable parser errors.
-    pub fn read_all<V: Visitor>(&mut self, visitor: &mut V) -> io::Result<()> {
-        while self.read_game(visitor)?.is_some() {}
-        Ok(())
+    /// I/O errors and [`PgnError::InputTooLarge`] are still returned.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, PgnError, Visitor};
+    ///
+    /// struct Recovered(bool);
+    ///
+    /// impl Visitor for Recovered {
+    ///     type Result = bool;
+    ///
+    ///     fn begin_game(&mut self) {
+    ///         self.0 = false;
+    ///     }
+    ///
+    ///     fn recovered(&mut self, _error: &PgnError) {
+    ///         self.0 = true;
+    ///     }
+    ///
+    ///     fn end_game(&mut self) -> bool {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// let pgn = b"1. e4 { unterminated *\n\n[Event \"?\"]\n\n1. d4 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.set_recover(true);
+    ///
+    /// let mut visitor = Recovered(false);
+    /// assert_eq!(reader.read_game(&mut visitor)?, Some(true));
+    /// assert_eq!(reader.read_game(&mut visitor)?, Some(false));
+    /// assert_eq!(reader.read_game(&mut visitor)?, None);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_recover(&mut self, recover: bool) {
+        self.recover = recover;
     }
 
-    /// Create an iterator over all games.
+    /// Limits how deeply variations can be nested, to protect visitors that
+    /// recurse into variations from adversarial inputs like
+    /// `((((...))))`. Defaults to 256.
     ///
-    /// # Errors
+    /// Variations beyond the limit are skipped together with the
+    /// variations nested inside them, without calling
+    /// [`Visitor::begin_variation()`] or [`Visitor::end_variation()`], and
+    /// reported as [`PgnError::VariationTooDeep`] to
+    /// [`Visitor::warning()`]. The rest of the game is read as usual.
     ///
-    /// * I/O error from the underlying reader.
-    /// * Irrecoverable parser errors.
-    pub fn into_iter<V: Visitor>(self, visitor: &mut V) -> IntoIter<'_, V, R> {
-        IntoIter {
-            reader: self,
-            visitor,
-        }
+    /// ```
+    /// use pgn_reader::{BufferedReader, PgnError, Skip, Visitor};
+    ///
+    /// #[derive(Default)]
+    /// struct Depth {
+    ///     depth: usize,
+    ///     max: usize,
+    ///     warnings: usize,
+    /// }
+    ///
+    /// impl Visitor for Depth {
+    ///     type Result = (usize, usize);
+    ///
+    ///     fn begin_variation(&mut self) -> Skip {
+    ///         self.depth += 1;
+    ///         self.max = self.max.max(self.depth);
+    ///         Skip(false)
+    ///     }
+    ///
+    ///     fn end_variation(&mut self) {
+    ///         self.depth -= 1;
+    ///     }
+    ///
+    ///     fn warning(&mut self, _error: &PgnError) {
+    ///         self.warnings += 1;
+    ///     }
+    ///
+    ///     fn end_game(&mut self) -> (usize, usize) {
+    ///         (self.max, self.warnings)
+    ///     }
+    /// }
+    ///
+    /// let pgn = b"1. e4 (1. d4 (1. c4 (1. Nf3))) (1. b3) 1... e5 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.set_max_variation_depth(2);
+    /// assert_eq!(reader.read_game(&mut Depth::default())?, Some((2, 1)));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_max_variation_depth(&mut self, depth: usize) {
+        self.max_variation_depth = depth;
     }
 
-    /// Gets the remaining bytes in the buffer and the underlying reader.
-    pub fn into_inner(self) -> Chain<Cursor<Buffer>, R> {
-        Cursor::new(self.buffer).chain(self.inner)
+    /// Does not end a comment at a `}` in an embedded command like
+    /// `[%cal ...]`, if the command is closed on the same line. Disabled by
+    /// default.
+    ///
+    /// The PGN standard does not allow `}` in comments, but some exports
+    /// contain it in the text of commands. In lenient mode, a `[%` that is
+    /// not closed on the same line is just text. Comments in skipped
+    /// movetext and variations end at the same place, so skipping does not
+    /// change where games are split.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, RawComment, Visitor};
+    ///
+    /// struct Comments(Vec<Vec<u8>>);
+    ///
+    /// impl Visitor for Comments {
+    ///     type Result = Vec<Vec<u8>>;
+    ///
+    ///     fn comment(&mut self, comment: RawComment<'_>) {
+    ///         self.0.push(comment.as_bytes().to_vec());
+    ///     }
+    ///
+    ///     fn end_game(&mut self) -> Self::Result {
+    ///         std::mem::take(&mut self.0)
+    ///     }
+    /// }
+    ///
+    /// let pgn = b"1. e4 { [%text :-}] [%cal Gd2d4] } e5 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.set_lenient_comments(true);
+    /// let comments = reader.read_game(&mut Comments(Vec::new()))?.unwrap();
+    /// assert_eq!(comments, [b" [%text :-}] [%cal Gd2d4] ".to_vec()]);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_lenient_comments(&mut self, lenient: bool) {
+        self.lenient_comments = lenient;
     }
 
-    /// Returns whether the reader has another game to parse, but does not
-    /// actually parse it.
+    /// Replaces `\r\n` and lone `\r` with `\n` in comments and header
+    /// values, for example in files from Windows with multi-line comments.
+    /// Disabled by default, so that visitors get the bytes of the input.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, Game, GameBuilder};
+    ///
+    /// let pgn = b"[Annotator \"A\rB\"]\r\n\r\n1. e4 { first\r\nsecond } *\r\n";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.set_normalize_line_endings(true);
+    /// let game: Game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+    /// assert_eq!(game.headers[0].1, b"A\nB");
+    /// assert_eq!(game.mainline.moves[0].comments[0], b" first\nsecond ");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_normalize_line_endings(&mut self, normalize: bool) {
+        self.normalize_line_endings = normalize;
+    }
+
+    /// Skips over `{ ... }` and `; ...` comments in the movetext without
+    /// calling [`Visitor::comment()`]. Disabled by default.
+    ///
+    /// This saves the work of visitors that would otherwise store or
+    /// normalize the comments, like [`GameBuilder`] or
+    /// [`PgnWriter`](crate::PgnWriter). Comments are still parsed far
+    /// enough to find their end, so malformed comments are errors like
+    /// before.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameBuilder};
+    ///
+    /// let pgn = b"1. e4 { best by test } e5 ; rest of line\n2. Nf3 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.set_skip_comments(true);
+    /// let game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+    /// assert_eq!(game.mainline.moves.len(), 3);
+    /// assert!(game.mainline.moves.iter().all(|m| m.comments.is_empty()));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_skip_comments(&mut self, skip: bool) {
+        self.skip_comments = skip;
+    }
+
+    /// Does not pass variations to the visitor, so that it only sees the
+    /// mainline, with its comments and NAGs. Disabled by default.
+    ///
+    /// This is like returning `Skip(true)` from every call to
+    /// [`Visitor::begin_variation()`], except that the visitor gets neither
+    /// [`Visitor::begin_variation()`] nor [`Visitor::end_variation()`].
+    /// Nested variations are skipped along with the variation they are in.
+    /// A `)` without a matching `(` is dropped.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameBuilder};
+    ///
+    /// let pgn = b"1. e4 (1. d4 (1. c4) d5) 1... e5 { solid } *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.set_skip_variations(true);
+    /// let game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+    /// assert_eq!(game.mainline.moves.len(), 2);
+    /// assert!(game.mainline.moves[0].variations.is_empty());
+    /// assert_eq!(game.mainline.moves[1].comments, [b" solid ".to_vec()]);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_skip_variations(&mut self, skip: bool) {
+        self.skip_variations = skip;
+    }
+
+    /// Passes the movetext of each game to
+    /// [`Visitor::raw_movetext()`] exactly as it appears in the input, from
+    /// after the headers up to and including the game termination marker,
+    /// without the surrounding whitespace. Disabled by default.
+    ///
+    /// This is also done for movetext that the visitor skips, so games can
+    /// be stored byte for byte without parsing the moves at all. The
+    /// movetext is collected across refills of the buffer, so it does not
+    /// need to fit into the buffer.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, Skip, Visitor};
+    ///
+    /// struct Raw(Vec<u8>);
+    ///
+    /// impl Visitor for Raw {
+    ///     type Result = Vec<u8>;
+    ///
+    ///     fn end_headers(&mut self) -> Skip {
+    ///         Skip(true)
+    ///     }
+    ///
+    ///     fn raw_movetext(&mut self, movetext: &[u8]) {
+    ///         self.0 = movetext.to_vec();
+    ///     }
+    ///
+    ///     fn end_game(&mut self) -> Vec<u8> {
+    ///         std::mem::take(&mut self.0)
+    ///     }
+    /// }
+    ///
+    /// let pgn = b"[White \"Tal\"]\n\n1.e4 {!}  e5\n2. Nf3 1-0\n\n";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.set_raw_movetext(true);
+    /// let raw = reader.read_game(&mut Raw(Vec::new()))?;
+    /// assert_eq!(raw.as_deref(), Some(&b"1.e4 {!}  e5\n2. Nf3 1-0"[..]));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_raw_movetext(&mut self, raw_movetext: bool) {
+        self.raw_movetext = raw_movetext;
+    }
+
+    /// Passes the header block of each game to [`Visitor::raw_headers()`]
+    /// exactly as it appears in the input, from the first `[` up to the
+    /// last `]`, without the surrounding whitespace. Disabled by default.
+    ///
+    /// Together with [`set_raw_movetext()`](Self::set_raw_movetext()),
+    /// this allows copying games byte for byte, keeping unusual spacing
+    /// and the order of the tags. Games without headers pass an empty
+    /// block. The headers are collected across refills of the buffer.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, Skip, Visitor};
+    ///
+    /// struct Raw(Vec<u8>);
+    ///
+    /// impl Visitor for Raw {
+    ///     type Result = Vec<u8>;
+    ///
+    ///     fn raw_headers(&mut self, headers: &[u8]) {
+    ///         self.0 = headers.to_vec();
+    ///     }
+    ///
+    ///     fn end_headers(&mut self) -> Skip {
+    ///         Skip(true)
+    ///     }
+    ///
+    ///     fn end_game(&mut self) -> Vec<u8> {
+    ///         std::mem::take(&mut self.0)
+    ///     }
+    /// }
+    ///
+    /// let pgn = b"[White  \"Tal\"] [Black \"Botvinnik\"]\n\n1. e4 1-0\n\n";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.set_raw_headers(true);
+    /// let raw = reader.read_game(&mut Raw(Vec::new()))?;
+    /// assert_eq!(
+    ///     raw.as_deref(),
+    ///     Some(&b"[White  \"Tal\"] [Black \"Botvinnik\"]"[..])
+    /// );
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_raw_headers(&mut self, raw_headers: bool) {
+        self.raw_headers = raw_headers;
+    }
+
+    /// Reads from the underlying reader until at least `n` bytes are
+    /// buffered, or until the end of the input. Returns the number of
+    /// buffered bytes, which is less than `n` only at the end of the input.
+    ///
+    /// Use this to do the I/O ahead of time, for example before parsing a
+    /// burst of games with low latency. If `n` exceeds the capacity of the
+    /// buffer, the buffer is extended and stays extended.
+    ///
+    /// ```
+    /// use pgn_reader::BufferedReader;
+    ///
+    /// let pgn = b"1. e4 e5 *\n\n1. d4 d5 *\n";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// assert_eq!(reader.fill_to(100_000)?, pgn.len());
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
     ///
     /// # Errors
     ///
     /// * I/O error from the underlying reader.
-    pub fn has_more(&mut self) -> io::Result<bool> {
-        self.skip_bom()?;
-        self.skip_whitespace()?;
-        Ok(self.fill_buffer_and_peek()?.is_some())
+    pub fn fill_to(&mut self, n: usize) -> io::Result<usize> {
+        if self.buffer.inner.grow(n) {
+            self.grows += 1;
+        }
+        self.buffer.inner.shift();
+        self.fill(n)?;
+        Ok(self.buffer.inner.available_data())
     }
-}
 
-impl<R: Read> ReadPgn for BufferedReader<R> {
-    type Err = io::Error;
-
-    fn fill_buffer_and_peek(&mut self) -> io::Result<Option<u8>> {
-        while self.buffer.inner.available_data() < MIN_BUFFER_SIZE {
+    /// Reads until at least `n` bytes are buffered, or until the end of the
+    /// input. The buffer must have room for `n` bytes.
+    fn fill(&mut self, n: usize) -> io::Result<()> {
+        while self.buffer.inner.available_data() < n {
+            // The buffer is zeroed only when it is allocated or grows, so
+            // reads go straight into the free space without clearing it.
             let remainder = self.buffer.inner.space();
             let size = self.inner.read(remainder)?;
 
@@ -615,146 +1937,4400 @@ impl<R: Read> ReadPgn for BufferedReader<R> {
                 break;
             }
 
+            self.bytes_read += size as u64;
+            if let Some(limit) = self.max_bytes {
+                if self.bytes_read > limit {
+                    return Err(PgnError::InputTooLarge { limit }.into());
+                }
+            }
+
+            self.end_line += count_lines(&remainder[..size]);
             self.buffer.inner.fill(size);
+            self.refills += 1;
+            self.max_buffer_len = self.max_buffer_len.max(self.buffer.inner.available_data());
         }
+        Ok(())
+    }
 
-        Ok(self.buffer.inner.data().first().cloned())
+    /// Read a single game, if any, and returns the result produced by the
+    /// visitor. Returns Ok(None) if the underlying reader is empty.
+    ///
+    /// Stretches of input without headers, moves or results are not games,
+    /// and are skipped, like stray comments between games or a checksum
+    /// line after the last game.
+    ///
+    /// With [`set_recover()`](BufferedReader::set_recover), syntax errors
+    /// end the game early instead. The visitor may not have received
+    /// [`end_headers()`](Visitor::end_headers) in that case.
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn read_game<V: Visitor>(&mut self, visitor: &mut V) -> io::Result<Option<V::Result>> {
+        self.measured(|reader| {
+            reader.skip_non_games()?;
+            match ReadPgn::read_game(reader, visitor) {
+                Err(err) if reader.recover => {
+                    let Some(pgn_err) = PgnError::from_io(&err).filter(|e| e.span().is_some())
+                    else {
+                        return Err(err);
+                    };
+                    visitor.recovered(pgn_err);
+                    reader.resync()?;
+                    Ok(Some(visitor.end_game()))
+                }
+                result => result,
+            }
+        })
     }
 
-    fn invalid_data() -> io::Error {
-        io::Error::from(io::ErrorKind::InvalidData)
+    /// Read only the headers of a single game, if any, and skip directly to
+    /// the next game. Returns the result produced by the visitor, or
+    /// Ok(None) if the underlying reader is empty.
+    ///
+    /// The visitor receives the header callbacks and
+    /// [`end_headers()`](Visitor::end_headers), whose return value is
+    /// ignored, followed by [`end_game()`](Visitor::end_game). This is faster
+    /// than returning `Skip(true)` from `end_headers()` in
+    /// [`read_game()`](BufferedReader::read_game).
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn read_headers_only<V: Visitor>(
+        &mut self,
+        visitor: &mut V,
+    ) -> io::Result<Option<V::Result>> {
+        self.measured(|reader| {
+            reader.skip_non_games()?;
+            ReadPgn::read_headers_only(reader, visitor)
+        })
     }
 
-    fn buffer(&self) -> &[u8] {
-        self.buffer.inner.data()
+    /// Gets the headers of the next game, if any, without consuming them.
+    /// The next [`read_game()`](BufferedReader::read_game) starts at the
+    /// same game.
+    ///
+    /// Since the underlying reader cannot be rewound, the headers are
+    /// parsed in the buffer, which grows until it holds all headers of the
+    /// game. Syntax errors in the headers are left to be reported by
+    /// `read_game()`, and only the headers before the error are returned.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"[Round \"2\"]\n[Date \"2024.01.02\"]\n\n1. e4 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    ///
+    /// let headers = reader.peek_headers()?.unwrap();
+    /// assert_eq!(headers[1], (b"Date".to_vec(), b"2024.01.02".to_vec()));
+    ///
+    /// let (_, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+    /// assert_eq!(moves.len(), 1);
+    /// assert_eq!(reader.peek_headers()?, None);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * [`PgnError::InputTooLarge`] if the headers exceed the limit of
+    ///   [`set_max_bytes()`](BufferedReader::set_max_bytes).
+    pub fn peek_headers(&mut self) -> io::Result<Option<HeaderList>> {
+        self.skip_non_games()?;
+        if self.fill_buffer_and_peek()?.is_none() {
+            return Ok(None);
+        }
+        loop {
+            let mut headers = SliceReader::new(self.buffer());
+            let mut builder = GameBuilder::new();
+            // The headers are complete if something other than headers
+            // follows them in the buffer.
+            let complete = headers.read_headers(&mut builder).is_ok() && headers.remaining() > 0;
+            if complete || !self.fill_more()? {
+                return Ok(Some(builder.end_game().headers));
+            }
+        }
     }
 
-    fn consume(&mut self, bytes: usize) {
-        self.buffer.inner.consume(bytes);
+    /// Skip a single game, if any. Returns `false` at the end of the input.
+    ///
+    /// This does not call a visitor. The type parameter is unused.
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn skip_game<V: Visitor>(&mut self) -> io::Result<bool> {
+        self.measured(|reader| {
+            reader.skip_non_games()?;
+            ReadPgn::skip_game(reader)
+        })
     }
 
-    fn peek(&self) -> Option<u8> {
-        self.buffer.inner.data().first().cloned()
+    /// Counts the remaining games by skipping them, which is faster than
+    /// reading them with a visitor, like for progress bars or sharding.
+    ///
+    /// ```
+    /// use pgn_reader::BufferedReader;
+    ///
+    /// let pgn = b"1. e4 e5 *\n\n[Event \"?\"]\n\n1. d4 { ; } d5 *\n\n1. c4 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// assert_eq!(reader.count_games()?, 3);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn count_games(&mut self) -> io::Result<usize> {
+        let mut count = 0;
+        while self.skip_game::<SkipVisitor>()? {
+            count += 1;
+        }
+        Ok(count)
     }
-}
 
-/// Iterator returned by
-/// [`BufferedReader::into_iter()`](struct.BufferedReader.html#method.into_iter).
-#[derive(Debug)]
-#[must_use]
-pub struct IntoIter<'a, V: 'a, R> {
-    visitor: &'a mut V,
-    reader: BufferedReader<R>,
+    /// Skips `index` games, without passing them to the visitor, and reads
+    /// the next one. Returns `None` if there are not that many games.
+    ///
+    /// Like [`Iterator::nth()`], this consumes the games up to and including
+    /// the one that was read, so `nth_game(0, ...)` reads the next game.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"1. a3 *\n\n1. b3 *\n\n1. c3 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    ///
+    /// let (_, moves) = reader.nth_game(1, &mut GameSummary::new())?.unwrap();
+    /// assert_eq!(moves[0].to_string(), "b3");
+    /// assert!(reader.nth_game(1, &mut GameSummary::new())?.is_none());
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn nth_game<V: Visitor>(
+        &mut self,
+        index: usize,
+        visitor: &mut V,
+    ) -> io::Result<Option<V::Result>> {
+        for _ in 0..index {
+            if !self.skip_game::<SkipVisitor>()? {
+                return Ok(None);
+            }
+        }
+        self.read_game(visitor)
+    }
+
+    /// Read all games, then call [`Visitor::finish()`]. Stops early once
+    /// the visitor [aborts](Visitor::abort).
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    ///
+    /// `finish()` is not called if reading fails.
+    pub fn read_all<V: Visitor>(&mut self, visitor: &mut V) -> io::Result<()> {
+        while !visitor.abort() && self.read_game(visitor)?.is_some() {}
+        visitor.finish();
+        Ok(())
+    }
+
+    /// Reads all remaining games, each with a fresh visitor from
+    /// `new_visitor`, and collects the results.
+    ///
+    /// This is convenient for tests and small inputs. To process large
+    /// inputs one game at a time, use [`games()`](BufferedReader::games).
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"1. e4 e5 *\n\n1. d4 *";
+    /// let games = BufferedReader::new_cursor(&pgn[..]).collect_games(GameSummary::new)?;
+    /// assert_eq!(games.len(), 2);
+    /// assert_eq!(games[1].1.len(), 1);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn collect_games<V: Visitor, F: FnMut() -> V>(
+        &mut self,
+        mut new_visitor: F,
+    ) -> io::Result<Vec<V::Result>> {
+        let mut results = Vec::new();
+        while let Some(result) = self.read_game(&mut new_visitor())? {
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// Reads only the games with the given indices, counted from the
+    /// current position, and quickly skips all others. Stops early at the
+    /// end of the input, or once the visitor [aborts](Visitor::abort).
+    ///
+    /// To jump directly to the selected games of a seekable source, see
+    /// [`read_selected_indexed()`](BufferedReader::read_selected_indexed).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"1. a3 *\n\n1. b3 *\n\n1. c3 *\n\n1. d3 *";
+    ///
+    /// let games = BufferedReader::new_cursor(&pgn[..])
+    ///     .read_selected(&[1, 3], &mut GameSummary::new())?;
+    /// assert_eq!(games.len(), 2);
+    /// assert_eq!(games[1].1[0].to_string(), "d3");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * [`io::ErrorKind::InvalidInput`] if the indices are not strictly
+    ///   increasing. Nothing is read in this case.
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn read_selected<V: Visitor>(
+        &mut self,
+        indices: &[usize],
+        visitor: &mut V,
+    ) -> io::Result<Vec<V::Result>> {
+        check_increasing(indices)?;
+
+        let mut results = Vec::with_capacity(indices.len());
+        let mut next = 0;
+        for &index in indices {
+            if visitor.abort() {
+                break;
+            }
+            while next < index {
+                if self.read_headers_only(&mut SkipVisitor)?.is_none() {
+                    return Ok(results);
+                }
+                next += 1;
+            }
+
+            match self.read_game(visitor)? {
+                Some(result) => results.push(result),
+                None => break,
+            }
+            next += 1;
+        }
+        Ok(results)
+    }
+
+    /// Create an iterator over all games. The iterator ends early once the
+    /// visitor [aborts](Visitor::abort).
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn into_iter<V: Visitor>(self, visitor: &mut V) -> IntoIter<'_, V, R> {
+        IntoIter {
+            reader: self,
+            visitor,
+        }
+    }
+
+    /// Create an iterator over the games, borrowing the reader, so that it
+    /// can still be used after the iterator is dropped.
+    ///
+    /// Games are only read when the iterator is advanced, so `take(n)`
+    /// stops after `n` games, and the iterator ends once the visitor
+    /// [aborts](Visitor::abort). Whatever was read ahead into the buffer is
+    /// not lost: reading continues at the next game, or the rest of the
+    /// input can be handed off with
+    /// [`into_inner()`](BufferedReader::into_inner).
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"1. e4 *\n\n1. d4 *\n\n1. c4 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// let mut visitor = GameSummary::new();
+    ///
+    /// let first_two = reader.games(&mut visitor).take(2).collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(first_two.len(), 2);
+    ///
+    /// let (_, moves) = reader.read_game(&mut visitor)?.unwrap();
+    /// assert_eq!(moves[0].to_string(), "c4");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn games<'a, V: Visitor>(&'a mut self, visitor: &'a mut V) -> Games<'a, V, R> {
+        Games {
+            reader: self,
+            visitor,
+        }
+    }
+
+    /// Reads the bytes of the next game without parsing it, or returns
+    /// `None` at the end of the input.
+    ///
+    /// The game is found like with [`skip_game()`](Self::skip_game()), and
+    /// is returned exactly as it appears in the input, from the first
+    /// header up to and including the whitespace after the movetext, so
+    /// that concatenating the games gives back the input, except for a
+    /// byte order mark and leading whitespace. Blank lines in comments do not end the game.
+    ///
+    /// ```
+    /// use pgn_reader::BufferedReader;
+    ///
+    /// let pgn = b"[Event \"A\"]\n\n1. e4 { a\n\nb } *\n\n1. d4 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// assert_eq!(
+    ///     reader.read_raw_game()?.as_deref(),
+    ///     Some(&b"[Event \"A\"]\n\n1. e4 { a\n\nb } *\n\n"[..])
+    /// );
+    /// assert_eq!(reader.read_raw_game()?.as_deref(), Some(&b"1. d4 *"[..]));
+    /// assert_eq!(reader.read_raw_game()?, None);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn read_raw_game(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.measured(|reader| {
+            reader.skip_non_games()?;
+            reader.skip_bom()?;
+            reader.skip_whitespace()?;
+            if reader.fill_buffer_and_peek()?.is_none() {
+                return Ok(None);
+            }
+
+            reader.start_recording(true);
+            let skipped = ReadPgn::skip_game(reader);
+            let raw = reader.finish_recording().map(<[u8]>::to_vec);
+            skipped?;
+            Ok(raw)
+        })
+    }
+
+    /// Creates an iterator over the bytes of each game, without parsing
+    /// them. See [`read_raw_game()`](Self::read_raw_game()).
+    ///
+    /// This suits handing each game to another tool as is, where
+    /// [`games()`](Self::games()) would need a visitor.
+    ///
+    /// ```
+    /// use pgn_reader::BufferedReader;
+    ///
+    /// let pgn = b"1. e4 *\n\n1. d4 *\n";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// let games = reader.raw_games().collect::<Result<Vec<_>, _>>()?;
+    /// assert_eq!(games, [&b"1. e4 *\n\n"[..], b"1. d4 *\n"]);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn raw_games(&mut self) -> RawGames<'_, R> {
+        RawGames { reader: self }
+    }
+
+    /// Starts over with a new underlying reader, and returns the old one.
+    ///
+    /// Buffered data, the offset, the line count and the
+    /// [statistics](BufferedReader::stats) are discarded, but the buffer
+    /// keeps its memory and the reader keeps its settings. This saves
+    /// allocations when reading many small inputs in turn.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let mut reader = BufferedReader::new_cursor(&b"1. e4 *\n\n1. d4 *"[..]);
+    /// reader.read_game(&mut GameSummary::new())?;
+    ///
+    /// reader.reset(std::io::Cursor::new(&b"\xef\xbb\xbf1. c4 *"[..]));
+    /// let (_, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+    /// assert_eq!(moves, ["c4".parse()?]);
+    /// assert_eq!(reader.read_game(&mut GameSummary::new())?, None);
+    /// # Ok::<_, Box<dyn std::error::Error>>(())
+    /// ```
+    pub fn reset(&mut self, inner: R) -> R {
+        self.buffer.reset();
+        self.offset = 0;
+        self.end_line = 1;
+        self.line_start = true;
+        self.bytes_read = 0;
+        self.refills = 0;
+        self.grows = 0;
+        self.max_buffer_len = 0;
+        self.recording = false;
+        self.raw.clear();
+        self.last_game_len = 0;
+        self.games = 0;
+        mem::replace(&mut self.inner, inner)
+    }
+
+    /// Gets the remaining bytes in the buffer and the underlying reader.
+    ///
+    /// Bytes that were read ahead into the buffer, but not consumed, come
+    /// first, so the rest of the input can be handed to another parser:
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let input = b"1. e4 e5 *\n\n---\nnot pgn";
+    /// let mut reader = BufferedReader::new_cursor(&input[..]);
+    /// reader.read_game(&mut GameSummary::new())?;
+    ///
+    /// let mut rest = String::new();
+    /// reader.into_inner().read_to_string(&mut rest)?;
+    /// assert_eq!(rest, "---\nnot pgn");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn into_inner(self) -> Chain<Cursor<Buffer>, R> {
+        Cursor::new(self.buffer).chain(self.inner)
+    }
+
+    /// Splits the reader into the underlying reader and the bytes that were
+    /// read ahead from it, but not consumed. Like
+    /// [`into_inner()`](BufferedReader::into_inner), but without wrapping
+    /// the underlying reader.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let mut reader = BufferedReader::new_cursor(&b"1. e4 e5 *\n\n[Event \"?\"]"[..]);
+    /// reader.read_game(&mut GameSummary::new())?;
+    ///
+    /// let (cursor, buffered) = reader.into_parts();
+    /// assert_eq!(buffered, b"[Event \"?\"]");
+    /// assert_eq!(cursor.position(), 23);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn into_parts(self) -> (R, Vec<u8>) {
+        (self.inner, self.buffer.inner.data().to_vec())
+    }
+
+    /// Gets the current position, to resume reading later with
+    /// [`resume()`](BufferedReader::resume).
+    ///
+    /// The state does not include the data that was read ahead into the
+    /// buffer. Resuming reads it again from the underlying reader.
+    ///
+    /// The offset counts consumed bytes, including a byte order mark, but
+    /// not bytes that were only read ahead. After
+    /// [`has_more()`](BufferedReader::has_more), which skips whitespace,
+    /// it is the start of the next game:
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"\xef\xbb\xbf1. e4 *\n\n\n1. d4 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    ///
+    /// let mut starts = Vec::new();
+    /// while reader.has_more()? {
+    ///     starts.push(reader.state().offset);
+    ///     reader.read_game(&mut GameSummary::new())?;
+    /// }
+    /// assert_eq!(starts, [3, 13]);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn state(&self) -> ReaderState {
+        ReaderState {
+            offset: self.offset,
+            line: self.line(),
+        }
+    }
+
+    /// Gets counters of the I/O done so far, for tuning the capacity of the
+    /// buffer (see [`BufferedReader::with_capacity()`]). Many grows mean
+    /// that tokens, usually comments, often do not fit into the buffer.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = format!("1. e4 {{ {} }} e5 *", "long comment ".repeat(10_000));
+    /// let mut reader = BufferedReader::new_cursor(pgn.as_bytes());
+    /// reader.read_game(&mut GameSummary::new())?;
+    ///
+    /// let stats = reader.stats();
+    /// assert_eq!(stats.bytes_read, pgn.len() as u64);
+    /// assert!(stats.grows > 0);
+    /// assert!(stats.max_buffer_len > 130_000);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn stats(&self) -> ReaderStats {
+        ReaderStats {
+            refills: self.refills,
+            grows: self.grows,
+            bytes_read: self.bytes_read,
+            max_buffer_len: self.max_buffer_len,
+        }
+    }
+
+    /// Gets the line number and byte offset of the first byte that was not
+    /// yet consumed, to point users at the place where reading stopped.
+    /// Errors include the same information in their [`Span`].
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary, Position};
+    ///
+    /// let pgn = b"[Event \"?\"]\n\n1. e4 e5 *\n\n1. d4 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.read_game(&mut GameSummary::new())?;
+    /// assert_eq!(reader.position(), Position { line: 5, byte: 25 });
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn position(&self) -> Position {
+        Position {
+            line: self.line(),
+            byte: self.offset,
+        }
+    }
+
+    /// Gets the number of bytes consumed by the last call of
+    /// [`read_game()`](BufferedReader::read_game),
+    /// [`read_headers_only()`](BufferedReader::read_headers_only),
+    /// [`skip_game()`](BufferedReader::skip_game) or
+    /// [`read_raw_game()`](BufferedReader::read_raw_game), including the
+    /// whitespace after the game. This is the difference of the byte offsets
+    /// of [`position()`](BufferedReader::position) before and after the
+    /// call, to report progress without counting the games first.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"[Event \"?\"]\n\n1. e4 e5 *\n\n1. d4 *";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.read_game(&mut GameSummary::new())?;
+    /// assert_eq!(reader.last_game_len(), 25);
+    /// reader.read_game(&mut GameSummary::new())?;
+    /// assert_eq!(reader.last_game_len(), 7);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn last_game_len(&self) -> u64 {
+        self.last_game_len
+    }
+
+    fn measured<T>(&mut self, read: impl FnOnce(&mut Self) -> io::Result<T>) -> io::Result<T> {
+        let start = self.offset;
+        let result = read(self);
+        self.last_game_len = self.offset - start;
+        result
+    }
+
+    /// Builds an index of the remaining games in a single forward pass,
+    /// consuming the reader.
+    ///
+    /// Offsets are relative to the position of the underlying reader when
+    /// this `BufferedReader` was created. Works for any source, including
+    /// pipes and decompressors that do not implement [`Seek`].
+    ///
+    /// ```
+    /// use pgn_reader::BufferedReader;
+    ///
+    /// let pgn = b"1. e4 e5 *\n\n1. d4 d5 *\n";
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    ///
+    /// let index = reader.build_index()?;
+    /// assert_eq!(index.len(), 2);
+    /// assert_eq!(&pgn[index[1].start as usize..index[1].end as usize], b"1. d4 d5 *\n");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn build_index(&mut self) -> io::Result<Vec<GameOffset>> {
+        let mut index = Vec::new();
+        while self.has_more()? {
+            let (start, line) = (self.offset, self.line());
+            self.read_headers(&mut SkipVisitor)?;
+            self.skip_to_next_game()?;
+            self.set_line_start(true);
+            index.push(GameOffset {
+                start,
+                end: self.offset,
+                line,
+            });
+        }
+        Ok(index)
+    }
+
+    /// Returns whether the reader has another game to parse, but does not
+    /// actually parse it.
+    ///
+    /// Like [`read_game()`](Self::read_game), this skips stretches of input
+    /// without headers, moves or results, like a final comment.
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    pub fn has_more(&mut self) -> io::Result<bool> {
+        self.skip_non_games()?;
+        Ok(self.fill_buffer_and_peek()?.is_some())
+    }
+}
+
+#[cfg(feature = "compression")]
+impl BufferedReader<Box<dyn Read + Send>> {
+    /// Opens a file and reads it through a streaming decoder if its
+    /// extension is `.zst` or `.gz`, like the monthly dumps of Lichess.
+    /// Other files are read as they are. Requires the `compression`
+    /// feature.
+    ///
+    /// The decompressed data is buffered like any other input, so memory
+    /// use does not depend on the size of the file.
+    ///
+    /// ```no_run
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let mut reader = BufferedReader::from_path("lichess_db_standard_rated_2013-01.pgn.zst")?;
+    /// let games = reader.games(&mut GameSummary::new()).count();
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error when opening the file.
+    /// * Invalid zstd header.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<BufferedReader<Box<dyn Read + Send>>> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let inner: Box<dyn Read + Send> = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("zst") => Box::new(zstd::Decoder::new(file)?),
+            Some("gz") => Box::new(flate2::read::MultiGzDecoder::new(file)),
+            _ => Box::new(file),
+        };
+        Ok(BufferedReader::new(inner))
+    }
+}
+
+impl<R: Read + Seek> BufferedReader<R> {
+    /// Creates a reader that continues where a previous reader over the
+    /// same data left off, by seeking `inner` to the offset of `state`.
+    ///
+    /// This assumes that the previous reader was created with its
+    /// underlying reader at offset 0. Settings like
+    /// [`set_max_bytes()`](BufferedReader::set_max_bytes) are not part of
+    /// the state, and byte limits count from the resumed position.
+    ///
+    /// ```
+    /// use std::io::Cursor;
+    /// use pgn_reader::{BufferedReader, GameSummary};
+    ///
+    /// let pgn = b"1. e4 *\n\n1. d4 *\n\n1. c4 *";
+    ///
+    /// let mut reader = BufferedReader::new_cursor(&pgn[..]);
+    /// reader.read_game(&mut GameSummary::new())?;
+    /// let state = reader.state();
+    ///
+    /// let mut reader = BufferedReader::resume(Cursor::new(&pgn[..]), state)?;
+    /// let (_, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+    /// assert_eq!(moves[0].to_string(), "d4");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from seeking the underlying reader.
+    pub fn resume(mut inner: R, state: ReaderState) -> io::Result<BufferedReader<R>> {
+        inner.seek(SeekFrom::Start(state.offset))?;
+        let mut reader = BufferedReader::new(inner);
+        reader.offset = state.offset;
+        reader.end_line = state.line;
+        Ok(reader)
+    }
+
+    /// Seeks the underlying reader to the start of a game previously
+    /// recorded with [`build_index()`](BufferedReader::build_index),
+    /// discarding any buffered data. The next
+    /// [`read_game()`](BufferedReader::read_game) reads that game.
+    ///
+    /// This assumes that the underlying reader was at offset 0 when the
+    /// index was built.
+    ///
+    /// # Errors
+    ///
+    /// * I/O error from the underlying reader.
+    pub fn seek_game(&mut self, game: GameOffset) -> io::Result<()> {
+        self.inner.seek(SeekFrom::Start(game.start))?;
+        self.buffer.reset();
+        self.offset = game.start;
+        self.end_line = game.line;
+        self.line_start = true;
+        Ok(())
+    }
+
+    /// Like [`read_selected()`](BufferedReader::read_selected), but seeks
+    /// directly to each selected game using an index from
+    /// [`build_index()`](BufferedReader::build_index). Stops early at
+    /// indices beyond the end of the index, or once the visitor
+    /// [aborts](Visitor::abort).
+    ///
+    /// # Errors
+    ///
+    /// * [`io::ErrorKind::InvalidInput`] if the indices are not strictly
+    ///   increasing. Nothing is read in this case.
+    /// * I/O error from the underlying reader.
+    /// * Irrecoverable parser errors.
+    pub fn read_selected_indexed<V: Visitor>(
+        &mut self,
+        index: &[GameOffset],
+        indices: &[usize],
+        visitor: &mut V,
+    ) -> io::Result<Vec<V::Result>> {
+        check_increasing(indices)?;
+
+        let mut results = Vec::with_capacity(indices.len());
+        for &game in indices.iter().map_while(|&i| index.get(i)) {
+            if visitor.abort() {
+                break;
+            }
+            self.seek_game(game)?;
+            match self.read_game(visitor)? {
+                Some(result) => results.push(result),
+                None => break,
+            }
+        }
+        Ok(results)
+    }
 }
 
-impl<'a, V: Visitor, R: Read> Iterator for IntoIter<'a, V, R> {
-    type Item = Result<V::Result, io::Error>;
+fn check_increasing(indices: &[usize]) -> io::Result<()> {
+    if indices.windows(2).all(|pair| pair[0] < pair[1]) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "game indices must be strictly increasing",
+        ))
+    }
+}
+
+impl<R: Read> ReadPgn for BufferedReader<R> {
+    type Err = io::Error;
+
+    fn fill_buffer_and_peek(&mut self) -> io::Result<Option<u8>> {
+        self.fill(self.capacity)?;
+        Ok(self.buffer.inner.data().first().cloned())
+    }
+
+    fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn count_game(&mut self) -> usize {
+        self.games += 1;
+        self.games - 1
+    }
+
+    fn line(&self) -> u64 {
+        self.end_line - count_lines(self.buffer.inner.data())
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.line_start
+    }
+
+    fn set_line_start(&mut self, line_start: bool) {
+        self.line_start = line_start;
+    }
+
+    fn invalid_san_policy(&self) -> InvalidSanPolicy {
+        self.invalid_san
+    }
+
+    fn min_buffer_size(&self) -> usize {
+        self.capacity
+    }
+
+    fn recover(&self) -> bool {
+        self.recover
+    }
+
+    fn max_variation_depth(&self) -> usize {
+        self.max_variation_depth
+    }
+
+    fn lenient_comments(&self) -> bool {
+        self.lenient_comments
+    }
+
+    fn normalize_line_endings(&self) -> bool {
+        self.normalize_line_endings
+    }
+
+    fn skip_comments(&self) -> bool {
+        self.skip_comments
+    }
+
+    fn skip_variations(&self) -> bool {
+        self.skip_variations
+    }
+
+    fn raw_headers(&self) -> bool {
+        self.raw_headers
+    }
+
+    fn raw_movetext(&self) -> bool {
+        self.raw_movetext
+    }
+
+    fn start_recording(&mut self, record: bool) {
+        self.recording = record;
+        self.raw.clear();
+    }
+
+    fn finish_recording(&mut self) -> Option<&[u8]> {
+        mem::replace(&mut self.recording, false).then_some(&self.raw[..])
+    }
+
+    fn fill_more(&mut self) -> io::Result<bool> {
+        let available = self.buffer.inner.available_data();
+        if available >= self.buffer_limit {
+            return Ok(false);
+        }
+        let n = min(available.max(self.capacity) * 2, self.buffer_limit);
+        Ok(self.fill_to(n)? > available)
+    }
+
+    fn buffer_limit_reached(&self) -> bool {
+        self.buffer.inner.available_data() >= self.buffer_limit
+    }
+
+    fn buffer(&self) -> &[u8] {
+        self.buffer.inner.data()
+    }
+
+    fn before(&self) -> &[u8] {
+        self.buffer.before()
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        if self.recording {
+            let data = self.buffer.inner.data();
+            self.raw.extend_from_slice(&data[..min(bytes, data.len())]);
+        }
+        self.offset += self.buffer.consume(bytes) as u64;
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.buffer.inner.data().first().cloned()
+    }
+}
+
+/// Checks if a game has any content, like headers, moves or a result.
+struct Probe {
+    found: bool,
+}
+
+impl Visitor for Probe {
+    type Result = ();
+
+    fn header(&mut self, _key: &[u8], _value: RawHeader<'_>) {
+        self.found = true;
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        Skip(self.found)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        // Null moves are also how the parser reads dashes, like in
+        // an -- end -- marker.
+        if san_plus.san != San::Null {
+            self.found = true;
+        }
+    }
+
+    fn outcome(&mut self, _outcome: Option<Outcome>) {
+        self.found = true;
+    }
+
+    fn end_game(&mut self) {}
+}
+
+/// A PGN reader that parses a byte slice in place, without copying it
+/// into a buffer.
+///
+/// This is the fastest way to read input that is already in memory, in
+/// particular huge local files mapped with `mmap`. Games do not need to
+/// fit into a buffer, and indexing or seeking is just slicing.
+///
+/// The reader borrows the slice, so the compiler makes sure that a memory
+/// map outlives it. However, the contents of a mapped file must not change
+/// while it is mapped. Writes by other processes are undefined behavior
+/// for any `&[u8]` in Rust, not only for this reader. See
+/// `examples/mmap.rs`.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{GameSummary, SliceReader};
+///
+/// let pgn = b"1. e4 e5 *\n\n1. d4 d5 *\n";
+/// let mut reader = SliceReader::new(pgn);
+///
+/// let index = reader.build_index()?;
+/// assert_eq!(reader.game(index[1]), b"1. d4 d5 *\n");
+///
+/// reader.seek_game(index[1]);
+/// let (_, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+/// assert_eq!(moves[0].to_string(), "d4");
+/// assert!(!reader.has_more()?);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct SliceReader<'a> {
+    input: &'a [u8],
+    data: &'a [u8],
+    offset: u64,
+    /// A known offset and its line number, to count lines from.
+    line_mark: Cell<(u64, u64)>,
+    invalid_san: InvalidSanPolicy,
+    max_variation_depth: usize,
+    lenient_comments: bool,
+    normalize_line_endings: bool,
+    skip_comments: bool,
+    skip_variations: bool,
+    raw_headers: bool,
+    raw_movetext: bool,
+    /// Offset where start_recording() was called, while recording.
+    raw_start: Option<u64>,
+    games: usize,
+}
+
+impl<'a> SliceReader<'a> {
+    /// Creates a reader over the whole slice.
+    pub fn new(input: &'a [u8]) -> SliceReader<'a> {
+        SliceReader {
+            input,
+            data: input,
+            offset: 0,
+            line_mark: Cell::new((0, 1)),
+            invalid_san: InvalidSanPolicy::default(),
+            max_variation_depth: MAX_VARIATION_DEPTH,
+            lenient_comments: false,
+            normalize_line_endings: false,
+            skip_comments: false,
+            skip_variations: false,
+            raw_headers: false,
+            raw_movetext: false,
+            raw_start: None,
+            games: 0,
+        }
+    }
+
+    /// Sets how to handle tokens in the movetext that are not valid SAN.
+    /// See [`BufferedReader::set_invalid_san_policy()`].
+    pub fn set_invalid_san_policy(&mut self, policy: InvalidSanPolicy) {
+        self.invalid_san = policy;
+    }
+
+    /// Limits how deeply variations can be nested. See
+    /// [`BufferedReader::set_max_variation_depth()`].
+    pub fn set_max_variation_depth(&mut self, depth: usize) {
+        self.max_variation_depth = depth;
+    }
+
+    /// Does not end a comment at a `}` in an embedded command. See
+    /// [`BufferedReader::set_lenient_comments()`].
+    pub fn set_lenient_comments(&mut self, lenient: bool) {
+        self.lenient_comments = lenient;
+    }
+
+    /// Replaces `\r\n` and lone `\r` with `\n` in comments and header
+    /// values. See [`BufferedReader::set_normalize_line_endings()`].
+    pub fn set_normalize_line_endings(&mut self, normalize: bool) {
+        self.normalize_line_endings = normalize;
+    }
+
+    /// Does not pass comments in the movetext to the visitor. See
+    /// [`BufferedReader::set_skip_comments()`].
+    pub fn set_skip_comments(&mut self, skip: bool) {
+        self.skip_comments = skip;
+    }
+
+    /// Does not pass variations to the visitor. See
+    /// [`BufferedReader::set_skip_variations()`].
+    pub fn set_skip_variations(&mut self, skip: bool) {
+        self.skip_variations = skip;
+    }
+
+    /// Passes the movetext of each game to [`Visitor::raw_movetext()`]. See
+    /// [`BufferedReader::set_raw_movetext()`]. The movetext is a subslice
+    /// of the input, so nothing is copied.
+    pub fn set_raw_movetext(&mut self, raw_movetext: bool) {
+        self.raw_movetext = raw_movetext;
+    }
+
+    /// Passes the header block of each game to [`Visitor::raw_headers()`].
+    /// See [`BufferedReader::set_raw_headers()`]. The headers are a
+    /// subslice of the input, so nothing is copied.
+    pub fn set_raw_headers(&mut self, raw_headers: bool) {
+        self.raw_headers = raw_headers;
+    }
+
+    /// Reads a single game, if any, and returns the result produced by the
+    /// visitor. Like [`BufferedReader::read_game()`].
+    ///
+    /// # Errors
+    ///
+    /// Irrecoverable parser errors.
+    pub fn read_game<V: Visitor>(&mut self, visitor: &mut V) -> io::Result<Option<V::Result>> {
+        self.skip_non_games()?;
+        ReadPgn::read_game(self, visitor)
+    }
+
+    /// Reads only the headers of a single game, if any. Like
+    /// [`BufferedReader::read_headers_only()`].
+    ///
+    /// # Errors
+    ///
+    /// Irrecoverable parser errors.
+    pub fn read_headers_only<V: Visitor>(
+        &mut self,
+        visitor: &mut V,
+    ) -> io::Result<Option<V::Result>> {
+        self.skip_non_games()?;
+        ReadPgn::read_headers_only(self, visitor)
+    }
+
+    /// Gets the headers of the next game, if any, without consuming them.
+    /// Like [`BufferedReader::peek_headers()`], but the headers do not need
+    /// to be buffered.
+    ///
+    /// # Errors
+    ///
+    /// Never fails, but returns a `Result` like
+    /// [`BufferedReader::peek_headers()`].
+    pub fn peek_headers(&mut self) -> io::Result<Option<HeaderList>> {
+        self.skip_non_games()?;
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+        let mut builder = GameBuilder::new();
+        let _ = SliceReader::new(self.data).read_headers(&mut builder);
+        Ok(Some(builder.end_game().headers))
+    }
+
+    /// Skips a single game, if any.
+    ///
+    /// # Errors
+    ///
+    /// Irrecoverable parser errors.
+    pub fn skip_game(&mut self) -> io::Result<bool> {
+        self.skip_non_games()?;
+        ReadPgn::skip_game(self)
+    }
+
+    /// Counts the remaining games by skipping them. Like
+    /// [`BufferedReader::count_games()`].
+    ///
+    /// # Errors
+    ///
+    /// Irrecoverable parser errors.
+    pub fn count_games(&mut self) -> io::Result<usize> {
+        let mut count = 0;
+        while self.skip_game()? {
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Skips `index` games and reads the next one. Like
+    /// [`BufferedReader::nth_game()`].
+    ///
+    /// # Errors
+    ///
+    /// Irrecoverable parser errors.
+    pub fn nth_game<V: Visitor>(
+        &mut self,
+        index: usize,
+        visitor: &mut V,
+    ) -> io::Result<Option<V::Result>> {
+        for _ in 0..index {
+            if !self.skip_game()? {
+                return Ok(None);
+            }
+        }
+        self.read_game(visitor)
+    }
+
+    /// Reads all games, then calls [`Visitor::finish()`].
+    ///
+    /// # Errors
+    ///
+    /// Irrecoverable parser errors. `finish()` is not called in this case.
+    pub fn read_all<V: Visitor>(&mut self, visitor: &mut V) -> io::Result<()> {
+        while !visitor.abort() && self.read_game(visitor)?.is_some() {}
+        visitor.finish();
+        Ok(())
+    }
+
+    /// Reads all remaining games, each with a fresh visitor. See
+    /// [`BufferedReader::collect_games()`].
+    ///
+    /// # Errors
+    ///
+    /// Irrecoverable parser errors.
+    pub fn collect_games<V: Visitor, F: FnMut() -> V>(
+        &mut self,
+        mut new_visitor: F,
+    ) -> io::Result<Vec<V::Result>> {
+        let mut results = Vec::new();
+        while let Some(result) = self.read_game(&mut new_visitor())? {
+            results.push(result);
+        }
+        Ok(results)
+    }
+
+    /// Returns whether there is another game to parse, but does not
+    /// actually parse it.
+    ///
+    /// Like [`read_game()`](Self::read_game), this skips stretches of input
+    /// without headers, moves or results, like a final comment.
+    ///
+    /// # Errors
+    ///
+    /// Never fails, but returns a `Result` like
+    /// [`BufferedReader::has_more()`].
+    pub fn has_more(&mut self) -> io::Result<bool> {
+        self.skip_non_games()?;
+        Ok(!self.data.is_empty())
+    }
+
+    /// Builds an index of the remaining games. Offsets are relative to the
+    /// start of the slice.
+    ///
+    /// # Errors
+    ///
+    /// Irrecoverable parser errors.
+    pub fn build_index(&mut self) -> io::Result<Vec<GameOffset>> {
+        let mut index = Vec::new();
+        while self.has_more()? {
+            let (start, line) = (self.offset, self.line());
+            self.read_headers(&mut SkipVisitor)?;
+            self.skip_to_next_game()?;
+            self.set_line_start(true);
+            index.push(GameOffset {
+                start,
+                end: self.offset,
+                line,
+            });
+        }
+        Ok(index)
+    }
+
+    /// Continues reading at the start of a game from
+    /// [`build_index()`](SliceReader::build_index).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offset is beyond the end of the slice.
+    pub fn seek_game(&mut self, game: GameOffset) {
+        self.data = &self.input[game.start as usize..];
+        self.offset = game.start;
+        self.line_mark.set((game.start, game.line));
+    }
+
+    /// Gets the bytes of a game from
+    /// [`build_index()`](SliceReader::build_index).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the offsets are beyond the end of the slice.
+    pub fn game(&self, game: GameOffset) -> &'a [u8] {
+        &self.input[game.start as usize..game.end as usize]
+    }
+
+    /// Gets the bytes that were not yet consumed.
+    pub fn remaining_input(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// Continues reading at an offset, keeping line numbers correct, with
+    /// `games` games before it.
+    pub(crate) fn skip_to(&mut self, offset: usize, games: usize) {
+        self.data = &self.input[offset..];
+        self.offset = offset as u64;
+        self.games = games;
+    }
+
+    /// Gets the line number and offset of the first byte that was not yet
+    /// consumed. Like [`BufferedReader::position()`].
+    pub fn position(&self) -> Position {
+        Position {
+            line: self.line(),
+            byte: self.offset,
+        }
+    }
+}
+
+/// Same as [`SliceReader::new()`].
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{GameSummary, SliceReader};
+///
+/// let mut reader = SliceReader::from(&b"1. e4 e5 *"[..]);
+/// let (_, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+/// assert_eq!(moves.len(), 2);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+impl<'a> From<&'a [u8]> for SliceReader<'a> {
+    fn from(input: &'a [u8]) -> SliceReader<'a> {
+        SliceReader::new(input)
+    }
+}
+
+impl ReadPgn for SliceReader<'_> {
+    type Err = io::Error;
+
+    fn fill_buffer_and_peek(&mut self) -> io::Result<Option<u8>> {
+        Ok(self.data.first().cloned())
+    }
+
+    fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    fn count_game(&mut self) -> usize {
+        self.games += 1;
+        self.games - 1
+    }
+
+    fn line(&self) -> u64 {
+        let (mark, line) = self.line_mark.get();
+        let line = if mark <= self.offset {
+            line + count_lines(&self.input[mark as usize..self.offset as usize])
+        } else {
+            line - count_lines(&self.input[self.offset as usize..mark as usize])
+        };
+        self.line_mark.set((self.offset, line));
+        line
+    }
+
+    fn at_line_start(&self) -> bool {
+        let before = &self.input[..self.offset as usize];
+        let before = before.strip_suffix(BOM).unwrap_or(before);
+        before.last().is_none_or(|&ch| ch == b'\n')
+    }
+
+    fn invalid_san_policy(&self) -> InvalidSanPolicy {
+        self.invalid_san
+    }
+
+    fn max_variation_depth(&self) -> usize {
+        self.max_variation_depth
+    }
+
+    fn lenient_comments(&self) -> bool {
+        self.lenient_comments
+    }
+
+    fn normalize_line_endings(&self) -> bool {
+        self.normalize_line_endings
+    }
+
+    fn skip_comments(&self) -> bool {
+        self.skip_comments
+    }
+
+    fn skip_variations(&self) -> bool {
+        self.skip_variations
+    }
+
+    fn raw_headers(&self) -> bool {
+        self.raw_headers
+    }
+
+    fn raw_movetext(&self) -> bool {
+        self.raw_movetext
+    }
+
+    fn start_recording(&mut self, record: bool) {
+        self.raw_start = record.then_some(self.offset);
+    }
+
+    fn finish_recording(&mut self) -> Option<&[u8]> {
+        let start = self.raw_start.take()?;
+        Some(&self.input[start as usize..self.offset as usize])
+    }
+
+    fn buffer(&self) -> &[u8] {
+        self.data
+    }
+
+    fn before(&self) -> &[u8] {
+        let before = &self.input[..self.offset as usize];
+        &before[before.len().saturating_sub(SNIPPET_BEFORE)..]
+    }
+
+    fn consume(&mut self, bytes: usize) {
+        let bytes = min(bytes, self.data.len());
+        self.data = &self.data[bytes..];
+        self.offset += bytes as u64;
+    }
+}
+
+/// Parses movetext that is already in memory, like a span captured earlier,
+/// calling only the movetext methods of the visitor
+/// ([`san()`](Visitor::san), [`nag()`](Visitor::nag),
+/// [`comment()`](Visitor::comment), variations and
+/// [`outcome()`](Visitor::outcome)).
+///
+/// Parsing stops at the end of the slice or at the end of the first game.
+/// This is the same parser that [`BufferedReader`] runs over its buffer, so
+/// the callbacks are exactly those of reading the movetext in a game.
+///
+/// ```
+/// use pgn_reader::{parse_movetext, SanPlus, Visitor};
+///
+/// struct Sans(Vec<String>);
+///
+/// impl Visitor for Sans {
+///     type Result = ();
+///
+///     fn san(&mut self, san_plus: SanPlus) {
+///         self.0.push(san_plus.to_string());
+///     }
+///
+///     fn end_game(&mut self) {}
+/// }
+///
+/// let mut sans = Sans(Vec::new());
+/// parse_movetext(b"1. e4 { king's pawn } e5 2. Nf3 *", &mut sans)?;
+/// assert_eq!(sans.0, ["e4", "e5", "Nf3"]);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Irrecoverable parser errors, like an unterminated comment.
+#[doc(alias = "tokenize_movetext")]
+pub fn parse_movetext<V: Visitor>(movetext: &[u8], visitor: &mut V) -> io::Result<()> {
+    SliceReader::new(movetext).read_movetext(visitor)
+}
+
+/// Iterator returned by
+/// [`BufferedReader::into_iter()`](struct.BufferedReader.html#method.into_iter).
+#[derive(Debug)]
+#[must_use]
+pub struct IntoIter<'a, V: 'a, R> {
+    visitor: &'a mut V,
+    reader: BufferedReader<R>,
+}
+
+impl<'a, V: Visitor, R: Read> Iterator for IntoIter<'a, V, R> {
+    type Item = Result<V::Result, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.visitor.abort() {
+            return None;
+        }
+        match self.reader.read_game(self.visitor) {
+            Ok(Some(result)) => Some(Ok(result)),
+            Ok(None) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }
+}
+
+/// Iterator returned by [`BufferedReader::games()`].
+#[derive(Debug)]
+#[must_use]
+pub struct Games<'a, V: 'a, R> {
+    visitor: &'a mut V,
+    reader: &'a mut BufferedReader<R>,
+}
+
+impl<'a, V: Visitor, R: Read> Iterator for Games<'a, V, R> {
+    type Item = Result<V::Result, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.visitor.abort() {
+            return None;
+        }
+        self.reader.read_game(self.visitor).transpose()
+    }
+}
+
+/// Iterator returned by [`BufferedReader::raw_games()`].
+#[derive(Debug)]
+#[must_use]
+pub struct RawGames<'a, R> {
+    reader: &'a mut BufferedReader<R>,
+}
+
+impl<R: Read> Iterator for RawGames<'_, R> {
+    type Item = Result<Vec<u8>, io::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.reader.read_raw_game().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use shakmaty::{File, Role, Square};
+
+    use super::*;
+    use crate::{analysis::GameSummary, game::Game, visitor::Tee, writer::PgnWriter};
+
+    struct _AssertObjectSafe<R>(Box<BufferedReader<R>>);
+
+    /// A reader that returns at most `chunk_size` bytes per call.
+    struct Chunked<'a> {
+        data: &'a [u8],
+        chunk_size: usize,
+    }
+
+    impl Read for Chunked<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = min(min(buf.len(), self.chunk_size), self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    /// A reader that returns one byte per call, like a slow pipe.
+    struct ByteByByteReader<R>(R);
+
+    impl<R: Read> Read for ByteByByteReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match buf.first_mut() {
+                Some(first) => self.0.read(std::slice::from_mut(first)),
+                None => Ok(0),
+            }
+        }
+    }
+
+    struct GameCounter {
+        count: usize,
+    }
+
+    #[allow(clippy::derivable_impls)]
+    impl Default for GameCounter {
+        fn default() -> GameCounter {
+            GameCounter { count: 0 }
+        }
+    }
+
+    impl Visitor for GameCounter {
+        type Result = ();
+
+        fn end_game(&mut self) {
+            self.count += 1;
+        }
+    }
+
+    #[test]
+    fn test_header_value_across_refill() -> Result<(), io::Error> {
+        let fen = b"rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+        let mut long = vec![b'y'; 3 * MIN_BUFFER_SIZE];
+        long.extend_from_slice(b"\\\"quoted\\\"");
+
+        for padding in [0, 1, 20, 57] {
+            // Puts the FEN header right across the end of the first buffer.
+            let mut pgn = b"[Event \"".to_vec();
+            pgn.resize(MIN_BUFFER_SIZE - padding, b'x');
+            pgn.extend_from_slice(b"\"]\n[FEN \"");
+            pgn.extend_from_slice(fen);
+            pgn.extend_from_slice(b"\"]\n[Annotator \"");
+            pgn.extend_from_slice(&long);
+            pgn.extend_from_slice(b"\"]\n\n1... e5 *\n");
+
+            for chunk_size in [1, 3, 1000] {
+                let mut reader = BufferedReader::new(Chunked {
+                    data: &pgn,
+                    chunk_size,
+                });
+                let (headers, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+                assert_eq!(headers[&b"FEN"[..]], fen);
+                assert_eq!(headers[&b"Annotator"[..]].len(), long.len() - 2);
+                assert!(headers[&b"Annotator"[..]].ends_with(b"\"quoted\""));
+                assert_eq!(moves.len(), 1);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_header_escape_at_buffer_end() -> Result<(), io::Error> {
+        // Moves the backslash over the end of the buffer, including right
+        // onto its last byte.
+        for len in 0..2 * MIN_CAPACITY {
+            let mut value = vec![b'x'; len];
+            value.extend_from_slice(b"\\\"y");
+            let mut pgn = b"[Event \"".to_vec();
+            pgn.extend_from_slice(&value);
+            pgn.extend_from_slice(b"\"]\n\n1. e4 *\n");
+
+            let mut reader = BufferedReader::with_capacity(
+                Chunked {
+                    data: &pgn,
+                    chunk_size: 1,
+                },
+                MIN_CAPACITY,
+            );
+            let (headers, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+            assert_eq!(headers[&b"Event"[..]][len..], b"\"y"[..], "len {}", len);
+            assert_eq!(moves.len(), 1);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_byte_by_byte() -> Result<(), io::Error> {
+        let mut pgn = b"\xef\xbb\xbf% escaped\r\n[Event \"A \\\"quoted\\\" event\"]\r\n\
+                        [FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\r\n[ Site  \"?\" ]\r\n\r\n\
+                        1. e4 $1 { a comment } Kd7 !? (1... Ke7 ; rest of line\r\n\
+                        2. e5 (2. Kd2 -- 3. Ke3) ) 2. e5 Zz9 2... Ke6 1/2-1/2\r\n\r\n\
+                        [Event \"B\"]\n\n1. d4 {"
+            .to_vec();
+        pgn.resize(pgn.len() + 3 * MIN_BUFFER_SIZE, b'c');
+        pgn.extend_from_slice(b"} d5 (1... Nf6 (1... f5)) 2. c4 *\n\n1. e4 e5 1-0");
+
+        let read = |reader: &mut dyn Read| -> io::Result<(Vec<Game>, Vec<u8>)> {
+            let games = BufferedReader::new(&mut *reader)
+                .into_iter(&mut GameBuilder::new())
+                .collect::<io::Result<Vec<_>>>()?;
+            let mut writer = PgnWriter::new(Vec::new());
+            for game in &games {
+                game.visit(&mut writer)?;
+            }
+            Ok((games, writer.into_inner()))
+        };
+
+        let bulk = read(&mut &pgn[..])?;
+        assert_eq!(bulk.0.len(), 3);
+        assert_eq!(read(&mut ByteByByteReader(&pgn[..]))?, bulk);
+        let sliced = SliceReader::new(&pgn).collect_games(GameBuilder::new)?;
+        assert_eq!(sliced, bulk.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Truncated {
+            skip: bool,
+            truncated: bool,
+        }
+
+        impl Visitor for Truncated {
+            type Result = bool;
+
+            fn end_headers(&mut self) -> Skip {
+                Skip(self.skip)
+            }
+
+            fn truncated(&mut self) {
+                self.truncated = true;
+            }
+
+            fn end_game(&mut self) -> bool {
+                mem::take(&mut self.truncated)
+            }
+        }
+
+        for (pgn, expected) in [
+            (&b"1. e4 e5 2. Nf3"[..], true),
+            (b"1. e4 e5 2. Nf3\n", true),
+            (b"1. e4 (1. d4 1-0", true),
+            (b"[Event \"?\"]\n", true),
+            (b"1. e4 e5 1-0", false),
+            (b"1. e4 e5 (1... c5) *\r\n", false),
+            (b"1. e4 e5\n\n", false),
+            (b"1. e4 e5\r\n\r\n", false),
+            (b"1. e4\n[Event \"?\"]\n\n1. d4 *", false),
+        ] {
+            let mut visitor = Truncated::default();
+            let buffered = BufferedReader::new_cursor(pgn).read_game(&mut visitor)?;
+            assert_eq!(buffered, Some(expected), "{}", pgn.escape_ascii());
+            let sliced = SliceReader::new(pgn).read_game(&mut visitor)?;
+            assert_eq!(sliced, Some(expected), "{}", pgn.escape_ascii());
+
+            let mut skip = Truncated {
+                skip: true,
+                ..Truncated::default()
+            };
+            assert_eq!(
+                BufferedReader::new_cursor(pgn).read_game(&mut skip)?,
+                Some(false)
+            );
+        }
+
+        let game = BufferedReader::new_cursor(&b"1. e4 e5 2."[..])
+            .read_game(&mut GameBuilder::new())?
+            .unwrap();
+        assert!(game.truncated);
+        let mut visitor = Truncated::default();
+        assert!(game.visit(&mut visitor));
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_comments() -> Result<(), io::Error> {
+        let mut pgn = b"{ start } 1. e4 { ".to_vec();
+        pgn.resize(pgn.len() + 3 * MIN_BUFFER_SIZE, b'c');
+        pgn.extend_from_slice(b" } e5 ; line\r\n2. Nf3 (2. Nc3 {x}) *\n\n1. d4 { unterminated");
+
+        let mut reader = BufferedReader::new_cursor(&pgn);
+        reader.set_skip_comments(true);
+        let game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+        assert_eq!(game.mainline.moves.len(), 3);
+        assert!(game.mainline.comments.is_empty());
+        assert!(game.mainline.moves[2].variations[0].moves[0]
+            .comments
+            .is_empty());
+        let err = reader.read_game(&mut GameBuilder::new()).unwrap_err();
+        assert!(matches!(
+            PgnError::from_io(&err),
+            Some(PgnError::UnterminatedComment(_))
+        ));
+
+        let mut reader = SliceReader::new(&pgn);
+        reader.set_skip_comments(true);
+        let sliced = reader.read_game(&mut GameBuilder::new())?.unwrap();
+        assert_eq!(sliced, game);
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_variations() -> Result<(), io::Error> {
+        let pgn = b"1. e4 $1 (1. d4 { (not a variation } (1. c4 ; )\n) d5) \
+                    1... e5 { kept } (1... c5 ((1... e6))) 2. Nf3 ) *\n\n\
+                    1. d4 (1. e4 *";
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.set_skip_variations(true);
+        let game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+        assert_eq!(game.mainline.moves.len(), 3);
+        assert!(game.mainline.moves.iter().all(|m| m.variations.is_empty()));
+        assert_eq!(game.mainline.moves[0].nags, [Nag(1)]);
+        assert_eq!(game.mainline.moves[1].comments, [b" kept ".to_vec()]);
+        assert_eq!(game.outcome, None);
+
+        // An unterminated variation runs until the end of the game.
+        let unterminated = reader.read_game(&mut GameBuilder::new())?.unwrap();
+        assert_eq!(unterminated.mainline.moves.len(), 1);
+        assert_eq!(reader.read_game(&mut GameBuilder::new())?, None);
+
+        let mut reader = SliceReader::new(pgn);
+        reader.set_skip_variations(true);
+        assert_eq!(reader.read_game(&mut GameBuilder::new())?, Some(game));
+        assert_eq!(
+            reader.read_game(&mut GameBuilder::new())?,
+            Some(unterminated)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_variations_stray_parenthesis() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Variations {
+            begins: usize,
+            ends: usize,
+        }
+
+        impl Visitor for Variations {
+            type Result = (usize, usize);
+
+            fn begin_variation(&mut self) -> Skip {
+                self.begins += 1;
+                Skip(false)
+            }
+
+            fn end_variation(&mut self) {
+                self.ends += 1;
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                (mem::take(&mut self.begins), mem::take(&mut self.ends))
+            }
+        }
+
+        let pgn = b"1. e4 ) e5 (1... c5) ) 2. Nf3 *";
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.set_skip_variations(true);
+        assert_eq!(reader.read_game(&mut Variations::default())?, Some((0, 0)));
+
+        let mut reader = SliceReader::new(pgn);
+        reader.set_skip_variations(true);
+        assert_eq!(reader.read_game(&mut Variations::default())?, Some((0, 0)));
+
+        let mut reader = SliceReader::new(pgn);
+        assert_eq!(reader.read_game(&mut Variations::default())?, Some((1, 3)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_buffer_limit() -> Result<(), io::Error> {
+        let long = vec![b'x'; 10_000];
+        let game = |open: &[u8], close: &[u8]| {
+            [
+                &b"[Event \"?\"]\n\n1. e4 "[..],
+                open,
+                &long,
+                close,
+                b" e5 *\n\n[Event \"Next\"]\n\n1. d4 *\n",
+            ]
+            .concat()
+        };
+        let header = [
+            &b"[Event \""[..],
+            &long,
+            b"\"]\n\n1. e4 *\n\n[Event \"Next\"]\n\n1. d4 *\n",
+        ]
+        .concat();
+
+        let limited = |pgn: &[u8]| {
+            let mut reader = BufferedReader::with_capacity(pgn, 64);
+            reader.buffer_limit = 1024;
+            let first = reader.read_game(&mut GameSummary::new());
+            let next = reader.read_game(&mut GameSummary::new());
+            assert!(reader.stats().max_buffer_len < 4096);
+            (
+                first.map_err(|err| PgnError::from_io(&err).cloned()),
+                next.unwrap(),
+            )
+        };
+
+        // Without a closing brace, the comment runs until the end.
+        let (first, next) = limited(&game(b"{", b""));
+        assert!(matches!(first, Err(Some(PgnError::UnterminatedComment(_)))));
+        assert!(next.is_none());
+
+        let (first, next) = limited(&game(b"{", b"}"));
+        assert!(matches!(first, Err(Some(PgnError::UnterminatedComment(_)))));
+        assert_eq!(next.unwrap().1.len(), 1);
+
+        let (first, _) = limited(&game(b";", b"\n"));
+        let (_, moves) = first.unwrap().unwrap();
+        assert_eq!(moves.len(), 2);
+
+        let (first, next) = limited(&header);
+        assert!(matches!(first, Err(Some(PgnError::UnterminatedHeader(_)))));
+        assert!(next.is_some());
+
+        // Within the limit, the buffer grows as needed.
+        let pgn = game(b"{", b"}");
+        let mut reader = BufferedReader::with_capacity(&pgn[..], 64);
+        assert_eq!(
+            reader.read_game(&mut GameSummary::new())?.unwrap().1.len(),
+            2
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset() -> Result<(), io::Error> {
+        let mut reader = BufferedReader::with_capacity(Cursor::new(b"1. e4 {".to_vec()), 64);
+        assert!(reader.read_game(&mut GameBuilder::new()).is_err());
+        let mut comment = b"[Event \"Long\"]\n\n1. e4 { ".to_vec();
+        comment.resize(1000, b'c');
+        comment.extend_from_slice(b" } *");
+        let old = reader.reset(Cursor::new(comment));
+        assert_eq!(old.into_inner(), b"1. e4 {");
+        assert_eq!(
+            reader
+                .read_game(&mut GameBuilder::new())?
+                .unwrap()
+                .headers
+                .len(),
+            1
+        );
+        let capacity = reader.buffer.inner.capacity();
+        assert!(capacity >= 1000);
+
+        let input = b"\xef\xbb\xbf%x\n1. d4 *\n\n\n1. c4 *";
+        reader.reset(Cursor::new(input.to_vec()));
+        assert_eq!(reader.buffer.inner.capacity(), capacity);
+        assert_eq!(reader.position().line, 1);
+        assert_eq!(reader.stats(), ReaderStats::default());
+        let games = reader.collect_games(GameBuilder::new)?;
+        assert_eq!(games.len(), 2);
+        assert_eq!(reader.position().line, 5);
+        assert_eq!(reader.stats().bytes_read, input.len() as u64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_nth_game() -> Result<(), io::Error> {
+        let mut pgn = Vec::new();
+        for i in 0..100 {
+            let game = format!("[Round \"{}\"]\n\n1. e4 {{ game {} }} (1. d4) *\n\n", i, i);
+            pgn.extend_from_slice(game.as_bytes());
+        }
+        let round = |game: Option<Game>| game.map(|game| game.headers[0].1.clone());
+
+        let mut reader = BufferedReader::new_cursor(&pgn);
+        assert_eq!(
+            round(reader.nth_game(0, &mut GameBuilder::new())?),
+            Some(b"0".to_vec())
+        );
+        assert_eq!(
+            round(reader.nth_game(41, &mut GameBuilder::new())?),
+            Some(b"42".to_vec())
+        );
+        assert_eq!(round(reader.nth_game(57, &mut GameBuilder::new())?), None);
+
+        let mut reader = SliceReader::new(&pgn);
+        assert_eq!(
+            round(reader.nth_game(99, &mut GameBuilder::new())?),
+            Some(b"99".to_vec())
+        );
+        assert_eq!(round(reader.nth_game(0, &mut GameBuilder::new())?), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_movetext_boundaries() -> Result<(), io::Error> {
+        struct Positions {
+            skip_movetext: bool,
+            skip_variations: bool,
+        }
+
+        impl Visitor for Positions {
+            type Result = ();
+
+            fn end_headers(&mut self) -> Skip {
+                Skip(self.skip_movetext)
+            }
+
+            fn begin_variation(&mut self) -> Skip {
+                Skip(self.skip_variations)
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let pgn = b"[Event \"A\"]\n\n1. e4 { a\n\n[Event \"no\"] } e5 ; { not a comment\n\
+                    % escaped\n2. Nf3 *\r\n\r\n[Event \"B\"]\n\n1. d4\n[Event \"C\"]\n\n\
+                    1. c4 (1. b3 ;{\n) *\n\n\n1. g3 { } *\n\n\
+                    [Event \"D\"]\n\n1. e4 { [%cal Ge2e4}] \n\n[Event \"x\"] } \
+                    (1. d4 { [%csl Rd4}]\n\n[Event \"y\"] }) *";
+        for (lenient, games) in [(false, 7), (true, 5)] {
+            for capacity in [16, 8192] {
+                let mut positions = Vec::new();
+                for (skip_movetext, skip_variations) in
+                    [(false, false), (false, true), (true, false)]
+                {
+                    let mut reader = BufferedReader::with_capacity(&pgn[..], capacity);
+                    reader.set_lenient_comments(lenient);
+                    let mut visitor = Positions {
+                        skip_movetext,
+                        skip_variations,
+                    };
+                    let mut offsets = Vec::new();
+                    while reader.read_game(&mut visitor)?.is_some() {
+                        offsets.push(reader.position());
+                    }
+                    positions.push(offsets);
+                }
+                assert_eq!(positions[0].len(), games, "lenient {}", lenient);
+                assert_eq!(positions[0], positions[1], "lenient {}", lenient);
+                assert_eq!(positions[0], positions[2], "lenient {}", lenient);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bracket_at_line_start_in_comment() -> Result<(), io::Error> {
+        struct Comments(Vec<Vec<u8>>);
+
+        impl Visitor for Comments {
+            type Result = Vec<Vec<u8>>;
+
+            fn comment(&mut self, comment: RawComment<'_>) {
+                self.0.push(comment.as_bytes().to_vec());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        let pgn =
+            b"1. e4 { wrapped\n[%clk 0:03:00] } e5 { quoting\n[Event \"Other\"]\n} *\n\n1. d4 *";
+        let mut reader = BufferedReader::new(&pgn[..]);
+        let comments = reader.read_game(&mut Comments(Vec::new()))?.unwrap();
+        assert_eq!(
+            comments,
+            [
+                b" wrapped\n[%clk 0:03:00] ".to_vec(),
+                b" quoting\n[Event \"Other\"]\n".to_vec(),
+            ]
+        );
+        assert!(reader.read_game(&mut Comments(Vec::new()))?.is_some());
+        assert!(reader.read_game(&mut Comments(Vec::new()))?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_garbage() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Tokens {
+            sans: Vec<String>,
+            garbage: Vec<Vec<u8>>,
+        }
+
+        impl Visitor for Tokens {
+            type Result = (Vec<String>, Vec<Vec<u8>>);
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.sans.push(san_plus.to_string());
+            }
+
+            fn garbage(&mut self, token: &[u8]) {
+                self.garbage.push(token.to_vec());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                (
+                    std::mem::take(&mut self.sans),
+                    std::mem::take(&mut self.garbage),
+                )
+            }
+        }
+
+        let pgn = b"1. d4 d5 2 Bf4 \"x\" 2... Nf6 + 3. exd6 e.p. O-O-O+ 4. 0-0-0# 0x7 Xe4 Xz9 1-0";
+        let (sans, garbage) = BufferedReader::new(&pgn[..])
+            .read_game(&mut Tokens::default())?
+            .unwrap();
+        assert_eq!(sans, ["d4", "d5", "Bf4", "Nf6", "exd6", "O-O-O+", "O-O-O#"]);
+        let expected: [&[u8]; 6] = [b"2", b"\"x\"", b"+", b"0x7", b"Xe4", b"Xz9"];
+        assert_eq!(garbage, expected);
+
+        // Reported tokens that look like moves go to unknown_token() instead.
+        let mut reader = BufferedReader::new(&pgn[..]);
+        reader.set_invalid_san_policy(InvalidSanPolicy::Report);
+        let (_, garbage) = reader.read_game(&mut Tokens::default())?.unwrap();
+        assert_eq!(garbage, expected[..4]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pre_game_comment() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Comments {
+            pre_game: Vec<Vec<u8>>,
+            other: Vec<Vec<u8>>,
+        }
+
+        impl Visitor for Comments {
+            type Result = (Vec<Vec<u8>>, Vec<Vec<u8>>);
+
+            fn comment(&mut self, comment: RawComment<'_>) {
+                self.other.push(comment.as_bytes().to_vec());
+            }
+
+            fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+                self.pre_game.push(comment.as_bytes().to_vec());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                (
+                    std::mem::take(&mut self.pre_game),
+                    std::mem::take(&mut self.other),
+                )
+            }
+        }
+
+        let pgn = b"{ a } ; b\n1. { c } e4 { d } *\n\n1... (1... e5 { e }) { f } *\n\n0-0 { g } *";
+        let mut reader = BufferedReader::new(&pgn[..]);
+        let mut visitor = Comments::default();
+        let game = |(pre_game, other): (Vec<Vec<u8>>, Vec<Vec<u8>>)| {
+            let text = |comments: Vec<Vec<u8>>| {
+                comments
+                    .into_iter()
+                    .map(|c| String::from_utf8(c).unwrap())
+                    .collect::<Vec<_>>()
+            };
+            (text(pre_game), text(other))
+        };
+        let (pre_game, other) = game(reader.read_game(&mut visitor)?.unwrap());
+        assert_eq!(pre_game, [" a ", " b", " c "]);
+        assert_eq!(other, [" d "]);
+        let (pre_game, other) = game(reader.read_game(&mut visitor)?.unwrap());
+        assert!(pre_game.is_empty());
+        assert_eq!(other, [" e ", " f "]);
+        let (pre_game, other) = game(reader.read_game(&mut visitor)?.unwrap());
+        assert!(pre_game.is_empty());
+        assert_eq!(other, [" g "]);
+
+        // By default, pre-game comments are ordinary comments.
+        let mut builder = GameBuilder::new();
+        let game = BufferedReader::new(&pgn[..])
+            .read_game(&mut builder)?
+            .unwrap();
+        assert_eq!(game.mainline.comments.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_game_index() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Index(Option<usize>);
+
+        impl Visitor for Index {
+            type Result = Option<usize>;
+
+            fn begin_game(&mut self) {
+                self.0 = None;
+            }
+
+            fn game_index(&mut self, index: usize) {
+                self.0 = Some(index);
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                self.0
+            }
+        }
+
+        let pgn = b"1. e4 *\n\n".repeat(8);
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        assert_eq!(reader.read_game(&mut Index::default())?, Some(Some(0)));
+        assert!(reader.skip_game::<Index>()?);
+        assert_eq!(
+            reader.read_headers_only(&mut Index::default())?,
+            Some(Some(2))
+        );
+        assert!(reader.read_raw_game()?.is_some());
+        assert_eq!(reader.nth_game(1, &mut Index::default())?, Some(Some(5)));
+        let mut visitor = Tee::new(Index::default(), Index::default());
+        assert_eq!(reader.read_game(&mut visitor)?, Some((Some(6), Some(6))));
+        reader.reset(Cursor::new(&pgn[..]));
+        assert_eq!(reader.read_game(&mut Index::default())?, Some(Some(0)));
+
+        let mut reader = SliceReader::new(&pgn);
+        assert!(reader.skip_game()?);
+        assert_eq!(reader.read_game(&mut Index::default())?, Some(Some(1)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_abort() -> Result<(), io::Error> {
+        struct Until {
+            games: usize,
+            limit: usize,
+        }
+
+        impl Visitor for Until {
+            type Result = usize;
+
+            fn end_game(&mut self) -> usize {
+                self.games += 1;
+                self.games
+            }
+
+            fn abort(&self) -> bool {
+                self.games >= self.limit
+            }
+        }
+
+        let pgn = b"1. e4 *\n\n".repeat(10);
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let mut visitor = Until { games: 0, limit: 3 };
+        let games = reader.games(&mut visitor).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(games, [1, 2, 3]);
+        // The reader stopped right after the last game.
+        assert_eq!(reader.count_games()?, 7);
+
+        let mut visitor = Until { games: 0, limit: 2 };
+        let games = BufferedReader::new_cursor(&pgn[..]).read_selected(&[1, 4, 6], &mut visitor)?;
+        assert_eq!(games, [1, 2]);
+
+        let mut reader = SliceReader::new(&pgn);
+        let mut visitor = Until { games: 0, limit: 4 };
+        reader.read_all(&mut visitor)?;
+        assert_eq!(visitor.games, 4);
+
+        // A tee goes on until both sides abort.
+        let mut visitor = Tee::new(Until { games: 0, limit: 2 }, Until { games: 0, limit: 5 });
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+        assert_eq!(visitor.into_inner().1.games, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_last_game_len() -> Result<(), io::Error> {
+        let pgn = b"\xef\xbb\xbf\n[Event \"A\"]\n\n1. e4 { \n\n } e5 *\n\n\n1. d4 *\n\n1. c4 1-0\n\n1. Nf3 *\n{ trailing }\n";
+        let mut reader = BufferedReader::new(Chunked {
+            data: pgn,
+            chunk_size: 5,
+        });
+        let mut lens = Vec::new();
+        let mut start = reader.position().byte;
+        loop {
+            let more = match lens.len() {
+                0 => reader.read_game(&mut GameSummary::new())?.is_some(),
+                1 => reader.read_headers_only(&mut GameSummary::new())?.is_some(),
+                2 => reader.skip_game::<GameSummary>()?,
+                _ => reader.read_raw_game()?.is_some(),
+            };
+            assert_eq!(reader.last_game_len(), reader.position().byte - start);
+            start = reader.position().byte;
+            lens.push(reader.last_game_len());
+            if !more {
+                break;
+            }
+        }
+        assert_eq!(lens, [37, 9, 11, 22, 0]);
+        assert_eq!(lens.iter().sum::<u64>(), pgn.len() as u64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_games() -> Result<(), io::Error> {
+        let long = b"1. e4 { \n\n[Event \"not a game\"]\n\n } e5 ".repeat(10);
+        let mut first = b"[Event \"A\"]\n[Site \"?\"]\n\n".to_vec();
+        first.extend(&long);
+        first.extend(b"1-0\r\n\r\n% escaped\n\n");
+        let games: [&[u8]; 3] = [&first, b"[Event \"B\"]\n\n1. d4 *\n\n", b"1. c4 *"];
+        let mut pgn = b"\xef\xbb\xbf\n".to_vec();
+        pgn.extend(games.concat());
+
+        let mut reader = BufferedReader::new(Chunked {
+            data: &pgn,
+            chunk_size: 7,
+        });
+        let raw = reader.raw_games().collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(raw, games);
+        assert_eq!(reader.read_raw_game()?, None);
+
+        // The reader can go on with a visitor after taking some games raw.
+        let mut reader = BufferedReader::with_capacity(&pgn[..], 64);
+        assert_eq!(
+            reader.raw_games().next().transpose()?.as_deref(),
+            Some(games[0])
+        );
+        let game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+        assert_eq!(game.headers, [(b"Event".to_vec(), b"B".to_vec())]);
+        assert_eq!(reader.read_raw_game()?.as_deref(), Some(games[2]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_stats() -> Result<(), io::Error> {
+        let pgn = b"1. e4 e5 *\n\n1. d4 d5 *\n".repeat(100);
+        let mut reader = BufferedReader::new(Chunked {
+            data: &pgn,
+            chunk_size: 100,
+        });
+        assert_eq!(reader.stats(), ReaderStats::default());
+        reader.read_all(&mut GameCounter::default())?;
+        assert_eq!(
+            reader.stats(),
+            ReaderStats {
+                refills: pgn.len().div_ceil(100) as u64,
+                grows: 0,
+                bytes_read: pgn.len() as u64,
+                max_buffer_len: pgn.len(),
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_take_games() -> Result<(), io::Error> {
+        let pgn = b"1. e4 *\n\n[Event \"?\"]\n\n1. d4 { ... } *\n\n[Event \"Rest\"]\n\n1. c4 *\n";
+        for chunk_size in [1, 7, 100] {
+            let mut reader = BufferedReader::with_capacity(
+                Chunked {
+                    data: pgn,
+                    chunk_size,
+                },
+                64,
+            );
+            let mut counter = GameCounter::default();
+            assert_eq!(reader.games(&mut counter).take(2).count(), 2);
+            assert_eq!(counter.count, 2);
+
+            let mut rest = Vec::new();
+            reader.into_inner().read_to_end(&mut rest)?;
+            assert_eq!(rest, b"[Event \"Rest\"]\n\n1. c4 *\n");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_game() -> Result<(), io::Error> {
+        let mut counter = GameCounter::default();
+        let mut reader = BufferedReader::new(io::Cursor::new(b"  "));
+        reader.read_game(&mut counter)?;
+        assert_eq!(counter.count, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailing_space() -> Result<(), io::Error> {
+        let mut counter = GameCounter::default();
+        let mut reader = BufferedReader::new(io::Cursor::new(b"1. e4 1-0\n\n\n\n\n  \n"));
+        reader.read_game(&mut counter)?;
+        assert_eq!(counter.count, 1);
+        reader.read_game(&mut counter)?;
+        assert_eq!(counter.count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailing_content() -> Result<(), io::Error> {
+        for tail in [
+            &b"\n\n  \t\r\n \n"[..],
+            b"\n\n{ trailing comment }\n",
+            b"\n\n% escaped\nsha256: 0123abcd\n",
+            b"\n\n\x00\xff\xfe garbage",
+            b"\n\n-- end --",
+            b"\n\n; checksum\n",
+        ] {
+            let mut pgn = b"[Event \"A\"]\n\n1. e4 e5 1-0\n\n1. d4 *".to_vec();
+            pgn.extend_from_slice(tail);
+
+            let mut counter = GameCounter::default();
+            BufferedReader::new_cursor(&pgn[..]).read_all(&mut counter)?;
+            assert_eq!(counter.count, 2, "{:?}", tail);
+
+            let mut reader = BufferedReader::new_cursor(&pgn[..]);
+            assert!(reader.skip_game::<SkipVisitor>()?);
+            assert!(reader.skip_game::<SkipVisitor>()?);
+            assert!(!reader.skip_game::<SkipVisitor>()?);
+
+            let mut reader = BufferedReader::new_cursor(&pgn[..]);
+            assert!(reader.skip_game::<SkipVisitor>()?);
+            assert!(reader.has_more()?);
+            assert!(reader.skip_game::<SkipVisitor>()?);
+            assert!(!reader.has_more()?, "{:?}", tail);
+
+            let count = BufferedReader::new_cursor(&pgn[..]).count_games()?;
+            assert_eq!(count, 2);
+            let index = BufferedReader::new_cursor(&pgn[..]).build_index()?;
+            assert_eq!(index.len(), count, "{:?}", tail);
+            let mut reader = SliceReader::new(&pgn);
+            assert_eq!(reader.build_index()?.len(), count, "{:?}", tail);
+            assert!(!reader.has_more()?);
+        }
+
+        // Truncated headers and comments are still errors.
+        for tail in [&b"\n\n[Event"[..], b"\n\n{ unterminated"] {
+            let mut pgn = b"1. e4 *".to_vec();
+            pgn.extend_from_slice(tail);
+            let mut counter = GameCounter::default();
+            let err = BufferedReader::new_cursor(&pgn[..])
+                .read_all(&mut counter)
+                .unwrap_err();
+            assert!(PgnError::from_io(&err).is_some());
+        }
+
+        // Stray comments between games are not games either.
+        let mut counter = GameCounter::default();
+        BufferedReader::new_cursor(&b"{ stray }\n\n1. e4 *"[..]).read_all(&mut counter)?;
+        assert_eq!(counter.count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_non_game_stretches() -> Result<(), io::Error> {
+        let mut long = b"{ ".to_vec();
+        long.resize(3 * MIN_BUFFER_SIZE, b'c');
+        long.extend_from_slice(b" }\n");
+
+        let mut pgn = b"{ leading }\n\n[Event \"A\"]\n\n1. e4 *\n\n".to_vec();
+        pgn.extend_from_slice(&long);
+        pgn.extend_from_slice(b"\n; between\n% escaped\n\n1. d4 *\n\n");
+        pgn.extend_from_slice(&long);
+
+        for capacity in [MIN_CAPACITY, MIN_BUFFER_SIZE] {
+            let mut reader = BufferedReader::with_capacity(&pgn[..], capacity);
+            let (headers, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+            assert_eq!(headers[&b"Event"[..]], b"A");
+            assert_eq!(moves.len(), 1);
+            assert!(reader.has_more()?);
+            assert_eq!(
+                reader.read_game(&mut GameSummary::new())?.unwrap().1.len(),
+                1
+            );
+            assert!(!reader.has_more()?);
+            assert_eq!(reader.read_game(&mut GameSummary::new())?, None);
+
+            assert_eq!(
+                BufferedReader::with_capacity(&pgn[..], capacity).count_games()?,
+                2
+            );
+            let index = BufferedReader::with_capacity(&pgn[..], capacity).build_index()?;
+            assert_eq!(index.len(), 2);
+        }
+
+        let mut reader = SliceReader::new(&pgn);
+        assert_eq!(reader.build_index()?.len(), 2);
+        assert!(!reader.has_more()?);
+        assert_eq!(SliceReader::new(&pgn).count_games()?, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_headers() -> Result<(), io::Error> {
+        struct HeaderCollector {
+            headers: Vec<(Vec<u8>, Vec<u8>)>,
+        }
+
+        impl Visitor for HeaderCollector {
+            type Result = ();
+
+            fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+                self.headers
+                    .push((key.to_owned(), value.as_bytes().to_owned()));
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let pgn = b"[Event \"Casual\"]\r\n\
+                    [White \"O\\\"Brien\"]  \n\
+                    [Black \"A\"] [Result \"*\"]\n\
+                    [NoValue]\n\
+                    [Site \"unterminated\n\
+                    [Round \"1\"]\n\
+                    [  White   \"x\"]\n\
+                    [\tOdd Key\t\"y\"]\n\
+                    [ \"z\"]\n\
+                    \n\
+                    *";
+
+        let mut collector = HeaderCollector {
+            headers: Vec::new(),
+        };
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.read_game(&mut collector)?;
+        assert_eq!(
+            collector.headers,
+            vec![
+                (b"Event".to_vec(), b"Casual".to_vec()),
+                (b"White".to_vec(), b"O\\\"Brien".to_vec()),
+                (b"Black".to_vec(), b"A".to_vec()),
+                (b"Result".to_vec(), b"*".to_vec()),
+                (b"Site".to_vec(), b"unterminated".to_vec()),
+                (b"Round".to_vec(), b"1".to_vec()),
+                (b"White".to_vec(), b"x".to_vec()),
+                (b"Odd Key".to_vec(), b"y".to_vec()),
+                (b"".to_vec(), b"z".to_vec()),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_long_tokens() -> Result<(), io::Error> {
+        let mut value = vec![b'v'; 20_000];
+        value.extend_from_slice(b"\\\"end");
+        let mut comment = vec![b'c'; 100_000];
+        comment.extend_from_slice(b"end");
+
+        let mut pgn = b"[Variant \"".to_vec();
+        pgn.extend_from_slice(&value);
+        pgn.extend_from_slice(b"\"]\n[Event \"Next\"]\n\n1. e4 {");
+        pgn.extend_from_slice(&comment);
+        pgn.extend_from_slice(b"} e5 *\n\n1. d4 *");
+
+        struct Collect(Vec<Vec<u8>>);
+
+        impl Visitor for Collect {
+            type Result = Vec<Vec<u8>>;
+
+            fn header(&mut self, _key: &[u8], value: RawHeader<'_>) {
+                self.0.push(value.as_bytes().to_vec());
+            }
+
+            fn comment(&mut self, comment: RawComment<'_>) {
+                self.0.push(comment.as_bytes().to_vec());
+            }
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0.push(san_plus.to_string().into_bytes());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        let mut reader = BufferedReader::new(Chunked {
+            data: &pgn,
+            chunk_size: 1000,
+        });
+        let mut collect = Collect(Vec::new());
+        assert_eq!(
+            reader.read_game(&mut collect)?.unwrap(),
+            [
+                value,
+                b"Next".to_vec(),
+                b"e4".to_vec(),
+                comment,
+                b"e5".to_vec()
+            ]
+        );
+        assert_eq!(reader.read_game(&mut collect)?.unwrap(), [b"d4"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_capacity() -> Result<(), io::Error> {
+        let mut pgn = Vec::new();
+        for i in 0..200 {
+            pgn.extend_from_slice(
+                format!(
+                    "[Event \"{}\"]\r\n[Site \"{}\"]\r\n\r\n\
+                     1. e4 {{ {} }} e5 (1... c5 $1) 2. Nf3 1/2-1/2\r\n\r\n",
+                    i,
+                    "s".repeat(i),
+                    "c".repeat(3 * i),
+                )
+                .as_bytes(),
+            );
+        }
+
+        let expected = BufferedReader::new_cursor(&pgn)
+            .into_iter(&mut GameSummary::new())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(expected.len(), 200);
+        for capacity in [0, 64, 100, 1 << 20] {
+            let games = BufferedReader::with_capacity(
+                Chunked {
+                    data: &pgn,
+                    chunk_size: 7,
+                },
+                capacity,
+            )
+            .into_iter(&mut GameSummary::new())
+            .collect::<io::Result<Vec<_>>>()?;
+            assert_eq!(games, expected, "capacity {}", capacity);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_crlf_headers() -> Result<(), io::Error> {
+        let pgn = b"[Event \"Casual\"]\r\n\
+                    [Site \"unterminated\r\n\
+                    [Round \"\r\n\
+                    [White \"A\"] \r\n\
+                    \r\n\
+                    1. e4 e5 *\r\n\
+                    \r\n\
+                    [Black \"at eof\r";
+
+        let mut summary = GameSummary::new();
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let (headers, _) = reader.read_game(&mut summary)?.unwrap();
+        assert_eq!(headers[&b"Event"[..]], b"Casual");
+        assert_eq!(headers[&b"Site"[..]], b"unterminated");
+        assert_eq!(headers[&b"Round"[..]], b"");
+        assert_eq!(headers[&b"White"[..]], b"A");
+        let (headers, _) = reader.read_game(&mut summary)?.unwrap();
+        assert_eq!(headers[&b"Black"[..]], b"at eof");
+        Ok(())
+    }
+
+    #[test]
+    fn test_unclosed_header_at_eof() -> Result<(), io::Error> {
+        struct White(Option<Vec<u8>>);
+
+        impl Visitor for White {
+            type Result = Option<Vec<u8>>;
+
+            fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+                if key == b"White" {
+                    self.0 = Some(value.as_bytes().to_owned());
+                }
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                self.0.take()
+            }
+        }
+
+        for pgn in [
+            &b"[Event \"?\"]\n[White \"unclosed"[..],
+            b"[White \"unclosed\\",
+        ] {
+            for chunk_size in [1, 8192] {
+                let mut reader = BufferedReader::new(Chunked {
+                    data: pgn,
+                    chunk_size,
+                });
+                let mut visitor = White(None);
+                let white = reader.read_game(&mut visitor)?;
+                assert_eq!(
+                    white.unwrap().as_deref().map(|v| &v[..8]),
+                    Some(&b"unclosed"[..])
+                );
+                assert_eq!(reader.read_game(&mut visitor)?, None);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_index() -> Result<(), io::Error> {
+        /// A reader that is not `Seek` and yields little data at a time.
+        struct Pipe<'a>(&'a [u8]);
+
+        impl Read for Pipe<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let n = min(min(buf.len(), 7), self.0.len());
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+
+        let pgn = b"\xef\xbb\xbf[Event \"A\"]\n\n1. e4 { a comment\n\non two lines } e5 *\n\n\n\
+                    [Event \"B\"]\n1. d4 1-0\n\n  1. c4 0-1";
+
+        let index = BufferedReader::new(Pipe(&pgn[..])).build_index()?;
+        let games = index
+            .iter()
+            .map(|game| &pgn[game.start as usize..game.end as usize])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            games,
+            vec![
+                &b"[Event \"A\"]\n\n1. e4 { a comment\n\non two lines } e5 *\n"[..],
+                &b"[Event \"B\"]\n1. d4 1-0\n"[..],
+                &b"1. c4 0-1"[..],
+            ]
+        );
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.seek_game(index[1])?;
+        let mut counter = GameCounter::default();
+        reader.read_all(&mut counter)?;
+        assert_eq!(counter.count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_in_escaped_line() {
+        /// Fails after the data is exhausted.
+        struct Failing {
+            data: Vec<u8>,
+            pos: usize,
+        }
+
+        impl Read for Failing {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                if self.pos >= self.data.len() {
+                    return Err(io::Error::new(io::ErrorKind::BrokenPipe, "mock"));
+                }
+                let n = min(min(buf.len(), 1000), self.data.len() - self.pos);
+                buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+                self.pos += n;
+                Ok(n)
+            }
+        }
+
+        struct Skipper(bool);
+
+        impl Visitor for Skipper {
+            type Result = ();
+
+            fn end_headers(&mut self) -> Skip {
+                Skip(self.0)
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let mut data = b"[Event \"?\"]\n\n1. e4\n%".to_vec();
+        data.resize(data.len() + 3 * MIN_BUFFER_SIZE, b'x');
+
+        for skip in [false, true] {
+            let mut reader = BufferedReader::new(Failing {
+                data: data.clone(),
+                pos: 0,
+            });
+            let err = reader.read_game(&mut Skipper(skip)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        }
+
+        let mut reader = BufferedReader::new(Failing { data, pos: 0 });
+        let err = reader.read_headers_only(&mut Skipper(true)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn test_whitespace_between_games() -> Result<(), io::Error> {
+        let mut pgn = Vec::new();
+        for i in 0..5 {
+            pgn.extend_from_slice(b"[Event \"?\"]\n\n1. e4 e5 *\n\n");
+            for _ in 0..(i * 50_000) {
+                pgn.extend_from_slice(b" \r\n\t");
+            }
+            pgn.extend_from_slice(b"\n% escaped\n\n");
+        }
+
+        for chunk_size in [7, 8192] {
+            let mut counter = GameCounter::default();
+            BufferedReader::new(Chunked {
+                data: &pgn,
+                chunk_size,
+            })
+            .read_all(&mut counter)?;
+            assert_eq!(counter.count, 5);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_no_blank_line_between_games() -> Result<(), io::Error> {
+        let pgn = b"[Event \"1\"]\n1. e4 e5 1-0\n[Event \"2\"]\n1. d4 { no result }\n\
+                    [Event \"3\"]\r\n1. c4 (1. Nf3\n) *\r\n[Event \"4\"]\n[Round \"1\"]\n1. g3 g6\n\
+                    %esc\n[Event \"5\"]\n1. d4 d5\n%one\r\n%two\n[Event \"6\"]\n\n1. c4 *";
+        let expected = [
+            (b"1", 2),
+            (b"2", 1),
+            (b"3", 1),
+            (b"4", 2),
+            (b"5", 2),
+            (b"6", 1),
+        ];
+
+        let check = |games: Vec<Game>| {
+            assert_eq!(games.len(), expected.len());
+            for (game, (event, plies)) in games.iter().zip(expected) {
+                assert_eq!(game.headers[0], (b"Event".to_vec(), event.to_vec()));
+                assert_eq!(game.mainline.moves.len(), plies);
+            }
+        };
+        for chunk_size in [1, 5, 8192] {
+            let mut reader = BufferedReader::new(Chunked {
+                data: pgn,
+                chunk_size,
+            });
+            check(reader.collect_games(GameBuilder::new)?);
+        }
+        check(SliceReader::new(pgn).collect_games(GameBuilder::new)?);
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let mut skipped = 0;
+        while reader.skip_game::<GameBuilder>()? {
+            skipped += 1;
+        }
+        assert_eq!(skipped, expected.len());
+        assert_eq!(
+            BufferedReader::new_cursor(&pgn[..]).count_games()?,
+            expected.len()
+        );
+        assert_eq!(
+            BufferedReader::new_cursor(&pgn[..]).build_index()?.len(),
+            expected.len()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_slice_reader() -> Result<(), io::Error> {
+        // A comment that is longer than the buffer of a BufferedReader.
+        let mut pgn = b"\xef\xbb\xbf[Event \"Long\"]\n\n1. e4 {".to_vec();
+        pgn.resize(pgn.len() + 3 * MIN_BUFFER_SIZE, b'x');
+        pgn.extend_from_slice(b"} e5 1-0\n\n");
+        let long_game_len = pgn.len();
+        for i in 0..100 {
+            pgn.extend_from_slice(format!("[Round \"{}\"]\n\n1. d4 d5 *\n\n", i).as_bytes());
+        }
+        let games_len = pgn.len();
+        pgn.extend_from_slice(b"{ trailing }\n");
+
+        let mut reader = SliceReader::new(&pgn);
+        let mut sliced = Vec::new();
+        while let Some(game) = reader.read_game(&mut GameSummary::new())? {
+            sliced.push(game);
+        }
+        assert_eq!(sliced.len(), 101);
+        assert_eq!(sliced[0].1.len(), 2);
+        assert_eq!(reader.position().byte, pgn.len() as u64);
+        assert!(reader.remaining_input().is_empty());
+
+        let buffered = BufferedReader::new_cursor(&pgn)
+            .into_iter(&mut GameSummary::new())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(sliced, buffered);
+
+        let index = SliceReader::new(&pgn[..games_len]).build_index()?;
+        assert_eq!(index.len(), 101);
+        assert_eq!(index[1].start, long_game_len as u64);
+        assert_eq!(reader.game(index[100]), b"[Round \"99\"]\n\n1. d4 d5 *\n");
+
+        reader.seek_game(index[0]);
+        assert!(reader.skip_game()?);
+        let (headers, _) = reader.read_headers_only(&mut GameSummary::new())?.unwrap();
+        assert_eq!(headers[&b"Round"[..]], b"0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_offset() -> Result<(), io::Error> {
+        let mut pgn = b"\xef\xbb\xbf".to_vec();
+        let mut starts = Vec::new();
+        for i in 0..500 {
+            pgn.extend_from_slice(b" \r\n");
+            starts.push(pgn.len() as u64);
+            pgn.extend_from_slice(format!("[Round \"{}\"]\n\n1. e4 e5 *\n", i).as_bytes());
+        }
+
+        let mut reader = BufferedReader::new(Chunked {
+            data: &pgn,
+            chunk_size: 1000,
+        });
+        let mut offsets = Vec::new();
+        while reader.has_more()? {
+            offsets.push(reader.state().offset);
+            reader.read_game(&mut GameCounter::default())?;
+        }
+        assert_eq!(offsets, starts);
+        assert_eq!(reader.state().offset, pgn.len() as u64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_resume() -> Result<(), io::Error> {
+        let mut pgn = Vec::new();
+        for i in 0..1000 {
+            pgn.extend_from_slice(format!("[Round \"{}\"]\n\n1. e4 e5 *\n\n", i).as_bytes());
+        }
+
+        fn round(reader: &mut BufferedReader<Cursor<&Vec<u8>>>) -> io::Result<Option<Vec<u8>>> {
+            Ok(reader
+                .read_game(&mut GameSummary::new())?
+                .map(|(headers, _)| headers[&b"Round"[..]].clone()))
+        }
+
+        // Serve a few games per request, resuming from the previous state.
+        let mut state = BufferedReader::new_cursor(&pgn).state();
+        assert_eq!(state.offset, 0);
+        let mut rounds = 0;
+        loop {
+            let mut reader = BufferedReader::resume(Cursor::new(&pgn), state)?;
+            for _ in 0..7 {
+                match round(&mut reader)? {
+                    Some(r) => {
+                        assert_eq!(r, rounds.to_string().as_bytes());
+                        rounds += 1;
+                    }
+                    None => break,
+                }
+            }
+            if !reader.has_more()? {
+                assert_eq!(reader.state().offset, pgn.len() as u64);
+                break;
+            }
+            state = reader.state();
+        }
+        assert_eq!(rounds, 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_member_gzip() -> Result<(), io::Error> {
+        use flate2::{
+            read::{GzDecoder, MultiGzDecoder},
+            write::GzEncoder,
+            Compression,
+        };
+
+        // Concatenated members, as produced by appending to a .gz file.
+        let mut gz = Vec::new();
+        for member in [&b"1. e4 e5 *\n\n"[..], b"1. d4 d5 *\n\n1. c4 *\n"] {
+            let mut encoder = GzEncoder::new(&mut gz, Compression::default());
+            io::Write::write_all(&mut encoder, member)?;
+            encoder.finish()?;
+        }
+
+        let mut counter = GameCounter::default();
+        BufferedReader::new(MultiGzDecoder::new(&gz[..])).read_all(&mut counter)?;
+        assert_eq!(counter.count, 3);
+
+        // A plain decoder stops after the first member.
+        let mut counter = GameCounter::default();
+        BufferedReader::new(GzDecoder::new(&gz[..])).read_all(&mut counter)?;
+        assert_eq!(counter.count, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_to() -> Result<(), io::Error> {
+        let mut pgn = Vec::new();
+        for _ in 0..1000 {
+            pgn.extend_from_slice(b"[Event \"?\"]\n\n1. e4 e5 *\n\n");
+        }
+
+        let mut reader = BufferedReader::new(Chunked {
+            data: &pgn,
+            chunk_size: 100,
+        });
+        assert_eq!(reader.fill_to(10_000)?, 10_000);
+        assert_eq!(reader.buffer().len(), 10_000);
+        assert_eq!(reader.buffer(), &pgn[..10_000]);
+
+        // Beyond the capacity and the end of the input.
+        let mut counter = GameCounter::default();
+        reader.read_game(&mut counter)?;
+        assert_eq!(
+            reader.fill_to(100_000)?,
+            pgn.len() - reader.offset() as usize
+        );
+        assert_eq!(reader.buffer(), &pgn[reader.offset() as usize..]);
+
+        reader.read_all(&mut counter)?;
+        assert_eq!(counter.count, 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_bytes() -> Result<(), io::Error> {
+        let mut pgn = Vec::new();
+        for _ in 0..1000 {
+            pgn.extend_from_slice(b"[Event \"?\"]\n\n1. e4 e5 *\n\n");
+        }
+
+        let mut reader = BufferedReader::new(Chunked {
+            data: &pgn,
+            chunk_size: 100,
+        });
+        reader.set_max_bytes(Some(pgn.len() as u64));
+        let mut counter = GameCounter::default();
+        reader.read_all(&mut counter)?;
+        assert_eq!(counter.count, 1000);
+
+        let mut reader = BufferedReader::new(Chunked {
+            data: &pgn,
+            chunk_size: 100,
+        });
+        reader.set_max_bytes(Some(pgn.len() as u64 - 1));
+        let mut counter = GameCounter::default();
+        let err = reader.read_all(&mut counter).unwrap_err();
+        assert_eq!(
+            PgnError::from_io(&err),
+            Some(&PgnError::InputTooLarge {
+                limit: pgn.len() as u64 - 1
+            })
+        );
+        assert!(counter.count < 1000);
+        Ok(())
+    }
+
+    #[test]
+    fn test_utf16() {
+        let le = b"\xff\xfe[\0E\0v\0e\0n\0t\0 \0\"\0?\0\"\0]\0";
+        let be = b"\xfe\xff\0[\0E\0v\0e\0n\0t\0 \0\"\0?\0\"\0]";
+        for (pgn, big_endian) in [(&le[..], false), (&be[..], true)] {
+            let expected = Some(&PgnError::Utf16 { big_endian });
+
+            let mut reader = BufferedReader::new_cursor(pgn);
+            reader.set_recover(true);
+            let err = reader.read_game(&mut GameCounter::default()).unwrap_err();
+            assert_eq!(PgnError::from_io(&err), expected);
+            let err = reader.count_games().unwrap_err();
+            assert_eq!(PgnError::from_io(&err), expected);
+
+            let mut reader = SliceReader::new(pgn);
+            let err = reader.read_game(&mut GameCounter::default()).unwrap_err();
+            assert_eq!(PgnError::from_io(&err), expected);
+        }
+
+        // Only the start of the input is checked.
+        let mut reader = BufferedReader::new_cursor(&b"1. e4 *\n\n\xff\xfe *"[..]);
+        assert_eq!(reader.count_games().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_read_selected() -> Result<(), io::Error> {
+        /// Checks that only selected games are read.
+        struct Selected;
+
+        impl Visitor for Selected {
+            type Result = ();
+
+            fn comment(&mut self, comment: RawComment<'_>) {
+                assert_eq!(comment.as_bytes(), b"selected");
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let mut pgn = Vec::new();
+        for i in 0..300 {
+            let comment = if [3, 17, 250].contains(&i) {
+                "selected"
+            } else {
+                "skipped\n\n[Event \"?\"]"
+            };
+            pgn.extend_from_slice(format!("[Round \"{}\"]\n\n{{{}}} *\n\n", i, comment).as_bytes());
+        }
+
+        let games = BufferedReader::new_cursor(&pgn[..])
+            .read_selected(&[3, 17, 250, 400], &mut Selected)?;
+        assert_eq!(games.len(), 3);
+
+        let index = BufferedReader::new_cursor(&pgn[..]).build_index()?;
+        let games = BufferedReader::new_cursor(&pgn[..]).read_selected_indexed(
+            &index,
+            &[3, 17, 250, 400],
+            &mut Selected,
+        )?;
+        assert_eq!(games.len(), 3);
+
+        let err = BufferedReader::new_cursor(&pgn[..])
+            .read_selected(&[17, 3], &mut Selected)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        Ok(())
+    }
+
+    #[test]
+    fn test_games() {
+        let pgn = b"1. e4 *\n\n1. d4 { unterminated";
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let mut counter = GameCounter::default();
+
+        let mut games = reader.games(&mut counter);
+        assert!(matches!(games.next(), Some(Ok(()))));
+        assert!(matches!(games.next(), Some(Err(_))));
+        assert!(games.next().is_none());
+        assert_eq!(counter.count, 1);
+    }
+
+    #[test]
+    fn test_movetext_callbacks() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Counts {
+            skip: bool,
+            sans: usize,
+            nags: usize,
+            comments: usize,
+            variations: usize,
+        }
+
+        impl Visitor for Counts {
+            type Result = (usize, usize, usize, usize);
+
+            fn end_headers(&mut self) -> Skip {
+                Skip(self.skip)
+            }
+
+            fn san(&mut self, _san_plus: SanPlus) {
+                self.sans += 1;
+            }
+
+            fn nag(&mut self, _nag: Nag) {
+                self.nags += 1;
+            }
+
+            fn comment(&mut self, _comment: RawComment<'_>) {
+                self.comments += 1;
+            }
+
+            fn end_variation(&mut self) {
+                self.variations += 1;
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                let counts = (self.sans, self.nags, self.comments, self.variations);
+                (self.sans, self.nags, self.comments, self.variations) = (0, 0, 0, 0);
+                counts
+            }
+        }
+
+        // A game much longer than the buffer, delivered in small chunks.
+        let mut pgn = b"[Event \"Long\"]\n\n".to_vec();
+        for _ in 0..2000 {
+            pgn.extend_from_slice(b"Nf3 $1 { out } Nf6 (Nc6) Ng1 Ng8 ");
+        }
+        pgn.extend_from_slice(b"*\n");
+
+        for skip in [false, true] {
+            let mut reader = BufferedReader::new(Chunked {
+                data: &pgn,
+                chunk_size: 100,
+            });
+            let mut counts = Counts {
+                skip,
+                ..Counts::default()
+            };
+            let expected = if skip {
+                (0, 0, 0, 0)
+            } else {
+                (10_000, 2000, 2000, 2000)
+            };
+            assert_eq!(reader.read_game(&mut counts)?, Some(expected));
+            assert!(!reader.has_more()?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_movetext() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Recorder {
+            events: Vec<String>,
+        }
+
+        impl Visitor for Recorder {
+            type Result = Vec<String>;
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.events.push(san_plus.to_string());
+            }
+
+            fn nag(&mut self, nag: Nag) {
+                self.events.push(nag.to_string());
+            }
+
+            fn comment(&mut self, comment: RawComment<'_>) {
+                self.events
+                    .push(String::from_utf8_lossy(comment.as_bytes()).into_owned());
+            }
+
+            fn begin_variation(&mut self) -> Skip {
+                self.events.push("(".to_owned());
+                Skip(false)
+            }
+
+            fn end_variation(&mut self) {
+                self.events.push(")".to_owned());
+            }
+
+            fn outcome(&mut self, outcome: Option<Outcome>) {
+                self.events.push(format!("{:?}", outcome));
+            }
+
+            fn end_game(&mut self) -> Vec<String> {
+                std::mem::take(&mut self.events)
+            }
+        }
+
+        let pgn = b"[Event \"Span\"]\n\n1. e4 e5!? { comment } (1... c5 $14) 2. Nf3 0-1\n";
+        let index = BufferedReader::new_cursor(&pgn[..]).build_index()?;
+        let span = &pgn[index[0].start as usize..index[0].end as usize];
+        let movetext = &span[span.windows(2).position(|w| w == b"\n\n").unwrap() + 2..];
+
+        let mut direct = Recorder::default();
+        let expected = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut direct)?
+            .unwrap();
+
+        let mut reparsed = Recorder::default();
+        parse_movetext(movetext, &mut reparsed)?;
+        assert_eq!(reparsed.end_game(), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_pawn_and_piece_moves() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Sans(Vec<SanPlus>);
+
+        impl Visitor for Sans {
+            type Result = ();
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0.push(san_plus);
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        fn normal(
+            role: Role,
+            file: Option<File>,
+            capture: bool,
+            to: Square,
+            promotion: Option<Role>,
+        ) -> San {
+            San::Normal {
+                role,
+                file,
+                rank: None,
+                capture,
+                to,
+                promotion,
+            }
+        }
+
+        // A lowercase file letter starts a pawn move, an uppercase letter a
+        // piece move, so bxc3 is a pawn capture and Bxc3 a bishop capture.
+        let mut sans = Sans::default();
+        parse_movetext(b"e4 exd5 e8=Q Be4 bxc3 Bxc3 exd8=Q+ b8=B", &mut sans)?;
+        assert_eq!(
+            sans.0.iter().map(|s| s.san.clone()).collect::<Vec<_>>(),
+            [
+                normal(Role::Pawn, None, false, Square::E4, None),
+                normal(Role::Pawn, Some(File::E), true, Square::D5, None),
+                normal(Role::Pawn, None, false, Square::E8, Some(Role::Queen)),
+                normal(Role::Bishop, None, false, Square::E4, None),
+                normal(Role::Pawn, Some(File::B), true, Square::C3, None),
+                normal(Role::Bishop, None, true, Square::C3, None),
+                normal(
+                    Role::Pawn,
+                    Some(File::E),
+                    true,
+                    Square::D8,
+                    Some(Role::Queen)
+                ),
+                normal(Role::Pawn, None, false, Square::B8, Some(Role::Bishop)),
+            ]
+        );
+        assert_eq!(sans.0[6].suffix, Some(Suffix::Check));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_san_policy() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Tokens(Vec<String>);
+
+        impl Visitor for Tokens {
+            type Result = Vec<String>;
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0.push(san_plus.to_string());
+            }
+
+            fn unknown_token(&mut self, token: &[u8]) {
+                self.0.push(format!("?{}", String::from_utf8_lossy(token)));
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        let pgn = b"1. e4 Xe5 2. Nf9 { Nf9 } Nc6 *\n\n1. d4 *";
+        let read = |policy| {
+            let mut reader = BufferedReader::new_cursor(&pgn[..]);
+            reader.set_invalid_san_policy(policy);
+            let mut tokens = Tokens::default();
+            let results = (0..2)
+                .map(|_| reader.read_game(&mut tokens))
+                .collect::<Vec<_>>();
+            (results, tokens.0)
+        };
+
+        let (results, _) = read(InvalidSanPolicy::Skip);
+        assert_eq!(
+            results[0].as_ref().unwrap(),
+            &Some(vec!["e4".to_owned(), "Nc6".to_owned()])
+        );
+
+        let (results, _) = read(InvalidSanPolicy::Report);
+        assert_eq!(
+            results[0].as_ref().unwrap().as_deref(),
+            Some(
+                &[
+                    "e4".to_owned(),
+                    "?Xe5".to_owned(),
+                    "?Nf9".to_owned(),
+                    "Nc6".to_owned()
+                ][..]
+            )
+        );
+        assert_eq!(results[1].as_ref().unwrap(), &Some(vec!["d4".to_owned()]));
+
+        let (results, leftover) = read(InvalidSanPolicy::Strict);
+        let err = results[0].as_ref().unwrap_err();
+        let span = PgnError::from_io(err).and_then(PgnError::span).unwrap();
+        assert_eq!(span.offset, 6);
+        assert_eq!(span.snippet, b"1. e4 Xe5 2. Nf9");
+        assert_eq!(span.before, 6);
+        assert_eq!(leftover, ["e4"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_context() {
+        struct Nothing;
+
+        impl Visitor for Nothing {
+            type Result = ();
+
+            fn end_game(&mut self) {}
+        }
+
+        let pgn = b"1. e4 e5 *\n\n1. d4 d5 2. c4 Xc6 3. Nc3 *";
+        let expected = Span {
+            offset: 27,
+            line: 3,
+            snippet: b"5 2. c4 Xc6 3. N".to_vec(),
+            before: 8,
+        };
+        for capacity in [1, 16, 8192] {
+            let mut reader = BufferedReader::with_capacity(&pgn[..], capacity);
+            reader.set_invalid_san_policy(InvalidSanPolicy::Strict);
+            let err = reader.read_all(&mut Nothing).unwrap_err();
+            assert_eq!(
+                PgnError::from_io(&err).and_then(PgnError::span),
+                Some(&expected)
+            );
+        }
+
+        let mut reader = SliceReader::new(pgn);
+        reader.set_invalid_san_policy(InvalidSanPolicy::Strict);
+        reader.read_game(&mut Nothing).unwrap();
+        let err = reader.read_game(&mut Nothing).unwrap_err();
+        assert_eq!(
+            PgnError::from_io(&err).and_then(PgnError::span),
+            Some(&expected)
+        );
+
+        // Clamped to the start of the input.
+        let mut reader = SliceReader::new(b"1. Xe4");
+        reader.set_invalid_san_policy(InvalidSanPolicy::Strict);
+        let err = reader.read_game(&mut Nothing).unwrap_err();
+        let span = PgnError::from_io(&err).and_then(PgnError::span).unwrap();
+        assert_eq!((span.snippet.as_slice(), span.before), (&b"1. Xe4"[..], 3));
+    }
+
+    #[test]
+    fn test_san_edge_cases() -> Result<(), io::Error> {
+        let pgn = b"1. exd6e.p. Z0 2. e8=Q+ Kxe8 3. O-O-O# (3. Nbd7 e.p.) 3... e4 \
+                    4. bxa3ep ep 4... fxg6 ep 5. hxg3";
+        let mut sans = Vec::new();
+        struct Collect<'a>(&'a mut Vec<SanPlus>);
+
+        impl Visitor for Collect<'_> {
+            type Result = ();
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0.push(san_plus);
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.set_invalid_san_policy(InvalidSanPolicy::Strict);
+        reader.read_game(&mut Collect(&mut sans))?;
+
+        // Structured fields, with suffixes separate from the move.
+        assert_eq!(
+            sans[0].san,
+            San::Normal {
+                role: Role::Pawn,
+                file: Some(File::E),
+                rank: None,
+                capture: true,
+                to: Square::D6,
+                promotion: None,
+            }
+        );
+        assert_eq!(sans[1].san, San::Null);
+        assert_eq!(sans[2].suffix, Some(Suffix::Check));
+        assert!(matches!(
+            sans[2].san,
+            San::Normal {
+                promotion: Some(Role::Queen),
+                ..
+            }
+        ));
+        assert_eq!(
+            sans[4],
+            SanPlus {
+                san: San::Castle(CastlingSide::QueenSide),
+                suffix: Some(Suffix::Checkmate),
+            }
+        );
+        assert!(matches!(
+            sans[5].san,
+            San::Normal {
+                role: Role::Knight,
+                file: Some(File::B),
+                ..
+            }
+        ));
+
+        // Display round-trips, except for the normalized null move and the
+        // dropped e.p. marker.
+        let tokens = sans.iter().map(ToString::to_string).collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            ["exd6", "--", "e8=Q+", "Kxe8", "O-O-O#", "Nbd7", "e4", "bxa3", "fxg6", "hxg3"]
+        );
+        assert_eq!(parse_san(b"exd6ep").ok(), parse_san(b"exd6").ok());
+        assert!(parse_san(b"Nep").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_outcome() -> Result<(), io::Error> {
+        struct Tokens(Vec<String>);
+
+        impl Visitor for Tokens {
+            type Result = Vec<String>;
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0.push(san_plus.to_string());
+            }
+
+            fn outcome(&mut self, outcome: Option<Outcome>) {
+                self.0.push(match outcome {
+                    Some(outcome) => outcome.to_string(),
+                    None => "*".to_owned(),
+                });
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        let pgn = b"1. e4 e5 2. O-O 0-0 0-1\n\n\
+                    1. d4 1/2-1/2\n\n\
+                    1. f3 e5 2. g4 Qh4#0-1\n\n\
+                    1. Nf31/2-1/2\n\n\
+                    1. c4 {1-0} (1. b3 *) 1-0\n\n\
+                    *";
+        let games = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut Tokens(Vec::new()))
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(
+            games,
+            [
+                &["e4", "e5", "O-O", "O-O", "0-1"][..],
+                &["d4", "1/2-1/2"],
+                &["f3", "e5", "g4", "Qh4#", "0-1"],
+                &["Nf3", "1/2-1/2"],
+                &["c4", "b3", "*", "1-0"],
+                &["*"],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_castling_notation() -> Result<(), io::Error> {
+        struct SanCollector {
+            sans: Vec<SanPlus>,
+        }
+
+        impl Visitor for SanCollector {
+            type Result = ();
+
+            fn san(&mut self, san: SanPlus) {
+                self.sans.push(san);
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let mut collector = SanCollector { sans: Vec::new() };
+        let mut reader =
+            BufferedReader::new_cursor(&b"1. O-O 0-0 2. o-o O-O-O 3. 0-0-0+ o-o-o# *"[..]);
+        reader.read_game(&mut collector)?;
+        let sides = collector
+            .sans
+            .iter()
+            .map(|san_plus| san_plus.san.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sides,
+            vec![
+                San::Castle(CastlingSide::KingSide),
+                San::Castle(CastlingSide::KingSide),
+                San::Castle(CastlingSide::KingSide),
+                San::Castle(CastlingSide::QueenSide),
+                San::Castle(CastlingSide::QueenSide),
+                San::Castle(CastlingSide::QueenSide),
+            ]
+        );
+        assert_eq!(collector.sans[4].suffix, Some(Suffix::Check));
+        assert_eq!(collector.sans[5].suffix, Some(Suffix::Checkmate));
+        Ok(())
+    }
+
+    #[test]
+    fn test_finish() -> Result<(), io::Error> {
+        struct Writer {
+            pending: Vec<usize>,
+            flushed: Vec<usize>,
+            finished: usize,
+        }
+
+        impl Visitor for Writer {
+            type Result = ();
+
+            fn end_game(&mut self) {
+                self.pending.push(self.pending.len() + self.flushed.len());
+            }
+
+            fn finish(&mut self) {
+                self.flushed.append(&mut self.pending);
+                self.finished += 1;
+            }
+        }
+
+        let mut writer = Writer {
+            pending: Vec::new(),
+            flushed: Vec::new(),
+            finished: 0,
+        };
+        let mut reader = BufferedReader::new_cursor(&b"1. e4 *\n\n1. d4 *\n\n1. c4 *"[..]);
+        reader.read_all(&mut writer)?;
+        assert!(writer.pending.is_empty());
+        assert_eq!(writer.flushed, [0, 1, 2]);
+        assert_eq!(writer.finished, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_headers_only() -> Result<(), io::Error> {
+        struct Events {
+            events: Vec<Vec<u8>>,
+        }
+
+        impl Visitor for Events {
+            type Result = ();
+
+            fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+                if key == b"Event" {
+                    self.events.push(value.as_bytes().to_owned());
+                }
+            }
+
+            fn san(&mut self, _san: SanPlus) {
+                panic!("movetext should be skipped");
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let pgn = b"[Event \"1\"]\n\n1. e4 { with a\n\n[Event \"fake\"]\n} e5 ; {\n\
+                    2. Nf3\n%{ escaped\n2... Nc6 *\n\
+                    [Event \"2\"]\n1. d4 d5\r\n\r\n\
+                    [Event \"3\"]\n\n1. c4 *";
+
+        for chunk_size in [1, 2, 3, 5, 8192] {
+            let mut events = Events { events: Vec::new() };
+            let mut reader = BufferedReader::new(Chunked {
+                data: &pgn[..],
+                chunk_size,
+            });
+            let mut fast = Vec::new();
+            while let Some(()) = reader.read_headers_only(&mut events)? {
+                fast.push(reader.offset);
+            }
+            assert_eq!(events.events, [&b"1"[..], b"2", b"3"]);
+
+            let mut reader = BufferedReader::new(Chunked {
+                data: &pgn[..],
+                chunk_size,
+            });
+            let mut slow = Vec::new();
+            while reader.skip_game::<SkipVisitor>()? {
+                slow.push(reader.offset);
+            }
+            assert_eq!(fast, slow);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_nag() -> Result<(), io::Error> {
+        struct NagCollector {
+            nags: Vec<Nag>,
+        }
+
+        impl Visitor for NagCollector {
+            type Result = ();
+
+            fn nag(&mut self, nag: Nag) {
+                self.nags.push(nag);
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let mut collector = NagCollector { nags: Vec::new() };
+        let mut reader = BufferedReader::new(io::Cursor::new(b"1.f3! e5$71 2.g4?? Qh4#!?"));
+        reader.read_game(&mut collector)?;
+        assert_eq!(
+            collector.nags,
+            vec![Nag::GOOD_MOVE, Nag(71), Nag::BLUNDER, Nag::SPECULATIVE_MOVE]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_suffix_glyphs() -> Result<(), io::Error> {
+        struct Tokens(Vec<String>);
+
+        impl Visitor for Tokens {
+            type Result = Vec<String>;
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0.push(san_plus.to_string());
+            }
+
+            fn nag(&mut self, nag: Nag) {
+                self.0.push(nag.to_string());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        let pgn = b"1. e4! e5? 2. Qh5!! Nc6?? 3. Bc4+!? Nf6?! 4. Qxf7#! *";
+        let tokens = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut Tokens(Vec::new()))?
+            .unwrap();
+        assert_eq!(
+            tokens,
+            [
+                "e4", "$1", "e5", "$2", "Qh5", "$3", "Nc6", "$4", "Bc4+", "$5", "Nf6", "$6",
+                "Qxf7#", "$1"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rest_of_line_comments() -> Result<(), io::Error> {
+        struct Tokens(Vec<String>);
+
+        impl Visitor for Tokens {
+            type Result = Vec<String>;
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0.push(san_plus.to_string());
+            }
+
+            fn comment(&mut self, comment: RawComment<'_>) {
+                self.0
+                    .push(String::from_utf8_lossy(comment.as_bytes()).into_owned());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        let pgn = b"1. e4 ; best by test { not a brace comment }\r\ne5 2. Nf3 *;\n[Event \"?\"]\n\n1. d4 ;";
+        let games = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut Tokens(Vec::new()))
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(
+            games,
+            [
+                &[
+                    "e4",
+                    " best by test { not a brace comment }",
+                    "e5",
+                    "Nf3",
+                    ""
+                ][..],
+                &["d4", ""],
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_null_moves() -> Result<(), io::Error> {
+        struct SanCollector {
+            sans: Vec<San>,
+        }
+
+        impl Visitor for SanCollector {
+            type Result = ();
+
+            fn san(&mut self, san: SanPlus) {
+                self.sans.push(san.san);
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let mut collector = SanCollector { sans: Vec::new() };
+        let mut reader = BufferedReader::new(io::Cursor::new(b"1. e4 -- 2. Nf3 -- 3. -- e5"));
+        reader.read_game(&mut collector)?;
+        assert_eq!(collector.sans.len(), 6);
+        assert_ne!(collector.sans[0], San::Null);
+        assert_eq!(collector.sans[1], San::Null);
+        assert_ne!(collector.sans[2], San::Null);
+        assert_eq!(collector.sans[3], San::Null);
+        assert_eq!(collector.sans[4], San::Null);
+        assert_ne!(collector.sans[5], San::Null);
+
+        #[derive(Default)]
+        struct Tokens(Vec<String>);
+
+        impl Visitor for Tokens {
+            type Result = Vec<String>;
+
+            fn san(&mut self, san: SanPlus) {
+                self.0.push(san.to_string());
+            }
+
+            fn null_move(&mut self) {
+                self.0.push("null".to_owned());
+            }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.reader.read_game(self.visitor) {
-            Ok(Some(result)) => Some(Ok(result)),
-            Ok(None) => None,
-            Err(err) => Some(Err(err)),
+            fn end_game(&mut self) -> Self::Result {
+                mem::take(&mut self.0)
+            }
         }
+
+        // Suffixes of null moves are dropped.
+        let pgn = b"1. e4 Z0 2. @@@@ -- (2... Nc6 Z0) 3. --+ *";
+        let tokens = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut Tokens::default())?
+            .unwrap();
+        assert_eq!(
+            tokens,
+            ["e4", "null", "null", "null", "Nc6", "null", "null"]
+        );
+
+        // Games in memory replay null moves the same way.
+        let game = BufferedReader::new_cursor(&pgn[..])
+            .read_game(&mut GameBuilder::new())?
+            .unwrap();
+        assert_eq!(game.mainline.moves[1].san_plus.san, San::Null);
+        assert_eq!(game.visit(&mut Tokens::default())[..4], tokens[..4]);
+        Ok(())
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_escape() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Escapes {
+            lines: Vec<Vec<u8>>,
+        }
 
-    struct _AssertObjectSafe<R>(Box<BufferedReader<R>>);
+        impl Visitor for Escapes {
+            type Result = usize;
 
-    struct GameCounter {
-        count: usize,
+            fn escape(&mut self, line: &[u8]) {
+                self.lines.push(line.to_owned());
+            }
+
+            fn begin_variation(&mut self) -> Skip {
+                Skip(self.lines.len() > 2)
+            }
+
+            fn end_game(&mut self) -> usize {
+                self.lines.len()
+            }
+        }
+
+        let pgn = b"% before the game\n\
+                    [Event \"?\"]\n\
+                    %tool v1 key=value\r\n\
+                    [White \"?\"]\n\
+                    \n\
+                    1. e4 e5\n\
+                    %in movetext\n\
+                    2. Nf3 (2. Nc3\n\
+                    %in variation\n\
+                    2... Nc6) (2. d4\n\
+                    %in skipped variation\n\
+                    ) *\n\
+                    \n\
+                    %after the game";
+
+        let mut visitor = Escapes::default();
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        assert_eq!(reader.read_game(&mut visitor)?, Some(3));
+        assert_eq!(reader.read_game(&mut visitor)?, None);
+        assert_eq!(
+            visitor.lines,
+            [&b"tool v1 key=value"[..], b"in movetext", b"in variation"]
+        );
+        Ok(())
     }
 
-    impl Default for GameCounter {
-        fn default() -> GameCounter {
-            GameCounter { count: 0 }
+    #[test]
+    fn test_escape_only_at_line_start() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Escapes {
+            lines: Vec<Vec<u8>>,
+            sans: Vec<String>,
+        }
+
+        impl Visitor for Escapes {
+            type Result = (Vec<Vec<u8>>, Vec<String>);
+
+            fn escape(&mut self, line: &[u8]) {
+                self.lines.push(line.to_owned());
+            }
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.sans.push(san_plus.to_string());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                (mem::take(&mut self.lines), mem::take(&mut self.sans))
+            }
+        }
+
+        let pgn = b"\xef\xbb\xbf% first\n[Event \"50% off\"]\n%second\n\n\
+                    1. e4 % not escaped\n%third\ne5 { 100% } 2. Nf3 *\n\n  \
+                    % not escaped either\n1. d4 *\n\n\
+                    \xef\xbb\xbf%fourth\n1. c4 *";
+
+        let expected = [
+            (
+                vec![b"second".to_vec(), b"third".to_vec()],
+                vec!["e4", "e5", "Nf3"],
+            ),
+            (vec![], vec!["d4"]),
+            (vec![], vec!["c4"]),
+        ];
+        let buffered = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut Escapes::default())
+            .collect::<io::Result<Vec<_>>>()?;
+        let sliced = SliceReader::new(pgn).collect_games(Escapes::default)?;
+        for games in [buffered, sliced] {
+            assert_eq!(games.len(), expected.len());
+            for ((lines, sans), (expected_lines, expected_sans)) in games.iter().zip(&expected) {
+                assert_eq!(lines, expected_lines);
+                assert_eq!(sans, expected_sans);
+            }
         }
+        assert_eq!(BufferedReader::new_cursor(&pgn[..]).build_index()?.len(), 3);
+        assert_eq!(SliceReader::new(pgn).build_index()?.len(), 3);
+        Ok(())
     }
 
-    impl Visitor for GameCounter {
-        type Result = ();
+    #[test]
+    fn test_recover() -> Result<(), io::Error> {
+        struct Recover {
+            sans: usize,
+            error: Option<PgnError>,
+        }
 
-        fn end_game(&mut self) {
-            self.count += 1;
+        impl Visitor for Recover {
+            type Result = (usize, Option<PgnError>);
+
+            fn begin_game(&mut self) {
+                self.sans = 0;
+                self.error = None;
+            }
+
+            fn san(&mut self, _san_plus: SanPlus) {
+                self.sans += 1;
+            }
+
+            fn recovered(&mut self, error: &PgnError) {
+                self.error = Some(error.clone());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                (self.sans, self.error.take())
+            }
         }
+
+        let pgn = b"[Event \"1\"]\n\n1. e4 e5 *\n\n\
+                    [Event \"2\"]\n\n1. d4 { unterminated d5 *\r\n\r\n\
+                    [Event \"3\"]\n\n1. c4 { closed } c5 *\n\n\
+                    [Event \"4\"]\n\n1. Nf3 Xd5 2. g3\n3. Bg2 *\n\n\
+                    [Event \"5\"]\n\n1. b3 *\n";
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.set_recover(true);
+        reader.set_invalid_san_policy(InvalidSanPolicy::Strict);
+        let mut visitor = Recover {
+            sans: 0,
+            error: None,
+        };
+        let games = reader
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+
+        assert_eq!(games.len(), 5);
+        assert_eq!(
+            games.iter().map(|(sans, _)| *sans).collect::<Vec<_>>(),
+            [2, 1, 2, 1, 1]
+        );
+        let recovered = games
+            .iter()
+            .enumerate()
+            .filter_map(|(index, (_, error))| error.as_ref().map(|_| index))
+            .collect::<Vec<_>>();
+        assert_eq!(recovered, [1, 3]);
+        assert!(matches!(games[1].1, Some(PgnError::UnterminatedComment(_))));
+        assert!(matches!(games[3].1, Some(PgnError::InvalidSan(_))));
+
+        // Without recovery, the comment swallows the next game.
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.read_game(&mut visitor)?;
+        let (sans, _) = reader.read_game(&mut visitor)?.unwrap();
+        assert_eq!(sans, 2);
+        Ok(())
     }
 
     #[test]
-    fn test_empty_game() -> Result<(), io::Error> {
-        let mut counter = GameCounter::default();
-        let mut reader = BufferedReader::new(io::Cursor::new(b"  "));
-        reader.read_game(&mut counter)?;
-        assert_eq!(counter.count, 0);
+    fn test_count_games() -> Result<(), io::Error> {
+        let inputs: [&[u8]; 7] = [
+            b"",
+            b"\xef\xbb\xbf  \n",
+            b"1. e4 *",
+            b"[Event \"?\"]\n[Site \"]\"]\n\n1. e4 { \n\n[ } e5 *\n\n[Event \"?\"]\n*",
+            b"1. e4 ; comment\n\n1. d4 (1. c4 {\n\n}) *\r\n\r\n1. Nf3 *\n% escape\n",
+            b"[Event \"1\"]\n[Event \"2\"]\n\n[Event \"3\"]\n1. e4 e5\n\n2. Nf3\n{ trailing }",
+            b"1. b3 *\n\n\n\n[Event \"?\"]\n\n\n1. g3 *\n\n{ checksum }\n",
+        ];
+        for pgn in inputs {
+            for chunk_size in [1, 3, 64, 1 << 16] {
+                let expected = BufferedReader::new(Chunked {
+                    data: pgn,
+                    chunk_size,
+                })
+                .games(&mut GameCounter::default())
+                .count();
+                let mut reader = BufferedReader::new(Chunked {
+                    data: pgn,
+                    chunk_size,
+                });
+                assert_eq!(reader.count_games()?, expected, "{:?}", pgn.escape_ascii());
+                assert_eq!(SliceReader::new(pgn).count_games()?, expected);
+            }
+        }
         Ok(())
     }
 
     #[test]
-    fn test_trailing_space() -> Result<(), io::Error> {
-        let mut counter = GameCounter::default();
-        let mut reader = BufferedReader::new(io::Cursor::new(b"1. e4 1-0\n\n\n\n\n  \n"));
-        reader.read_game(&mut counter)?;
-        assert_eq!(counter.count, 1);
-        reader.read_game(&mut counter)?;
-        assert_eq!(counter.count, 1);
+    fn test_position() -> Result<(), io::Error> {
+        let pgn = b"[Event \"?\"]\n[Site \"]\"]\n\n1. e4 { \n\n[ } e5 *\n\n\
+                    1. e4 ; comment\n\n1. d4 (1. c4 {\n\n}) *\r\n\r\n% escape\n1. Nf3 *\n\n\
+                    [Event \"?\"]\n\n1. e4 {\n} *";
+        let line = |offset: u64| {
+            1 + pgn[..offset as usize]
+                .iter()
+                .filter(|&&ch| ch == b'\n')
+                .count() as u64
+        };
+        for chunk_size in [1, 3, 64, 1 << 16] {
+            for skip in [false, true] {
+                let mut reader = BufferedReader::new(Chunked {
+                    data: pgn,
+                    chunk_size,
+                });
+                let mut slice = SliceReader::new(pgn);
+                loop {
+                    let position = reader.position();
+                    assert_eq!(position.line, line(position.byte));
+                    assert_eq!(slice.position(), position);
+                    let more = if skip {
+                        slice.skip_game()?;
+                        reader.skip_game::<SkipVisitor>()?
+                    } else {
+                        slice.read_game(&mut GameCounter::default())?;
+                        reader.read_game(&mut GameCounter::default())?.is_some()
+                    };
+                    if !more {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let index = reader.build_index()?;
+        assert_eq!(
+            index.iter().map(|game| game.line).collect::<Vec<_>>(),
+            [1, 8, 10, 15, 17]
+        );
+        reader.seek_game(index[4])?;
+        assert_eq!(
+            reader.position(),
+            Position {
+                line: 17,
+                byte: index[4].start
+            }
+        );
+
+        let err = BufferedReader::new_cursor(&b"1. e4\n\n\n{ unterminated"[..])
+            .read_all(&mut GameCounter::default())
+            .unwrap_err();
+        let span = PgnError::from_io(&err).and_then(PgnError::span).unwrap();
+        assert_eq!((span.line, span.offset), (4, 9));
         Ok(())
     }
 
     #[test]
-    fn test_nag() -> Result<(), io::Error> {
-        struct NagCollector {
-            nags: Vec<Nag>,
+    fn test_move_number() -> Result<(), io::Error> {
+        struct Numbers(Vec<(u32, bool)>);
+
+        impl Visitor for Numbers {
+            type Result = Vec<(u32, bool)>;
+
+            fn move_number(&mut self, number: u32, black_to_move: bool) {
+                self.0.push((number, black_to_move));
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.0)
+            }
         }
 
-        impl Visitor for NagCollector {
-            type Result = ();
+        let pgn = b"1. e4 { 2. } e5 2.Nf3 (2. d4 2... exd4) 2... Nc6 3. ... a6 \
+                    12...b5 99999999999. 13 Nf3 1/2-1/2";
+        assert_eq!(
+            BufferedReader::new_cursor(&pgn[..]).read_game(&mut Numbers(Vec::new()))?,
+            Some(vec![
+                (1, false),
+                (2, false),
+                (2, false),
+                (2, true),
+                (2, true),
+                (3, true),
+                (12, true),
+            ])
+        );
+        Ok(())
+    }
 
-            fn nag(&mut self, nag: Nag) {
-                self.nags.push(nag);
+    #[test]
+    fn test_max_variation_depth() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Depth {
+            depth: usize,
+            max: usize,
+            warnings: Vec<u64>,
+            sans: usize,
+        }
+
+        impl Visitor for Depth {
+            type Result = (usize, Vec<u64>, usize);
+
+            fn begin_game(&mut self) {
+                *self = Depth::default();
             }
 
-            fn end_game(&mut self) {}
+            fn san(&mut self, _san_plus: SanPlus) {
+                self.sans += 1;
+            }
+
+            fn begin_variation(&mut self) -> Skip {
+                self.depth += 1;
+                self.max = self.max.max(self.depth);
+                Skip(false)
+            }
+
+            fn end_variation(&mut self) {
+                self.depth -= 1;
+            }
+
+            fn warning(&mut self, error: &PgnError) {
+                assert!(matches!(error, PgnError::VariationTooDeep(_)));
+                self.warnings.push(error.span().unwrap().offset);
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                assert_eq!(self.depth, 0);
+                (self.max, std::mem::take(&mut self.warnings), self.sans)
+            }
         }
 
-        let mut collector = NagCollector { nags: Vec::new() };
-        let mut reader = BufferedReader::new(io::Cursor::new(b"1.f3! e5$71 2.g4?? Qh4#!?"));
-        reader.read_game(&mut collector)?;
+        let mut pgn = b"1. e4 ".to_vec();
+        pgn.extend(b"(1. d4 ".repeat(100_000));
+        pgn.extend(b")".repeat(100_000));
+        pgn.extend(b" 1... e5 *\n\n1. d4 *");
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let (max, warnings, sans) = reader.read_game(&mut Depth::default())?.unwrap();
+        assert_eq!(max, 256);
+        assert_eq!(warnings, [6 + 256 * 7]);
+        assert_eq!(sans, 2 + 256);
+        let (max, warnings, sans) = reader.read_game(&mut Depth::default())?.unwrap();
+        assert_eq!((max, warnings.len(), sans), (0, 0, 1));
+
+        let mut reader = SliceReader::new(b"1. e4 (1. d4 (1. c4)) (1. b3 (1. g3)) *");
+        reader.set_max_variation_depth(1);
+        let (max, warnings, sans) = reader.read_game(&mut Depth::default())?.unwrap();
+        assert_eq!((max, warnings, sans), (1, vec![13, 29], 3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_line_endings() {
         assert_eq!(
-            collector.nags,
-            vec![Nag::GOOD_MOVE, Nag(71), Nag::BLUNDER, Nag::SPECULATIVE_MOVE]
+            normalize_line_endings(b"a\r\nb\rc\r\r\n"),
+            &b"a\nb\nc\n\n"[..]
         );
+        assert!(matches!(normalize_line_endings(b"a\nb"), Cow::Borrowed(_)));
+        assert_eq!(normalize_line_endings(b"\r"), &b"\n"[..]);
+    }
+
+    #[test]
+    fn test_lenient_comments() -> Result<(), io::Error> {
+        assert_eq!(find_comment_end(b" [%a }] }", false, false), Some(5));
+        assert_eq!(find_comment_end(b" [%a }] }", true, false), Some(8));
+        assert_eq!(find_comment_end(b" [%a }\n] }", true, false), Some(5));
+        assert_eq!(find_comment_end(b" [a }] }", true, false), Some(4));
+        assert_eq!(find_comment_end(b" [%a }", true, false), None);
+        assert_eq!(find_comment_end(b" [%a }", true, true), Some(5));
+        assert_eq!(find_comment_end(b" [", true, false), None);
+
+        struct Comments(Vec<Vec<u8>>);
+
+        impl Visitor for Comments {
+            type Result = Vec<Vec<u8>>;
+
+            fn comment(&mut self, comment: RawComment<'_>) {
+                self.0.push(comment.as_bytes().to_vec());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.0)
+            }
+        }
+
+        // Seen in the wild: smileys in the text of a command.
+        let mut pgn = b"1. e4 { [%evp 0,19,22] [%c_effect e4;type;Book}] } e5 ".to_vec();
+        pgn.extend(b" ".repeat(40));
+        pgn.extend(b"{ [%cal Gd2d4] and a } smiley } 2. Nf3 { [%broken } *\n");
+        for lenient in [false, true] {
+            let mut reader = BufferedReader::with_capacity(
+                Chunked {
+                    data: &pgn,
+                    chunk_size: 5,
+                },
+                64,
+            );
+            reader.set_lenient_comments(lenient);
+            let comments = reader.read_game(&mut Comments(Vec::new()))?.unwrap();
+            let expected: &[&[u8]] = if lenient {
+                &[
+                    b" [%evp 0,19,22] [%c_effect e4;type;Book}] ",
+                    b" [%cal Gd2d4] and a ",
+                    b" [%broken ",
+                ]
+            } else {
+                &[
+                    b" [%evp 0,19,22] [%c_effect e4;type;Book",
+                    b" [%cal Gd2d4] and a ",
+                    b" [%broken ",
+                ]
+            };
+            assert_eq!(comments, expected);
+        }
         Ok(())
     }
 
     #[test]
-    fn test_null_moves() -> Result<(), io::Error> {
-        struct SanCollector {
-            sans: Vec<San>,
+    fn test_peek_headers() -> Result<(), io::Error> {
+        let long = "x".repeat(300);
+        let pgn = format!(
+            "\u{feff}[Event \"{long}\"]\n[Site \"{long}\"]\n[Round \"1\"]\n\n1. e4 *\n\n1. d4 *\n\n[Round \"3"
+        );
+        let round = |headers: &HeaderList| {
+            headers
+                .iter()
+                .find(|(key, _)| key == b"Round")
+                .map(|(_, value)| value.clone())
+        };
+
+        let mut reader = BufferedReader::with_capacity(pgn.as_bytes(), 64);
+        let headers = reader.peek_headers()?.unwrap();
+        assert_eq!(headers.len(), 3);
+        assert_eq!(round(&headers), Some(b"1".to_vec()));
+        assert_eq!(reader.peek_headers()?, Some(headers));
+        let (_, moves) = reader.read_game(&mut GameSummary::new())?.unwrap();
+        assert_eq!(moves[0].to_string(), "e4");
+        assert_eq!(reader.peek_headers()?, Some(Vec::new()));
+        reader.read_game(&mut GameSummary::new())?;
+        assert_eq!(round(&reader.peek_headers()?.unwrap()), Some(b"3".to_vec()));
+        reader.read_game(&mut GameSummary::new())?;
+        assert_eq!(reader.peek_headers()?, None);
+
+        let mut reader = SliceReader::new(pgn.as_bytes());
+        assert_eq!(reader.peek_headers()?.unwrap().len(), 3);
+        assert_eq!(reader.count_games()?, 3);
+        assert_eq!(reader.peek_headers()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_movetext() -> Result<(), io::Error> {
+        struct Raw {
+            skip: bool,
+            raw: Option<Vec<u8>>,
         }
 
-        impl Visitor for SanCollector {
-            type Result = ();
+        impl Visitor for Raw {
+            type Result = Option<Vec<u8>>;
 
-            fn san(&mut self, san: SanPlus) {
-                self.sans.push(san.san);
+            fn begin_game(&mut self) {
+                self.raw = None;
             }
 
-            fn end_game(&mut self) {}
+            fn end_headers(&mut self) -> Skip {
+                Skip(self.skip)
+            }
+
+            fn raw_movetext(&mut self, movetext: &[u8]) {
+                self.raw = Some(movetext.to_vec());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                self.raw.take()
+            }
         }
 
-        let mut collector = SanCollector { sans: Vec::new() };
-        let mut reader = BufferedReader::new(io::Cursor::new(b"1. e4 -- 2. Nf3 -- 3. -- e5"));
-        reader.read_game(&mut collector)?;
-        assert_eq!(collector.sans.len(), 6);
-        assert_ne!(collector.sans[0], San::Null);
-        assert_eq!(collector.sans[1], San::Null);
-        assert_ne!(collector.sans[2], San::Null);
-        assert_eq!(collector.sans[3], San::Null);
-        assert_eq!(collector.sans[4], San::Null);
-        assert_ne!(collector.sans[5], San::Null);
+        let long = b"1. e4 { long comment } e5 ".repeat(100);
+        let mut pgn = b"[Event \"A\"]\n\n".to_vec();
+        pgn.extend(&long);
+        pgn.extend(
+            b"1-0\r\n\r\n[Event \"B\"]\n\n1.d4  (1.c4)\n;rest\n* \n\n1. e4 { open\n\n[Event \"C\"]\n\n1. d4 *",
+        );
+
+        let mut expected = long.clone();
+        expected.extend(b"1-0");
+        let expected: [&[u8]; 2] = [&expected, b"1.d4  (1.c4)\n;rest\n*"];
+
+        for skip in [false, true] {
+            let mut visitor = Raw { skip, raw: None };
+
+            let mut reader = BufferedReader::with_capacity(&pgn[..], 64);
+            reader.set_raw_movetext(true);
+            for raw in expected {
+                assert_eq!(reader.read_game(&mut visitor)?, Some(Some(raw.to_vec())));
+            }
+
+            let mut reader = SliceReader::new(&pgn);
+            reader.set_raw_movetext(true);
+            for raw in expected {
+                assert_eq!(reader.read_game(&mut visitor)?, Some(Some(raw.to_vec())));
+            }
+        }
+
+        // Nothing is recorded for a game that fails, or by default.
+        let mut visitor = Raw {
+            skip: false,
+            raw: None,
+        };
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.set_raw_movetext(true);
+        reader.set_recover(true);
+        reader.read_game(&mut visitor)?;
+        reader.read_game(&mut visitor)?;
+        assert_eq!(reader.read_game(&mut visitor)?, Some(None));
+        assert_eq!(
+            reader.read_game(&mut visitor)?,
+            Some(Some(b"1. d4 *".to_vec()))
+        );
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        assert_eq!(reader.read_game(&mut visitor)?, Some(None));
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_builder() -> Result<(), io::Error> {
+        let pgn = b"[Event \"A\"]\n\n1. e4 { c } (1. d4) Xd5 *\n\n";
+        let builder = ReaderBuilder::new()
+            .capacity(16)
+            .invalid_san_policy(InvalidSanPolicy::Strict)
+            .recover(true)
+            .skip_comments(true)
+            .raw_headers(true)
+            .raw_movetext(true);
+
+        let mut reader = builder.clone().build(&pgn[..]);
+        assert_eq!(reader.capacity, MIN_CAPACITY);
+        let game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+        assert_eq!(game.raw_headers.as_deref(), Some(&b"[Event \"A\"]"[..]));
+        assert_eq!(game.raw_movetext, None);
+        assert_eq!(game.mainline.moves.len(), 1);
+        assert!(game.mainline.moves[0].comments.is_empty());
+        assert_eq!(game.mainline.moves[0].variations.len(), 1);
+        assert!(reader.read_game(&mut GameBuilder::new())?.is_none());
+
+        let mut reader = builder.skip_variations(true).build_slice(pgn);
+        let err = reader.read_game(&mut GameBuilder::new()).unwrap_err();
+        assert!(matches!(
+            PgnError::from_io(&err),
+            Some(PgnError::InvalidSan(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_raw_headers() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Raw {
+            headers: Option<Vec<u8>>,
+            movetext: Option<Vec<u8>>,
+        }
+
+        impl Visitor for Raw {
+            type Result = (Option<Vec<u8>>, Option<Vec<u8>>);
+
+            fn raw_headers(&mut self, headers: &[u8]) {
+                self.headers = Some(headers.to_vec());
+            }
+
+            fn raw_movetext(&mut self, movetext: &[u8]) {
+                self.movetext = Some(movetext.to_vec());
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                (self.headers.take(), self.movetext.take())
+            }
+        }
+
+        let long = b"[Annotator \"x\"]  \n".repeat(20);
+        let mut headers = b"[Event  \"A\"] [Site \"?\"]\r\n".to_vec();
+        headers.extend(&long);
+        headers.extend(b"[Round \"1\"]");
+        let mut pgn = b"\n\n".to_vec();
+        pgn.extend(&headers);
+        pgn.extend(b"\n\n1. e4 1-0\n\n1. d4 *\n\n[Event \"C\"]\n\n*");
+        let expected: [&[u8]; 3] = [&headers, b"", b"[Event \"C\"]"];
+
+        let mut reader = BufferedReader::with_capacity(&pgn[..], 64);
+        reader.set_raw_headers(true);
+        for raw in expected {
+            assert_eq!(
+                reader.read_game(&mut Raw::default())?,
+                Some((Some(raw.to_vec()), None))
+            );
+        }
+
+        let mut reader = SliceReader::new(&pgn);
+        reader.set_raw_headers(true);
+        reader.set_raw_movetext(true);
+        assert_eq!(
+            reader.read_game(&mut Raw::default())?,
+            Some((Some(headers.clone()), Some(b"1. e4 1-0".to_vec())))
+        );
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.set_raw_headers(true);
+        for raw in expected {
+            assert_eq!(
+                reader.read_headers_only(&mut Raw::default())?,
+                Some((Some(raw.to_vec()), None))
+            );
+        }
+
+        // Games keep the block, and pass it on when visited.
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        reader.set_raw_headers(true);
+        let game = reader.read_game(&mut GameBuilder::new())?.unwrap();
+        assert_eq!(game.raw_headers.as_deref(), Some(&headers[..]));
+        assert_eq!(game.visit(&mut Raw::default()).0, Some(headers.clone()));
+
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        assert_eq!(reader.read_game(&mut Raw::default())?, Some((None, None)));
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn test_from_path() -> Result<(), io::Error> {
+        use std::{fs, io::Write};
+
+        let pgn = b"[Event \"?\"]\n\n1. e4 e5 *\n\n1. d4 *\n";
+        let dir = std::env::temp_dir().join(format!("pgn-reader-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(pgn)?;
+        let files = [
+            ("games.pgn", pgn.to_vec()),
+            ("games.pgn.zst", zstd::encode_all(&pgn[..], 0)?),
+            ("games.pgn.gz", gz.finish()?),
+        ];
+        for (name, data) in files {
+            let path = dir.join(name);
+            fs::write(&path, data)?;
+            let mut reader = BufferedReader::from_path(&path)?;
+            assert_eq!(reader.count_games()?, 2, "{}", name);
+        }
+
+        fs::remove_dir_all(&dir)
+    }
+
+    #[test]
+    fn test_setup() -> Result<(), io::Error> {
+        #[derive(Default)]
+        struct Setup {
+            events: Vec<Vec<u8>>,
+        }
+
+        impl Visitor for Setup {
+            type Result = Vec<Vec<u8>>;
+
+            fn header(&mut self, key: &[u8], _value: RawHeader<'_>) {
+                self.events.push(key.to_vec());
+            }
+
+            fn setup(&mut self, fen: &[u8]) {
+                self.events.push(fen.to_vec());
+            }
+
+            fn end_headers(&mut self) -> Skip {
+                self.events.push(b"end_headers".to_vec());
+                Skip(false)
+            }
+
+            fn end_game(&mut self) -> Self::Result {
+                std::mem::take(&mut self.events)
+            }
+        }
+
+        let pgn = b"[FEN \"8/8/8/8/8/8/8/K1k5 b - - 0 40\"]\n[Event \"?\"]\n\n1... Kc2 *\n\n\
+                    [Event \"?\"]\n\n1. e4 *\n\n\
+                    [SetUp \"1\"]\n[FEN \"?\"]\n[FEN \"\\\"quoted\\\"\"]\n\n*";
+        let mut reader = BufferedReader::new_cursor(&pgn[..]);
+        let mut visitor = Setup::default();
+        assert_eq!(
+            reader.read_game(&mut visitor)?.unwrap(),
+            [
+                &b"FEN"[..],
+                b"Event",
+                b"8/8/8/8/8/8/8/K1k5 b - - 0 40",
+                b"end_headers"
+            ]
+        );
+        assert_eq!(
+            reader.read_game(&mut visitor)?.unwrap(),
+            [&b"Event"[..], b"end_headers"]
+        );
+        assert_eq!(
+            reader.read_headers_only(&mut visitor)?.unwrap(),
+            [&b"SetUp"[..], b"FEN", b"FEN", b"\"quoted\"", b"end_headers"]
+        );
         Ok(())
     }
 }
@@ -12,6 +12,8 @@ pub struct Skip(pub bool);
 
 /// A numeric annotation glyph like `?`, `!!` or `$42`.
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Nag(pub u8);
 
 impl Nag {
@@ -73,8 +75,99 @@ impl Nag {
 
     /// A dubious move (`?!`).
     pub const DUBIOUS_MOVE: Nag = Nag(6);
+
+    /// The conventional symbol of the annotation, like `!!` for `$3`, `±`
+    /// for `$16` or `⟳` for `$32`, or `None` for annotations that are
+    /// usually only written as text.
+    ///
+    /// Annotations for White and Black that only differ in the side, like
+    /// `$22` and `$23` for zugzwang, share a symbol.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::Nag;
+    ///
+    /// assert_eq!(Nag::BRILLIANT_MOVE.symbol(), Some("!!"));
+    /// assert_eq!(Nag(14).symbol(), Some("⩲"));
+    /// assert_eq!(Nag(23).symbol(), Some("⨀"));
+    /// assert_eq!(Nag(9).symbol(), None);
+    /// ```
+    pub fn symbol(&self) -> Option<&'static str> {
+        NAG_SYMBOLS
+            .iter()
+            .find(|(nag, _)| *nag == self.0)
+            .map(|(_, symbol)| *symbol)
+    }
+
+    /// Finds the annotation for a symbol, like `!!` or `±`. Common ASCII
+    /// spellings, like `+/-` for `±`, are also recognized. For a symbol
+    /// that is shared by White and Black, the annotation for White is
+    /// returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::Nag;
+    ///
+    /// assert_eq!(Nag::from_symbol("?!"), Some(Nag::DUBIOUS_MOVE));
+    /// assert_eq!(Nag::from_symbol("∓"), Some(Nag(17)));
+    /// assert_eq!(Nag::from_symbol("-/+"), Some(Nag(17)));
+    /// assert_eq!(Nag::from_symbol("$17"), None);
+    /// ```
+    pub fn from_symbol(symbol: &str) -> Option<Nag> {
+        let symbol = match symbol {
+            "+=" | "+/=" => "⩲",
+            "=+" | "=/+" => "⩱",
+            "+/-" => "±",
+            "-/+" => "∓",
+            "+-" | "+--" => "+−",
+            "-+" | "--+" => "−+",
+            "=/∞" | "=/oo" => "=∞",
+            "oo" => "∞",
+            other => other,
+        };
+        NAG_SYMBOLS
+            .iter()
+            .find(|(_, candidate)| *candidate == symbol)
+            .map(|(nag, _)| Nag(*nag))
+    }
 }
 
+/// Symbols of numeric annotation glyphs, with the annotation for White
+/// before the one for Black if they share a symbol.
+const NAG_SYMBOLS: [(u8, &str); 29] = [
+    (1, "!"),
+    (2, "?"),
+    (3, "!!"),
+    (4, "??"),
+    (5, "!?"),
+    (6, "?!"),
+    (7, "□"),
+    (10, "="),
+    (13, "∞"),
+    (14, "⩲"),
+    (15, "⩱"),
+    (16, "±"),
+    (17, "∓"),
+    (18, "+−"),
+    (19, "−+"),
+    (22, "⨀"),
+    (23, "⨀"),
+    (32, "⟳"),
+    (33, "⟳"),
+    (36, "↑"),
+    (37, "↑"),
+    (40, "→"),
+    (41, "→"),
+    (44, "=∞"),
+    (45, "=∞"),
+    (132, "⇆"),
+    (133, "⇆"),
+    (138, "⊕"),
+    (139, "⊕"),
+];
+
 impl fmt::Display for Nag {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "${}", self.0)
@@ -141,14 +234,17 @@ impl<'a> RawHeader<'a> {
     /// the value actually contains escape sequences.
     pub fn decode(&self) -> Cow<'a, [u8]> {
         let mut head = 0;
+        let mut search = 0;
         let mut decoded: Vec<u8> = Vec::new();
-        for escape in memchr::memchr_iter(b'\\', self.0) {
+        while let Some(escape) = memchr::memchr(b'\\', &self.0[search..]).map(|i| search + i) {
             match self.0.get(escape + 1).cloned() {
                 Some(ch) if ch == b'\\' || ch == b'"' => {
                     decoded.extend_from_slice(&self.0[head..escape]);
                     head = escape + 1;
+                    // The escaped character is taken literally.
+                    search = escape + 2;
                 }
-                _ => (),
+                _ => search = escape + 1,
             }
         }
         if head == 0 {
@@ -214,6 +310,26 @@ mod tests {
         assert_eq!(Nag::from_ascii(b"$33"), Ok(Nag(33)));
     }
 
+    #[test]
+    fn test_nag_symbol() {
+        for nag in 0..=255 {
+            let nag = Nag(nag);
+            if let Some(symbol) = nag.symbol() {
+                let parsed = Nag::from_symbol(symbol).unwrap();
+                assert_eq!(parsed.symbol(), Some(symbol));
+                assert!(parsed == nag || parsed.0 + 1 == nag.0);
+            }
+        }
+        assert_eq!(Nag(36).symbol(), Some("↑"));
+        assert_eq!(Nag(37).symbol(), Some("↑"));
+        assert_eq!(Nag(40).symbol(), Some("→"));
+        assert_eq!(Nag(41).symbol(), Some("→"));
+        assert_eq!(Nag(139).symbol(), Some("⊕"));
+        assert_eq!(Nag(140).symbol(), None);
+        assert_eq!(Nag::from_symbol("+-"), Some(Nag(18)));
+        assert_eq!(Nag::from_symbol("!!!"), None);
+    }
+
     #[test]
     fn test_raw_header() {
         let header = RawHeader(b"Hello world");
@@ -224,5 +340,23 @@ mod tests {
 
         let header = RawHeader(b"\\Hello \\\"world\\\\");
         assert_eq!(header.decode().as_ref(), b"\\Hello \"world\\");
+
+        // Decoding escapes allocates.
+        let header = RawHeader(b"O\\\"Brien");
+        assert!(matches!(header.decode_utf8(), Ok(Cow::Owned(name)) if name == "O\"Brien"));
+
+        // Consecutive escapes.
+        let header = RawHeader(b"a\\\\\\\\b\\\\\\\"");
+        assert!(matches!(header.decode(), Cow::Owned(value) if value == b"a\\\\b\\\""));
+
+        // Without escapes, the value is borrowed, even with lone backslashes.
+        assert!(matches!(
+            RawHeader(b"plain").decode(),
+            Cow::Borrowed(b"plain")
+        ));
+        assert!(matches!(
+            RawHeader(b"Hello \\world\\").decode_utf8(),
+            Ok(Cow::Borrowed("Hello \\world\\"))
+        ));
     }
 }
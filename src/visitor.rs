@@ -1,6 +1,14 @@
-use shakmaty::{san::SanPlus, Outcome};
+use std::ops::Range;
 
-use crate::types::{Nag, RawComment, RawHeader, Skip};
+use shakmaty::{
+    san::{San, SanPlus},
+    Outcome,
+};
+
+use crate::{
+    error::PgnError,
+    types::{Nag, RawComment, RawHeader, Skip},
+};
 
 /// Consumes games from a reader.
 ///
@@ -11,11 +19,62 @@ pub trait Visitor {
 
     /// Called at the start of the game.
     fn begin_game(&mut self) {}
+    /// Called directly after [`begin_game()`](Visitor::begin_game) with the
+    /// index of the game, counting from 0 for the first game of the reader.
+    /// Games that were skipped or read with another visitor count as well,
+    /// so the index matches [`nth_game()`](crate::BufferedReader::nth_game),
+    /// which saves visitors from keeping their own counter.
+    ///
+    /// The count starts over with
+    /// [`BufferedReader::reset()`](crate::BufferedReader::reset), and does
+    /// not follow [`seek_game()`](crate::BufferedReader::seek_game). Each
+    /// chunk of [`par_games()`](crate::par_games) counts on its own.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, Visitor};
+    ///
+    /// struct Index(usize);
+    ///
+    /// impl Visitor for Index {
+    ///     type Result = usize;
+    ///
+    ///     fn game_index(&mut self, index: usize) {
+    ///         self.0 = index;
+    ///     }
+    ///
+    ///     fn end_game(&mut self) -> usize {
+    ///         self.0
+    ///     }
+    /// }
+    ///
+    /// let mut reader = BufferedReader::new_cursor(&b"1. e4 *\n\n1. d4 *\n\n1. c4 *"[..]);
+    /// assert_eq!(reader.read_game(&mut Index(0))?, Some(0));
+    /// reader.skip_game::<Index>()?;
+    /// assert_eq!(reader.read_game(&mut Index(0))?, Some(2));
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn game_index(&mut self, _index: usize) {}
 
     /// Called directly before reading game headers.
     fn begin_headers(&mut self) {}
     /// Called when parsing a game header like `[White "Deep Blue"]`.
+    ///
+    /// Whitespace around the key is trimmed. The key may be empty, as in
+    /// `[ "value"]`.
     fn header(&mut self, _key: &[u8], _value: RawHeader<'_>) {}
+    /// Called after the headers, directly before
+    /// [`end_headers()`](Visitor::end_headers), if there is a `FEN` header
+    /// with the starting position. `fen` is the decoded value of the last
+    /// `FEN` header.
+    ///
+    /// The `SetUp` header is not required, because in practice it is often
+    /// missing.
+    fn setup(&mut self, _fen: &[u8]) {}
+    /// Called after the headers, directly before
+    /// [`end_headers()`](Visitor::end_headers), with the header block
+    /// exactly as it appears in the input, if the reader is set to
+    /// [pass it on](crate::BufferedReader::set_raw_headers).
+    fn raw_headers(&mut self, _headers: &[u8]) {}
     /// Called after reading the headers of a game. May skip quickly over
     /// the following move text directly to
     /// [`end_game()`](trait.Visitor.html#tymethod.end_game).
@@ -23,12 +82,62 @@ pub trait Visitor {
         Skip(false)
     }
 
-    /// Called for each move, like `Nf3+`.
+    /// Called for each move number, like `12.`, `12...` or `12. ...`.
+    /// `black_to_move` is set for the forms with `...`.
+    fn move_number(&mut self, _number: u32, _black_to_move: bool) {}
+    /// Called for each move, like `Nf3+`. The move is already parsed into
+    /// an owned value that does not borrow from the reader, so it can be
+    /// stored as is.
     fn san(&mut self, _san_plus: SanPlus) {}
+    /// Called for each null move, like `--` or `Z0`, instead of
+    /// [`Visitor::san()`]. See [`NULL_MOVES`](crate::reader::NULL_MOVES)
+    /// for the recognized tokens.
+    ///
+    /// By default, the null move is passed on to [`Visitor::san()`] as
+    /// [`San::Null`], without a suffix.
+    fn null_move(&mut self) {
+        self.san(SanPlus {
+            san: San::Null,
+            suffix: None,
+        });
+    }
+    /// Called for each token in the movetext that looks like a move, but is
+    /// not valid SAN, if the reader is set to
+    /// [`InvalidSanPolicy::Report`](crate::reader::InvalidSanPolicy::Report).
+    fn unknown_token(&mut self, _token: &[u8]) {}
+    /// Called for each token in the movetext that the reader cannot make
+    /// sense of, because it is not a move, a move number, a NAG, a comment
+    /// or an outcome. Examples are `12` without a dot, `+` on its own or a
+    /// stray `"`. The token is skipped and the game is
+    /// read as usual.
+    ///
+    /// Tokens that look like moves, but are not valid SAN, like `Xe4`, are
+    /// passed here too, unless the
+    /// [`InvalidSanPolicy`](crate::reader::InvalidSanPolicy) says otherwise.
+    /// With the default policy, this sees every token that is dropped.
+    fn garbage(&mut self, _token: &[u8]) {}
     /// Called for each numeric annotation glyph like `!?` or `$7`.
     fn nag(&mut self, _nag: Nag) {}
-    /// Called for each `{ comment }`.
+    /// Called for each `{ comment }`, and for each `; comment` until the
+    /// end of the line.
+    ///
+    /// Comments are passed on in the order they appear, directly after the
+    /// callbacks for what precedes them. So a comment after
+    /// [`san()`](Visitor::san) and any [`nag()`](Visitor::nag) annotates
+    /// that move, a comment directly after
+    /// [`begin_variation()`](Visitor::begin_variation) comes before the
+    /// first move of the variation, and a comment after
+    /// [`end_variation()`](Visitor::end_variation) follows the variation.
+    /// Comments before the first move of the game go to
+    /// [`pre_game_comment()`](Visitor::pre_game_comment) instead.
     fn comment(&mut self, _comment: RawComment<'_>) {}
+    /// Called for each comment in the movetext before the first move or
+    /// variation of the game, like a comment describing the game.
+    ///
+    /// By default, the comment is passed on to [`Visitor::comment()`].
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        self.comment(comment);
+    }
     /// Called for each `(`. May skip over the following variation directly
     /// to [`end_variation()`](trait.Visitor.html#method.end_variation) (or to
     /// [`end_game()`](trait.Visitor.html#tymethod.end_game) if no matching `)`
@@ -42,11 +151,921 @@ pub trait Visitor {
     /// Called for each game termination, like `*` or `1-0`.
     fn outcome(&mut self, _outcome: Option<Outcome>) {}
 
+    /// Called for each line starting with the `%` escape, between the
+    /// headers or in the movetext of a game. `line` is the rest of the line
+    /// after the `%`, without the line break.
+    ///
+    /// Escaped lines before the headers of a game, in skipped movetext or
+    /// variations, and lines that do not fit into the buffer of the reader
+    /// are skipped without calling this.
+    fn escape(&mut self, _line: &[u8]) {}
+
+    /// Called after the movetext of a game if the input ended before an
+    /// outcome like `1-0` or `*` terminated the mainline, and there was no
+    /// blank line after the movetext. This usually means that the input was
+    /// cut off in the middle of the game, like an incomplete download.
+    ///
+    /// Not called if the movetext was skipped.
+    fn truncated(&mut self) {}
+
+    /// Called after the movetext of a game with the movetext exactly as it
+    /// appears in the input, if the reader is set to
+    /// [pass it on](crate::BufferedReader::set_raw_movetext). This happens
+    /// even if the movetext was skipped.
+    fn raw_movetext(&mut self, _movetext: &[u8]) {}
+
+    /// Called for problems that the reader worked around without giving up
+    /// on the game, like variations that are nested too deeply (see
+    /// [`BufferedReader::set_max_variation_depth()`](crate::BufferedReader::set_max_variation_depth)).
+    fn warning(&mut self, _error: &PgnError) {}
+
+    /// Called instead of failing with a syntax error, if the reader is set
+    /// to [recover](crate::BufferedReader::set_recover) from malformed
+    /// games. The rest of the game is skipped, followed by
+    /// [`end_game()`](Visitor::end_game).
+    fn recovered(&mut self, _error: &PgnError) {}
+
     /// Called after parsing a game. Can produce a custom result.
     fn end_game(&mut self) -> Self::Result;
+
+    /// Called once by
+    /// [`BufferedReader::read_all()`](crate::BufferedReader::read_all)
+    /// after the last game. Visitors that buffer output should flush it
+    /// here.
+    ///
+    /// The reader only borrows the visitor and never drops it, so when
+    /// driving [`read_game()`](crate::BufferedReader::read_game) manually,
+    /// call this (or flush otherwise) after the last game.
+    fn finish(&mut self) {}
+
+    /// Asked before each game by [`read_all()`](crate::BufferedReader::read_all),
+    /// the [game iterators](crate::BufferedReader::games) and
+    /// [`read_selected()`](crate::BufferedReader::read_selected). Returning
+    /// `true` stops them without reading further, for example once a
+    /// search has found its game.
+    ///
+    /// The game in progress is always read to the end, including
+    /// [`end_game()`](Visitor::end_game), so its result is still returned.
+    /// `read_all()` still calls [`finish()`](Visitor::finish).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, RawHeader, Visitor};
+    ///
+    /// struct FindRound {
+    ///     round: &'static [u8],
+    ///     games: usize,
+    ///     found: bool,
+    /// }
+    ///
+    /// impl Visitor for FindRound {
+    ///     type Result = ();
+    ///
+    ///     fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+    ///         self.found |= key == b"Round" && value.as_bytes() == self.round;
+    ///     }
+    ///
+    ///     fn end_game(&mut self) {
+    ///         self.games += 1;
+    ///     }
+    ///
+    ///     fn abort(&self) -> bool {
+    ///         self.found
+    ///     }
+    /// }
+    ///
+    /// let pgn = b"[Round \"1\"]\n\n*\n\n[Round \"2\"]\n\n*\n\n[Round \"3\"]\n\n*";
+    ///
+    /// let mut visitor = FindRound { round: b"2", games: 0, found: false };
+    /// BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+    /// assert_eq!(visitor.games, 2);
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    fn abort(&self) -> bool {
+        false
+    }
+}
+
+/// Forwards all callbacks to another visitor, but drops the result of each
+/// game.
+///
+/// Useful for visitors that only aggregate state across games, so that
+/// iterating over a reader does not accumulate results.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{visitor::Discard, BufferedReader, SanPlus, Visitor};
+///
+/// struct TotalPlies(usize);
+///
+/// impl Visitor for TotalPlies {
+///     type Result = usize;
+///
+///     fn san(&mut self, _san_plus: SanPlus) {
+///         self.0 += 1;
+///     }
+///
+///     fn end_game(&mut self) -> usize {
+///         self.0
+///     }
+/// }
+///
+/// let pgn = b"1. e4 e5 *\n\n1. d4 *";
+///
+/// let mut visitor = Discard::new(TotalPlies(0));
+/// BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+/// assert_eq!(visitor.into_inner().0, 3);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Discard<V> {
+    inner: V,
+}
+
+impl<V> Discard<V> {
+    pub fn new(inner: V) -> Discard<V> {
+        Discard { inner }
+    }
+
+    /// Gets a reference to the wrapped visitor.
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    /// Returns the wrapped visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+impl<V: Visitor> Visitor for Discard<V> {
+    type Result = ();
+
+    fn begin_game(&mut self) {
+        self.inner.begin_game();
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.inner.game_index(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.inner.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.inner.header(key, value);
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.inner.setup(fen);
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        self.inner.raw_headers(headers);
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.inner.end_headers()
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        self.inner.move_number(number, black_to_move);
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.inner.san(san_plus);
+    }
+
+    fn null_move(&mut self) {
+        self.inner.null_move();
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        self.inner.unknown_token(token);
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        self.inner.garbage(token);
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.inner.nag(nag);
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        self.inner.comment(comment);
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        self.inner.pre_game_comment(comment);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.inner.begin_variation()
+    }
+
+    fn end_variation(&mut self) {
+        self.inner.end_variation();
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.inner.outcome(outcome);
+    }
+
+    fn escape(&mut self, line: &[u8]) {
+        self.inner.escape(line);
+    }
+
+    fn truncated(&mut self) {
+        self.inner.truncated();
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        self.inner.raw_movetext(movetext);
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        self.inner.warning(error);
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.inner.recovered(error);
+    }
+
+    fn end_game(&mut self) {
+        let _ = self.inner.end_game();
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.inner.abort()
+    }
+}
+
+/// Forwards all callbacks to two visitors, to run both in a single pass
+/// over the input, like writing games while collecting statistics.
+///
+/// Each visitor receives exactly the callbacks it would receive if it was
+/// reading alone. The reader only skips the movetext or a variation if
+/// both visitors skip it. Otherwise the callbacks are withheld from the
+/// visitor that skipped it, until the corresponding
+/// [`end_variation()`](Visitor::end_variation) or
+/// [`end_game()`](Visitor::end_game). Likewise, reading only stops early
+/// once both visitors [abort](Visitor::abort).
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{visitor::Tee, BufferedReader, GameSummary, MovetextFeatures};
+///
+/// let pgn = b"1. e4 e5 (1... c5) 2. Nf3 *";
+///
+/// let mut visitor = Tee::new(GameSummary::new(), MovetextFeatures::default());
+/// let ((_, moves), features) = BufferedReader::new_cursor(&pgn[..])
+///     .read_game(&mut visitor)?
+///     .unwrap();
+/// assert_eq!(moves.len(), 3); // the mainline
+/// assert!(features.variations);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Tee<A, B> {
+    a: A,
+    b: B,
+    gate_a: Gate,
+    gate_b: Gate,
+}
+
+/// What a visitor of a [`Tee`] has skipped.
+#[derive(Debug, Clone, Default)]
+struct Gate {
+    skip_movetext: bool,
+    /// Number of open variations, counted from the variation that was
+    /// skipped.
+    skip_depth: usize,
+}
+
+impl Gate {
+    fn forward(&self) -> bool {
+        !self.skip_movetext && self.skip_depth == 0
+    }
+
+    fn begin_variation<V: Visitor>(&mut self, visitor: &mut V) {
+        if self.forward() {
+            if let Skip(true) = visitor.begin_variation() {
+                self.skip_depth = 1;
+            }
+        } else if self.skip_depth > 0 {
+            self.skip_depth += 1;
+        }
+    }
+
+    fn end_variation<V: Visitor>(&mut self, visitor: &mut V) {
+        if self.skip_depth > 0 {
+            self.skip_depth -= 1;
+            if self.skip_depth == 0 {
+                visitor.end_variation();
+            }
+        } else if !self.skip_movetext {
+            visitor.end_variation();
+        }
+    }
+}
+
+impl<A, B> Tee<A, B> {
+    pub fn new(a: A, b: B) -> Tee<A, B> {
+        Tee {
+            a,
+            b,
+            gate_a: Gate::default(),
+            gate_b: Gate::default(),
+        }
+    }
+
+    /// Returns the wrapped visitors.
+    pub fn into_inner(self) -> (A, B) {
+        (self.a, self.b)
+    }
+}
+
+impl<A: Visitor, B: Visitor> Visitor for Tee<A, B> {
+    type Result = (A::Result, B::Result);
+
+    fn begin_game(&mut self) {
+        self.gate_a = Gate::default();
+        self.gate_b = Gate::default();
+        self.a.begin_game();
+        self.b.begin_game();
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.a.game_index(index);
+        self.b.game_index(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.a.begin_headers();
+        self.b.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        self.a.header(key, value.clone());
+        self.b.header(key, value);
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        self.a.setup(fen);
+        self.b.setup(fen);
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        self.a.raw_headers(headers);
+        self.b.raw_headers(headers);
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        let Skip(skip_a) = self.a.end_headers();
+        let Skip(skip_b) = self.b.end_headers();
+        self.gate_a.skip_movetext = skip_a;
+        self.gate_b.skip_movetext = skip_b;
+        Skip(skip_a && skip_b)
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        if self.gate_a.forward() {
+            self.a.move_number(number, black_to_move);
+        }
+        if self.gate_b.forward() {
+            self.b.move_number(number, black_to_move);
+        }
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        if self.gate_a.forward() {
+            self.a.san(san_plus.clone());
+        }
+        if self.gate_b.forward() {
+            self.b.san(san_plus);
+        }
+    }
+
+    fn null_move(&mut self) {
+        if self.gate_a.forward() {
+            self.a.null_move();
+        }
+        if self.gate_b.forward() {
+            self.b.null_move();
+        }
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        if self.gate_a.forward() {
+            self.a.unknown_token(token);
+        }
+        if self.gate_b.forward() {
+            self.b.unknown_token(token);
+        }
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        if self.gate_a.forward() {
+            self.a.garbage(token);
+        }
+        if self.gate_b.forward() {
+            self.b.garbage(token);
+        }
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        if self.gate_a.forward() {
+            self.a.nag(nag.clone());
+        }
+        if self.gate_b.forward() {
+            self.b.nag(nag);
+        }
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        if self.gate_a.forward() {
+            self.a.comment(comment.clone());
+        }
+        if self.gate_b.forward() {
+            self.b.comment(comment);
+        }
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        if self.gate_a.forward() {
+            self.a.pre_game_comment(comment.clone());
+        }
+        if self.gate_b.forward() {
+            self.b.pre_game_comment(comment);
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.gate_a.begin_variation(&mut self.a);
+        self.gate_b.begin_variation(&mut self.b);
+        Skip(!self.gate_a.forward() && !self.gate_b.forward())
+    }
+
+    fn end_variation(&mut self) {
+        self.gate_a.end_variation(&mut self.a);
+        self.gate_b.end_variation(&mut self.b);
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        if self.gate_a.forward() {
+            self.a.outcome(outcome);
+        }
+        if self.gate_b.forward() {
+            self.b.outcome(outcome);
+        }
+    }
+
+    fn escape(&mut self, line: &[u8]) {
+        if self.gate_a.forward() {
+            self.a.escape(line);
+        }
+        if self.gate_b.forward() {
+            self.b.escape(line);
+        }
+    }
+
+    fn truncated(&mut self) {
+        if !self.gate_a.skip_movetext {
+            self.a.truncated();
+        }
+        if !self.gate_b.skip_movetext {
+            self.b.truncated();
+        }
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        self.a.raw_movetext(movetext);
+        self.b.raw_movetext(movetext);
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        if self.gate_a.forward() {
+            self.a.warning(error);
+        }
+        if self.gate_b.forward() {
+            self.b.warning(error);
+        }
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.a.recovered(error);
+        self.b.recovered(error);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        (self.a.end_game(), self.b.end_game())
+    }
+
+    fn finish(&mut self) {
+        self.a.finish();
+        self.b.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.a.abort() && self.b.abort()
+    }
+}
+
+/// Records each game in memory, and passes it on to the inner visitor only
+/// once the whole game has been seen and accepted.
+///
+/// This is for decisions that need the entire movetext, like checking the
+/// number of plies or the outcome, where
+/// [`end_headers()`](Visitor::end_headers) would be too early. A
+/// `predicate` visitor reads the game as it comes in, and `accept` turns
+/// its result into the decision. Accepted games are replayed to the inner
+/// visitor with the same callbacks, in the same order, as if it was reading
+/// alone. Produces `None` for the other games.
+///
+/// The movetext and all variations are always read, so that they can be
+/// replayed. Only one game is held in memory at a time.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{visitor::Buffered, BufferedReader, PgnWriter, PlyCountCheck};
+///
+/// let pgn = b"[PlyCount \"3\"]\n\n1. e4 e5 2. Nf3 *\n\n[PlyCount \"41\"]\n\n1. d4 d5 *";
+///
+/// let mut visitor = Buffered::new(
+///     PlyCountCheck::new(),
+///     |mismatch| mismatch.is_none(),
+///     PgnWriter::new(Vec::new()),
+/// );
+/// BufferedReader::new_cursor(&pgn[..]).read_all(&mut visitor)?;
+/// assert_eq!(
+///     visitor.into_inner().into_inner(),
+///     b"[PlyCount \"3\"]\n\n1. e4 e5 2. Nf3 *\n\n"
+/// );
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[doc(alias = "BufferingVisitor")]
+#[derive(Debug, Clone)]
+pub struct Buffered<P, F, V> {
+    predicate: P,
+    accept: F,
+    inner: V,
+    gate: Gate,
+    events: Vec<Event>,
+    /// Byte strings of the events, referenced by range.
+    bytes: Vec<u8>,
+}
+
+/// A callback recorded by a [`Buffered`] visitor.
+#[derive(Debug, Clone)]
+enum Event {
+    GameIndex(usize),
+    BeginHeaders,
+    Header(Range<usize>, Range<usize>),
+    Setup(Range<usize>),
+    RawHeaders(Range<usize>),
+    EndHeaders,
+    MoveNumber(u32, bool),
+    San(SanPlus),
+    NullMove,
+    UnknownToken(Range<usize>),
+    Garbage(Range<usize>),
+    Nag(Nag),
+    Comment(Range<usize>),
+    PreGameComment(Range<usize>),
+    BeginVariation,
+    EndVariation,
+    Outcome(Option<Outcome>),
+    Escape(Range<usize>),
+    Truncated,
+    RawMovetext(Range<usize>),
+    Warning(PgnError),
+    Recovered(PgnError),
+}
+
+impl<P, F, V> Buffered<P, F, V>
+where
+    P: Visitor,
+    F: FnMut(P::Result) -> bool,
+{
+    pub fn new(predicate: P, accept: F, inner: V) -> Buffered<P, F, V> {
+        Buffered {
+            predicate,
+            accept,
+            inner,
+            gate: Gate::default(),
+            events: Vec::new(),
+            bytes: Vec::new(),
+        }
+    }
+}
+
+impl<P, F, V> Buffered<P, F, V> {
+    /// Gets a reference to the wrapped visitor.
+    pub fn get_ref(&self) -> &V {
+        &self.inner
+    }
+
+    /// Returns the wrapped visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+
+    fn record(&mut self, bytes: &[u8]) -> Range<usize> {
+        let start = self.bytes.len();
+        self.bytes.extend_from_slice(bytes);
+        start..self.bytes.len()
+    }
+}
+
+impl<P, F, V> Buffered<P, F, V> {
+    /// Gets a reference to the predicate visitor.
+    pub(crate) fn predicate(&self) -> &P {
+        &self.predicate
+    }
+
+    /// Ends the game for the predicate only, without replaying it.
+    pub(crate) fn decide(&mut self) -> P::Result
+    where
+        P: Visitor,
+    {
+        self.predicate.end_game()
+    }
+
+    /// Replays the recorded game to a visitor other than the inner one.
+    pub(crate) fn replay_to<W: Visitor>(&self, visitor: &mut W) -> W::Result {
+        replay(&self.events, &self.bytes, visitor)
+    }
+}
+
+/// Passes the recorded events of a [`Buffered`] visitor to `inner`.
+fn replay<V: Visitor>(events: &[Event], bytes: &[u8], inner: &mut V) -> V::Result {
+    let text = |range: &Range<usize>| &bytes[range.clone()];
+    let mut gate = Gate::default();
+
+    inner.begin_game();
+    for event in events.iter() {
+        match event {
+            Event::GameIndex(index) => inner.game_index(*index),
+            Event::BeginHeaders => inner.begin_headers(),
+            Event::Header(key, value) => inner.header(text(key), RawHeader(text(value))),
+            Event::Setup(fen) => inner.setup(text(fen)),
+            Event::RawHeaders(headers) => inner.raw_headers(text(headers)),
+            Event::EndHeaders => {
+                let Skip(skip) = inner.end_headers();
+                gate.skip_movetext = skip;
+            }
+            Event::BeginVariation => gate.begin_variation(inner),
+            Event::EndVariation => gate.end_variation(inner),
+            Event::Truncated => {
+                if !gate.skip_movetext {
+                    inner.truncated();
+                }
+            }
+            Event::RawMovetext(movetext) => inner.raw_movetext(text(movetext)),
+            Event::Recovered(error) => inner.recovered(error),
+            _ if !gate.forward() => {}
+            Event::MoveNumber(number, black_to_move) => inner.move_number(*number, *black_to_move),
+            Event::San(san_plus) => inner.san(san_plus.clone()),
+            Event::NullMove => inner.null_move(),
+            Event::UnknownToken(token) => inner.unknown_token(text(token)),
+            Event::Garbage(token) => inner.garbage(text(token)),
+            Event::Nag(nag) => inner.nag(nag.clone()),
+            Event::Comment(comment) => inner.comment(RawComment(text(comment))),
+            Event::PreGameComment(comment) => inner.pre_game_comment(RawComment(text(comment))),
+            Event::Outcome(outcome) => inner.outcome(*outcome),
+            Event::Escape(line) => inner.escape(text(line)),
+            Event::Warning(error) => inner.warning(error),
+        }
+    }
+    inner.end_game()
 }
 
-pub(crate) struct SkipVisitor;
+impl<P, F, V> Visitor for Buffered<P, F, V>
+where
+    P: Visitor,
+    F: FnMut(P::Result) -> bool,
+    V: Visitor,
+{
+    type Result = Option<V::Result>;
+
+    fn begin_game(&mut self) {
+        self.gate = Gate::default();
+        self.events.clear();
+        self.bytes.clear();
+        self.predicate.begin_game();
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.events.push(Event::GameIndex(index));
+        self.predicate.game_index(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.events.push(Event::BeginHeaders);
+        self.predicate.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        let event = Event::Header(self.record(key), self.record(value.as_bytes()));
+        self.events.push(event);
+        self.predicate.header(key, value);
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        let event = Event::Setup(self.record(fen));
+        self.events.push(event);
+        self.predicate.setup(fen);
+    }
+
+    fn raw_headers(&mut self, headers: &[u8]) {
+        let event = Event::RawHeaders(self.record(headers));
+        self.events.push(event);
+        self.predicate.raw_headers(headers);
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.events.push(Event::EndHeaders);
+        let Skip(skip) = self.predicate.end_headers();
+        self.gate.skip_movetext = skip;
+        Skip(false)
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        self.events.push(Event::MoveNumber(number, black_to_move));
+        if self.gate.forward() {
+            self.predicate.move_number(number, black_to_move);
+        }
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.events.push(Event::San(san_plus.clone()));
+        if self.gate.forward() {
+            self.predicate.san(san_plus);
+        }
+    }
+
+    fn null_move(&mut self) {
+        self.events.push(Event::NullMove);
+        if self.gate.forward() {
+            self.predicate.null_move();
+        }
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        let event = Event::UnknownToken(self.record(token));
+        self.events.push(event);
+        if self.gate.forward() {
+            self.predicate.unknown_token(token);
+        }
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        let event = Event::Garbage(self.record(token));
+        self.events.push(event);
+        if self.gate.forward() {
+            self.predicate.garbage(token);
+        }
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.events.push(Event::Nag(nag.clone()));
+        if self.gate.forward() {
+            self.predicate.nag(nag);
+        }
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        let event = Event::Comment(self.record(comment.as_bytes()));
+        self.events.push(event);
+        if self.gate.forward() {
+            self.predicate.comment(comment);
+        }
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        let event = Event::PreGameComment(self.record(comment.as_bytes()));
+        self.events.push(event);
+        if self.gate.forward() {
+            self.predicate.pre_game_comment(comment);
+        }
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.events.push(Event::BeginVariation);
+        self.gate.begin_variation(&mut self.predicate);
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        self.events.push(Event::EndVariation);
+        self.gate.end_variation(&mut self.predicate);
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.events.push(Event::Outcome(outcome));
+        if self.gate.forward() {
+            self.predicate.outcome(outcome);
+        }
+    }
+
+    fn escape(&mut self, line: &[u8]) {
+        let event = Event::Escape(self.record(line));
+        self.events.push(event);
+        if self.gate.forward() {
+            self.predicate.escape(line);
+        }
+    }
+
+    fn truncated(&mut self) {
+        self.events.push(Event::Truncated);
+        if !self.gate.skip_movetext {
+            self.predicate.truncated();
+        }
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        let event = Event::RawMovetext(self.record(movetext));
+        self.events.push(event);
+        self.predicate.raw_movetext(movetext);
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        self.events.push(Event::Warning(error.clone()));
+        if self.gate.forward() {
+            self.predicate.warning(error);
+        }
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.events.push(Event::Recovered(error.clone()));
+        self.predicate.recovered(error);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        let decision = self.predicate.end_game();
+        let result =
+            (self.accept)(decision).then(|| replay(&self.events, &self.bytes, &mut self.inner));
+        self.events.clear();
+        self.bytes.clear();
+        result
+    }
+
+    fn finish(&mut self) {
+        self.predicate.finish();
+        self.inner.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.inner.abort()
+    }
+}
+
+/// A visitor that skips everything, which is what the reader itself uses
+/// to skip and count games.
+///
+/// The reader still reads the headers, but skips the movetext by only
+/// looking for the end of the game, without parsing moves, comments or
+/// variations. Without a visitor,
+/// [`BufferedReader::skip_game()`](crate::BufferedReader::skip_game) and
+/// [`BufferedReader::count_games()`](crate::BufferedReader::count_games)
+/// do the same.
+///
+/// To look at the headers as well, implement [`Visitor`] with
+/// [`end_headers()`](Visitor::end_headers) returning `Skip(true)`, like
+/// this visitor does. In a [`Tee`], it leaves the decision to skip to the
+/// other visitor.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{visitor::SkipVisitor, BufferedReader};
+///
+/// let pgn = b"1. e4 e5 *\n\n[Event \"?\"]\n\n1. d4 (1. c4) *";
+///
+/// let games = BufferedReader::new_cursor(&pgn[..])
+///     .into_iter(&mut SkipVisitor::new())
+///     .count();
+/// assert_eq!(games, 2);
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SkipVisitor;
+
+impl SkipVisitor {
+    /// Creates the visitor.
+    pub const fn new() -> SkipVisitor {
+        SkipVisitor
+    }
+}
 
 impl Visitor for SkipVisitor {
     type Result = ();
@@ -59,3 +1078,269 @@ impl Visitor for SkipVisitor {
     }
     fn end_game(&mut self) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::reader::BufferedReader;
+
+    #[test]
+    fn test_discard() -> io::Result<()> {
+        #[derive(Default)]
+        struct Log {
+            events: Vec<String>,
+        }
+
+        impl Visitor for Log {
+            type Result = Vec<String>;
+
+            fn begin_game(&mut self) {
+                self.events.push("begin_game".to_owned());
+            }
+
+            fn header(&mut self, key: &[u8], _value: RawHeader<'_>) {
+                self.events.push(String::from_utf8_lossy(key).into_owned());
+            }
+
+            fn end_headers(&mut self) -> Skip {
+                // Skip the movetext of the second game.
+                Skip(self.events.len() > 3)
+            }
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.events.push(san_plus.to_string());
+            }
+
+            fn nag(&mut self, nag: Nag) {
+                self.events.push(nag.to_string());
+            }
+
+            fn comment(&mut self, _comment: RawComment<'_>) {
+                self.events.push("comment".to_owned());
+            }
+
+            fn begin_variation(&mut self) -> Skip {
+                self.events.push("(".to_owned());
+                Skip(false)
+            }
+
+            fn end_variation(&mut self) {
+                self.events.push(")".to_owned());
+            }
+
+            fn outcome(&mut self, outcome: Option<Outcome>) {
+                self.events.push(format!("{:?}", outcome));
+            }
+
+            fn end_game(&mut self) -> Vec<String> {
+                self.events.clone()
+            }
+        }
+
+        let pgn = b"[White \"?\"]\n\n1. e4 $1 { c } (1. d4) 1-0\n\n[Black \"?\"]\n\n1. c4 *";
+        let mut visitor = Discard::new(Log::default());
+        let results: Vec<()> = BufferedReader::new_cursor(&pgn[..])
+            .into_iter(&mut visitor)
+            .collect::<io::Result<_>>()?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            visitor.into_inner().events,
+            [
+                "begin_game",
+                "White",
+                "e4",
+                "$1",
+                "comment",
+                "(",
+                "d4",
+                ")",
+                "Some(Decisive { winner: White })",
+                "begin_game",
+                "Black",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_tee() -> io::Result<()> {
+        struct Log {
+            skip_movetext: bool,
+            skip_variations: bool,
+            events: Vec<String>,
+        }
+
+        impl Log {
+            fn new(skip_movetext: bool, skip_variations: bool) -> Log {
+                Log {
+                    skip_movetext,
+                    skip_variations,
+                    events: Vec::new(),
+                }
+            }
+        }
+
+        impl Visitor for Log {
+            type Result = Vec<String>;
+
+            fn begin_game(&mut self) {
+                self.events.clear();
+            }
+
+            fn end_headers(&mut self) -> Skip {
+                Skip(self.skip_movetext)
+            }
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.events.push(san_plus.to_string());
+            }
+
+            fn comment(&mut self, _comment: RawComment<'_>) {
+                self.events.push("comment".to_owned());
+            }
+
+            fn begin_variation(&mut self) -> Skip {
+                self.events.push("(".to_owned());
+                Skip(self.skip_variations)
+            }
+
+            fn end_variation(&mut self) {
+                self.events.push(")".to_owned());
+            }
+
+            fn outcome(&mut self, _outcome: Option<Outcome>) {
+                self.events.push("outcome".to_owned());
+            }
+
+            fn end_game(&mut self) -> Vec<String> {
+                std::mem::take(&mut self.events)
+            }
+        }
+
+        let pgn = b"1. e4 { c } e5 (1... c5 (1... e6) 2. Nf3) 2. Nf3 1-0";
+        let read = |a: Log, b: Log| {
+            BufferedReader::new_cursor(&pgn[..])
+                .read_game(&mut Tee::new(a, b))
+                .map(Option::unwrap)
+        };
+        let alone = |mut log: Log| {
+            BufferedReader::new_cursor(&pgn[..])
+                .read_game(&mut log)
+                .map(Option::unwrap)
+        };
+
+        let (a, b) = read(Log::new(false, true), Log::new(false, false))?;
+        assert_eq!(a, ["e4", "comment", "e5", "(", ")", "Nf3", "outcome"]);
+        assert_eq!(
+            b,
+            ["e4", "comment", "e5", "(", "c5", "(", "e6", ")", "Nf3", ")", "Nf3", "outcome"]
+        );
+        assert_eq!(a, alone(Log::new(false, true))?);
+        assert_eq!(b, alone(Log::new(false, false))?);
+
+        let (a, b) = read(Log::new(true, false), Log::new(false, true))?;
+        assert!(a.is_empty());
+        assert_eq!(b, alone(Log::new(false, true))?);
+
+        let (a, b) = read(Log::new(true, false), Log::new(true, true))?;
+        assert!(a.is_empty() && b.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_buffered() -> io::Result<()> {
+        use crate::game::{Game, GameBuilder};
+
+        struct Log {
+            skip_movetext: bool,
+            skip_variations: bool,
+            events: Vec<String>,
+        }
+
+        impl Visitor for Log {
+            type Result = Vec<String>;
+
+            fn begin_game(&mut self) {
+                self.events.clear();
+            }
+
+            fn game_index(&mut self, index: usize) {
+                self.events.push(format!("#{index}"));
+            }
+
+            fn end_headers(&mut self) -> Skip {
+                Skip(self.skip_movetext)
+            }
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.events.push(san_plus.to_string());
+            }
+
+            fn begin_variation(&mut self) -> Skip {
+                self.events.push("(".to_owned());
+                Skip(self.skip_variations)
+            }
+
+            fn end_variation(&mut self) {
+                self.events.push(")".to_owned());
+            }
+
+            fn truncated(&mut self) {
+                self.events.push("truncated".to_owned());
+            }
+
+            fn raw_movetext(&mut self, _movetext: &[u8]) {
+                self.events.push("raw".to_owned());
+            }
+
+            fn end_game(&mut self) -> Vec<String> {
+                std::mem::take(&mut self.events)
+            }
+        }
+
+        let pgn = b"[White \"A\"]\n[FEN \"8/8/8/8/8/8/8/K6k w - - 0 1\"]\n\n\
+                    1. Kb1 { c } $1 Kg1 (1... Kh2 (1... Kg2) 2. Kc1) 2. Kc1 1-0\n\n\
+                    [White \"B\"]\n\n1. e4 *\n\n1. d4 d5 (1... Nf6) 2. c4";
+        let reader = || {
+            let mut reader = BufferedReader::new_cursor(&pgn[..]);
+            reader.set_raw_headers(true);
+            reader.set_raw_movetext(true);
+            reader
+        };
+        let long = |game: Game| game.mainline.moves.len() > 1;
+
+        let mut visitor = Buffered::new(GameBuilder::new(), long, GameBuilder::new());
+        let buffered = reader()
+            .into_iter(&mut visitor)
+            .collect::<io::Result<Vec<_>>>()?;
+        let alone = reader()
+            .into_iter(&mut GameBuilder::new())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(
+            buffered,
+            [Some(alone[0].clone()), None, Some(alone[2].clone())]
+        );
+
+        for (skip_movetext, skip_variations) in [(false, false), (false, true), (true, false)] {
+            let log = || Log {
+                skip_movetext,
+                skip_variations,
+                events: Vec::new(),
+            };
+            let mut visitor = Buffered::new(GameBuilder::new(), long, log());
+            let buffered = reader()
+                .into_iter(&mut visitor)
+                .collect::<io::Result<Vec<_>>>()?;
+            let alone = reader()
+                .into_iter(&mut log())
+                .collect::<io::Result<Vec<_>>>()?;
+            assert_eq!(
+                buffered,
+                [Some(alone[0].clone()), None, Some(alone[2].clone())]
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,983 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+};
+
+use shakmaty::{
+    fen::Fen,
+    san::{San, SanPlus},
+    Color, Outcome,
+};
+
+use crate::{
+    error::PgnError,
+    game::Game,
+    headers::SEVEN_TAG_ROSTER,
+    reader::BufferedReader,
+    types::{Nag, RawComment, RawHeader, Skip},
+    visitor::Visitor,
+};
+
+/// Maximum length of movetext lines written by [`PgnWriter`], as required
+/// by the PGN standard.
+const MAX_LINE_LEN: usize = 80;
+
+/// How castling moves are written.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Default)]
+pub enum CastlingNotation {
+    /// `O-O` and `O-O-O`, as required by the PGN standard.
+    #[default]
+    LetterO,
+    /// `0-0` and `0-0-0`.
+    Zero,
+}
+
+impl CastlingNotation {
+    /// Displays a move, writing castling in this notation.
+    ///
+    /// ```
+    /// use pgn_reader::{CastlingNotation, SanPlus};
+    ///
+    /// let san_plus: SanPlus = "O-O-O+".parse().unwrap();
+    /// assert_eq!(CastlingNotation::LetterO.display(&san_plus).to_string(), "O-O-O+");
+    /// assert_eq!(CastlingNotation::Zero.display(&san_plus).to_string(), "0-0-0+");
+    /// ```
+    pub fn display(self, san_plus: &SanPlus) -> DisplaySan<'_> {
+        DisplaySan {
+            san_plus,
+            castling: self,
+        }
+    }
+}
+
+/// Displays a move with a configured [`CastlingNotation`].
+#[derive(Debug, Clone)]
+pub struct DisplaySan<'a> {
+    san_plus: &'a SanPlus,
+    castling: CastlingNotation,
+}
+
+impl fmt::Display for DisplaySan<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.castling, &self.san_plus.san) {
+            (CastlingNotation::Zero, San::Castle(side)) => {
+                f.write_str(if side.is_king_side() { "0-0" } else { "0-0-0" })?;
+                if let Some(suffix) = self.san_plus.suffix {
+                    write!(f, "{}", suffix)?;
+                }
+                Ok(())
+            }
+            _ => write!(f, "{}", self.san_plus),
+        }
+    }
+}
+
+/// A visitor that writes games back to PGN.
+///
+/// The output is in the export format of the PGN standard: one tag pair per
+/// line, a blank line, the movetext wrapped at 80 columns with move numbers
+/// regenerated, and a blank line after each game. Header values are written
+/// exactly as they were read, so escapes are preserved.
+///
+/// Comments are written in braces. A comment that contains `}`, like one
+/// from a `;` rest-of-line comment, is written as a rest-of-line comment
+/// instead, or with the `}` dropped if it also contains a line break.
+///
+/// Move numbers are counted from the plies of the game, starting at the
+/// `FEN` header if any, so wrong or missing numbers in the input are
+/// repaired. A move by Black gets a number like `2...` at the start of a
+/// variation, after a variation, and after a comment (see
+/// [`set_number_after_comments()`](PgnWriter::set_number_after_comments)).
+///
+/// Produces `Ok(())` for each game, or the first I/O error of the game.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{BufferedReader, PgnWriter};
+///
+/// let pgn = b"[White \"Fischer\"]\n\n1. e4 {best by test} e5 ( 1... c5 ) 2. Nf3 1-0";
+///
+/// let mut writer = PgnWriter::new(Vec::new());
+/// BufferedReader::new_cursor(&pgn[..]).read_game(&mut writer)?.unwrap()?;
+///
+/// assert_eq!(
+///     writer.into_inner(),
+///     b"[White \"Fischer\"]\n\n1. e4 {best by test} 1... e5 (1... c5) 2. Nf3 1-0\n\n"
+/// );
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct PgnWriter<W> {
+    out: W,
+    castling: CastlingNotation,
+    line_per_game: bool,
+    number_after_comments: bool,
+    error: Option<io::Error>,
+    headers: bool,
+    /// The last token, held back so that a closing parenthesis can be
+    /// attached before deciding whether it fits on the line.
+    pending: Vec<u8>,
+    column: usize,
+    open_variation: bool,
+    ply: u64,
+    variations: Vec<u64>,
+    needs_number: bool,
+    outcome: Option<Outcome>,
+}
+
+impl<W: Write> PgnWriter<W> {
+    pub fn new(out: W) -> PgnWriter<W> {
+        PgnWriter {
+            out,
+            castling: CastlingNotation::default(),
+            line_per_game: false,
+            number_after_comments: true,
+            error: None,
+            headers: false,
+            pending: Vec::new(),
+            column: 0,
+            open_variation: false,
+            ply: 0,
+            variations: Vec::new(),
+            needs_number: true,
+            outcome: None,
+        }
+    }
+
+    /// Sets the notation used for castling moves.
+    pub fn set_castling_notation(&mut self, castling: CastlingNotation) {
+        self.castling = castling;
+    }
+
+    /// Writes each game on a single line, including the headers, instead of
+    /// the export format. Line breaks in comments are replaced with spaces,
+    /// and `}` is dropped from comments.
+    /// The output is easy to process with line-oriented tools like `grep`,
+    /// and each line can be read back like any other PGN.
+    ///
+    /// Read back as a whole, only games with headers stay apart: a line
+    /// that does not start with `[` continues the movetext of the game
+    /// before it, because only a blank line or a header ends a game. Keep
+    /// at least one header, or read the output line by line.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, PgnWriter};
+    ///
+    /// let pgn = b"[White \"Fischer\"]\n[Black \"Spassky\"]\n\n1. e4 {multi\nline} e5 *";
+    ///
+    /// let mut writer = PgnWriter::new(Vec::new());
+    /// writer.set_line_per_game(true);
+    /// BufferedReader::new_cursor(&pgn[..]).read_all(&mut writer)?;
+    ///
+    /// assert_eq!(
+    ///     writer.into_inner(),
+    ///     b"[White \"Fischer\"] [Black \"Spassky\"] 1. e4 {multi line} 1... e5 *\n",
+    /// );
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_line_per_game(&mut self, line_per_game: bool) {
+        self.line_per_game = line_per_game;
+    }
+
+    /// Sets whether a move by Black after a comment gets a move number,
+    /// like `1. e4 {best by test} 1... e5`, as required by the PGN
+    /// standard. Enabled by default. Disabling it gives the more compact
+    /// `1. e4 {best by test} e5`, which reads back the same.
+    ///
+    /// ```
+    /// use pgn_reader::{BufferedReader, PgnWriter};
+    ///
+    /// let pgn = b"1. e4 {best by test} e5 2. Nf3 *";
+    ///
+    /// let mut writer = PgnWriter::new(Vec::new());
+    /// writer.set_number_after_comments(false);
+    /// BufferedReader::new_cursor(&pgn[..]).read_all(&mut writer)?;
+    ///
+    /// assert_eq!(writer.into_inner(), b"1. e4 {best by test} e5 2. Nf3 *\n\n");
+    /// # Ok::<_, std::io::Error>(())
+    /// ```
+    pub fn set_number_after_comments(&mut self, number_after_comments: bool) {
+        self.number_after_comments = number_after_comments;
+    }
+
+    /// Gets a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    /// Gets a mutable reference to the underlying writer.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Returns the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn record(&mut self, result: io::Result<()>) {
+        if let Err(err) = result {
+            self.error.get_or_insert(err);
+        }
+    }
+
+    /// Starts a new token, writing the previous one.
+    fn begin_token(&mut self) -> &mut Vec<u8> {
+        let result = self.flush_token();
+        self.record(result);
+        if self.open_variation {
+            self.open_variation = false;
+            self.pending.push(b'(');
+        }
+        &mut self.pending
+    }
+
+    fn flush_token(&mut self) -> io::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        if self.column > 0 {
+            if !self.line_per_game && self.column + 1 + self.pending.len() > MAX_LINE_LEN {
+                self.out.write_all(b"\n")?;
+                self.column = 0;
+            } else {
+                self.out.write_all(b" ")?;
+                self.column += 1;
+            }
+        }
+
+        self.out.write_all(&self.pending)?;
+        self.column = match memchr::memrchr(b'\n', &self.pending) {
+            Some(newline) => self.pending.len() - newline - 1,
+            None => self.column + self.pending.len(),
+        };
+        self.pending.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Visitor for PgnWriter<W> {
+    type Result = io::Result<()>;
+
+    fn begin_game(&mut self) {
+        self.error = None;
+        self.headers = false;
+        self.pending.clear();
+        self.column = 0;
+        self.open_variation = false;
+        self.ply = 0;
+        self.variations.clear();
+        self.needs_number = true;
+        self.outcome = None;
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if key == b"FEN" {
+            if let Ok(Fen(setup)) = Fen::from_ascii(&value.decode()) {
+                self.ply =
+                    u64::from(setup.fullmoves.get() - 1) * 2 + u64::from(setup.turn.is_black());
+            }
+        }
+
+        self.headers = true;
+        if self.line_per_game {
+            let token = self.begin_token();
+            token.push(b'[');
+            token.extend_from_slice(key);
+            token.extend_from_slice(b" \"");
+            token.extend_from_slice(value.as_bytes());
+            token.extend_from_slice(b"\"]");
+            return;
+        }
+
+        let result = (|| {
+            self.out.write_all(b"[")?;
+            self.out.write_all(key)?;
+            self.out.write_all(b" \"")?;
+            self.out.write_all(value.as_bytes())?;
+            self.out.write_all(b"\"]\n")
+        })();
+        self.record(result);
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        if self.headers && !self.line_per_game {
+            let result = self.out.write_all(b"\n");
+            self.record(result);
+        }
+        Skip(false)
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        let number = self.ply / 2 + 1;
+        let turn = Color::from_white(self.ply & 1 == 0);
+        if turn.is_white() || self.needs_number {
+            let dots = if turn.is_white() { "." } else { "..." };
+            let _ = write!(self.begin_token(), "{}{}", number, dots);
+        }
+        let castling = self.castling;
+        let _ = write!(self.begin_token(), "{}", castling.display(&san_plus));
+        self.ply += 1;
+        self.needs_number = false;
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        let _ = write!(self.begin_token(), "{}", nag);
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        let comment = comment.as_bytes();
+        let line_per_game = self.line_per_game;
+        if !line_per_game && comment.contains(&b'}') && !comment.contains(&b'\n') {
+            // A `}` would end a comment in braces early, but fits in a
+            // rest-of-line comment, which is also where it most likely
+            // came from.
+            let token = self.begin_token();
+            token.push(b';');
+            token.extend_from_slice(comment);
+            let result = self.flush_token().and_then(|()| self.out.write_all(b"\n"));
+            self.record(result);
+            self.column = 0;
+        } else {
+            let token = self.begin_token();
+            token.push(b'{');
+            token.extend(
+                comment
+                    .iter()
+                    .filter(|&&ch| ch != b'}')
+                    .map(|&ch| match ch {
+                        b'\r' | b'\n' if line_per_game => b' ',
+                        _ => ch,
+                    }),
+            );
+            token.push(b'}');
+        }
+        self.needs_number |= self.number_after_comments;
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        // The variation is an alternative to the last move.
+        self.variations.push(self.ply);
+        self.ply = self.ply.saturating_sub(1);
+        self.needs_number = true;
+        self.open_variation = true;
+        Skip(false)
+    }
+
+    fn end_variation(&mut self) {
+        if self.open_variation {
+            self.begin_token();
+        }
+        self.pending.push(b')');
+        if let Some(ply) = self.variations.pop() {
+            self.ply = ply;
+        }
+        self.needs_number = true;
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.outcome = outcome;
+    }
+
+    fn end_game(&mut self) -> io::Result<()> {
+        let token = match self.outcome {
+            Some(outcome) => outcome.as_str(),
+            None => "*",
+        };
+        self.begin_token().extend_from_slice(token.as_bytes());
+        let end: &[u8] = if self.line_per_game { b"\n" } else { b"\n\n" };
+        let result = self.flush_token().and_then(|()| self.out.write_all(end));
+        self.record(result);
+        self.error.take().map_or(Ok(()), Err)
+    }
+}
+
+/// Writes games to a single PGN with a [`PgnWriter`], each followed by a
+/// blank line.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{writer::write_games, Game};
+///
+/// let mut out = Vec::new();
+/// write_games(&mut out, vec![Game::default(), Game::default()])?;
+/// assert_eq!(out, b"*\n\n*\n\n");
+/// # Ok::<_, std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Stops at the first I/O error.
+pub fn write_games<W: Write>(out: W, games: impl IntoIterator<Item = Game>) -> io::Result<()> {
+    let mut writer = PgnWriter::new(out);
+    for game in games {
+        game.visit(&mut writer)?;
+    }
+    writer.finish();
+    writer.out.flush()
+}
+
+/// Rewrites all games of a PGN in the canonical form of [`PgnWriter`].
+///
+/// Reading the output back gives the same headers, moves, NAGs, comments
+/// and outcomes, in the same tree of variations, as reading the input.
+/// Normalizing the output again leaves it unchanged. The canonical form
+/// is:
+///
+/// * Headers in their original order and with their original escapes,
+///   one per line, followed by a blank line unless there are none.
+/// * Movetext tokens separated by single spaces, and wrapped before 80
+///   columns. Comments are kept as they are, including line breaks.
+/// * A move number before every move by White, and before a move by Black
+///   at the start of a variation or after a comment or variation.
+/// * NAGs as `$1`, also for suffixes like `!`, and castling as `O-O`.
+/// * `;` comments in braces, unless they contain `}`, which stay `;`
+///   comments followed by a line break. Escaped `%` lines and unknown
+///   tokens are dropped.
+/// * The game termination marker, `*` if missing, followed by a blank
+///   line.
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::writer::normalize;
+///
+/// let pgn = b"[White \"Fischer\"]\n\n1.e4 e5!? ; open\n2. o-o *";
+///
+/// let mut out = Vec::new();
+/// normalize(&pgn[..], &mut out)?;
+/// assert_eq!(out, b"[White \"Fischer\"]\n\n1. e4 e5 $5 { open} 2. O-O *\n\n");
+///
+/// let mut again = Vec::new();
+/// normalize(&out[..], &mut again)?;
+/// assert_eq!(again, out);
+/// # Ok::<_, std::io::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Stops at the first I/O error or irrecoverable parser error.
+pub fn normalize<R: Read, W: Write>(input: R, out: W) -> io::Result<()> {
+    let mut reader = BufferedReader::new(input);
+    let mut writer = PgnWriter::new(out);
+    while let Some(result) = reader.read_game(&mut writer)? {
+        result?;
+    }
+    writer.finish();
+    writer.out.flush()
+}
+
+/// Passes games on to another visitor with only whitelisted headers, by
+/// default the Seven Tag Roster.
+///
+/// Combined with a [`PgnWriter`], this scrubs metadata like site URLs,
+/// player IDs or ratings from a database before publishing it. Escaped `%`
+/// lines are not passed on either, because they may carry any kind of
+/// metadata, and neither is the
+/// [raw header block](crate::Visitor::raw_headers).
+///
+/// # Examples
+///
+/// ```
+/// use pgn_reader::{Anonymizer, BufferedReader, PgnWriter};
+///
+/// let pgn = b"[White \"Carlsen\"]\n[WhiteElo \"2882\"]\n\n1. e4 *";
+///
+/// let mut anonymizer = Anonymizer::new(PgnWriter::new(Vec::new()));
+/// BufferedReader::new_cursor(&pgn[..]).read_all(&mut anonymizer)?;
+///
+/// assert_eq!(
+///     anonymizer.into_inner().into_inner(),
+///     b"[White \"Carlsen\"]\n\n1. e4 *\n\n"
+/// );
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Anonymizer<V> {
+    inner: V,
+    tags: Vec<Vec<u8>>,
+}
+
+impl<V: Visitor> Anonymizer<V> {
+    /// Keeps only the Seven Tag Roster.
+    pub fn new(inner: V) -> Anonymizer<V> {
+        Anonymizer::with_tags(inner, SEVEN_TAG_ROSTER)
+    }
+
+    /// Keeps only the given tags.
+    pub fn with_tags<I>(inner: V, tags: I) -> Anonymizer<V>
+    where
+        I: IntoIterator,
+        I::Item: Into<Vec<u8>>,
+    {
+        Anonymizer {
+            inner,
+            tags: tags.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns the wrapped visitor.
+    pub fn into_inner(self) -> V {
+        self.inner
+    }
+}
+
+impl<V: Visitor> Visitor for Anonymizer<V> {
+    type Result = V::Result;
+
+    fn begin_game(&mut self) {
+        self.inner.begin_game();
+    }
+
+    fn game_index(&mut self, index: usize) {
+        self.inner.game_index(index);
+    }
+
+    fn begin_headers(&mut self) {
+        self.inner.begin_headers();
+    }
+
+    fn header(&mut self, key: &[u8], value: RawHeader<'_>) {
+        if self.tags.iter().any(|tag| tag == key) {
+            self.inner.header(key, value);
+        }
+    }
+
+    fn setup(&mut self, fen: &[u8]) {
+        if self.tags.iter().any(|tag| tag == b"FEN") {
+            self.inner.setup(fen);
+        }
+    }
+
+    fn end_headers(&mut self) -> Skip {
+        self.inner.end_headers()
+    }
+
+    fn move_number(&mut self, number: u32, black_to_move: bool) {
+        self.inner.move_number(number, black_to_move);
+    }
+
+    fn san(&mut self, san_plus: SanPlus) {
+        self.inner.san(san_plus);
+    }
+
+    fn null_move(&mut self) {
+        self.inner.null_move();
+    }
+
+    fn unknown_token(&mut self, token: &[u8]) {
+        self.inner.unknown_token(token);
+    }
+
+    fn garbage(&mut self, token: &[u8]) {
+        self.inner.garbage(token);
+    }
+
+    fn nag(&mut self, nag: Nag) {
+        self.inner.nag(nag);
+    }
+
+    fn comment(&mut self, comment: RawComment<'_>) {
+        self.inner.comment(comment);
+    }
+
+    fn pre_game_comment(&mut self, comment: RawComment<'_>) {
+        self.inner.pre_game_comment(comment);
+    }
+
+    fn begin_variation(&mut self) -> Skip {
+        self.inner.begin_variation()
+    }
+
+    fn end_variation(&mut self) {
+        self.inner.end_variation();
+    }
+
+    fn outcome(&mut self, outcome: Option<Outcome>) {
+        self.inner.outcome(outcome);
+    }
+
+    fn truncated(&mut self) {
+        self.inner.truncated();
+    }
+
+    fn raw_movetext(&mut self, movetext: &[u8]) {
+        self.inner.raw_movetext(movetext);
+    }
+
+    fn warning(&mut self, error: &PgnError) {
+        self.inner.warning(error);
+    }
+
+    fn recovered(&mut self, error: &PgnError) {
+        self.inner.recovered(error);
+    }
+
+    fn end_game(&mut self) -> Self::Result {
+        self.inner.end_game()
+    }
+
+    fn finish(&mut self) {
+        self.inner.finish();
+    }
+
+    fn abort(&self) -> bool {
+        self.inner.abort()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::*;
+    use crate::{
+        analysis::GameSummary,
+        game::{GameBuilder, SanMove, Variation},
+        reader::BufferedReader,
+        visitor::Visitor,
+    };
+
+    #[test]
+    fn test_castling_notation() -> io::Result<()> {
+        struct Render(Vec<String>);
+
+        impl Visitor for Render {
+            type Result = ();
+
+            fn san(&mut self, san_plus: SanPlus) {
+                self.0
+                    .push(CastlingNotation::default().display(&san_plus).to_string());
+            }
+
+            fn end_game(&mut self) {}
+        }
+
+        let mut render = Render(Vec::new());
+        BufferedReader::new_cursor(&b"1. e4 e5 2. 0-0-0 o-o#"[..]).read_game(&mut render)?;
+        assert_eq!(render.0, ["e4", "e5", "O-O-O", "O-O#"]);
+        Ok(())
+    }
+
+    fn write(pgn: &[u8]) -> io::Result<Vec<u8>> {
+        let mut writer = PgnWriter::new(Vec::new());
+        for result in BufferedReader::new_cursor(pgn).into_iter(&mut writer) {
+            result??;
+        }
+        Ok(writer.into_inner())
+    }
+
+    #[test]
+    fn test_pgn_writer() -> io::Result<()> {
+        let pgn = b"[Event \"Escaped \\\"quotes\\\"\"]\n[FEN \"8/8/8/8/8/8/8/K1k5 b - - 0 40\"]\n\
+                    \n\
+                    Kc2 2. Ka2 ( 2. Kb2 $2 ( 2. Ka1 ) ) Kc3 ( ) 1/2-1/2";
+        assert_eq!(
+            String::from_utf8(write(pgn)?).unwrap(),
+            "[Event \"Escaped \\\"quotes\\\"\"]\n[FEN \"8/8/8/8/8/8/8/K1k5 b - - 0 40\"]\n\
+             \n\
+             40... Kc2 41. Ka2 (41. Kb2 $2 (41. Ka1)) 41... Kc3 () 1/2-1/2\n\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_numbers() -> io::Result<()> {
+        let pgn = b"7. e4 1... e5 Nf3 9. Nc6 { x } 3. Bb5 (12. Bc4 { y } Bc5) a6 4... O-O *";
+        assert_eq!(
+            String::from_utf8(write(pgn)?).unwrap(),
+            "1. e4 e5 2. Nf3 Nc6 { x } 3. Bb5 (3. Bc4 { y } 3... Bc5) 3... a6 4. O-O *\n\n"
+        );
+
+        let mut writer = PgnWriter::new(Vec::new());
+        writer.set_number_after_comments(false);
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut writer)?;
+        assert_eq!(
+            String::from_utf8(writer.into_inner()).unwrap(),
+            "1. e4 e5 2. Nf3 Nc6 { x } 3. Bb5 (3. Bc4 { y } Bc5) 3... a6 4. O-O *\n\n"
+        );
+
+        // Numbers at the start of a line remain, even after a comment.
+        let pgn = b"[FEN \"4k3/8/8/8/8/8/8/4K3 b - - 0 12\"]\n\n{ start } Kd7 Kd2 *";
+        let mut writer = PgnWriter::new(Vec::new());
+        writer.set_number_after_comments(false);
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut writer)?;
+        assert!(writer
+            .into_inner()
+            .ends_with(b"\n\n{ start } 12... Kd7 13. Kd2 *\n\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_writer_max_fullmoves() -> io::Result<()> {
+        let pgn = b"[FEN \"4k3/8/8/8/8/8/8/4K3 b - - 0 4294967295\"]\n\nKd7 Kd2 Ke7 *";
+        let written = write(pgn)?;
+        assert!(written.ends_with(b"\n\n4294967295... Kd7 4294967296. Kd2 Ke7 *\n\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_pgn_writer_wrapping() -> io::Result<()> {
+        let mut pgn = Vec::new();
+        for _ in 0..20 {
+            pgn.extend_from_slice(b"Nf3 Nf6 Ng1 Ng8 ");
+        }
+        pgn.extend_from_slice(b"*\n\n*");
+
+        let written = write(&pgn)?;
+        let text = String::from_utf8(written).unwrap();
+        let (first, second) = text.split_once("\n\n").unwrap();
+        assert!(first.lines().all(|line| line.len() <= MAX_LINE_LEN));
+        assert!(first.lines().count() > 1);
+        assert!(first.starts_with("1. Nf3 Nf6 2. Ng1 Ng8 3. Nf3"));
+        assert_eq!(second, "*\n\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_per_game() -> io::Result<()> {
+        let pgn = b"[Event \"A\"]\n[Site \"?\"]\n\n1. e4 e5 2. Nf3 {a long comment} Nc6 (2... d6 3. d4) 3. Bb5 a6 4. Ba4 Nf6 5. O-O Be7 6. Re1 b5 7. Bb3 d6 8. c3 O-O 9. h3 1/2-1/2\n\n\
+                    [Event \"B\"]\n\n1. d4 $1 d5 *\n\n\
+                    1. c4 0-1\n\n";
+
+        let mut writer = PgnWriter::new(Vec::new());
+        writer.set_line_per_game(true);
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut writer)?;
+        let lines = writer.into_inner();
+
+        let expected = write(pgn)?;
+        let mut reparsed = Vec::new();
+        for line in lines.split_inclusive(|&ch| ch == b'\n') {
+            assert_eq!(line.iter().filter(|&&ch| ch == b'\n').count(), 1);
+            reparsed.extend(write(line)?);
+        }
+        assert_eq!(
+            String::from_utf8(reparsed).unwrap(),
+            String::from_utf8(expected)
+                .unwrap()
+                .replace("a long\ncomment", "a long comment")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_per_game_read_back() -> io::Result<()> {
+        let write_lines = |pgn: &[u8]| -> io::Result<Vec<u8>> {
+            let mut writer = PgnWriter::new(Vec::new());
+            writer.set_line_per_game(true);
+            BufferedReader::new_cursor(pgn).read_all(&mut writer)?;
+            Ok(writer.into_inner())
+        };
+        let count = |pgn: &[u8]| BufferedReader::new_cursor(pgn).count_games();
+
+        let pgn = b"[Event \"A\"]\n\n1. e4 *\n\n[Event \"B\"]\n\n1. d4 *\n\n[Event \"C\"]\n\n*\n\n";
+        assert_eq!(count(&write_lines(pgn)?)?, 3);
+
+        // Without headers, the games only stay apart line by line.
+        let pgn = b"1. e4 *\n\n1. d4 *\n\n1. c4 *\n\n";
+        let lines = write_lines(pgn)?;
+        assert_eq!(lines, b"1. e4 *\n1. d4 *\n1. c4 *\n");
+        assert_eq!(count(&lines)?, 1);
+        let games = lines
+            .split_inclusive(|&ch| ch == b'\n')
+            .map(count)
+            .sum::<io::Result<usize>>()?;
+        assert_eq!(games, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_anonymizer() -> io::Result<()> {
+        let pgn = b"[Event \"Titled Arena\"]\n\
+                    [Site \"https://lichess.org/abcdefgh\"]\n\
+                    [White \"alice\"]\n\
+                    [Black \"bob\"]\n\
+                    [WhiteElo \"2500\"]\n\
+                    [BlackRatingDiff \"-7\"]\n\
+                    [GameId \"abcdefgh\"]\n\
+                    [Result \"1-0\"]\n\
+                    \n\
+                    1. e4 {[%clk 0:03:00]} 1... e5 (1... c5 $1) 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0\n\n";
+
+        let mut anonymizer = Anonymizer::new(PgnWriter::new(Vec::new()));
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut anonymizer)?;
+        let written = String::from_utf8(anonymizer.into_inner().into_inner()).unwrap();
+        let (headers, movetext) = written.split_once("\n\n").unwrap();
+        assert_eq!(
+            headers,
+            "[Event \"Titled Arena\"]\n\
+             [Site \"https://lichess.org/abcdefgh\"]\n\
+             [White \"alice\"]\n\
+             [Black \"bob\"]\n\
+             [Result \"1-0\"]"
+        );
+        assert_eq!(
+            movetext.as_bytes(),
+            &pgn[pgn.windows(2).position(|w| w == b"\n\n").unwrap() + 2..]
+        );
+
+        let mut anonymizer = Anonymizer::with_tags(PgnWriter::new(Vec::new()), [&b"White"[..]]);
+        BufferedReader::new_cursor(&pgn[..]).read_all(&mut anonymizer)?;
+        assert!(anonymizer
+            .into_inner()
+            .into_inner()
+            .starts_with(b"[White \"alice\"]\n\n1. e4"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_round_trip() -> io::Result<()> {
+        let mut long = b"[Event \"Long\"]\n\n".to_vec();
+        for i in 0..30 {
+            long.extend_from_slice(b"Nf3 Nf6 { shuffle } Ng1 Ng8 ");
+            if i % 7 == 0 {
+                long.extend_from_slice(b"(Nc3 Nc6 (Nh6) Nb1\n) ");
+            }
+        }
+        long.extend_from_slice(b"1/2-1/2");
+
+        let corpus: [&[u8]; 8] = [
+            b"[Event \"Escaped \\\"quotes\\\" and \\\\\"]\n[Site \"?\"]\n\n\
+              { before } 1. e4! e5?? 2. Qh5 $18 Nc6 ( 2... g6 3. Qxe5+ ) 3. Bc4 Nf6 4. Qxf7# 1-0",
+            b"[FEN \"8/8/8/8/8/8/8/K1k5 b - - 0 40\"]\n\nKc2 Ka2 { multi\nline\ncomment } Kc3 *",
+            b"1.e4 c5;sicilian\n2.Nf3 d6 3.d4 cxd4 4.Nxd4 Nf6 5.Nc3 a6 6.Be3 e5 7.Nb3 Be6 8.f3 Be7 \
+              9.Qd2 O-O 10.o-o-o Nbd7 11.g4 b5 12.g5 b4 13.Ne2 Ne8 14.f4 a5 15.f5 a4 0-1",
+            b"1. d4 d5 (1... Nf6 (1... f5 2. g3 (2. Bg5)) 2. c4) 2. c4 () 2... e6 $1 $14 *",
+            b"%escaped line\n[Black \"Someone\"]\n%another\n\n1. c4 {1} {2} e5 2. g3 1/2-1/2",
+            b"1. e4 e5 2. Nf3",
+            b"*",
+            &long,
+        ];
+
+        let mut input = Vec::new();
+        for pgn in corpus {
+            input.extend_from_slice(pgn);
+            input.extend_from_slice(b"\n\n");
+        }
+        let mut normalized = Vec::new();
+        normalize(&input[..], &mut normalized)?;
+
+        let original = BufferedReader::new_cursor(&input).collect_games(GameBuilder::new)?;
+        let reparsed = BufferedReader::new_cursor(&normalized).collect_games(GameBuilder::new)?;
+        assert_eq!(reparsed.len(), corpus.len());
+        for (original, reparsed) in original.into_iter().zip(reparsed) {
+            // Only the truncated game differs, by its termination marker.
+            assert_eq!(
+                Game {
+                    truncated: false,
+                    ..original
+                },
+                reparsed
+            );
+        }
+
+        let text = String::from_utf8(normalized.clone()).unwrap();
+        assert!(text.lines().all(|line| line.len() <= MAX_LINE_LEN));
+        assert!(!text.contains('%'));
+
+        let mut again = Vec::new();
+        normalize(&normalized[..], &mut again)?;
+        assert_eq!(
+            String::from_utf8(again).unwrap(),
+            String::from_utf8(normalized).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rest_of_line_comment_round_trip() -> io::Result<()> {
+        // Without the line break, 1... e5 would be part of the comment, and
+        // in braces, 2. Qh5 would be a move.
+        let pgn = b"1. e4 ; see } 2. Qh5\n1... e5 (1... c5 ;a}b\n) 2. Bc4 ; plain\n*";
+        let written = write(pgn)?;
+        assert_eq!(
+            String::from_utf8(written.clone()).unwrap(),
+            "1. e4 ; see } 2. Qh5\n1... e5 (1... c5 ;a}b\n) 2. Bc4 { plain} *\n\n"
+        );
+
+        let original = BufferedReader::new_cursor(&pgn[..]).collect_games(GameBuilder::new)?;
+        let reparsed = BufferedReader::new_cursor(&written).collect_games(GameBuilder::new)?;
+        assert_eq!(reparsed, original);
+        assert_eq!(write(&written)?, written);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_games() -> io::Result<()> {
+        fn san_move(san: &str) -> SanMove {
+            SanMove::new(san.parse().unwrap())
+        }
+
+        let mut annotated = Game {
+            headers: vec![
+                (b"White".to_vec(), b"O\\\"Brien".to_vec()),
+                (b"Black".to_vec(), b"?".to_vec()),
+            ],
+            mainline: Variation {
+                comments: vec![b"start".to_vec()],
+                moves: vec![san_move("e4"), san_move("e5"), san_move("Nf3")],
+            },
+            outcome: Some(Outcome::Draw),
+            truncated: false,
+            raw_headers: None,
+            raw_movetext: None,
+        };
+        annotated.mainline.moves[0].nags.push(Nag::GOOD_MOVE);
+        annotated.mainline.moves[1].comments.push(b"solid".to_vec());
+        annotated.mainline.moves[1].variations.push(Variation {
+            comments: Vec::new(),
+            moves: vec![san_move("c5"), san_move("Nf3")],
+        });
+
+        let from_position = Game {
+            headers: vec![(b"FEN".to_vec(), b"4k3/8/8/8/8/8/8/4K2R b K - 0 30".to_vec())],
+            mainline: Variation {
+                comments: Vec::new(),
+                moves: vec![san_move("Kd7"), san_move("O-O")],
+            },
+            outcome: None,
+            truncated: false,
+            raw_headers: None,
+            raw_movetext: None,
+        };
+
+        let games = vec![annotated, from_position, Game::default()];
+        let mut out = Vec::new();
+        write_games(&mut out, games.clone())?;
+        assert_eq!(
+            String::from_utf8_lossy(&out),
+            "[White \"O\\\"Brien\"]\n[Black \"?\"]\n\n\
+             {start} 1. e4 $1 e5 {solid} (1... c5 2. Nf3) 2. Nf3 1/2-1/2\n\n\
+             [FEN \"4k3/8/8/8/8/8/8/4K2R b K - 0 30\"]\n\n\
+             30... Kd7 31. O-O *\n\n\
+             *\n\n"
+        );
+
+        // Reading the output back gives the same games.
+        let reparsed = BufferedReader::new_cursor(&out)
+            .into_iter(&mut GameSummary::new())
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(reparsed.len(), games.len());
+        for (game, (headers, moves)) in games.iter().zip(reparsed) {
+            assert_eq!(headers.len(), game.headers.len());
+            for (key, value) in &game.headers {
+                assert_eq!(headers[key], RawHeader(value).decode().as_ref());
+            }
+            let expected: Vec<San> = game
+                .mainline
+                .moves
+                .iter()
+                .map(|m| m.san_plus.san.clone())
+                .collect();
+            assert_eq!(moves, expected);
+        }
+
+        let mut rewritten = PgnWriter::new(Vec::new());
+        BufferedReader::new_cursor(&out).read_all(&mut rewritten)?;
+        assert_eq!(rewritten.into_inner(), out);
+        Ok(())
+    }
+}